@@ -1,6 +1,9 @@
 use super::{time::Era, Point};
+use anyhow::{anyhow, bail, Context as _};
 use dcspark_core::{BlockId, SlotNumber};
+use deps::serde_json;
 use std::borrow::Cow;
+use std::path::Path;
 
 #[derive(Clone, Debug)]
 pub struct NetworkConfiguration {
@@ -19,15 +22,15 @@ impl NetworkConfiguration {
             relay: (Cow::Borrowed("relays-new.cardano-mainnet.iohk.io."), 3001),
             from: Point::BlockHeader {
                 slot_nb: SlotNumber::new(4492800),
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de",
                 ),
             },
-            genesis_parent: BlockId::new(
+            genesis_parent: BlockId::new_static(
                 "5f20df933584822601f9e3f8c024eb5eb252fe8cefb24d1317dc3d432e940ebb",
             ),
             genesis: Point::BlockHeader {
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "89d9b5a5b8ddc8d7e5a6795e9774d97faf1efea59b2caf7eaf9f8c5b32059df4",
                 ),
                 slot_nb: SlotNumber::new(0),
@@ -45,15 +48,15 @@ impl NetworkConfiguration {
             ),
             from: Point::BlockHeader {
                 slot_nb: SlotNumber::new(1598400),
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "02b1c561715da9e540411123a6135ee319b02f60b9a11a603d3305556c04329f",
                 ),
             },
-            genesis_parent: BlockId::new(
+            genesis_parent: BlockId::new_static(
                 "96fceff972c2c06bd3bb5243c39215333be6d56aaf4823073dca31afe5038471",
             ),
             genesis: Point::BlockHeader {
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "8f8602837f7c6f8b8867dd1cbc1842cf51a27eaed2c70ef48325d00f8efb320f",
                 ),
                 slot_nb: SlotNumber::new(0),
@@ -68,15 +71,15 @@ impl NetworkConfiguration {
             relay: (Cow::Borrowed("preprod-node.world.dev.cardano.org."), 30000),
             from: Point::BlockHeader {
                 slot_nb: SlotNumber::new(86400),
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "c4a1595c5cc7a31eda9e544986fe9387af4e3491afe0ca9a80714f01951bbd5c",
                 ),
             },
-            genesis_parent: BlockId::new(
+            genesis_parent: BlockId::new_static(
                 "d4b8de7a11d929a323373cbab6c1a9bdc931beffff11db111cf9d57356ee1937",
             ),
             genesis: Point::BlockHeader {
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "9ad7ff320c9cf74e0f5ee78d22a85ce42bb0a487d0506bf60cfb5a91ea4497d2",
                 ),
                 slot_nb: SlotNumber::new(0),
@@ -91,15 +94,15 @@ impl NetworkConfiguration {
             relay: (Cow::Borrowed("preview-node.world.dev.cardano.org."), 30002),
             from: Point::BlockHeader {
                 slot_nb: SlotNumber::new(25400),
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "8542d7f0b744f40f3de6164294b5feb0095307d46c7290acc8a5d9bd802acb8e",
                 ),
             },
-            genesis_parent: BlockId::new(
+            genesis_parent: BlockId::new_static(
                 "72593f260b66f26bef4fc50b38a8f24d3d3633ad2e854eaf73039eb9402706f1",
             ),
             genesis: Point::BlockHeader {
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "268ae601af8f9214804735910a3301881fbe0eec9936db7d1fb9fc39e93d1e37",
                 ),
                 slot_nb: SlotNumber::new(0),
@@ -120,15 +123,15 @@ impl NetworkConfiguration {
             ),
             from: Point::BlockHeader {
                 slot_nb: SlotNumber::new(20),
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "6a7d97aae2a65ca790fd14802808b7fce00a3362bd7b21c4ed4ccb4296783b98",
                 ),
             },
-            genesis_parent: BlockId::new(
+            genesis_parent: BlockId::new_static(
                 "785eb88427e136378a15b0a152a8bfbeec7a611529ccda29c43a1e60ffb48eaa",
             ),
             genesis: Point::BlockHeader {
-                hash: BlockId::new(
+                hash: BlockId::new_static(
                     "6a7d97aae2a65ca790fd14802808b7fce00a3362bd7b21c4ed4ccb4296783b98",
                 ),
                 slot_nb: SlotNumber::new(20),
@@ -136,4 +139,160 @@ impl NetworkConfiguration {
             shelley_era_config: Era::SHELLEY_SANCHO,
         }
     }
+
+    /// configuration of a custom network from its shelley genesis file
+    ///
+    /// The network is assumed to start in the shelley era (like preview or
+    /// sancho): the era starts at slot 0 of epoch 0, at the `systemStart`
+    /// of the genesis. The chain is fetched from the `genesis` block, the
+    /// first block of the chain whose parent is `genesis_parent`.
+    pub fn from_genesis(
+        shelley_genesis: impl AsRef<Path>,
+        relay: (Cow<'static, str>, u16),
+        genesis_parent: BlockId,
+        genesis: Point,
+    ) -> anyhow::Result<Self> {
+        let path = shelley_genesis.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to read the genesis {}", path.display()))?;
+        let shelley_genesis: ShelleyGenesis = serde_json::from_reader(file)
+            .with_context(|| format!("invalid shelley genesis {}", path.display()))?;
+
+        Ok(Self {
+            chain_info: shelley_genesis.network_info()?,
+            relay,
+            from: genesis.clone(),
+            genesis_parent,
+            genesis,
+            shelley_era_config: shelley_genesis.era()?,
+        })
+    }
+}
+
+/// the fields of the shelley genesis file describing the network
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShelleyGenesis {
+    network_magic: u32,
+    network_id: String,
+    system_start: String,
+    slot_length: f64,
+    epoch_length: u64,
+}
+
+impl ShelleyGenesis {
+    fn network_info(&self) -> anyhow::Result<cml_chain::genesis::network_info::NetworkInfo> {
+        let network_id = match self.network_id.as_str() {
+            "Mainnet" => 1,
+            "Testnet" => 0,
+            network_id => bail!("unknown network id {network_id} in the genesis"),
+        };
+        Ok(cml_chain::genesis::network_info::NetworkInfo::new(
+            network_id,
+            cml_core::network::ProtocolMagic::from(self.network_magic),
+        ))
+    }
+
+    fn era(&self) -> anyhow::Result<Era> {
+        // the slots are counted in seconds by the era parameters
+        if self.slot_length < 1.0 || self.slot_length.fract() != 0.0 {
+            bail!(
+                "unsupported slot length {}, only whole seconds are supported",
+                self.slot_length
+            );
+        }
+        let slot_length = self.slot_length as u64;
+
+        Ok(Era {
+            first_slot: 0,
+            start_epoch: 0,
+            known_time: parse_system_start(&self.system_start)?,
+            slot_length,
+            epoch_length_seconds: self.epoch_length * slot_length,
+        })
+    }
+}
+
+/// the unix time of the `YYYY-MM-DDTHH:MM:SSZ` system start of the genesis
+fn parse_system_start(system_start: &str) -> anyhow::Result<u64> {
+    let invalid = || anyhow!("invalid system start {system_start}");
+    let (date, time) = system_start
+        .strip_suffix('Z')
+        .and_then(|date_time| date_time.split_once('T'))
+        .ok_or_else(invalid)?;
+    let fields = |value: &str, separator| -> anyhow::Result<Vec<u64>> {
+        value
+            .split(separator)
+            .map(|field| field.parse().map_err(|_| invalid()))
+            .collect()
+    };
+    let (year, month, day) = match fields(date, '-')?.as_slice() {
+        [year @ 1970.., month @ 1..=12, day @ 1..=31] => (*year, *month, *day),
+        _ => return Err(invalid()),
+    };
+    let seconds = match fields(time.split('.').next().unwrap_or(time), ':')?.as_slice() {
+        [hours @ 0..=23, minutes @ 0..=59, seconds @ 0..=60] => {
+            hours * 3600 + minutes * 60 + seconds
+        }
+        _ => return Err(invalid()),
+    };
+
+    // days since the unix epoch of the proleptic gregorian date
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era)
+        .checked_sub(719468)
+        .ok_or_else(invalid)?;
+
+    Ok(days * 86400 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_start() {
+        assert_eq!(parse_system_start("1970-01-01T00:00:00Z").unwrap(), 0);
+        // the known times of the shelley eras of preprod, preview and sancho
+        assert_eq!(
+            parse_system_start("2022-06-21T00:00:00Z").unwrap(),
+            Era::SHELLEY_PREPROD.known_time
+        );
+        assert_eq!(
+            parse_system_start("2022-10-25T00:00:00Z").unwrap(),
+            Era::SHELLEY_PREVIEW.known_time
+        );
+        assert_eq!(
+            parse_system_start("2023-06-15T00:30:00Z").unwrap(),
+            Era::SHELLEY_SANCHO.known_time
+        );
+        assert!(parse_system_start("2023-06-15 00:30:00").is_err());
+        assert!(parse_system_start("2023-13-15T00:30:00Z").is_err());
+    }
+
+    #[test]
+    fn shelley_genesis_era() {
+        let genesis: ShelleyGenesis = serde_json::from_str(
+            r#"{
+                "networkMagic": 4,
+                "networkId": "Testnet",
+                "systemStart": "2023-06-15T00:30:00Z",
+                "slotLength": 1,
+                "epochLength": 86400,
+                "securityParam": 432
+            }"#,
+        )
+        .unwrap();
+
+        let era = genesis.era().unwrap();
+        let expected = Era::SHELLEY_SANCHO;
+        assert_eq!(era.known_time, expected.known_time);
+        assert_eq!(era.slot_length, expected.slot_length);
+        assert_eq!(era.epoch_length_seconds, expected.epoch_length_seconds);
+        assert_eq!(era.first_slot, expected.first_slot);
+    }
 }
@@ -53,7 +53,7 @@ pub(crate) fn get_parent_id(header: &cml_multi_era::utils::MultiEraBlockHeader)
     header
         .prev_hash()
         .as_ref()
-        .map(|id| BlockId::new(id.to_string()))
+        .map(|id| BlockId::new_unchecked(id.to_string()))
         .unwrap_or_else(|| BlockId::new_static("0x0000000000000000000000000000000000000000"))
 }
 
@@ -110,7 +110,7 @@ impl BlockEvent {
         let header = &block.header();
         Ok(BlockEvent {
             raw_block: raw_block.to_vec(),
-            id: BlockId::new(hex::encode(block.hash())),
+            id: BlockId::new_unchecked(hex::encode(block.hash())),
             parent_id: get_parent_id(header),
             block_number: BlockNumber::new(header.block_number()),
             slot_number: SlotNumber::new(header.slot()),
@@ -3,16 +3,17 @@ mod event;
 mod point;
 pub mod time;
 
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 pub use self::event::{BlockEvent, CardanoNetworkEvent};
 use crate::Source;
 use anyhow::{Context as _, Result};
-use cardano_net::{NetworkDescription, NetworkHandle};
-pub use cardano_sdk::protocol::Tip;
-use cardano_sdk::protocol::Version;
+use cardano_net::{NetworkDescription, NetworkError, NetworkHandle};
+pub use cardano_sdk::protocol::{Tip, Version};
 pub use configuration::NetworkConfiguration;
-use dcspark_core::critical_error;
+use dcspark_core::error::ErrorClass;
+use dcspark_core::{critical_error, HealthCheck, ServiceState, ServiceStatus};
 pub use point::*;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::Duration;
@@ -20,6 +21,9 @@ use tracing::{debug, error, info, warn, Instrument};
 
 const TX_PROCESSING_CHANNEL_BOUND: usize = 1000;
 
+/// the node-to-node versions proposed to the node, the lowest first
+pub const NET_VERSIONS: [Version; 3] = [Version::V6, Version::V7, Version::V8];
+
 type Event = CardanoNetworkEvent<BlockEvent, Tip>;
 
 pub struct CardanoSource {
@@ -31,6 +35,67 @@ pub struct CardanoSource {
     // This can happen in the first pull, since the Multiverse doesn't have a block to provide, so
     // we take it from the network settings.
     default_from: Point,
+    health: SharedHealth,
+}
+
+type SharedHealth = Arc<Mutex<SourceHealth>>;
+
+/// what the request handler knows of the connection with the node
+#[derive(Default)]
+struct SourceHealth {
+    connected: bool,
+    stopped: bool,
+    /// the last block range request reached the tip of the node
+    up_to_date: bool,
+    tip: Option<Tip>,
+    /// the node-to-node version accepted by the node on the last connection
+    version: Option<Version>,
+    last_error: Option<String>,
+}
+
+fn update_health(health: &SharedHealth, update: impl FnOnce(&mut SourceHealth)) {
+    // the lock is never held across a panic, but there is no reason
+    // to stop reporting the status if it was
+    let mut health = health
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    update(&mut health)
+}
+
+impl HealthCheck for CardanoSource {
+    /// * `Stopped` if the request handler exited;
+    /// * `Degraded` if the connection with the node was lost;
+    /// * `Ready` if the last block range request reached the tip of the node,
+    ///   the blocks may still be buffered;
+    /// * `Syncing` otherwise.
+    fn status(&self) -> ServiceStatus {
+        let health = self
+            .health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let state = if health.stopped {
+            ServiceState::Stopped
+        } else if !health.connected {
+            ServiceState::Degraded
+        } else if health.up_to_date {
+            ServiceState::Ready
+        } else {
+            ServiceState::Syncing
+        };
+
+        let mut status = ServiceStatus::new(state);
+        if let Some(tip) = &health.tip {
+            status = status.with_detail("tip", tip);
+        }
+        if let Some(version) = health.version {
+            status = status.with_detail("version", version as u64);
+        }
+        if let Some(error) = &health.last_error {
+            status = status.with_detail("last_error", error);
+        }
+        status
+    }
 }
 
 #[async_trait::async_trait]
@@ -80,7 +145,9 @@ impl Source for CardanoSource {
 
         if self.service.send((from, tx)).await.is_err() {
             error!("block processing service stopped");
-            return Err(anyhow::anyhow!("request handler stoped"));
+            // the request handler only exits when the source is stopped,
+            // pulling again will not succeed
+            return Err(anyhow::anyhow!(ErrorClass::Critical).context("request handler stoped"));
         }
 
         self.current.replace(rx);
@@ -112,16 +179,22 @@ impl CardanoSource {
                     "addr_test"
                 },
             },
-            net_versions: vec![Version::V6, Version::V7, Version::V8],
+            net_versions: NET_VERSIONS.to_vec(),
             known_points: vec![],
         };
 
-        let handle = NetworkHandle::start(&config)
+        let (handle, version) = start_network(&config)
             .await
+            .context(ErrorClass::Retryable)
             .context("Failed to establish connection with the node")?;
 
         let (tx, rx) = mpsc::channel(1);
         let (exit_tx, exit_rx) = oneshot::channel();
+        let health = Arc::new(Mutex::new(SourceHealth {
+            connected: true,
+            version: Some(version),
+            ..SourceHealth::default()
+        }));
 
         // we don't need the handle, since we can signalkill the task by just dropping the request
         // channel, and the task can't error.
@@ -133,6 +206,7 @@ impl CardanoSource {
                 tip_update_pace,
                 network_config.clone(),
                 config,
+                Arc::clone(&health),
             )
             .instrument(tracing::info_span!("request handler")),
         );
@@ -142,6 +216,7 @@ impl CardanoSource {
             current: None,
             exit_rx,
             default_from: network_config.from.clone(),
+            health,
         })
     }
 
@@ -161,6 +236,47 @@ impl CardanoSource {
     pub fn clear_buffers(&mut self) {
         self.current = None
     }
+
+    /// the node-to-node version accepted by the node, it may change when
+    /// the connection is reestablished
+    pub fn version(&self) -> Option<Version> {
+        self.health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .version
+    }
+}
+
+/// connect to the node proposing the [`NET_VERSIONS`] one at a time, the
+/// highest first
+///
+/// The node accepts the highest of the proposed versions it supports, so
+/// the connection is the same as when proposing all of them at once, but
+/// [`NetworkHandle::start`] does not tell which version was accepted.
+async fn start_network(
+    config: &NetworkDescription,
+) -> std::result::Result<(NetworkHandle, Version), NetworkError> {
+    let (lowest, higher) = NET_VERSIONS.split_first().expect("versions are proposed");
+    for &version in higher.iter().rev() {
+        let description = NetworkDescription {
+            net_versions: vec![version],
+            ..config.clone()
+        };
+        match NetworkHandle::start(&description).await {
+            Ok(handle) => return Ok((handle, version)),
+            Err(error @ NetworkError::HandshakeError(_)) => {
+                debug!(?version, %error, "version refused by the node");
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    let description = NetworkDescription {
+        net_versions: vec![*lowest],
+        ..config.clone()
+    };
+    let handle = NetworkHandle::start(&description).await?;
+    Ok((handle, *lowest))
 }
 
 async fn request_handler(
@@ -170,6 +286,7 @@ async fn request_handler(
     tip_update_pace: Duration,
     network_config: NetworkConfiguration,
     config: NetworkDescription,
+    health: SharedHealth,
 ) {
     // initially set this to a time in the past, which guarantees an event in the tip fetch.
     let mut last_tip_event = Instant::now()
@@ -182,13 +299,20 @@ async fn request_handler(
         if handle.is_none() {
             info!("trying to reestablish connection with the node");
 
-            match NetworkHandle::start(&config).await {
-                Ok(new_handle) => {
-                    info!("connection reestablished succesfully");
+            match start_network(&config).await {
+                Ok((new_handle, version)) => {
+                    info!(?version, "connection reestablished succesfully");
                     handle.replace(new_handle);
+                    update_health(&health, |health| {
+                        health.connected = true;
+                        health.version = Some(version);
+                    });
                 }
                 Err(error) => {
                     error!(%error, "failed to reestablish connection with the node");
+                    update_health(&health, |health| {
+                        health.last_error = Some(error.to_string())
+                    });
 
                     // this will make the `pull` return None.
                     //
@@ -218,20 +342,28 @@ async fn request_handler(
             tip_update_pace,
             &network_config,
             ignore_first_block,
+            &health,
         )
         .await
         {
             warn!(error = %e, "dropping connection handle");
+            update_health(&health, |health| {
+                health.connected = false;
+                health.up_to_date = false;
+                health.last_error = Some(e.to_string());
+            });
             current_handle.stop().await;
         } else {
             handle = Some(current_handle);
         }
     }
 
+    update_health(&health, |health| health.stopped = true);
     let _ = exit_signal.send(());
 }
 
-#[tracing::instrument(skip(handle, channel))]
+#[tracing::instrument(skip(handle, channel, health))]
+#[allow(clippy::too_many_arguments)]
 async fn block_fetch(
     handle: &mut NetworkHandle,
     from: Vec<Point>,
@@ -240,6 +372,7 @@ async fn block_fetch(
     tip_update_pace: Duration,
     network_config: &NetworkConfiguration,
     ignore_first_block: bool,
+    health: &SharedHealth,
 ) -> Result<()> {
     let points: Result<Vec<_>> = from
         .into_iter()
@@ -262,6 +395,7 @@ async fn block_fetch(
             (from, tip)
         }
         cardano_net::ChainIntersection::NotFound(tip) => {
+            update_health(health, |health| health.tip = Some(tip.clone()));
             // this would cause `pull` to return None, which the 'puller' could potentially use as
             // a signal to change update the from argument the next time.
             warn!(%tip, "couldn't find a starting point in the node's current branch");
@@ -269,7 +403,13 @@ async fn block_fetch(
         }
     };
 
-    if tip.point == from {
+    let up_to_date = tip.point == from;
+    update_health(health, |health| {
+        health.tip = Some(tip.clone());
+        health.up_to_date = up_to_date;
+    });
+
+    if up_to_date {
         info!("source is up to date, nothing to pull");
         return Ok(());
     }
@@ -319,6 +459,7 @@ async fn block_fetch(
     }
 
     debug!("block range request finished successfully");
+    update_health(health, |health| health.up_to_date = true);
 
     Ok(())
 }
@@ -1,5 +1,6 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, Context as _};
 use dcspark_core::{BlockId, SlotNumber};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Hash)]
 pub enum Point {
@@ -28,8 +29,50 @@ impl From<cardano_sdk::protocol::Point> for Point {
         } else {
             Point::BlockHeader {
                 slot_nb: SlotNumber::new(point.slot_nb()),
-                hash: BlockId::new(point.hash().to_string()),
+                hash: BlockId::new_unchecked(point.hash().to_string()),
             }
         }
     }
 }
+
+/// parse `origin` or a block header point `slot,block_hash`
+impl FromStr for Point {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        if s == "origin" {
+            return Ok(Point::Origin);
+        }
+
+        let (slot, hash) = s
+            .split_once(',')
+            .ok_or_else(|| anyhow!("the point should be either origin or slot,block_hash"))?;
+        Ok(Point::BlockHeader {
+            slot_nb: SlotNumber::new(slot.parse().context("invalid slot of the point")?),
+            hash: BlockId::from_hex(hash)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("origin".parse::<Point>().unwrap(), Point::Origin);
+        assert_eq!(
+            "4492800,aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de"
+                .parse::<Point>()
+                .unwrap(),
+            Point::BlockHeader {
+                slot_nb: SlotNumber::new(4492800),
+                hash: BlockId::new_static(
+                    "aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de"
+                ),
+            }
+        );
+        assert!("4492800".parse::<Point>().is_err());
+        assert!("slot,aa83".parse::<Point>().is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use dcspark_core::{EraParams, SlotNumber, Timestamp};
+
 const EPOCH_LENGTH_IN_SECONDS: u64 = 432000;
 const BYRON_SLOT_DURATION: u64 = 20;
 
@@ -51,20 +53,38 @@ impl Era {
         epoch_length_seconds: 86400,
     };
 
-    pub const fn compute_timestamp(&self, slot: u64) -> u64 {
-        self.known_time + (slot - self.first_slot) * self.slot_length
+    /// the era parameters, the shelley eras have one slot per second
+    /// so the epoch length in seconds is also the number of slots.
+    pub const fn params(&self) -> EraParams {
+        EraParams {
+            first_slot: SlotNumber::new(self.first_slot),
+            first_epoch: self.start_epoch,
+            system_start: Timestamp::new(self.known_time),
+            slot_length: self.slot_length,
+            epoch_length: self.epoch_length_seconds,
+        }
+    }
+
+    /// the time at which the slot starts, `None` if the slot is before the
+    /// era
+    pub fn compute_timestamp(&self, slot: u64) -> Option<u64> {
+        self.params()
+            .slot_to_timestamp(SlotNumber::new(slot))
+            .map(|timestamp| timestamp.into_inner())
     }
 
     pub fn absolute_slot_to_epoch(&self, slot: u64) -> Option<u64> {
-        slot.checked_sub(self.first_slot)
-            .map(|slot_relative_to_era| {
-                self.start_epoch + slot_relative_to_era / self.epoch_length_seconds
-            })
+        self.params().slot_to_epoch(SlotNumber::new(slot))
     }
 
     pub fn absolute_slot_to_relative(&self, slot: u64) -> Option<u64> {
-        slot.checked_sub(self.first_slot)
-            .map(|slot_relative_to_era| slot_relative_to_era % self.epoch_length_seconds)
+        self.params().slot_in_epoch(SlotNumber::new(slot))
+    }
+}
+
+impl From<&Era> for EraParams {
+    fn from(era: &Era) -> Self {
+        era.params()
     }
 }
 
@@ -120,8 +140,9 @@ mod tests {
         assert_eq!(epoch, 81);
         let epoch = era.absolute_slot_to_epoch(33350429).unwrap();
         assert_eq!(epoch, 81);
-        let timestamp = era.compute_timestamp(33350429);
+        let timestamp = era.compute_timestamp(33350429).unwrap();
         assert_eq!(timestamp, 1689033629);
+        assert_eq!(None, era.compute_timestamp(86400 - 1));
         let epoch = era.absolute_slot_to_epoch(33346852).unwrap();
         assert_eq!(epoch, 80);
         let epoch = era.absolute_slot_to_epoch(33350398).unwrap();
@@ -147,7 +168,7 @@ mod tests {
         assert_eq!(epoch, 280);
         let relative_slot = era.absolute_slot_to_relative(24278356).unwrap();
         assert_eq!(relative_slot, 86356);
-        let timestamp = era.compute_timestamp(24278356);
+        let timestamp = era.compute_timestamp(24278356).unwrap();
         assert_eq!(timestamp, 1690934356);
 
         let epoch = era.absolute_slot_to_epoch(100).unwrap();
@@ -2,6 +2,7 @@ pub mod rollback;
 
 use crate::{EventObject, GetNextFrom, PullFrom, Source};
 use anyhow::{anyhow, Result};
+use dcspark_core::error::ErrorClass;
 use multiverse::{BestBlock, BestBlockSelectionRule, Variant};
 use std::{
     fmt::{Debug, Display},
@@ -94,7 +95,8 @@ where
                 for k in self.multiverse.tips().iter() {
                     let v =
                         self.multiverse.get(k).unwrap().next_from().ok_or_else(|| {
-                            anyhow!("tip doesn't have an entry in the multiverse")
+                            anyhow!(ErrorClass::Critical)
+                                .context("tip doesn't have an entry in the multiverse")
                         })?;
 
                     checkpoints.push(v);
@@ -109,10 +111,10 @@ where
                         // checking if the block belongs to the same branch right now.
                         return Ok(Some(confirmed));
                     } else if let Some(from) = from {
-                        anyhow::ensure!(
-                            from == &confirmed_point,
-                            "non continuous pull not supported yet"
-                        );
+                        if from != &confirmed_point {
+                            return Err(anyhow!(ErrorClass::InvalidInput)
+                                .context("non continuous pull not supported yet"));
+                        }
 
                         // TODO: re-check this
                         checkpoints.push(from.clone());
@@ -201,6 +203,27 @@ where
     Ok(selected.map(|entry_ref| entry_ref.inner().clone()))
 }
 
+/// the status of the inner source, with the confirmed block and the
+/// number of tips of the multiverse in the details
+impl<K, V, InnerSource> dcspark_core::HealthCheck for MultiverseSource<K, V, InnerSource>
+where
+    InnerSource: dcspark_core::HealthCheck,
+    K: Eq + Hash + Display,
+{
+    fn status(&self) -> dcspark_core::ServiceStatus {
+        let mut status = self
+            .source
+            .status()
+            .with_detail("multiverse_tips", self.multiverse.tips().len());
+
+        if let Some(confirmed) = &self.confirmed {
+            status = status.with_detail("confirmed", confirmed);
+        }
+
+        status
+    }
+}
+
 #[async_trait::async_trait]
 impl dcspark_core::StoppableService
     for MultiverseSource<
@@ -0,0 +1,127 @@
+//! Decode Cardano addresses
+//!
+//! The addresses are given as arguments, or one per line on stdin. They can
+//! be bech32 (shelley), base58 (byron) or hex encoded:
+//!
+//! ```text
+//! $ address-inspector addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8
+//! addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8
+//!   type: enterprise
+//!   network id: 1 (mainnet)
+//!   payment credential: key <hash hex> (addr_vkh1...)
+//! ```
+//!
+//! The tool exits with an error if any address is invalid.
+use anyhow::bail;
+use clap::Parser;
+use dcspark_core::{Address, Credential, ParsedAddress, StakePointer};
+use std::fmt::Display;
+use std::io::BufRead;
+
+const MAINNET_NETWORK_ID: u8 = 1;
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// the addresses to decode, read from stdin if none is given
+    #[clap(value_parser)]
+    pub addresses: Vec<String>,
+}
+
+fn address_type(address: &ParsedAddress) -> &'static str {
+    match address {
+        ParsedAddress::Base { .. } => "base",
+        ParsedAddress::Enterprise { .. } => "enterprise",
+        ParsedAddress::Pointer { .. } => "pointer",
+        ParsedAddress::Reward { .. } => "reward",
+        ParsedAddress::Byron { .. } => "byron",
+    }
+}
+
+/// the kind and the hash of the credential, followed by its bech32
+/// encoding for the key hashes
+fn credential(credential: &Credential, bech32: impl Display) -> String {
+    match credential {
+        Credential::Key(hash) => format!("key {} ({bech32})", hex::encode(hash)),
+        Credential::Script(script) => format!("script {script}"),
+    }
+}
+
+fn print(address: &str, parsed: &ParsedAddress) {
+    println!("{address}");
+    println!("  type: {}", address_type(parsed));
+
+    match parsed {
+        ParsedAddress::Byron {
+            protocol_magic: None,
+        } => println!("  protocol magic: mainnet"),
+        ParsedAddress::Byron {
+            protocol_magic: Some(protocol_magic),
+        } => println!("  protocol magic: {protocol_magic}"),
+        _ => {}
+    }
+    if let Some(network_id) = parsed.network_id() {
+        let network = if network_id == MAINNET_NETWORK_ID {
+            "mainnet"
+        } else {
+            "testnet"
+        };
+        println!("  network id: {network_id} ({network})");
+    }
+    if let Some(payment) = parsed.payment_credential() {
+        println!(
+            "  payment credential: {}",
+            credential(payment.credential(), payment)
+        );
+    }
+    if let Some(stake) = parsed.stake_credential() {
+        println!(
+            "  stake credential: {}",
+            credential(stake.credential(), stake)
+        );
+    }
+    if let ParsedAddress::Pointer {
+        pointer:
+            StakePointer {
+                slot,
+                transaction_index,
+                certificate_index,
+            },
+        ..
+    } = parsed
+    {
+        println!(
+            "  pointer: slot {slot}, transaction index {transaction_index}, certificate index {certificate_index}"
+        );
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let Cli { mut addresses } = Cli::parse();
+
+    if addresses.is_empty() {
+        for line in std::io::stdin().lock().lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                addresses.push(line.trim().to_string());
+            }
+        }
+    }
+
+    let mut invalid = 0;
+    for address in addresses.iter() {
+        match Address::new(address.clone()).parse() {
+            Ok(parsed) => print(address, &parsed),
+            Err(error) => {
+                println!("{address}");
+                println!("  invalid: {error}");
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        bail!("{invalid} invalid addresses");
+    }
+    Ok(())
+}
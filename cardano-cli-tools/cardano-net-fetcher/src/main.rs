@@ -1,29 +1,191 @@
+use anyhow::bail;
 use clap::Parser;
-use dcspark_blockchain_source::cardano::Point::BlockHeader;
-use dcspark_blockchain_source::cardano::{CardanoNetworkEvent, CardanoSource};
+use dcspark_blockchain_source::cardano::{
+    BlockEvent, CardanoNetworkEvent, CardanoSource, Point, Tip,
+};
+use dcspark_blockchain_source::multiverse::MultiverseSource;
 use dcspark_blockchain_source::{GetNextFrom, Source};
-use dcspark_core::{BlockId, SlotNumber};
-use std::borrow::Cow;
+use dcspark_core::{BlockId, BlockNumber, HealthCheck, ServiceState, ServiceStatus};
+use multiverse::{BestBlockSelectionRule, Multiverse};
+use network_profiles::NetworkArgs;
+use std::path::PathBuf;
 use std::time::Duration;
 
+/// the sled tree of the stored blocks
+const STORE_DOMAIN: &str = "blocks";
+
+/// wait between two pulls while no new block is confirmed
+const PULL_INTERVAL: Duration = Duration::from_secs(5);
+
+type StoredBlock = CardanoNetworkEvent<BlockEvent, Tip>;
+
 #[derive(Parser, Debug)]
 #[clap(version)]
 struct Cli {
-    #[clap(long, value_parser, default_value = "mainnet")]
-    pub network: String,
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    /// the block to start after, `slot,block_hash`
+    #[clap(long, value_parser)]
+    pub since: Option<Point>,
+    /// override the relay of the network profile
     #[clap(long, value_parser)]
-    pub since: Option<String>,
+    pub relay_host: Option<String>,
     #[clap(long, value_parser)]
-    pub relay_host: String,
+    pub relay_port: Option<u16>,
+    /// store the blocks in a multiverse persisted at this path instead of
+    /// printing them, the fetch resumes from the stored tips on restart
     #[clap(long, value_parser)]
-    pub relay_port: u16,
+    pub store: Option<PathBuf>,
+    /// only emit the blocks with this many blocks on top of them, the forks
+    /// served by the relay are reported as rollbacks
+    #[clap(long, value_parser)]
+    pub confirmation_depth: Option<usize>,
+}
+
+/// reports the blocks served by the relay that do not extend the previous
+/// block, i.e. the switches to another fork
+struct RollbackNotices {
+    source: CardanoSource,
+    last: Option<BlockId>,
+}
+
+#[async_trait::async_trait]
+impl Source for RollbackNotices {
+    type Event = StoredBlock;
+    type From = Vec<Point>;
+
+    async fn pull(&mut self, from: &Self::From) -> anyhow::Result<Option<Self::Event>> {
+        let event = self.source.pull(from).await?;
+        if let Some(CardanoNetworkEvent::Block(block)) = &event {
+            match self.last.replace(block.id.clone()) {
+                Some(last) if last != block.parent_id && last != block.id => println!(
+                    "Rollback to {}: block #{} {} does not extend {last}",
+                    block.parent_id, block.block_number, block.id,
+                ),
+                _ => {}
+            }
+        }
+        Ok(event)
+    }
+}
+
+impl HealthCheck for RollbackNotices {
+    fn status(&self) -> ServiceStatus {
+        self.source.status()
+    }
+}
+
+fn emit(
+    store: &mut Option<Multiverse<BlockId, StoredBlock>>,
+    event: StoredBlock,
+) -> anyhow::Result<()> {
+    let block = match &event {
+        CardanoNetworkEvent::Tip(_) => return Ok(()),
+        CardanoNetworkEvent::Block(block) => block,
+    };
+
+    match store {
+        Some(multiverse) => {
+            println!(
+                "Stored block #{}, point: {}@{}",
+                block.block_number, block.id, block.slot_number,
+            );
+            if !multiverse.contains(&block.id) {
+                multiverse.insert(event)?;
+            }
+        }
+        None => println!(
+            "Block #{}, point: {}@{}, raw cbor hex: {}",
+            block.block_number,
+            block.id,
+            block.slot_number,
+            hex::encode(&block.raw_block),
+        ),
+    }
+    Ok(())
+}
+
+/// emit every block served by the relay
+async fn follow(
+    mut source: CardanoSource,
+    mut store: Option<Multiverse<BlockId, StoredBlock>>,
+    mut pull_from: Vec<Point>,
+) -> anyhow::Result<()> {
+    if let Some(multiverse) = store.as_ref().filter(|_| pull_from.is_empty()) {
+        pull_from = multiverse
+            .tips()
+            .iter()
+            .filter_map(|tip| multiverse.get(tip).and_then(GetNextFrom::next_from))
+            .collect();
+        if !pull_from.is_empty() {
+            println!(
+                "Resuming from {} stored blocks, tips: {pull_from:?}",
+                multiverse.len()
+            );
+        }
+    }
+
+    while let Some(event) = source.pull(&pull_from).await? {
+        if let Some(point) = event.next_from() {
+            pull_from = vec![point];
+        }
+        emit(&mut store, event)?;
+    }
+
+    Ok(())
 }
 
-fn parse_since(since: String) -> anyhow::Result<(BlockId, SlotNumber)> {
-    let mut parts: Vec<_> = since.split(',').collect();
-    let slot: SlotNumber = SlotNumber::new(parts.remove(0).parse()?);
-    let hash: BlockId = BlockId::new(parts.remove(0).to_owned());
-    Ok((hash, slot))
+/// emit the blocks once `confirmation_depth` blocks are on top of them
+async fn follow_confirmed(
+    source: CardanoSource,
+    mut store: Option<Multiverse<BlockId, StoredBlock>>,
+    mut pull_from: Option<Point>,
+    confirmation_depth: usize,
+) -> anyhow::Result<()> {
+    if let Some(multiverse) = store.as_ref().filter(|_| pull_from.is_none()) {
+        pull_from = multiverse
+            .preferred_fork_tip(BestBlockSelectionRule::LongestChain {
+                depth: 0,
+                age_gap: 0,
+            })
+            .and_then(|tip| multiverse.get(tip.inner()))
+            .and_then(GetNextFrom::next_from);
+        if let Some(point) = &pull_from {
+            println!(
+                "Resuming from {} stored blocks, tip: {point:?}",
+                multiverse.len()
+            );
+        }
+    }
+
+    let mut source = MultiverseSource::new(
+        Multiverse::<BlockId, StoredBlock>::temporary()?,
+        confirmation_depth,
+        RollbackNotices { source, last: None },
+    );
+
+    // the multiverse source returns nothing until a new block is confirmed,
+    // or if the connection with the relay was lost
+    loop {
+        let event = match source.pull(&pull_from).await? {
+            Some(event) => event,
+            None => {
+                let status = source.status();
+                if status.state == ServiceState::Stopped {
+                    bail!("the connection with the relay stopped");
+                }
+                if let Some(error) = status.details.get("last_error") {
+                    bail!("the connection with the relay failed: {error}");
+                }
+                tokio::time::sleep(PULL_INTERVAL).await;
+                continue;
+            }
+        };
+        if let Some(point) = event.next_from() {
+            pull_from = Some(point);
+        }
+        emit(&mut store, event)?;
+    }
 }
 
 #[tokio::main]
@@ -33,53 +195,20 @@ async fn main() -> anyhow::Result<()> {
         since,
         relay_host,
         relay_port,
+        store,
+        confirmation_depth,
     } = Cli::parse();
 
-    let base_config = match network.as_ref() {
-        "mainnet" => dcspark_blockchain_source::cardano::NetworkConfiguration::mainnet(),
-        "preprod" => dcspark_blockchain_source::cardano::NetworkConfiguration::preprod(),
-        "preview" => dcspark_blockchain_source::cardano::NetworkConfiguration::preview(),
-        "sancho" => dcspark_blockchain_source::cardano::NetworkConfiguration::sancho(),
-        _ => return Err(anyhow::anyhow!("network not supported by source")),
-    };
+    let network_config = network.load()?.with_relay(relay_host, relay_port).config;
 
-    let mut pull_from = match since {
-        None => vec![],
-        Some(since) => {
-            let (since_hash, since_slot) = parse_since(since)?;
-            vec![BlockHeader {
-                slot_nb: since_slot,
-                hash: since_hash,
-            }]
-        }
-    };
-
-    let network_config = dcspark_blockchain_source::cardano::NetworkConfiguration {
-        relay: (Cow::from(relay_host), relay_port),
-        ..base_config
-    };
-
-    let mut source = CardanoSource::connect(&network_config, Duration::from_secs(20)).await?;
-
-    while let Some(event) = source.pull(&pull_from).await? {
-        let block = match &event {
-            CardanoNetworkEvent::Tip(_) => continue,
-            CardanoNetworkEvent::Block(block) => block.clone(),
-        };
+    let store = store
+        .map(|path| Multiverse::<BlockId, StoredBlock>::open(path, STORE_DOMAIN, BlockNumber::MIN))
+        .transpose()?;
 
-        pull_from = event
-            .next_from()
-            .map(|point| vec![point])
-            .unwrap_or(pull_from.clone());
+    let source = CardanoSource::connect(&network_config, Duration::from_secs(20)).await?;
 
-        println!(
-            "Block #{}, point: {}@{}, raw cbor hex: {}",
-            block.block_number,
-            block.id,
-            block.slot_number,
-            hex::encode(block.raw_block),
-        );
+    match confirmation_depth {
+        None => follow(source, store, since.into_iter().collect()).await,
+        Some(depth) => follow_confirmed(source, store, since, depth).await,
     }
-
-    Ok(())
 }
@@ -0,0 +1,238 @@
+//! Follow the chain served by a relay and report its forks
+//!
+//! Every block served by the relay is inserted in a multiverse, the blocks
+//! with more than `keep` blocks on top of them are pruned. A block that does
+//! not extend the previous one is a reorg: the blocks rolled back and the
+//! common ancestor are logged, and the fork tree of the multiverse is
+//! rendered.
+mod tree;
+
+use clap::{Parser, ValueEnum};
+use dcspark_blockchain_source::cardano::Point::BlockHeader;
+use dcspark_blockchain_source::cardano::{
+    BlockEvent, CardanoNetworkEvent, CardanoSource, Point, Tip,
+};
+use dcspark_blockchain_source::{GetNextFrom, Source};
+use dcspark_core::{BlockId, BlockNumber};
+use multiverse::{BestBlock, BestBlockSelectionRule, Multiverse};
+use network_profiles::NetworkArgs;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// the sled tree of the stored blocks
+const STORE_DOMAIN: &str = "blocks";
+
+type StoredBlock = CardanoNetworkEvent<BlockEvent, Tip>;
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    /// the block to start after, `slot,block_hash`
+    #[clap(long, value_parser)]
+    pub since: Option<Point>,
+    #[clap(long, value_parser)]
+    pub relay_host: Option<String>,
+    #[clap(long, value_parser)]
+    pub relay_port: Option<u16>,
+    /// persist the multiverse at this path, the follow resumes from its
+    /// preferred tip on restart
+    #[clap(long, value_parser)]
+    pub store: Option<PathBuf>,
+    /// number of blocks kept under the tip, the forks older than that are pruned
+    #[clap(long, value_parser, default_value = "20")]
+    pub keep: usize,
+    #[clap(long, value_enum, default_value = "ascii")]
+    pub render: Render,
+    /// also render the fork tree every this many blocks, by default it is
+    /// only rendered on reorgs
+    #[clap(long, value_parser)]
+    pub render_every: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Render {
+    Ascii,
+    Dot,
+    None,
+}
+
+/// a switch of the relay to another fork
+struct Reorg {
+    /// the blocks of the previous fork, from its tip
+    rolled_back: Vec<BlockId>,
+    /// the last block common to both forks, unknown if it was pruned
+    ancestor: Option<BlockId>,
+}
+
+struct Follower {
+    multiverse: Multiverse<BlockId, StoredBlock>,
+    tip: Option<BlockId>,
+    keep: usize,
+}
+
+impl Follower {
+    fn block(&self, id: &BlockId) -> Option<&BlockEvent> {
+        match self.multiverse.get(id) {
+            Some(CardanoNetworkEvent::Block(block)) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// the known blocks from `id` down to the oldest ancestor in the multiverse
+    fn chain(&self, id: &BlockId) -> Vec<BlockId> {
+        let mut chain = Vec::new();
+        let mut current = self.block(id);
+        while let Some(block) = current {
+            chain.push(block.id.clone());
+            current = self.block(&block.parent_id);
+        }
+        chain
+    }
+
+    /// the reorg leading to `block`, if it does not extend the tip
+    fn reorg(&self, block: &BlockEvent) -> Option<Reorg> {
+        let tip = self.tip.as_ref()?;
+        if &block.parent_id == tip || &block.id == tip {
+            return None;
+        }
+
+        let previous = self.chain(tip);
+        // the relay serves again blocks of the current fork after a rollback
+        if previous.contains(&block.id) {
+            return None;
+        }
+
+        let previous_set: HashSet<&BlockId> = previous.iter().collect();
+        let ancestor = std::iter::once(block.parent_id.clone())
+            .chain(self.chain(&block.parent_id))
+            .find(|id| previous_set.contains(id));
+        let rolled_back = previous
+            .into_iter()
+            .take_while(|id| Some(id) != ancestor.as_ref())
+            .collect();
+        Some(Reorg {
+            rolled_back,
+            ancestor,
+        })
+    }
+
+    /// insert the block and prune the forks deeper than `keep`
+    fn insert(&mut self, block: BlockEvent) -> anyhow::Result<()> {
+        self.tip = Some(block.id.clone());
+        if self.multiverse.contains(&block.id) {
+            return Ok(());
+        }
+        self.multiverse.insert(CardanoNetworkEvent::Block(block))?;
+
+        let BestBlock { discarded, .. } =
+            self.multiverse
+                .select_best_block(BestBlockSelectionRule::LongestChain {
+                    depth: self.keep,
+                    age_gap: 1,
+                });
+        for discarded in discarded {
+            self.multiverse.remove(&discarded)?;
+        }
+        Ok(())
+    }
+
+    fn render(&self, render: Render) {
+        let roots = tree::fork_tree(&self.multiverse);
+        match render {
+            Render::Ascii => print!("{}", tree::ascii(&roots, self.tip.as_ref())),
+            Render::Dot => print!("{}", tree::dot(&roots, self.tip.as_ref())),
+            Render::None => {}
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Cli {
+        network,
+        since,
+        relay_host,
+        relay_port,
+        store,
+        keep,
+        render,
+        render_every,
+    } = Cli::parse();
+
+    let network_config = network.load()?.with_relay(relay_host, relay_port).config;
+
+    let multiverse: Multiverse<BlockId, StoredBlock> = match store {
+        Some(path) => Multiverse::open(path, STORE_DOMAIN, BlockNumber::MIN)?,
+        None => Multiverse::temporary()?,
+    };
+    // resume from the preferred tip of the stored multiverse
+    let stored_tip = multiverse
+        .preferred_fork_tip(BestBlockSelectionRule::LongestChain {
+            depth: 0,
+            age_gap: 0,
+        })
+        .map(|tip| tip.inner().clone());
+    let mut follower = Follower {
+        multiverse,
+        tip: stored_tip,
+        keep,
+    };
+
+    let mut pull_from: Vec<Point> = match since {
+        Some(since) => vec![since],
+        None => follower
+            .tip
+            .as_ref()
+            .and_then(|tip| follower.multiverse.get(tip))
+            .and_then(GetNextFrom::next_from)
+            .into_iter()
+            .collect(),
+    };
+
+    let mut source = CardanoSource::connect(&network_config, Duration::from_secs(20)).await?;
+
+    let mut followed = 0usize;
+    while let Some(event) = source.pull(&pull_from).await? {
+        let block = match event {
+            CardanoNetworkEvent::Block(block) => block,
+            CardanoNetworkEvent::Tip(_) => continue,
+        };
+        pull_from = vec![BlockHeader {
+            slot_nb: block.slot_number,
+            hash: block.id.clone(),
+        }];
+
+        println!(
+            "Block #{}, point: {}@{}",
+            block.block_number, block.id, block.slot_number,
+        );
+        let reorg = follower.reorg(&block);
+        if let Some(Reorg {
+            rolled_back,
+            ancestor,
+        }) = &reorg
+        {
+            let ancestor = ancestor
+                .as_ref()
+                .map_or_else(|| "a pruned block".to_string(), BlockId::to_string);
+            let rolled_back: Vec<String> = rolled_back.iter().map(BlockId::to_string).collect();
+            println!(
+                "Reorg of depth {} to {ancestor}, rolled back: [{}], new tip: {}",
+                rolled_back.len(),
+                rolled_back.join(", "),
+                block.id,
+            );
+        }
+        follower.insert(block)?;
+
+        followed += 1;
+        if reorg.is_some() || render_every.is_some_and(|every| followed.is_multiple_of(every)) {
+            follower.render(render);
+        }
+    }
+
+    Ok(())
+}
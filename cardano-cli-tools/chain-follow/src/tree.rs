@@ -0,0 +1,160 @@
+//! Render the fork tree of the multiverse
+//!
+//! The chains without fork are folded in one segment, only the first and
+//! the last block of a segment are shown.
+
+use dcspark_blockchain_source::cardano::{BlockEvent, CardanoNetworkEvent};
+use dcspark_core::BlockId;
+use multiverse::Multiverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::StoredBlock;
+
+/// number of characters of the block ids shown in the tree
+const SHORT_ID_LENGTH: usize = 8;
+
+/// a chain of blocks without fork, followed by the forks
+pub struct Segment<'a> {
+    blocks: Vec<&'a BlockEvent>,
+    children: Vec<Segment<'a>>,
+}
+
+impl<'a> Segment<'a> {
+    fn build(first: &'a BlockEvent, children: &HashMap<&BlockId, Vec<&'a BlockEvent>>) -> Self {
+        let mut blocks = vec![first];
+        loop {
+            let last = *blocks.last().expect("a segment has at least one block");
+            match children.get(&last.id).map(Vec::as_slice) {
+                Some([child]) => blocks.push(*child),
+                _ => break,
+            }
+        }
+
+        let last = *blocks.last().expect("a segment has at least one block");
+        let children = children
+            .get(&last.id)
+            .into_iter()
+            .flatten()
+            .map(|child| Segment::build(*child, children))
+            .collect();
+        Segment { blocks, children }
+    }
+
+    fn first(&self) -> &BlockEvent {
+        self.blocks
+            .first()
+            .expect("a segment has at least one block")
+    }
+
+    fn last(&self) -> &BlockEvent {
+        self.blocks
+            .last()
+            .expect("a segment has at least one block")
+    }
+
+    fn label(&self, tip: Option<&BlockId>) -> String {
+        let first = self.first();
+        let last = self.last();
+        let mut label = if self.blocks.len() == 1 {
+            format!("#{} {}", first.block_number, short(&first.id))
+        } else {
+            format!(
+                "#{} {} .. #{} {} ({} blocks)",
+                first.block_number,
+                short(&first.id),
+                last.block_number,
+                short(&last.id),
+                self.blocks.len()
+            )
+        };
+        if tip == Some(&last.id) {
+            label.push_str(" [tip]");
+        }
+        label
+    }
+}
+
+fn short(id: &BlockId) -> String {
+    id.to_string().chars().take(SHORT_ID_LENGTH).collect()
+}
+
+/// the segments starting at the roots of the multiverse
+pub fn fork_tree(multiverse: &Multiverse<BlockId, StoredBlock>) -> Vec<Segment<'_>> {
+    let blocks: Vec<&BlockEvent> = multiverse
+        .iter()
+        .filter_map(|event| match event {
+            CardanoNetworkEvent::Block(block) => Some(block),
+            CardanoNetworkEvent::Tip(_) => None,
+        })
+        .collect();
+
+    let mut roots = Vec::new();
+    let mut children: HashMap<&BlockId, Vec<&BlockEvent>> = HashMap::new();
+    for block in blocks {
+        if multiverse.contains(&block.parent_id) {
+            children.entry(&block.parent_id).or_default().push(block);
+        } else {
+            roots.push(block);
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|root| Segment::build(root, &children))
+        .collect()
+}
+
+/// the tree drawn with box-drawing characters, `tip` is marked
+pub fn ascii(roots: &[Segment], tip: Option<&BlockId>) -> String {
+    fn draw(out: &mut String, segment: &Segment, tip: Option<&BlockId>, prefix: &str, last: bool) {
+        let (branch, indent) = if last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        let _ = writeln!(out, "{prefix}{branch}{}", segment.label(tip));
+
+        let prefix = format!("{prefix}{indent}");
+        for (index, child) in segment.children.iter().enumerate() {
+            draw(
+                out,
+                child,
+                tip,
+                &prefix,
+                index + 1 == segment.children.len(),
+            );
+        }
+    }
+
+    let mut out = String::new();
+    for (index, root) in roots.iter().enumerate() {
+        draw(&mut out, root, tip, "", index + 1 == roots.len());
+    }
+    out
+}
+
+/// the tree in the graphviz DOT format, one node per segment
+pub fn dot(roots: &[Segment], tip: Option<&BlockId>) -> String {
+    fn draw(out: &mut String, segment: &Segment, tip: Option<&BlockId>) {
+        let id = &segment.last().id;
+        let style = if tip == Some(id) { ", style=bold" } else { "" };
+        let _ = writeln!(
+            out,
+            "  \"{id}\" [label=\"{}\"{style}];",
+            segment.label(None)
+        );
+
+        for child in segment.children.iter() {
+            let _ = writeln!(out, "  \"{id}\" -> \"{}\";", child.last().id);
+            draw(out, child, tip);
+        }
+    }
+
+    let mut out = String::from("digraph forks {\n  rankdir=LR;\n  node [shape=box];\n");
+    for root in roots {
+        draw(&mut out, root, tip);
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,251 @@
+//! The backends telling which addresses are used
+
+use anyhow::{anyhow, bail, Context};
+use network_profiles::NetworkProfile;
+use reqwest::{blocking::Client, header::CONTENT_TYPE, StatusCode};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// maximum number of addresses carp accepts in one `/address/used` request
+const CARP_ADDRESS_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Carp,
+    Blockfrost,
+    Koios,
+}
+
+impl FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "carp" => Ok(BackendKind::Carp),
+            "blockfrost" => Ok(BackendKind::Blockfrost),
+            "koios" => Ok(BackendKind::Koios),
+            _ => bail!("Invalid backend. Should be either carp, blockfrost or koios."),
+        }
+    }
+}
+
+impl BackendKind {
+    /// the url of the backend in the network `profile`, carp only has
+    /// public instances for mainnet, preprod and preview
+    pub fn default_base_url(&self, profile: &NetworkProfile) -> anyhow::Result<String> {
+        let url = match self {
+            BackendKind::Carp => match profile.name.as_str() {
+                network @ ("mainnet" | "preprod" | "preview") => {
+                    Some(format!("https://gate.flint-wallet.com/{network}/carp"))
+                }
+                _ => None,
+            },
+            BackendKind::Blockfrost => profile.blockfrost_url.clone(),
+            BackendKind::Koios => profile.koios_url.clone(),
+        };
+        url.ok_or_else(|| {
+            anyhow!(
+                "no {self:?} instance for {}, set --backend-url",
+                profile.name
+            )
+        })
+    }
+}
+
+pub trait UsedAddressBackend {
+    /// the used addresses among `addresses`
+    fn used(&self, addresses: &[String]) -> anyhow::Result<HashSet<String>>;
+}
+
+pub struct Carp {
+    client: Client,
+    base_url: String,
+    until_block: String,
+}
+
+impl Carp {
+    /// connect to the carp backend at `base_url`, the addresses are checked
+    /// until its latest block
+    pub fn connect(base_url: String) -> anyhow::Result<Self> {
+        let client = Client::new();
+
+        let latest = client
+            .post(format!("{base_url}{}", "/block/latest"))
+            .header(CONTENT_TYPE, "application/json")
+            .body(r#"{"offset": 0}"#)
+            .send()
+            .context("couldn't send /block/latest request")?;
+
+        let latest: BlockLatestResponse =
+            miniserde::json::from_str(&latest.text().context("failed to latest block")?)
+                .context("couldn't parse /block/latest response")?;
+
+        Ok(Self {
+            client,
+            base_url,
+            until_block: latest.block.hash,
+        })
+    }
+}
+
+impl UsedAddressBackend for Carp {
+    fn used(&self, addresses: &[String]) -> anyhow::Result<HashSet<String>> {
+        let mut used = HashSet::new();
+        for addresses in addresses.chunks(CARP_ADDRESS_LIMIT) {
+            let result = self
+                .client
+                .post(format!("{}{}", self.base_url, "/address/used"))
+                .header(CONTENT_TYPE, "application/json")
+                .body(miniserde::json::to_string(&AddressUsed {
+                    addresses: addresses.to_vec(),
+                    until_block: self.until_block.clone(),
+                }))
+                .send()
+                .context("couldn't send request to /address/used")?;
+
+            if !result.status().is_success() {
+                bail!("error checking in the backend if the addresses are used");
+            }
+
+            let result = miniserde::json::from_str::<AddressUsedResult>(
+                &result
+                    .text()
+                    .context("failed to read /address/used response")?,
+            )
+            .context("couldn't parse /address/used response")?;
+            used.extend(result.addresses);
+        }
+        Ok(used)
+    }
+}
+
+/// blockfrost answers `404` for the addresses never seen on chain
+pub struct Blockfrost {
+    client: Client,
+    base_url: String,
+    project_id: String,
+}
+
+impl Blockfrost {
+    pub fn new(base_url: String, project_id: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            project_id,
+        }
+    }
+}
+
+impl UsedAddressBackend for Blockfrost {
+    fn used(&self, addresses: &[String]) -> anyhow::Result<HashSet<String>> {
+        let mut used = HashSet::new();
+        for address in addresses {
+            let result = self
+                .client
+                .get(format!("{}/addresses/{address}/total", self.base_url))
+                .header("project_id", &self.project_id)
+                .send()
+                .context("couldn't send request to /addresses/total")?;
+
+            match result.status() {
+                StatusCode::NOT_FOUND => {}
+                status if status.is_success() => {
+                    used.insert(address.clone());
+                }
+                status => bail!("error checking in blockfrost if {address} is used: {status}"),
+            }
+        }
+        Ok(used)
+    }
+}
+
+/// koios lists the transactions of an address, an address without
+/// transaction is unused
+pub struct Koios {
+    client: Client,
+    base_url: String,
+}
+
+impl Koios {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+        }
+    }
+}
+
+impl UsedAddressBackend for Koios {
+    fn used(&self, addresses: &[String]) -> anyhow::Result<HashSet<String>> {
+        let mut used = HashSet::new();
+        for address in addresses {
+            let result = self
+                .client
+                .post(format!("{}{}", self.base_url, "/address_txs"))
+                .header(CONTENT_TYPE, "application/json")
+                .body(miniserde::json::to_string(&AddressTxsRequest {
+                    addresses: vec![address.clone()],
+                }))
+                .send()
+                .context("couldn't send request to /address_txs")?;
+
+            if !result.status().is_success() {
+                bail!("error checking in koios if {address} is used");
+            }
+
+            let txs = miniserde::json::from_str::<Vec<AddressTx>>(
+                &result
+                    .text()
+                    .context("failed to read /address_txs response")?,
+            )
+            .context("couldn't parse /address_txs response")?;
+            if !txs.is_empty() {
+                used.insert(address.clone());
+            }
+        }
+        Ok(used)
+    }
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct AddressUsed {
+    addresses: Vec<String>,
+    #[serde(rename = "untilBlock")]
+    until_block: String,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct AddressUsedAfter {
+    tx: String,
+    block: String,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct AddressUsedResult {
+    addresses: Vec<String>,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct BlockLatestResponse {
+    block: BlockLatestResponseBlock,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct BlockLatestResponseBlock {
+    era: u64,
+    hash: String,
+    height: u64,
+    epoch: u64,
+    slot: u64,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct AddressTxsRequest {
+    #[serde(rename = "_addresses")]
+    addresses: Vec<String>,
+}
+
+#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
+struct AddressTx {
+    tx_hash: String,
+}
@@ -1,71 +1,234 @@
-//! Tool to derivate the first cardano address from a public key
+//! Tool to derivate the cardano addresses of a public key
 //!
-//! Also perform a check on the carp backend to see if the address is in use.
+//! Also perform a check on a backend (carp, blockfrost or koios) to see which
+//! addresses are in use: the external and internal chains are scanned until
+//! `gap_limit` consecutive addresses are unused.
+//!
+//! With `--stake`, the reward address is derived instead, and its delegation
+//! and rewards are looked up on koios: carp does not index the rewards.
+//!
+//! The network is a profile of the `network-profiles` crate, selected with
+//! `--network` or `DCSPARK_NETWORK`, like the other cardano-cli-tools.
+mod backend;
+
 use anyhow::{anyhow, bail, Context};
+use backend::{BackendKind, Blockfrost, Carp, Koios, UsedAddressBackend};
 use cardano_multiplatform_lib::{
-    address::{BaseAddress, StakeCredential},
+    address::{BaseAddress, RewardAddress, StakeCredential},
     crypto::Bip32PublicKey,
 };
+use clap::Parser;
+use network_profiles::{NetworkArgs, NetworkProfile};
 use reqwest::{blocking::Client, header::CONTENT_TYPE};
-use std::str::FromStr;
-use structopt::StructOpt;
 
 const STAKING_KEY_INDEX: u32 = 0;
 const EXTERNAL: u32 = 0;
+const INTERNAL: u32 = 1;
 const CHIMERIC_ACCOUNT_DERIVATION: u32 = 2;
 
-#[derive(Debug)]
-#[repr(u8)]
-enum NetworkId {
-    Testnet = 0,
-    Mainnet = 1,
+#[derive(Debug, Parser)]
+#[clap(version)]
+/// Perform derivation of the input public key, then generate the cardano base addresses
+/// corresponding to it, and check in the backend which addresses are in use.
+///
+/// The external and internal chains are scanned until `gap-limit` consecutive addresses
+/// are unused, the used addresses and the first unused address of each chain are reported.
+///
+/// With `--stake`, print the first address and the reward address, then the delegation
+/// status and the withdrawable rewards of the reward address.
+///
+/// With `--offline`, only print the derived addresses: the first `gap-limit` addresses of
+/// each chain, or the first address and the reward address with `--stake`.
+///
+/// # Usage:
+///
+/// cargo run --bin cip1852-first-address -- --network mainnet [--stake] [--offline] --backend carp --gap-limit 20 --public-key 00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000
+struct Opt {
+    #[clap(short, long, value_parser)]
+    /// root public key of the derivation tree in hex format
+    public_key: String,
+
+    #[clap(flatten)]
+    network: NetworkArgs,
+
+    #[clap(short, long, value_parser, default_value = "20")]
+    /// number of consecutive unused addresses ending the scan of a chain
+    gap_limit: u32,
+
+    #[clap(long)]
+    /// look up the reward address instead of scanning the chains
+    stake: bool,
+
+    #[clap(short, long, value_parser, default_value = "carp")]
+    /// backend checking the addresses: carp, blockfrost or koios
+    backend: BackendKind,
+
+    #[clap(long, value_parser)]
+    /// base url of the backend, defaults to the one of the network profile
+    backend_url: Option<String>,
+
+    #[clap(long, value_parser, env = "BLOCKFROST_PROJECT_ID")]
+    /// project id of the blockfrost backend
+    blockfrost_project_id: Option<String>,
+
+    #[clap(long)]
+    /// only print the derived addresses, without querying any backend
+    offline: bool,
 }
 
-impl FromStr for NetworkId {
-    type Err = anyhow::Error;
+impl Opt {
+    fn backend_url(&self, profile: &NetworkProfile) -> anyhow::Result<String> {
+        match &self.backend_url {
+            Some(url) => Ok(url.clone()),
+            None => self.backend.default_base_url(profile),
+        }
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "mainnet" => Ok(NetworkId::Mainnet),
-            "testnet" => Ok(NetworkId::Testnet),
-            _ => bail!("Invalid network id. Should be either mainnet or testnet."),
+    fn connect(&self, profile: &NetworkProfile) -> anyhow::Result<Box<dyn UsedAddressBackend>> {
+        let base_url = self.backend_url(profile)?;
+        Ok(match self.backend {
+            BackendKind::Carp => Box::new(Carp::connect(base_url)?),
+            BackendKind::Blockfrost => {
+                let project_id = self
+                    .blockfrost_project_id
+                    .clone()
+                    .context("the blockfrost backend requires a project id")?;
+                Box::new(Blockfrost::new(base_url, project_id))
+            }
+            BackendKind::Koios => Box::new(Koios::new(base_url)),
+        })
+    }
+
+    /// koios url of the rewards lookup: the backend url if the backend is koios
+    fn koios_url(&self, profile: &NetworkProfile) -> anyhow::Result<String> {
+        match self.backend {
+            BackendKind::Koios => self.backend_url(profile),
+            _ => BackendKind::Koios.default_base_url(profile),
         }
     }
 }
 
-impl std::fmt::Display for NetworkId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let as_str = match self {
-            NetworkId::Testnet => "testnet",
-            NetworkId::Mainnet => "mainnet",
-        };
+/// the base address at `index` of the `chain` (external or internal) of the account
+fn derive_address(
+    account: &Bip32PublicKey,
+    chain: u32,
+    index: u32,
+    staking: &StakeCredential,
+    network_id: u8,
+) -> anyhow::Result<String> {
+    let spending = account
+        .derive(chain)
+        .map_err(|e| anyhow!("couldn't derive chain {chain}: {e}"))?
+        .derive(index)
+        .map_err(|e| anyhow!("couldn't derive address {index}: {e}"))?;
+
+    BaseAddress::new(
+        network_id,
+        &StakeCredential::from_keyhash(&spending.to_raw_key().hash()),
+        staking,
+    )
+    .to_address()
+    .to_bech32(None)
+    .map_err(|e| anyhow!("couldn't encode address {index}: {e}"))
+}
+
+/// print the delegation status and the withdrawable rewards of the reward address
+fn lookup_rewards(koios_url: &str, reward_address: String) -> anyhow::Result<()> {
+    let result = Client::new()
+        .post(format!("{koios_url}{}", "/account_info"))
+        .header(CONTENT_TYPE, "application/json")
+        .body(miniserde::json::to_string(&AccountInfoRequest {
+            stake_addresses: vec![reward_address],
+        }))
+        .send()
+        .context("couldn't send request to /account_info")?;
+
+    if !result.status().is_success() {
+        bail!("error looking up the reward address in the backend");
+    }
+
+    let accounts = miniserde::json::from_str::<Vec<AccountInfo>>(
+        &result
+            .text()
+            .context("failed to read /account_info response")?,
+    )
+    .context("couldn't parse /account_info response")?;
 
-        write!(f, "{as_str}")
+    match accounts.into_iter().next() {
+        None => println!("status:\n never registered"),
+        Some(account) => {
+            println!("status:\n {}", account.status);
+            println!(
+                "delegated to:\n {}",
+                account.delegated_pool.as_deref().unwrap_or("none")
+            );
+            println!("withdrawable rewards:\n {}", account.rewards_available);
+        }
     }
+
+    Ok(())
 }
 
-#[derive(Debug, StructOpt)]
-/// Perform derivation of the input public key, then generate the cardano base address
-/// corresponding to it, and check in the carp backend if the address is in use.
-///
-/// # Usage:
-///
-/// cargo run --bin cip1852-first-address -- --network mainnet --public-key 00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000
-struct Opt {
-    #[structopt(short, long)]
-    /// root public key of the derivation tree in hex format
-    public_key: String,
+struct ChainScan {
+    /// the used addresses, with their index
+    used: Vec<(u32, String)>,
+    /// the first address after the last used address, with its index
+    first_unused: (u32, String),
+}
+
+/// scan the `chain` by batches of `gap_limit` addresses until `gap_limit`
+/// consecutive addresses are unused
+fn scan_chain(
+    backend: &dyn UsedAddressBackend,
+    account: &Bip32PublicKey,
+    chain: u32,
+    staking: &StakeCredential,
+    network_id: u8,
+    gap_limit: u32,
+) -> anyhow::Result<ChainScan> {
+    let mut used = Vec::new();
+    let mut next_unused = 0;
+    let mut start = 0;
+
+    loop {
+        let end = start
+            .checked_add(gap_limit)
+            .ok_or_else(|| anyhow!("no unused address found in chain {chain}"))?;
+        let addresses = (start..end)
+            .map(|index| derive_address(account, chain, index, staking, network_id))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let used_in_batch = backend.used(&addresses)?;
+
+        for (index, address) in (start..end).zip(addresses) {
+            if used_in_batch.contains(&address) {
+                used.push((index, address));
+                next_unused = index + 1;
+            }
+        }
+
+        if end - next_unused >= gap_limit {
+            break;
+        }
+        start = end;
+    }
 
-    #[structopt(short, long)]
-    network: NetworkId,
+    let first_unused = (
+        next_unused,
+        derive_address(account, chain, next_unused, staking, network_id)?,
+    );
+    Ok(ChainScan { used, first_unused })
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    let opt = Opt::from_args();
+    let opt = Opt::parse();
+    let profile = opt.network.load()?;
+    let network_id = profile.config.chain_info.network_id();
 
-    let carp_base_url = format!("https://gate.flint-wallet.com/{}/carp", opt.network);
+    if opt.gap_limit == 0 {
+        bail!("the gap limit should be at least 1");
+    }
 
-    let pk = hex::decode(opt.public_key)
+    let pk = hex::decode(&opt.public_key)
         .context("public key should be a valid hex string")
         .and_then(|bytes| {
             Bip32PublicKey::from_bytes(&bytes).map_err(|_| anyhow!("invalid public key"))
@@ -75,91 +238,76 @@ fn main() -> Result<(), anyhow::Error> {
         .derive(CHIMERIC_ACCOUNT_DERIVATION)
         .and_then(|pk| pk.derive(STAKING_KEY_INDEX))
         .map_err(|e| anyhow!("couldn't derive staking key. Reason {e}"))?;
+    let staking = StakeCredential::from_keyhash(&staking_key.to_raw_key().hash());
 
-    let spending = pk
-        .derive(EXTERNAL)
-        .map_err(|e| anyhow!("couldn't derive external tree: {e}"))?
-        .derive(0)
-        .map_err(|e| anyhow!("couldn't derive first address: {e}"))?;
-
-    let base_address = BaseAddress::new(
-        opt.network as u8,
-        &StakeCredential::from_keyhash(&spending.to_raw_key().hash()),
-        &StakeCredential::from_keyhash(&staking_key.to_raw_key().hash()),
-    );
-
-    println!(
-        "checking backend for address:\n {}",
-        base_address.to_address().to_bech32(None).unwrap()
-    );
-
-    let client = Client::new();
-
-    let latest = client
-        .post(format!("{carp_base_url}{}", "/block/latest"))
-        .header(CONTENT_TYPE, "application/json")
-        .body(r#"{"offset": 0}"#)
-        .send()
-        .context("couldn't send /block/latest request")?;
-
-    let latest: BlockLatestResponse =
-        miniserde::json::from_str(&latest.text().context("failed to latest block")?)
-            .context("couldn't parse /block/latest response")?;
+    if opt.stake {
+        let reward_address = RewardAddress::new(network_id, &staking)
+            .to_address()
+            .to_bech32(None)
+            .map_err(|e| anyhow!("couldn't encode reward address: {e}"))?;
 
-    let result = client
-        .post(format!("{carp_base_url}{}", "/address/used"))
-        .header(CONTENT_TYPE, "application/json")
-        .body(miniserde::json::to_string(&AddressUsed {
-            addresses: vec![base_address.to_address().to_bech32(None).unwrap()],
-            until_block: latest.block.hash,
-        }))
-        .send()
-        .context("couldn't send request to /address/used")?;
+        println!(
+            "first address:\n {}",
+            derive_address(&pk, EXTERNAL, 0, &staking, network_id)?
+        );
+        println!("reward address:\n {reward_address}");
 
-    if !result.status().is_success() {
-        bail!("error checking in the backend if the address is used");
+        if opt.offline {
+            return Ok(());
+        }
+        return lookup_rewards(&opt.koios_url(&profile)?, reward_address);
     }
 
-    let result = miniserde::json::from_str::<AddressUsedResult>(&result.text().unwrap())
-        .context("couldn't parse /address/used response")?;
-
-    if !result.addresses.is_empty() {
-        println!("result:\n used");
-    } else {
-        println!("result:\n unused");
+    if opt.offline {
+        for (name, chain) in [("external", EXTERNAL), ("internal", INTERNAL)] {
+            println!("{name} chain:");
+            for index in 0..opt.gap_limit {
+                let address = derive_address(&pk, chain, index, &staking, network_id)?;
+                println!(" #{index}: {address}");
+            }
+        }
+        return Ok(());
     }
 
-    Ok(())
-}
+    let backend = opt.connect(&profile)?;
 
-#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
-struct AddressUsed {
-    addresses: Vec<String>,
-    #[serde(rename = "untilBlock")]
-    until_block: String,
-}
+    for (name, chain) in [("external", EXTERNAL), ("internal", INTERNAL)] {
+        println!(
+            "scanning {name} chain with a gap limit of {}:",
+            opt.gap_limit
+        );
 
-#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
-struct AddressUsedAfter {
-    tx: String,
-    block: String,
-}
+        let scan = scan_chain(
+            backend.as_ref(),
+            &pk,
+            chain,
+            &staking,
+            network_id,
+            opt.gap_limit,
+        )?;
+        if scan.used.is_empty() {
+            println!(" no used address");
+        }
+        for (index, address) in scan.used {
+            println!(" used #{index}: {address}");
+        }
+        let (index, address) = scan.first_unused;
+        println!(" first unused #{index}: {address}");
+    }
 
-#[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
-struct AddressUsedResult {
-    addresses: Vec<String>,
+    Ok(())
 }
 
 #[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
-struct BlockLatestResponse {
-    block: BlockLatestResponseBlock,
+struct AccountInfoRequest {
+    #[serde(rename = "_stake_addresses")]
+    stake_addresses: Vec<String>,
 }
 
 #[derive(miniserde::Serialize, miniserde::Deserialize, Debug)]
-struct BlockLatestResponseBlock {
-    era: u64,
-    hash: String,
-    height: u64,
-    epoch: u64,
-    slot: u64,
+struct AccountInfo {
+    status: String,
+    delegated_pool: Option<String>,
+    /// lovelaces, as a decimal string
+    rewards_available: String,
 }
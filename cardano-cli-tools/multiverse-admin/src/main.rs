@@ -0,0 +1,257 @@
+//! Inspect and maintain the persisted multiverses
+//!
+//! The multiverses of blocks stored by the cardano-net-fetcher and the
+//! chain-follow tools are kept in a sled database, one domain (sled tree)
+//! per multiverse:
+//!
+//! ```text
+//! $ multiverse-admin --db ./blocks.sled domains
+//! blocks: 2160 entries
+//! size on disk: 3145728 bytes
+//! $ multiverse-admin --db ./blocks.sled info
+//! entries: 2160, from #9250001 to #9252160
+//! roots: 1
+//!   #9250001 <block_id>
+//! tips: 2
+//!   ...
+//! ```
+//!
+//! The database is locked by sled, the process using it has to be stopped
+//! first.
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use dcspark_blockchain_source::cardano::{BlockEvent, CardanoNetworkEvent, Tip};
+use dcspark_core::{BlockId, BlockNumber};
+use multiverse::{EntryRef, Multiverse, Variant, Verification};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// the sled tree of the stored blocks
+const STORE_DOMAIN: &str = "blocks";
+
+/// the tree sled creates in every database
+const SLED_DEFAULT_TREE: &[u8] = b"__sled__default";
+
+/// number of characters of the block ids shown in the graph
+const SHORT_ID_LENGTH: usize = 8;
+
+type StoredBlock = CardanoNetworkEvent<BlockEvent, Tip>;
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// path of the sled database
+    #[clap(long, value_parser)]
+    pub db: PathBuf,
+    /// the multiverse to work on
+    #[clap(long, value_parser, default_value = STORE_DOMAIN)]
+    pub domain: String,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// list the domains of the database
+    Domains,
+    /// show the size, the roots and the tips of the multiverse
+    Info,
+    /// write the entries as NDJSON records, ordered by block number
+    Export {
+        /// write to the file instead of stdout
+        #[clap(long, value_parser)]
+        output: Option<PathBuf>,
+    },
+    /// remove the entries below the block number
+    Prune {
+        #[clap(long, value_parser)]
+        below: u64,
+    },
+    /// check every entry can be loaded
+    Verify {
+        /// remove the corrupted entries
+        #[clap(long, action)]
+        repair: bool,
+    },
+    /// print the fork graph in the graphviz DOT format
+    Dot,
+}
+
+/// sled creates the missing trees on open
+fn check_domain(db: &sled::Db, domain: &str) -> anyhow::Result<()> {
+    if !db.tree_names().iter().any(|name| name == domain.as_bytes()) {
+        bail!("no domain {domain} in the database");
+    }
+    Ok(())
+}
+
+fn load(db: sled::Db, domain: &str) -> anyhow::Result<Multiverse<BlockId, StoredBlock>> {
+    check_domain(&db, domain)?;
+    Multiverse::load_from(db, domain, BlockNumber::MIN)
+        .with_context(|| format!("couldn't load {domain}, try the verify command"))
+}
+
+fn describe(
+    multiverse: &Multiverse<BlockId, StoredBlock>,
+    ids: impl IntoIterator<Item = Arc<BlockId>>,
+) -> Vec<String> {
+    let mut entries: Vec<(BlockNumber, String)> = ids
+        .into_iter()
+        .filter_map(|id| multiverse.get(&id))
+        .map(|entry| {
+            (
+                entry.block_number(),
+                format!("#{} {}", entry.block_number(), entry.id()),
+            )
+        })
+        .collect();
+    entries.sort();
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn domains(db: &sled::Db) -> anyhow::Result<()> {
+    for name in db.tree_names() {
+        if name == SLED_DEFAULT_TREE {
+            continue;
+        }
+        let entries = db.open_tree(&name)?.len();
+        println!("{}: {entries} entries", String::from_utf8_lossy(&name));
+    }
+    println!("size on disk: {} bytes", db.size_on_disk()?);
+    Ok(())
+}
+
+fn info(multiverse: &Multiverse<BlockId, StoredBlock>) {
+    match (multiverse.iter().next(), multiverse.iter().last()) {
+        (Some(first), Some(last)) => println!(
+            "entries: {}, from #{} to #{}",
+            multiverse.len(),
+            first.block_number(),
+            last.block_number()
+        ),
+        _ => println!("entries: 0"),
+    }
+
+    let roots = describe(multiverse, multiverse.roots());
+    println!("roots: {}", roots.len());
+    for root in roots {
+        println!("  {root}");
+    }
+    let tips = describe(multiverse, multiverse.tips());
+    println!("tips: {}", tips.len());
+    for tip in tips {
+        println!("  {tip}");
+    }
+}
+
+fn export(
+    multiverse: &Multiverse<BlockId, StoredBlock>,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    for entry in multiverse.iter() {
+        serde_json::to_writer(&mut out, entry)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn prune(mut multiverse: Multiverse<BlockId, StoredBlock>, below: u64) -> anyhow::Result<()> {
+    let below = BlockNumber::new(below);
+    let pruned: Vec<EntryRef<BlockId>> = multiverse
+        .iter()
+        .take_while(|entry| entry.block_number() < below)
+        .map(|entry| EntryRef::new(entry.id().clone()))
+        .collect();
+    for entry in pruned.iter() {
+        multiverse.remove(entry)?;
+    }
+    println!("pruned {} entries, {} left", pruned.len(), multiverse.len());
+    Ok(())
+}
+
+fn verify(db: &sled::Db, domain: &str, repair: bool) -> anyhow::Result<()> {
+    check_domain(db, domain)?;
+    let Verification { entries, corrupted } =
+        Multiverse::<BlockId, StoredBlock>::verify(db, domain, repair)?;
+    for key in corrupted.iter() {
+        println!("corrupted entry {}", hex::encode(key));
+    }
+    println!("{entries} entries, {} corrupted", corrupted.len());
+
+    match (corrupted.is_empty(), repair) {
+        (true, _) => Ok(()),
+        (false, true) => {
+            println!("removed the corrupted entries");
+            Ok(())
+        }
+        (false, false) => {
+            bail!("the multiverse is corrupted, run with --repair to remove the entries")
+        }
+    }
+}
+
+fn short(id: &BlockId) -> String {
+    id.to_string().chars().take(SHORT_ID_LENGTH).collect()
+}
+
+fn dot(multiverse: &Multiverse<BlockId, StoredBlock>) -> String {
+    let tips = multiverse.tips();
+    let mut out = String::from("digraph multiverse {\n  rankdir=LR;\n  node [shape=box];\n");
+    for entry in multiverse.iter() {
+        let id = entry.id();
+        let style = if tips.contains(id) {
+            ", style=bold"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "  \"{id}\" [label=\"#{} {}\"{style}];",
+            entry.block_number(),
+            short(id)
+        );
+        if multiverse.contains(entry.parent_id()) {
+            let _ = writeln!(out, "  \"{}\" -> \"{id}\";", entry.parent_id());
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn main() -> anyhow::Result<()> {
+    let Cli {
+        db,
+        domain,
+        command,
+    } = Cli::parse();
+
+    let db = sled::Config::new().path(&db).open().with_context(|| {
+        format!(
+            "couldn't open {}, is it used by another process?",
+            db.display()
+        )
+    })?;
+
+    match command {
+        Command::Domains => domains(&db),
+        Command::Info => {
+            info(&load(db, &domain)?);
+            Ok(())
+        }
+        Command::Export { output } => export(&load(db, &domain)?, output),
+        Command::Prune { below } => prune(load(db, &domain)?, below),
+        Command::Verify { repair } => verify(&db, &domain, repair),
+        Command::Dot => {
+            print!("{}", dot(&load(db, &domain)?));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Network profiles shared by the cardano-cli-tools
+//!
+//! A profile names a network: the configuration of the [`CardanoSource`]
+//! (protocol magic, relays, era) and the public instances of the backends.
+//! The built-in profiles are mainnet, preprod, preview and sancho. The
+//! configuration file (`--config` or `DCSPARK_CONFIG`) can override them
+//! and add custom networks, the profile is selected with `--network` or
+//! `DCSPARK_NETWORK`:
+//!
+//! ```yaml
+//! networks:
+//!   mainnet:
+//!     relays:
+//!       - host: relay.example.com
+//!         port: 3001
+//!   devnet:
+//!     shelley_genesis: ./shelley-genesis.json
+//!     genesis: 20,6a7d97aae2a65ca790fd14802808b7fce00a3362bd7b21c4ed4ccb4296783b98
+//!     genesis_parent: 785eb88427e136378a15b0a152a8bfbeec7a611529ccda29c43a1e60ffb48eaa
+//!     relays:
+//!       - host: localhost
+//!         port: 3001
+//!     koios_url: http://localhost:8053/api/v1
+//! ```
+//!
+//! A custom network is configured with [`NetworkConfiguration::from_genesis`],
+//! the path of the genesis is relative to the configuration file.
+//!
+//! [`CardanoSource`]: dcspark_blockchain_source::cardano::CardanoSource
+
+use anyhow::{anyhow, bail, Context};
+use dcspark_blockchain_source::cardano::time::Era;
+use dcspark_blockchain_source::cardano::{NetworkConfiguration, Point};
+use dcspark_core::BlockId;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// the network profile to use, flatten it in the command line of the tools
+#[derive(clap::Args, Debug)]
+pub struct NetworkArgs {
+    /// yaml file of the network profiles
+    #[clap(long, value_parser, env = "DCSPARK_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// mainnet, preprod, preview, sancho, or a profile of the configuration file
+    #[clap(long, value_parser, env = "DCSPARK_NETWORK", default_value = "mainnet")]
+    pub network: String,
+}
+
+impl NetworkArgs {
+    pub fn load(&self) -> anyhow::Result<NetworkProfile> {
+        let profiles = match &self.config {
+            Some(path) => Profiles::load(path)?,
+            None => Profiles::default(),
+        };
+        profiles.profile(&self.network)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Relay {
+    pub host: String,
+    pub port: u16,
+}
+
+/// a resolved network profile
+#[derive(Debug, Clone)]
+pub struct NetworkProfile {
+    pub name: String,
+    /// configuration of the source, connecting to the first relay
+    pub config: NetworkConfiguration,
+    pub relays: Vec<(Cow<'static, str>, u16)>,
+    pub blockfrost_url: Option<String>,
+    pub koios_url: Option<String>,
+}
+
+impl NetworkProfile {
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (config, public_name) = match name {
+            "mainnet" => (NetworkConfiguration::mainnet(), Some("mainnet")),
+            "preprod" => (NetworkConfiguration::preprod(), Some("preprod")),
+            "preview" => (NetworkConfiguration::preview(), Some("preview")),
+            "sancho" => (NetworkConfiguration::sancho(), None),
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            relays: vec![config.relay.clone()],
+            config,
+            blockfrost_url: public_name
+                .map(|network| format!("https://cardano-{network}.blockfrost.io/api/v0")),
+            koios_url: public_name.map(|network| match network {
+                "mainnet" => "https://api.koios.rest/api/v1".to_string(),
+                network => format!("https://{network}.koios.rest/api/v1"),
+            }),
+        })
+    }
+
+    /// replace the first relay by the one given on the command line
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_relay(mut self, host: Option<String>, port: Option<u16>) -> Self {
+        let relay = (
+            host.map(Cow::from).unwrap_or(self.config.relay.0),
+            port.unwrap_or(self.config.relay.1),
+        );
+        self.config.relay = relay.clone();
+        match self.relays.first_mut() {
+            Some(first) => *first = relay,
+            None => self.relays.push(relay),
+        }
+        self
+    }
+
+    fn apply(mut self, profile: ProfileConfig) -> anyhow::Result<Self> {
+        if !profile.relays.is_empty() {
+            self.relays = profile
+                .relays
+                .into_iter()
+                .map(|relay| (Cow::from(relay.host), relay.port))
+                .collect();
+            self.config.relay = self.relays[0].clone();
+        }
+        if let Some(from) = profile.from {
+            self.config.from = from.parse()?;
+        }
+        if let Some(era) = profile.era {
+            self.config.shelley_era_config = era;
+        }
+        if let Some(url) = profile.blockfrost_url {
+            self.blockfrost_url = Some(url);
+        }
+        if let Some(url) = profile.koios_url {
+            self.koios_url = Some(url);
+        }
+        Ok(self)
+    }
+}
+
+/// a profile of the configuration file, every field is optional for the
+/// built-in profiles
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileConfig {
+    #[serde(default)]
+    relays: Vec<Relay>,
+    /// the point the chain is fetched from by default, `slot,block_hash`
+    from: Option<String>,
+    era: Option<Era>,
+    shelley_genesis: Option<PathBuf>,
+    /// the first block of a custom network, `slot,block_hash`
+    genesis: Option<String>,
+    genesis_parent: Option<String>,
+    blockfrost_url: Option<String>,
+    koios_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profiles {
+    #[serde(default)]
+    networks: HashMap<String, ProfileConfig>,
+    /// the directory of the configuration file
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl Profiles {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to read the configuration {}", path.display()))?;
+        let mut profiles: Self = serde_yaml::from_reader(file)
+            .with_context(|| format!("invalid configuration {}", path.display()))?;
+        profiles.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(profiles)
+    }
+
+    /// the profile `name`, a built-in one overridden by the configuration
+    /// file or a custom one
+    pub fn profile(mut self, name: &str) -> anyhow::Result<NetworkProfile> {
+        let profile = self.networks.remove(name);
+        match (NetworkProfile::builtin(name), profile) {
+            (Some(builtin), None) => Ok(builtin),
+            (Some(builtin), Some(profile)) if profile.shelley_genesis.is_none() => {
+                builtin.apply(profile)
+            }
+            (_, Some(profile)) => self.custom(name, profile),
+            (None, None) => bail!("unknown network {name}, it is neither built-in nor configured"),
+        }
+    }
+
+    fn custom(&self, name: &str, mut profile: ProfileConfig) -> anyhow::Result<NetworkProfile> {
+        let missing = |field: &str| anyhow!("the custom network {name} requires {field}");
+        let shelley_genesis = profile
+            .shelley_genesis
+            .take()
+            .ok_or_else(|| missing("shelley_genesis"))?;
+        let genesis: Point = profile
+            .genesis
+            .take()
+            .ok_or_else(|| missing("genesis"))?
+            .parse()?;
+        let genesis_parent = BlockId::from_hex(
+            &profile
+                .genesis_parent
+                .take()
+                .ok_or_else(|| missing("genesis_parent"))?,
+        )?;
+        let relay = profile.relays.first().ok_or_else(|| missing("a relay"))?;
+
+        let config = NetworkConfiguration::from_genesis(
+            self.base_dir.join(shelley_genesis),
+            (Cow::from(relay.host.clone()), relay.port),
+            genesis_parent,
+            genesis,
+        )?;
+        NetworkProfile {
+            name: name.to_string(),
+            relays: vec![config.relay.clone()],
+            config,
+            blockfrost_url: None,
+            koios_url: None,
+        }
+        .apply(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles(yaml: &str) -> Profiles {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn builtin() {
+        let profile = Profiles::default().profile("preprod").unwrap();
+
+        assert_eq!(profile.relays, vec![profile.config.relay.clone()]);
+        assert_eq!(
+            profile.koios_url.as_deref(),
+            Some("https://preprod.koios.rest/api/v1")
+        );
+        assert!(Profiles::default().profile("devnet").is_err());
+    }
+
+    #[test]
+    fn override_builtin() {
+        let profile = profiles(
+            r#"
+networks:
+  mainnet:
+    relays:
+      - host: relay.example.com
+        port: 3001
+      - host: backup.example.com
+        port: 3002
+    koios_url: http://localhost:8053/api/v1
+"#,
+        )
+        .profile("mainnet")
+        .unwrap();
+
+        assert_eq!(profile.config.relay, (Cow::from("relay.example.com"), 3001));
+        assert_eq!(profile.relays.len(), 2);
+        assert_eq!(
+            profile.koios_url.as_deref(),
+            Some("http://localhost:8053/api/v1")
+        );
+        assert_eq!(
+            profile.blockfrost_url.as_deref(),
+            Some("https://cardano-mainnet.blockfrost.io/api/v0")
+        );
+
+        let profile = profile.with_relay(Some("localhost".to_string()), None);
+        assert_eq!(profile.config.relay, (Cow::from("localhost"), 3001));
+        assert_eq!(profile.relays[0], profile.config.relay);
+    }
+
+    #[test]
+    fn custom_requires_genesis() {
+        let result = profiles(
+            r#"
+networks:
+  devnet:
+    relays:
+      - host: localhost
+        port: 3001
+"#,
+        )
+        .profile("devnet");
+
+        assert!(result.is_err());
+    }
+}
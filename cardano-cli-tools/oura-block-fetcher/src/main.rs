@@ -1,135 +1,194 @@
-use anyhow::anyhow;
+use anyhow::bail;
 use clap::Parser;
-use oura::filters::selection;
-use oura::filters::selection::Predicate;
-use oura::mapper;
-use oura::mapper::ChainWellKnownInfo;
-use oura::model::EventData;
-use oura::pipelining::{FilterProvider, SourceProvider};
-use oura::sources::{n2c, n2n, AddressArg, BearerKind, IntersectArg, MagicArg, PointArg};
-use oura::utils::{Utils, WithUtils};
+use dcspark_blockchain_source::cardano::{BlockEvent, CardanoNetworkEvent, CardanoSource, Point};
+use dcspark_blockchain_source::Source;
+use dcspark_core::{BlockId, HealthCheck, ServiceState};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use network_profiles::NetworkArgs;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::time::Duration;
+
+/// wait between two pulls once the tip of the relay is reached
+const PULL_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Parser, Debug)]
 #[clap(version)]
 struct Cli {
-    #[clap(long, value_parser, default_value = "mainnet")]
-    pub magic: String,
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    /// the block to start after, `slot,block_hash`
+    #[clap(long, value_parser)]
+    pub since: Option<Point>,
     #[clap(long, value_parser)]
-    pub bearer: BearerKind,
+    pub relay_host: Option<String>,
     #[clap(long, value_parser)]
-    pub since: Option<String>,
+    pub relay_port: Option<u16>,
+    /// write the blocks as NDJSON records to the file, gzipped if the path
+    /// ends with `.gz`, instead of printing them
     #[clap(long, value_parser)]
-    pub socket: String,
+    pub output: Option<PathBuf>,
+    /// stop after the block `slot,block_hash`, or with `tip` once the tip
+    /// of the relay is reached
+    #[clap(long, value_parser)]
+    pub until: Option<Until>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let Cli {
-        magic,
-        bearer,
-        socket,
-        since,
-    } = Cli::parse();
+#[derive(Debug, Clone)]
+enum Until {
+    Tip,
+    Point { slot: u64, hash: BlockId },
+}
+
+impl FromStr for Until {
+    type Err = anyhow::Error;
 
-    let magic = MagicArg::from_str(&magic).map_err(|_| anyhow!("magic arg failed"))?;
-
-    let well_known = match magic.0 {
-        4 => ChainWellKnownInfo {
-            byron_epoch_length: 86400,
-            byron_slot_length: 20,
-            byron_known_slot: 0,
-            byron_known_hash: "".to_string(),
-            byron_known_time: 1686789000,
-            shelley_epoch_length: 86400,
-            shelley_slot_length: 1,
-            shelley_known_slot: 0,
-            shelley_known_hash: "".to_string(),
-            shelley_known_time: 1686789000,
-            address_hrp: "addr_test".to_string(),
-            adahandle_policy: "".to_string(),
-        },
-        _ => ChainWellKnownInfo::try_from_magic(*magic)
-            .map_err(|_| anyhow!("chain well known info failed"))?,
-    };
-
-    let utils = Arc::new(Utils::new(well_known));
-
-    let mapper = mapper::Config {
-        include_transaction_details: true,
-        include_block_cbor: true,
-        ..Default::default()
-    };
-
-    let since = match since {
-        None => IntersectArg::Origin,
-        Some(string) => IntersectArg::Point(
-            PointArg::from_str(&string).map_err(|err| anyhow!("can't parse point: {err}"))?,
-        ),
-    };
-    let since = Some(since);
-
-    let (source_handle, source_rx) = match bearer {
-        #[allow(deprecated)]
-        BearerKind::Unix => {
-            let source_config = n2c::Config {
-                address: AddressArg(BearerKind::Unix, socket),
-                magic: Some(magic),
-                well_known: None,
-                mapper,
-                since: None,
-                min_depth: 0,
-                intersect: since,
-                retry_policy: None,
-                finalize: None, // TODO: configurable
-            };
-            WithUtils::new(source_config, utils).bootstrap()
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "tip" {
+            return Ok(Until::Tip);
         }
-        #[allow(deprecated)]
-        BearerKind::Tcp => {
-            let source_config = n2n::Config {
-                address: AddressArg(BearerKind::Tcp, socket),
-                magic: Some(magic),
-                well_known: None,
-                mapper,
-                since: None,
-                min_depth: 0,
-                intersect: since,
-                retry_policy: None,
-                finalize: None, // TODO: configurable
-            };
-            WithUtils::new(source_config, utils).bootstrap()
+        match s.parse::<Point>()? {
+            Point::BlockHeader { slot_nb, hash } => Ok(Until::Point {
+                slot: slot_nb.into_inner(),
+                hash,
+            }),
+            Point::Origin => bail!("until should be either tip or slot,block_hash"),
         }
     }
-    .map_err(|e| {
-        anyhow!("failed to bootstrap source: {e}. Are you sure cardano-node is running?")
-    })?;
+}
+
+/// a fetched block, one per line of the NDJSON output
+#[derive(Debug, Serialize)]
+struct BlockLine {
+    number: u64,
+    hash: String,
+    slot: u64,
+    cbor_hex: String,
+}
 
-    let mut handles = Vec::new();
-    handles.push(source_handle);
+enum Output {
+    Stdout,
+    File(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
 
-    let check = Predicate::VariantIn(vec![String::from("Block"), String::from("Rollback")]);
+impl Output {
+    fn create(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let path = match path {
+            None => return Ok(Output::Stdout),
+            Some(path) => path,
+        };
+        let file = BufWriter::new(File::create(&path)?);
+        if path.extension().is_some_and(|extension| extension == "gz") {
+            Ok(Output::Gzip(GzEncoder::new(file, Compression::default())))
+        } else {
+            Ok(Output::File(file))
+        }
+    }
+
+    fn write(&mut self, block: &BlockLine) -> anyhow::Result<()> {
+        let writer: &mut dyn Write = match self {
+            Output::Stdout => {
+                println!(
+                    "Block #{}, point: {}@{}, raw cbor hex: {}",
+                    block.number, block.hash, block.slot, block.cbor_hex
+                );
+                return Ok(());
+            }
+            Output::File(file) => file,
+            Output::Gzip(gzip) => gzip,
+        };
+        serde_json::to_writer(&mut *writer, block)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Output::Stdout => {}
+            Output::File(mut file) => file.flush()?,
+            Output::Gzip(gzip) => gzip.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn block_line(block: &BlockEvent) -> BlockLine {
+    BlockLine {
+        number: block.block_number.into_inner(),
+        hash: block.id.to_string(),
+        slot: block.slot_number.into_inner(),
+        cbor_hex: hex::encode(&block.raw_block),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Cli {
+        network,
+        since,
+        relay_host,
+        relay_port,
+        output,
+        until,
+    } = Cli::parse();
 
-    let filter_setup = selection::Config { check };
+    let network_config = network.load()?.with_relay(relay_host, relay_port).config;
 
-    let (filter_handle, filter_rx) = filter_setup
-        .bootstrap(source_rx)
-        .map_err(|_| anyhow!("failed to bootstrap filter"))?;
+    let mut source = CardanoSource::connect(&network_config, Duration::from_secs(20)).await?;
+    let mut output = Output::create(output)?;
+    let mut pull_from: Vec<Point> = since.into_iter().collect();
+    let mut fetched = false;
 
-    handles.push(filter_handle);
+    loop {
+        let block = match source.pull(&pull_from).await? {
+            Some(CardanoNetworkEvent::Block(block)) => block,
+            Some(CardanoNetworkEvent::Tip(_)) => continue,
+            // the source returns nothing once the tip of the relay is
+            // reached, or if the connection with the relay was lost
+            None => {
+                let status = source.status();
+                if let Some(error) = status.details.get("last_error") {
+                    output.finish()?;
+                    bail!("the connection with the relay failed: {error}");
+                }
+                if status.state == ServiceState::Ready {
+                    if matches!(until, Some(Until::Tip)) {
+                        break;
+                    }
+                } else if !fetched && !pull_from.is_empty() {
+                    // the relay is not at the tip and served no block: the
+                    // since point is not on its chain
+                    output.finish()?;
+                    bail!("the since point is not on the chain of the relay");
+                }
+                tokio::time::sleep(PULL_INTERVAL).await;
+                continue;
+            }
+        };
+        fetched = true;
+        pull_from = vec![Point::BlockHeader {
+            slot_nb: block.slot_number,
+            hash: block.id.clone(),
+        }];
+        let line = block_line(&block);
 
-    for input in filter_rx.into_iter() {
-        if let EventData::Block(block_record) = input.data {
-            let cbor = block_record
-                .cbor_hex
-                .ok_or_else(|| anyhow!("cbor is not presented"))?;
-            println!(
-                "Block #{}, point: {}@{}, raw cbor hex: {}",
-                block_record.number, block_record.hash, block_record.slot, cbor
-            );
+        match &until {
+            Some(Until::Point { slot, hash }) if line.slot >= *slot => {
+                if line.slot > *slot || &block.id != hash {
+                    output.finish()?;
+                    bail!("the until point {slot},{hash} is not on the chain");
+                }
+                output.write(&line)?;
+                break;
+            }
+            _ => output.write(&line)?,
         }
     }
 
-    Ok(())
+    output.finish()
 }
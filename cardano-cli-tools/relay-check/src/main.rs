@@ -0,0 +1,167 @@
+//! Check the relays before pointing a source at them
+//!
+//! Every relay is connected to with a [`CardanoSource`], then its tip is
+//! queried with a chain-sync intersection. A relay is healthy if the
+//! handshake succeeds, the tip is returned and both round trips are below
+//! `--max-latency`. The node-to-node version accepted by the relay is
+//! reported:
+//!
+//! ```text
+//! $ relay-check --network preprod relay.example.com:3001
+//! relay.example.com:3001: healthy, version 8, handshake 212ms, tip query 48ms, tip: ...
+//! ```
+//!
+//! Without argument, the relays of the network profile are checked. The tool
+//! exits with an error if any relay is unhealthy.
+use anyhow::{anyhow, bail, Context};
+use clap::Parser;
+use dcspark_blockchain_source::cardano::{CardanoSource, NetworkConfiguration, Point, Version};
+use dcspark_blockchain_source::Source;
+use dcspark_core::{BlockId, HealthCheck, SlotNumber};
+use network_profiles::NetworkArgs;
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// the relays to check as `host:port`, the relays of the network
+    /// profile if none is given
+    #[clap(value_parser)]
+    pub relays: Vec<String>,
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    /// give up on a relay after this many seconds
+    #[clap(long, value_parser, default_value = "10")]
+    pub timeout: u64,
+    /// the relays slower than this many milliseconds to answer are unhealthy
+    #[clap(long, value_parser)]
+    pub max_latency: Option<u64>,
+}
+
+struct Report {
+    version: Version,
+    handshake: Duration,
+    tip_query: Duration,
+    tip: String,
+}
+
+fn parse_relay(relay: &str) -> anyhow::Result<(Cow<'static, str>, u16)> {
+    let (host, port) = relay
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("invalid relay {relay}, expected host:port"))?;
+    let port = port
+        .parse()
+        .with_context(|| format!("invalid port in {relay}"))?;
+    Ok((Cow::from(host.to_string()), port))
+}
+
+async fn check(config: &NetworkConfiguration, timeout: Duration) -> anyhow::Result<Report> {
+    let start = Instant::now();
+    let mut source = tokio::time::timeout(
+        timeout,
+        CardanoSource::connect(config, Duration::from_secs(20)),
+    )
+    .await
+    .context("handshake timed out")?
+    .context("handshake failed")?;
+    let handshake = start.elapsed();
+    let version = source
+        .version()
+        .ok_or_else(|| anyhow!("the relay did not accept any version"))?;
+
+    // a point that is on no chain: the relay answers the intersection with
+    // its tip and no block is fetched
+    let unknown = Point::BlockHeader {
+        slot_nb: SlotNumber::new(0),
+        hash: BlockId::new_static(
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ),
+    };
+    let start = Instant::now();
+    tokio::time::timeout(timeout, source.pull(&vec![unknown]))
+        .await
+        .context("tip query timed out")??;
+    let tip_query = start.elapsed();
+
+    let status = source.status();
+    if let Some(error) = status.details.get("last_error") {
+        bail!("tip query failed: {error}");
+    }
+    let tip = status
+        .details
+        .get("tip")
+        .cloned()
+        .ok_or_else(|| anyhow!("the relay did not return its tip"))?;
+
+    Ok(Report {
+        version,
+        handshake,
+        tip_query,
+        tip,
+    })
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Cli {
+        relays,
+        network,
+        timeout,
+        max_latency,
+    } = Cli::parse();
+
+    let profile = network.load()?;
+    let base_config = profile.config;
+    let relays = if relays.is_empty() {
+        profile.relays
+    } else {
+        relays
+            .iter()
+            .map(String::as_str)
+            .map(parse_relay)
+            .collect::<anyhow::Result<_>>()?
+    };
+
+    let mut unhealthy = 0;
+    for relay in relays {
+        let name = format!("{}:{}", relay.0, relay.1);
+        let config = NetworkConfiguration {
+            relay,
+            ..base_config.clone()
+        };
+
+        let report = check(&config, Duration::from_secs(timeout))
+            .await
+            .and_then(|report| match max_latency.map(Duration::from_millis) {
+                Some(max) if report.handshake > max || report.tip_query > max => Err(anyhow!(
+                    "too slow, handshake {:?}, tip query {:?}",
+                    report.handshake,
+                    report.tip_query
+                )),
+                _ => Ok(report),
+            });
+        match report {
+            Ok(Report {
+                version,
+                handshake,
+                tip_query,
+                tip,
+            }) => println!(
+                "{name}: healthy, version {}, handshake {}ms, tip query {}ms, tip: {tip}",
+                version as u64,
+                handshake.as_millis(),
+                tip_query.as_millis(),
+            ),
+            Err(error) => {
+                println!("{name}: unhealthy, {error:#}");
+                unhealthy += 1;
+            }
+        }
+    }
+
+    if unhealthy > 0 {
+        bail!("{unhealthy} unhealthy relays");
+    }
+    Ok(())
+}
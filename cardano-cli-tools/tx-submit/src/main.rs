@@ -0,0 +1,297 @@
+//! Submit a signed transaction and wait for its confirmation
+//!
+//! The transaction is submitted to blockfrost, then the chain is followed
+//! from the relay of the network, starting at the blockfrost tip, until the
+//! block including the transaction has `confirmations` blocks on top of it.
+//!
+//! Every step is printed as one JSON line on stdout:
+//!
+//! ```json
+//! {"status":"submitted","tx_id":"..."}
+//! {"status":"confirmed","tx_id":"...","block_id":"...","block_number":1,"slot":1,"confirmations":3}
+//! ```
+//!
+//! A transaction rejected by the ledger (HTTP 400) prints
+//! `{"status":"rejected","error":"..."}`. Any other failure of the
+//! submission prints
+//! `{"status":"failed","http_status":503,"error":"...","retryable":true}`,
+//! the authentication errors (401, 403), the rate limits (425, 429) and
+//! the server errors (5xx) are retryable. The tool exits with an error in
+//! both cases.
+use anyhow::{anyhow, bail, Context};
+use clap::Parser;
+use dcspark_blockchain_source::cardano::Point::BlockHeader;
+use dcspark_blockchain_source::cardano::{
+    BlockEvent, CardanoNetworkEvent, CardanoSource, NetworkConfiguration, Tip,
+};
+use dcspark_blockchain_source::multiverse::MultiverseSource;
+use dcspark_blockchain_source::Source;
+use dcspark_core::{BlockId, HealthCheck, ServiceState, SlotNumber};
+use multiverse::Multiverse;
+use network_profiles::NetworkArgs;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// wait between two pulls while no new block is confirmed
+const PULL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// the signed transaction: a file holding the cbor (raw, hex or a
+    /// cardano-cli text envelope), or the cbor hex itself
+    #[clap(long, value_parser)]
+    pub tx: String,
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    /// url of the blockfrost api, defaults to the one of the network profile
+    #[clap(long, value_parser)]
+    pub blockfrost_url: Option<String>,
+    #[clap(long, value_parser, env = "BLOCKFROST_PROJECT_ID")]
+    pub blockfrost_project_id: String,
+    #[clap(long, value_parser)]
+    pub relay_host: Option<String>,
+    #[clap(long, value_parser)]
+    pub relay_port: Option<u16>,
+    /// number of blocks on top of the block including the transaction
+    #[clap(long, value_parser, default_value = "0")]
+    pub confirmations: usize,
+    /// give up waiting for the confirmation after this many seconds
+    #[clap(long, value_parser, default_value = "600")]
+    pub timeout: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum Report {
+    Submitted {
+        tx_id: String,
+    },
+    Rejected {
+        error: String,
+    },
+    Failed {
+        http_status: u16,
+        error: String,
+        retryable: bool,
+    },
+    Confirmed {
+        tx_id: String,
+        block_id: BlockId,
+        block_number: u64,
+        slot: u64,
+        confirmations: usize,
+    },
+    Timeout {
+        tx_id: String,
+        seconds: u64,
+    },
+}
+
+impl Report {
+    fn print(&self) -> anyhow::Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
+
+/// the text envelope written by `cardano-cli transaction sign`
+#[derive(Debug, Deserialize)]
+struct TextEnvelope {
+    #[serde(rename = "cborHex")]
+    cbor_hex: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestBlock {
+    hash: String,
+    slot: u64,
+}
+
+fn read_transaction(tx: &str) -> anyhow::Result<Vec<u8>> {
+    if !Path::new(tx).exists() {
+        return hex::decode(tx.trim()).context("the transaction is neither a file nor hex");
+    }
+
+    let bytes = std::fs::read(tx).with_context(|| format!("couldn't read {tx}"))?;
+    if let Ok(envelope) = serde_json::from_slice::<TextEnvelope>(&bytes) {
+        return hex::decode(envelope.cbor_hex).context("invalid cborHex in the text envelope");
+    }
+    match std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| hex::decode(text.trim()).ok())
+    {
+        Some(cbor) => Ok(cbor),
+        None => Ok(bytes),
+    }
+}
+
+/// the submission failed for another reason than the transaction itself
+/// and may succeed later, e.g. with a valid project id
+fn is_retryable(status: StatusCode) -> bool {
+    // 425 is returned by blockfrost when its mempool is full
+    matches!(status.as_u16(), 401 | 403 | 425 | 429) || status.is_server_error()
+}
+
+/// the ids of the transactions of the block
+fn transaction_ids(block: &BlockEvent) -> anyhow::Result<Vec<String>> {
+    let block = cml_multi_era::MultiEraBlock::from_explicit_network_cbor_bytes(&block.raw_block)
+        .map_err(|error| anyhow!("couldn't decode block {}: {error}", block.id))?;
+    Ok(block
+        .transaction_bodies()
+        .iter()
+        .map(|body| body.hash().to_string())
+        .collect())
+}
+
+/// follow the chain until the block including `tx_id` is confirmed
+async fn wait_for_confirmation(
+    config: &NetworkConfiguration,
+    tx_id: &str,
+    confirmations: usize,
+) -> anyhow::Result<Report> {
+    let source = CardanoSource::connect(config, Duration::from_secs(20)).await?;
+    let mut source = MultiverseSource::new(
+        Multiverse::<BlockId, CardanoNetworkEvent<BlockEvent, Tip>>::temporary()?,
+        confirmations,
+        source,
+    );
+
+    let mut pull_from = None;
+    loop {
+        // the multiverse source returns nothing until a new block is confirmed,
+        // or if the connection with the relay was lost
+        let block = match source.pull(&pull_from).await? {
+            Some(CardanoNetworkEvent::Block(block)) => block,
+            Some(_) => continue,
+            None => {
+                let status = source.status();
+                if status.state == ServiceState::Stopped {
+                    bail!("the connection with the relay stopped");
+                }
+                if let Some(error) = status.details.get("last_error") {
+                    bail!("the connection with the relay failed: {error}");
+                }
+                tokio::time::sleep(PULL_INTERVAL).await;
+                continue;
+            }
+        };
+        pull_from = Some(BlockHeader {
+            slot_nb: block.slot_number,
+            hash: block.id.clone(),
+        });
+
+        if transaction_ids(&block)?.iter().any(|id| id == tx_id) {
+            return Ok(Report::Confirmed {
+                tx_id: tx_id.to_string(),
+                block_id: block.id,
+                block_number: block.block_number.into_inner(),
+                slot: block.slot_number.into_inner(),
+                confirmations,
+            });
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Cli {
+        tx,
+        network,
+        blockfrost_url,
+        blockfrost_project_id,
+        relay_host,
+        relay_port,
+        confirmations,
+        timeout,
+    } = Cli::parse();
+
+    let cbor = read_transaction(&tx)?;
+    let profile = network.load()?.with_relay(relay_host, relay_port);
+    let blockfrost_url = match blockfrost_url.or(profile.blockfrost_url) {
+        Some(url) => url,
+        None => bail!(
+            "no blockfrost instance for {}, set --blockfrost-url",
+            profile.name
+        ),
+    };
+    let client = reqwest::Client::new();
+
+    // the chain is followed from the tip at the time of the submission
+    let latest: LatestBlock = client
+        .get(format!("{blockfrost_url}/blocks/latest"))
+        .header("project_id", &blockfrost_project_id)
+        .send()
+        .await
+        .context("couldn't send request to /blocks/latest")?
+        .error_for_status()?
+        .json()
+        .await
+        .context("couldn't parse /blocks/latest response")?;
+
+    let response = client
+        .post(format!("{blockfrost_url}/tx/submit"))
+        .header("project_id", &blockfrost_project_id)
+        .header(CONTENT_TYPE, "application/cbor")
+        .body(cbor)
+        .send()
+        .await
+        .context("couldn't send request to /tx/submit")?;
+
+    let status = response.status();
+    if status == StatusCode::BAD_REQUEST {
+        let error = response.text().await.unwrap_or_default();
+        Report::Rejected { error }.print()?;
+        bail!("the transaction was rejected");
+    }
+    if !status.is_success() {
+        let error = response.text().await.unwrap_or_default();
+        let retryable = is_retryable(status);
+        Report::Failed {
+            http_status: status.as_u16(),
+            error: error.clone(),
+            retryable,
+        }
+        .print()?;
+        if retryable {
+            bail!("the submission failed with {status}, it can be retried: {error}");
+        }
+        bail!("the submission failed with {status}: {error}");
+    }
+    let tx_id: String = response
+        .json()
+        .await
+        .context("couldn't parse /tx/submit response")?;
+    Report::Submitted {
+        tx_id: tx_id.clone(),
+    }
+    .print()?;
+
+    let config = NetworkConfiguration {
+        from: BlockHeader {
+            slot_nb: SlotNumber::new(latest.slot),
+            hash: BlockId::from_hex(&latest.hash)?,
+        },
+        ..profile.config
+    };
+
+    match tokio::time::timeout(
+        Duration::from_secs(timeout),
+        wait_for_confirmation(&config, &tx_id, confirmations),
+    )
+    .await
+    {
+        Ok(report) => report?.print(),
+        Err(_) => {
+            Report::Timeout {
+                tx_id,
+                seconds: timeout,
+            }
+            .print()?;
+            bail!("the transaction was not confirmed in time")
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! The backends listing the UTxOs of an address
+
+use anyhow::{anyhow, bail, Context};
+use dcspark_core::tx::{Datum, TransactionAsset, UTxODetails, UtxoPointer};
+use dcspark_core::{Address, AssetName, BlockNumber, Hash32, PolicyId, Regulated, Value};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// number of UTxOs in a page of the blockfrost responses
+const BLOCKFROST_PAGE_SIZE: usize = 100;
+const LOVELACE: &str = "lovelace";
+/// length of a policy id in hex, the rest of a blockfrost unit is the asset name
+const POLICY_ID_HEX_LENGTH: usize = 56;
+
+pub enum Backend {
+    Blockfrost {
+        client: Client,
+        base_url: String,
+        project_id: String,
+    },
+    Koios {
+        client: Client,
+        base_url: String,
+    },
+}
+
+impl Backend {
+    /// the UTxOs currently held by the `address`
+    pub async fn utxos(&self, address: &str) -> anyhow::Result<Vec<UTxODetails>> {
+        match self {
+            Backend::Blockfrost {
+                client,
+                base_url,
+                project_id,
+            } => blockfrost_utxos(client, base_url, project_id, address).await,
+            Backend::Koios { client, base_url } => koios_utxos(client, base_url, address).await,
+        }
+    }
+}
+
+fn pointer(tx_hash: &str, output_index: u64) -> anyhow::Result<UtxoPointer> {
+    format!("{tx_hash}#{output_index}")
+        .parse()
+        .with_context(|| format!("invalid utxo pointer {tx_hash}#{output_index}"))
+}
+
+fn asset(policy_id: &str, asset_name: &str, quantity: &str) -> anyhow::Result<TransactionAsset> {
+    TransactionAsset::new(
+        PolicyId::new(policy_id.to_string()),
+        AssetName::new(asset_name.to_string()),
+        quantity.parse::<Value<Regulated>>()?,
+    )
+    .map_err(|error| anyhow!("invalid asset {policy_id}.{asset_name}: {error}"))
+}
+
+fn datum(hash: Option<&str>, inline: Option<&str>) -> anyhow::Result<Option<Datum>> {
+    Ok(match (inline, hash) {
+        (Some(inline), _) => Some(Datum::Inline(hex::decode(inline)?)),
+        (None, Some(hash)) => Some(Datum::Hash(Hash32::from_hex(hash)?)),
+        (None, None) => None,
+    })
+}
+
+fn details(
+    pointer: UtxoPointer,
+    address: &str,
+    value: Value<Regulated>,
+    assets: Vec<TransactionAsset>,
+    datum: Option<Datum>,
+    creation_height: Option<BlockNumber>,
+) -> UTxODetails {
+    UTxODetails {
+        pointer,
+        address: Address::new(address.to_string()),
+        value,
+        assets,
+        metadata: Arc::new(serde_json::Value::Null),
+        datum,
+        script_ref: None,
+        extra: None,
+        creation_height,
+        script_spend: None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockfrostUtxo {
+    tx_hash: String,
+    output_index: u64,
+    amount: Vec<BlockfrostAmount>,
+    data_hash: Option<String>,
+    inline_datum: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockfrostAmount {
+    unit: String,
+    quantity: String,
+}
+
+async fn blockfrost_utxos(
+    client: &Client,
+    base_url: &str,
+    project_id: &str,
+    address: &str,
+) -> anyhow::Result<Vec<UTxODetails>> {
+    let mut utxos = Vec::new();
+    for page in 1.. {
+        let response = client
+            .get(format!("{base_url}/addresses/{address}/utxos"))
+            .query(&[("page", page)])
+            .header("project_id", project_id)
+            .send()
+            .await
+            .context("couldn't send request to /addresses/utxos")?;
+
+        // blockfrost answers 404 for the addresses never seen on chain
+        if response.status() == StatusCode::NOT_FOUND {
+            break;
+        }
+        let page: Vec<BlockfrostUtxo> = response
+            .error_for_status()
+            .with_context(|| format!("error listing the utxos of {address}"))?
+            .json()
+            .await
+            .context("couldn't parse /addresses/utxos response")?;
+        let last_page = page.len() < BLOCKFROST_PAGE_SIZE;
+
+        for utxo in page {
+            let mut value = Value::zero();
+            let mut assets = Vec::new();
+            for amount in utxo.amount {
+                if amount.unit == LOVELACE {
+                    value = amount.quantity.parse()?;
+                } else if amount.unit.len() >= POLICY_ID_HEX_LENGTH {
+                    let (policy_id, asset_name) = amount.unit.split_at(POLICY_ID_HEX_LENGTH);
+                    assets.push(asset(policy_id, asset_name, &amount.quantity)?);
+                } else {
+                    bail!("invalid unit {} in the utxos of {address}", amount.unit);
+                }
+            }
+
+            utxos.push(details(
+                pointer(&utxo.tx_hash, utxo.output_index)?,
+                address,
+                value,
+                assets,
+                datum(utxo.data_hash.as_deref(), utxo.inline_datum.as_deref())?,
+                None,
+            ));
+        }
+
+        if last_page {
+            break;
+        }
+    }
+    Ok(utxos)
+}
+
+#[derive(Debug, Serialize)]
+struct KoiosRequest<'a> {
+    #[serde(rename = "_addresses")]
+    addresses: [&'a str; 1],
+    #[serde(rename = "_extended")]
+    extended: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct KoiosUtxo {
+    tx_hash: String,
+    tx_index: u64,
+    value: String,
+    block_height: Option<u64>,
+    datum_hash: Option<String>,
+    inline_datum: Option<KoiosInlineDatum>,
+    #[serde(default)]
+    asset_list: Vec<KoiosAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KoiosInlineDatum {
+    bytes: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KoiosAsset {
+    policy_id: String,
+    asset_name: Option<String>,
+    quantity: String,
+}
+
+async fn koios_utxos(
+    client: &Client,
+    base_url: &str,
+    address: &str,
+) -> anyhow::Result<Vec<UTxODetails>> {
+    let utxos: Vec<KoiosUtxo> = client
+        .post(format!("{base_url}/address_utxos"))
+        .json(&KoiosRequest {
+            addresses: [address],
+            extended: true,
+        })
+        .send()
+        .await
+        .context("couldn't send request to /address_utxos")?
+        .error_for_status()
+        .with_context(|| format!("error listing the utxos of {address}"))?
+        .json()
+        .await
+        .context("couldn't parse /address_utxos response")?;
+
+    utxos
+        .into_iter()
+        .map(|utxo| {
+            let assets = utxo
+                .asset_list
+                .iter()
+                .map(|koios_asset| {
+                    asset(
+                        &koios_asset.policy_id,
+                        koios_asset.asset_name.as_deref().unwrap_or_default(),
+                        &koios_asset.quantity,
+                    )
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            Ok(details(
+                pointer(&utxo.tx_hash, utxo.tx_index)?,
+                address,
+                utxo.value.parse()?,
+                assets,
+                datum(
+                    utxo.datum_hash.as_deref(),
+                    utxo.inline_datum.as_ref().map(|datum| datum.bytes.as_str()),
+                )?,
+                utxo.block_height.map(BlockNumber::new),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,139 @@
+//! Dump the UTxOs of addresses
+//!
+//! The UTxOs are listed by blockfrost or koios and printed as JSON, an array
+//! of `UTxODetails` ready to be loaded in a `UTxOStore` for the selection,
+//! or as CSV with one line per UTxO:
+//!
+//! ```csv
+//! pointer,address,value,assets,datum
+//! <tx_id>#0,addr1...,1500000,asset1...:10;asset1...:3,inline
+//! ```
+//!
+//! The datum column is `hash`, `inline` or empty. The reference scripts are
+//! not dumped.
+mod backend;
+
+use anyhow::{bail, Context};
+use backend::Backend;
+use clap::{Parser, ValueEnum};
+use dcspark_core::tx::{Datum, UTxODetails};
+use network_profiles::{NetworkArgs, NetworkProfile};
+use std::io::{BufRead, Write};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BackendKind {
+    Blockfrost,
+    Koios,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// the addresses, read from stdin (one per line) if none are given
+    #[clap(value_parser)]
+    pub addresses: Vec<String>,
+    #[clap(flatten)]
+    pub network: NetworkArgs,
+    #[clap(long, value_enum, default_value = "koios")]
+    pub backend: BackendKind,
+    /// base url of the backend, defaults to the one of the network profile
+    #[clap(long, value_parser)]
+    pub backend_url: Option<String>,
+    #[clap(long, value_parser, env = "BLOCKFROST_PROJECT_ID")]
+    pub blockfrost_project_id: Option<String>,
+    #[clap(long, value_enum, default_value = "json")]
+    pub format: Format,
+}
+
+fn backend(
+    kind: BackendKind,
+    profile: NetworkProfile,
+    base_url: Option<String>,
+    project_id: Option<String>,
+) -> anyhow::Result<Backend> {
+    let base_url = match kind {
+        BackendKind::Blockfrost => base_url.or(profile.blockfrost_url),
+        BackendKind::Koios => base_url.or(profile.koios_url),
+    };
+    let base_url = match base_url {
+        Some(base_url) => base_url,
+        None => bail!("no backend for {}, set --backend-url", profile.name),
+    };
+
+    let client = reqwest::Client::new();
+    Ok(match kind {
+        BackendKind::Blockfrost => Backend::Blockfrost {
+            client,
+            base_url,
+            project_id: project_id.context("the blockfrost backend requires a project id")?,
+        },
+        BackendKind::Koios => Backend::Koios { client, base_url },
+    })
+}
+
+fn write_csv(out: &mut impl Write, utxos: &[UTxODetails]) -> anyhow::Result<()> {
+    writeln!(out, "pointer,address,value,assets,datum")?;
+    for utxo in utxos {
+        let assets = utxo
+            .assets
+            .iter()
+            .map(|asset| format!("{}:{}", asset.fingerprint, asset.quantity))
+            .collect::<Vec<_>>()
+            .join(";");
+        let datum = match utxo.datum {
+            None => "",
+            Some(Datum::Hash(_)) => "hash",
+            Some(Datum::Inline(_)) => "inline",
+        };
+        writeln!(
+            out,
+            "{},{},{},{assets},{datum}",
+            utxo.pointer, utxo.address, utxo.value
+        )?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let Cli {
+        mut addresses,
+        network,
+        backend: kind,
+        backend_url,
+        blockfrost_project_id,
+        format,
+    } = Cli::parse();
+
+    if addresses.is_empty() {
+        for line in std::io::stdin().lock().lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                addresses.push(line.trim().to_string());
+            }
+        }
+    }
+
+    let backend = backend(kind, network.load()?, backend_url, blockfrost_project_id)?;
+
+    let mut utxos = Vec::new();
+    for address in addresses.iter() {
+        utxos.extend(backend.utxos(address).await?);
+    }
+
+    let mut out = std::io::stdout().lock();
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, &utxos)?;
+            writeln!(out)?;
+        }
+        Format::Csv => write_csv(&mut out, &utxos)?,
+    }
+    Ok(())
+}
@@ -1,39 +1,19 @@
-use anyhow::{anyhow, Context as _, Result};
+use anyhow::{Context as _, Result};
 
-use cryptoxide::hashing::blake2b::Blake2b;
 use dcspark_core::{AssetName, PolicyId, TokenId};
 
-fn fingerprint_hash(policy: &PolicyId, name: &AssetName) -> anyhow::Result<[u8; 20]> {
-    let mut buf = vec![0u8; 28 + name.as_ref().len() / 2];
-    hex::decode_to_slice(policy.as_ref(), &mut buf[..28])
-        .with_context(|| anyhow!("Failed to decode PolicyId: {policy}"))?;
-    hex::decode_to_slice(name.as_ref(), &mut buf[28..])
-        .with_context(|| anyhow!("Failed to decode AssetName: {name}"))?;
-
-    let b2b = Blake2b::<{ 20 * 8 }>::new().update(&buf);
-
-    let mut out = [0; 20];
-    b2b.finalize_at(&mut out);
-
-    Ok(out)
-}
-
+/// compute the CIP14 fingerprint of the asset, see [`TokenId::cip14`]
+///
+/// This used to return the hex encoded hash padded with `0` to 64
+/// characters, the token ids stored in that format are converted with
+/// [`TokenId::migrate_legacy_cip14`].
 pub fn fingerprint(policy: &PolicyId, name: &AssetName) -> Result<TokenId> {
-    fingerprint_hash(policy, name).map(|hash| TokenId::new(format!("{:0<64}", hex::encode(hash))))
+    TokenId::cip14(policy, name).context("Couldn't compute the asset fingerprint")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bech32::ToBase32;
-
-    const HRP: &str = "asset";
-
-    pub fn fingerprint_bech32(policy: &PolicyId, name: &AssetName) -> Result<String> {
-        let bytes = fingerprint_hash(policy, name)?;
-        bech32::encode(HRP, bytes.to_base32(), bech32::Variant::Bech32)
-            .context("Couldn't compute bech32 asset fingerprint")
-    }
 
     struct TestVector {
         policy_id: PolicyId,
@@ -109,10 +89,11 @@ mod tests {
     #[test]
     fn test_vectors() {
         for (index, test) in TESTS.iter().enumerate() {
-            let computed = fingerprint_bech32(&test.policy_id, &test.asset_name).unwrap();
+            let computed = fingerprint(&test.policy_id, &test.asset_name).unwrap();
 
             assert_eq!(
-                computed, test.asset_fingerprint,
+                computed.as_ref(),
+                test.asset_fingerprint,
                 "Failed to run the vector test {index}"
             )
         }
@@ -1,7 +1,9 @@
 mod cip14;
+pub mod metadata;
 pub mod multisig_plan;
 pub mod network_id;
 pub mod payment_credentials;
+pub mod transaction;
 pub mod utxo;
 
 pub use cip14::fingerprint;
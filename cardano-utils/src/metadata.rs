@@ -0,0 +1,200 @@
+use anyhow::{anyhow, bail, Context as _};
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_multiplatform_lib::metadata::{
+    decode_metadatum_to_json_str, encode_json_str_to_metadatum, AuxiliaryData,
+    GeneralTransactionMetadata, MetadataJsonSchema,
+};
+use deps::serde_json;
+
+/// the ledger limit of the text and bytes metadata, longer ones have to
+/// be split in chunks
+pub const MAX_METADATUM_CHUNK_SIZE: usize = 64;
+
+/// build the auxiliary data of the transaction from the JSON `metadata`
+///
+/// The metadata is an object of the labels (as strings, e.g. `"674"`) to
+/// their values, encoded following the `schema` (see the CIP-25 / cardano-cli
+/// JSON mappings). `null` or an empty object means there is no metadata.
+///
+/// Fails if a text or bytes metadatum is longer than
+/// [`MAX_METADATUM_CHUNK_SIZE`] or if the encoded auxiliary data is larger
+/// than `max_size` bytes.
+pub fn json_to_auxiliary_data(
+    metadata: &serde_json::Value,
+    schema: MetadataJsonSchema,
+    max_size: usize,
+) -> anyhow::Result<Option<AuxiliaryData>> {
+    let labels = match metadata {
+        serde_json::Value::Null => return Ok(None),
+        serde_json::Value::Object(labels) if labels.is_empty() => return Ok(None),
+        serde_json::Value::Object(labels) => labels,
+        _ => bail!("the metadata should be an object of the labels to their values"),
+    };
+
+    let mut general = GeneralTransactionMetadata::new();
+    for (label, value) in labels {
+        let label_number: u64 = label
+            .parse()
+            .with_context(|| format!("invalid metadata label {label}"))?;
+        check_chunk_sizes(value, schema).with_context(|| format!("invalid metadata {label}"))?;
+        let metadatum = encode_json_str_to_metadatum(value.to_string(), schema)
+            .map_err(|error| anyhow!("Failed to encode the metadata {label}: {error}"))?;
+
+        general.insert(&BigNum::from(label_number), &metadatum);
+    }
+
+    let mut auxiliary_data = AuxiliaryData::new();
+    auxiliary_data.set_metadata(&general);
+
+    let size = auxiliary_data.to_bytes().len();
+    if size > max_size {
+        bail!("the metadata is {size} bytes, above the limit of {max_size} bytes");
+    }
+
+    Ok(Some(auxiliary_data))
+}
+
+/// the JSON metadata of the auxiliary data, reverse of
+/// [`json_to_auxiliary_data`]. `null` if there is no metadata.
+pub fn auxiliary_data_to_json(
+    auxiliary_data: &AuxiliaryData,
+    schema: MetadataJsonSchema,
+) -> anyhow::Result<serde_json::Value> {
+    let general = match auxiliary_data.metadata() {
+        Some(general) => general,
+        None => return Ok(serde_json::Value::Null),
+    };
+
+    let mut labels = serde_json::Map::new();
+    let keys = general.keys();
+    for index in 0..keys.len() {
+        let label = keys.get(index);
+        let metadatum = general
+            .get(&label)
+            .ok_or_else(|| anyhow!("not found metadata {}", u64::from(label)))?;
+        let json = decode_metadatum_to_json_str(&metadatum, schema).map_err(|error| {
+            anyhow!(
+                "Failed to decode the metadata {}: {error}",
+                u64::from(label)
+            )
+        })?;
+        labels.insert(u64::from(label).to_string(), serde_json::from_str(&json)?);
+    }
+
+    Ok(serde_json::Value::Object(labels))
+}
+
+fn check_chunk_size(chunk: &str, size: usize) -> anyhow::Result<()> {
+    if size > MAX_METADATUM_CHUNK_SIZE {
+        bail!(
+            "{chunk} is {size} bytes long, above the {} bytes of a metadatum",
+            MAX_METADATUM_CHUNK_SIZE
+        );
+    }
+    Ok(())
+}
+
+/// check the texts, and the bytes (hex encoded with a `0x` prefix or under
+/// a `bytes` key depending on the schema), fit in a metadatum
+fn check_chunk_sizes(value: &serde_json::Value, schema: MetadataJsonSchema) -> anyhow::Result<()> {
+    match value {
+        serde_json::Value::String(text) => match (schema, text.strip_prefix("0x")) {
+            (MetadataJsonSchema::BasicConversions, Some(hex)) => {
+                check_chunk_size(text, hex.len() / 2)
+            }
+            _ => check_chunk_size(text, text.len()),
+        },
+        serde_json::Value::Object(object) => {
+            for (key, value) in object {
+                check_chunk_sizes(&serde_json::Value::String(key.clone()), schema)?;
+                match (schema, key.as_str(), value) {
+                    (
+                        MetadataJsonSchema::DetailedSchema,
+                        "bytes",
+                        serde_json::Value::String(hex),
+                    ) => check_chunk_size(hex, hex.len() / 2)?,
+                    _ => check_chunk_sizes(value, schema)?,
+                }
+            }
+            Ok(())
+        }
+        serde_json::Value::Array(values) => values
+            .iter()
+            .try_for_each(|value| check_chunk_sizes(value, schema)),
+        serde_json::Value::Null | serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIZE: usize = 16384;
+
+    #[test]
+    fn round_trip() {
+        let metadata = serde_json::json!({
+            "674": { "msg": ["hello", "world"] },
+            "87": "mainnet.cardano-evm.c1",
+        });
+
+        let auxiliary_data =
+            json_to_auxiliary_data(&metadata, MetadataJsonSchema::NoConversions, MAX_SIZE)
+                .unwrap()
+                .unwrap();
+        let json =
+            auxiliary_data_to_json(&auxiliary_data, MetadataJsonSchema::NoConversions).unwrap();
+
+        assert_eq!(json, metadata);
+    }
+
+    #[test]
+    fn detailed_schema_round_trip() {
+        let metadata = serde_json::json!({
+            "1": { "list": [{ "int": 42 }, { "bytes": "cafe" }, { "string": "text" }] },
+        });
+
+        let auxiliary_data =
+            json_to_auxiliary_data(&metadata, MetadataJsonSchema::DetailedSchema, MAX_SIZE)
+                .unwrap()
+                .unwrap();
+        let json =
+            auxiliary_data_to_json(&auxiliary_data, MetadataJsonSchema::DetailedSchema).unwrap();
+
+        assert_eq!(json, metadata);
+    }
+
+    #[test]
+    fn no_metadata() {
+        let schema = MetadataJsonSchema::NoConversions;
+
+        assert!(
+            json_to_auxiliary_data(&serde_json::Value::Null, schema, MAX_SIZE)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            json_to_auxiliary_data(&serde_json::json!({}), schema, MAX_SIZE)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let schema = MetadataJsonSchema::NoConversions;
+        let long_text = "a".repeat(MAX_METADATUM_CHUNK_SIZE + 1);
+
+        assert!(json_to_auxiliary_data(&serde_json::json!(["674"]), schema, MAX_SIZE).is_err());
+        assert!(
+            json_to_auxiliary_data(&serde_json::json!({ "msg": 1 }), schema, MAX_SIZE).is_err()
+        );
+        assert!(
+            json_to_auxiliary_data(&serde_json::json!({ "674": long_text }), schema, MAX_SIZE)
+                .is_err()
+        );
+        assert!(json_to_auxiliary_data(&serde_json::json!({ "674": "hello" }), schema, 4).is_err());
+    }
+}
@@ -1,15 +1,67 @@
+use crate::network_id::NetworkInfo;
 use anyhow::{anyhow, Context as _};
 use cardano_multiplatform_lib::address::{Address, EnterpriseAddress, StakeCredential};
 use cardano_multiplatform_lib::crypto::{Ed25519KeyHash, ScriptHash};
-use cardano_multiplatform_lib::{NativeScript, NativeScripts, ScriptNOfK, ScriptPubkey};
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_multiplatform_lib::plutus::{PlutusScript, PlutusV1Script, PlutusV2Script};
+use cardano_multiplatform_lib::{
+    NativeScript, NativeScripts, ScriptAll, ScriptNOfK, ScriptPubkey, TimelockExpiry,
+    TimelockStart, TransactionWitnessSet,
+};
 use deps::serde_json;
 use serde::{Deserialize, Deserializer};
 use std::path::Path;
+use std::str::FromStr as _;
+
+/// assumed size of a redeemer in the witness set, the plutus data
+/// of the redeemer is expected to be small.
+const ASSUMED_SIZE_OF_ONE_REDEEMER: usize = 2 // array
+    + 1 // tag
+    + 2 // index
+    + 64 // plutus data
+    + 1 + 9 + 9 // execution units
+;
+
+/// size of one bound of the validity interval in the transaction body
+pub(crate) const ASSUMED_SIZE_OF_VALIDITY_BOUND: usize = 1 + 9;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct MultisigPlan {
     pub quorum: u32,
     pub keys: Vec<Hash>,
+
+    /// the funds can only be spent from this slot (included)
+    #[serde(default)]
+    pub valid_after: Option<u64>,
+    /// the funds can only be spent before this slot (excluded)
+    #[serde(default)]
+    pub valid_before: Option<u64>,
+
+    /// the funds are locked by a plutus script instead of the native script.
+    /// The `quorum` of `keys` are then expected to be required signers of
+    /// the transaction.
+    #[serde(default)]
+    pub plutus: Option<PlutusPlan>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlutusVersion {
+    V1,
+    V2,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlutusPlan {
+    pub version: PlutusVersion,
+    /// the hex encoded flat script, as found in the `cborHex` of the
+    /// compiled script envelope
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub script: Vec<u8>,
+    /// assumed fee (in lovelace) to pay for the execution units of
+    /// the script for every input locked by the script
+    #[serde(default)]
+    pub assumed_execution_fee: u64,
 }
 
 impl MultisigPlan {
@@ -21,22 +73,48 @@ impl MultisigPlan {
             .with_context(|| anyhow!("failed to read multisig plan from {}", path.display()))
     }
 
-    pub fn hash(&self) -> ScriptHash {
-        let script = self.to_script().get(0).hash().to_bytes();
+    /// the hash of the script locking the funds: the plutus script if any,
+    /// the native script otherwise
+    pub fn script_hash(&self) -> ScriptHash {
+        if let Some(plutus) = self.to_plutus_script() {
+            return plutus.hash();
+        }
+
+        let script = self.to_native_script().hash().to_bytes();
 
         ScriptHash::from_bytes(script)
             .map_err(|error| anyhow!("Invalid hash {}", error))
             .expect("Script should be valid all the time already")
     }
 
+    #[deprecated(note = "use `script_hash`")]
+    pub fn hash(&self) -> ScriptHash {
+        self.script_hash()
+    }
+
+    /// the enterprise address of the script, see [`Self::to_address`]
     pub fn address(&self, network_id: u8) -> Address {
-        let native_bytes = self.hash();
+        let script_hash = self.script_hash();
 
-        let address = StakeCredential::from_scripthash(&native_bytes);
+        let address = StakeCredential::from_scripthash(&script_hash);
         EnterpriseAddress::new(network_id, &address).to_address()
     }
 
+    /// the enterprise address locking the funds on the `network`
+    pub fn to_address(&self, network: &NetworkInfo) -> Address {
+        self.address(network.network_info().network_id())
+    }
+
     pub fn to_script(&self) -> NativeScripts {
+        let mut scripts = NativeScripts::new();
+        scripts.add(&self.to_native_script());
+
+        scripts
+    }
+
+    /// the `quorum` of `keys` native script, within the validity interval
+    /// if the plan has a timelock
+    pub fn to_native_script(&self) -> NativeScript {
         let keys = {
             let mut scripts = NativeScripts::new();
 
@@ -47,12 +125,77 @@ impl MultisigPlan {
             scripts
         };
 
-        let mut scripts = NativeScripts::new();
         let script = ScriptNOfK::new(self.quorum, &keys);
-        let script = NativeScript::new_script_n_of_k(&script);
-        scripts.add(&script);
+        let mut script = NativeScript::new_script_n_of_k(&script);
 
-        scripts
+        // the plans without timelock keep the bare N of K script so
+        // their hash (and address) is unchanged
+        if self.has_timelock() {
+            let mut clauses = NativeScripts::new();
+            clauses.add(&script);
+            if let Some(slot) = self.valid_after {
+                let start = TimelockStart::new(&BigNum::from_str(&slot.to_string()).unwrap());
+                clauses.add(&NativeScript::new_timelock_start(&start));
+            }
+            if let Some(slot) = self.valid_before {
+                let expiry = TimelockExpiry::new(&BigNum::from_str(&slot.to_string()).unwrap());
+                clauses.add(&NativeScript::new_timelock_expiry(&expiry));
+            }
+            script = NativeScript::new_script_all(&ScriptAll::new(&clauses));
+        }
+
+        script
+    }
+
+    pub fn to_plutus_script(&self) -> Option<PlutusScript> {
+        let plutus = self.plutus.as_ref()?;
+
+        let script = match plutus.version {
+            PlutusVersion::V1 => PlutusScript::from_v1(&PlutusV1Script::new(plutus.script.clone())),
+            PlutusVersion::V2 => PlutusScript::from_v2(&PlutusV2Script::new(plutus.script.clone())),
+        };
+        Some(script)
+    }
+
+    pub fn has_timelock(&self) -> bool {
+        self.valid_after.is_some() || self.valid_before.is_some()
+    }
+
+    /// size of the script and of its redeemer (if any) in the witness set
+    pub fn script_witness_size(&self) -> usize {
+        if let Some(plutus) = &self.plutus {
+            // bytes of the script + the CBOR overhead of the witness set entry
+            return plutus.script.len() + 2 + 4 + ASSUMED_SIZE_OF_ONE_REDEEMER;
+        }
+
+        let mut set = TransactionWitnessSet::new();
+        set.set_native_scripts(&self.to_script());
+        set.to_bytes().len()
+    }
+
+    /// size added to the transaction body: the validity interval for the
+    /// timelocks and the required signers for the plutus scripts
+    pub fn body_overhead_size(&self) -> usize {
+        let mut size = 0;
+        if self.valid_after.is_some() {
+            size += ASSUMED_SIZE_OF_VALIDITY_BOUND;
+        }
+        if self.valid_before.is_some() {
+            size += ASSUMED_SIZE_OF_VALIDITY_BOUND;
+        }
+        if self.plutus.is_some() {
+            // required signers: key + set + the key hashes
+            size += 1 + 3 + self.quorum as usize * (2 + 28);
+        }
+        size
+    }
+
+    /// assumed fee for executing the plutus script of one input
+    pub fn assumed_execution_fee(&self) -> u64 {
+        self.plutus
+            .as_ref()
+            .map(|plutus| plutus.assumed_execution_fee)
+            .unwrap_or_default()
     }
 }
 
@@ -71,3 +214,97 @@ where
 
     Ed25519KeyHash::from_bytes(bytes).map_err(D::Error::custom)
 }
+
+fn deserialize_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let bytes = String::deserialize(deserializer)?;
+    hex::decode(bytes).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(extra: serde_json::Value) -> MultisigPlan {
+        let mut plan = serde_json::json!({
+            "quorum": 1,
+            "keys": [
+                "00000000000000000000000000000000000000000000000000000000",
+                "00000000000000000000000000000000000000000000000000000001",
+            ]
+        });
+        plan.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(plan).unwrap()
+    }
+
+    #[test]
+    fn timelock_changes_the_script() {
+        let bare = plan(serde_json::json!({}));
+        let timelocked = plan(serde_json::json!({ "valid_before": 1000 }));
+
+        assert!(!bare.has_timelock());
+        assert!(timelocked.has_timelock());
+        assert_ne!(
+            bare.script_hash().to_bytes(),
+            timelocked.script_hash().to_bytes()
+        );
+        assert!(timelocked.script_witness_size() > bare.script_witness_size());
+        assert_eq!(
+            timelocked.body_overhead_size(),
+            ASSUMED_SIZE_OF_VALIDITY_BOUND
+        );
+    }
+
+    #[test]
+    fn plutus_plan() {
+        let plutus = plan(serde_json::json!({
+            "plutus": {
+                "version": "v2",
+                "script": "4e4d01000033222220051200120011",
+                "assumed_execution_fee": 200000,
+            }
+        }));
+
+        assert!(plutus.to_plutus_script().is_some());
+        assert_eq!(plutus.assumed_execution_fee(), 200000);
+        assert!(plutus.body_overhead_size() > 0);
+        assert_ne!(
+            plutus.script_hash().to_bytes(),
+            plan(serde_json::json!({})).script_hash().to_bytes()
+        );
+    }
+
+    #[test]
+    fn address_from_the_script() {
+        let plan = plan(serde_json::json!({ "valid_after": 10 }));
+
+        assert_eq!(
+            plan.script_hash().to_bytes(),
+            plan.to_native_script().hash().to_bytes()
+        );
+        assert_eq!(
+            plan.to_script().get(0).to_bytes(),
+            plan.to_native_script().to_bytes()
+        );
+
+        let address = plan.to_address(&NetworkInfo::Mainnet);
+        assert_eq!(address.network_id().unwrap(), 1);
+        assert_eq!(
+            address
+                .payment_cred()
+                .and_then(|credential| credential.to_scripthash())
+                .map(|hash| hash.to_bytes()),
+            Some(plan.script_hash().to_bytes())
+        );
+        assert_eq!(
+            plan.to_address(&NetworkInfo::Testnet).to_bytes(),
+            plan.address(0).to_bytes()
+        );
+    }
+}
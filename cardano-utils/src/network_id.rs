@@ -5,6 +5,7 @@ use cardano_multiplatform_lib::builders::tx_builder::{
 use cardano_multiplatform_lib::ledger::alonzo::fees::LinearFee;
 use cardano_multiplatform_lib::ledger::common::value::{BigNum, Coin};
 use cardano_multiplatform_lib::TransactionWitnessSet;
+use dcspark_core::{Address, ParsedAddress};
 use serde::Deserialize;
 
 // set an overhead so we keep some room
@@ -13,14 +14,14 @@ use serde::Deserialize;
 // of transactions and so no deterministic fee
 const ASSUMED_OVERHEAD: usize = 50;
 
-const ASSUMED_SIZE_EMPTY_TX: usize = 17 + ASSUMED_OVERHEAD;
-const ASSUMED_SIZE_OF_ONE_INPUT: usize
+pub(crate) const ASSUMED_SIZE_EMPTY_TX: usize = 17 + ASSUMED_OVERHEAD;
+pub(crate) const ASSUMED_SIZE_OF_ONE_INPUT: usize
 = 2  // size of a CBOR array with length > 23
     + 2 // CBOR Array + size of 32
     + 32 // size of the transaction hash
     + 2  // size of an index up to 255 entries
 ;
-const ASSUMED_SIZE_OF_ONE_OUTPUT: usize
+pub(crate) const ASSUMED_SIZE_OF_ONE_OUTPUT: usize
 = 5  // size of a CBOR array with length > 24
     + 1 // array of a tuple
     + 2 // bytes of 57 entries
@@ -45,6 +46,37 @@ const ASSUMED_SIZE_OF_ONE_WITNESS: usize
 
 const DEFAULT_MAX_TX_SIZE: usize = 16384;
 
+/// the fee of a transaction of `size` bytes: `coefficient * size + constant`
+pub fn fee_for_size(size: usize, params: &LinearFee) -> Coin {
+    fee_for_extra_size(size, params)
+        .checked_add(&params.constant())
+        .unwrap()
+}
+
+/// the fee of `size` more bytes in a transaction: `coefficient * size`
+pub fn fee_for_extra_size(size: usize, params: &LinearFee) -> Coin {
+    params
+        .coefficient()
+        .checked_mul(&BigNum::from(size as u64))
+        .unwrap()
+}
+
+/// size of the script, of its witnesses and of the extra fields of the
+/// transaction body required to spend from the plan
+pub(crate) fn plan_size_overhead(plan: &MultisigPlan) -> usize {
+    let mut size = ASSUMED_OVERHEAD;
+
+    // get the size of the multisig script and of the extra
+    // fields required in the transaction body
+    size += plan.script_witness_size();
+    size += plan.body_overhead_size();
+
+    // add the size of the witnesses
+    size += plan.quorum as usize * ASSUMED_SIZE_OF_ONE_WITNESS;
+
+    size
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub enum NetworkInfo {
@@ -76,6 +108,12 @@ impl NetworkInfo {
         }
     }
 
+    /// check the given address belongs to this network
+    pub fn is_valid_address(&self, address: &ParsedAddress) -> bool {
+        let network_info = self.network_info();
+        address.is_valid_for(network_info.network_id(), network_info.protocol_magic())
+    }
+
     #[inline]
     pub fn max_tx_size(&self) -> usize {
         match self {
@@ -90,13 +128,7 @@ impl NetworkInfo {
     /// This will be used as a base for our operation
     /// of custom input selections
     pub fn assumed_empty_transaction(&self) -> Coin {
-        let fee = self.linear_fee();
-        let assumed_size_input = BigNum::from_str(&ASSUMED_SIZE_EMPTY_TX.to_string()).unwrap();
-        fee.coefficient()
-            .checked_mul(&assumed_size_input)
-            .unwrap()
-            .checked_add(&fee.constant())
-            .unwrap()
+        fee_for_size(ASSUMED_SIZE_EMPTY_TX, &self.linear_fee())
     }
 
     /// get the assumed cost of one input
@@ -104,9 +136,7 @@ impl NetworkInfo {
     /// This will be used as a base for our operation
     /// of custom input selections
     pub fn assumed_cost_one_input(&self) -> Coin {
-        let fee = self.linear_fee();
-        let assumed_size = BigNum::from_str(&ASSUMED_SIZE_OF_ONE_INPUT.to_string()).unwrap();
-        fee.coefficient().checked_mul(&assumed_size).unwrap()
+        fee_for_extra_size(ASSUMED_SIZE_OF_ONE_INPUT, &self.linear_fee())
     }
 
     /// get the assumed cost of one output, output with a native asset
@@ -114,9 +144,7 @@ impl NetworkInfo {
     /// This will be used as a base for our operation
     /// of custom input selections
     pub fn assumed_cost_one_output(&self) -> Coin {
-        let fee = self.linear_fee();
-        let assumed_size = BigNum::from_str(&ASSUMED_SIZE_OF_ONE_OUTPUT.to_string()).unwrap();
-        fee.coefficient().checked_mul(&assumed_size).unwrap()
+        fee_for_extra_size(ASSUMED_SIZE_OF_ONE_OUTPUT, &self.linear_fee())
     }
 
     /// get the assumed cost of one witness
@@ -124,9 +152,7 @@ impl NetworkInfo {
     /// This will be used as a base for our operation
     /// of custom input selections
     pub fn assumed_cost_one_witness(&self) -> Coin {
-        let fee = self.linear_fee();
-        let assumed_size = BigNum::from_str(&ASSUMED_SIZE_OF_ONE_WITNESS.to_string()).unwrap();
-        fee.coefficient().checked_mul(&assumed_size).unwrap()
+        fee_for_extra_size(ASSUMED_SIZE_OF_ONE_WITNESS, &self.linear_fee())
     }
 
     #[inline]
@@ -145,33 +171,19 @@ impl NetworkInfo {
     }
 
     pub fn estimate_size_overhead(&self, plan: &MultisigPlan) -> usize {
-        let mut size = ASSUMED_OVERHEAD;
-
-        // get the size of the multisig native script
-        size += {
-            let plan = plan.to_script();
-            let mut set = TransactionWitnessSet::new();
-            set.set_native_scripts(&plan);
-            set.to_bytes().len()
-        };
-
-        // add the size of the witnesses
-        size += plan.quorum as usize * ASSUMED_SIZE_OF_ONE_WITNESS;
-
-        size
+        plan_size_overhead(plan)
     }
 
-    /// get the assumed cost of one native script for bridge (based on quorum size)
+    /// get the assumed cost of the script for bridge (based on quorum size),
+    /// including the timelock validity interval and the plutus required signers.
+    /// The cost of executing a plutus script is not included, see
+    /// [`MultisigPlan::assumed_execution_fee`].
     ///
     /// This will be used as a base for our operation
     /// of custom input selections
     pub fn assumed_cost_native_script(&self, plan: &MultisigPlan) -> Coin {
-        let plan = plan.to_script();
-        let mut set = TransactionWitnessSet::new();
-        set.set_native_scripts(&plan);
-        let bytes = BigNum::from_str(&set.to_bytes().len().to_string()).unwrap();
-        let fee = self.linear_fee();
-        fee.coefficient().checked_mul(&bytes).unwrap()
+        let size = plan.script_witness_size() + plan.body_overhead_size();
+        fee_for_extra_size(size, &self.linear_fee())
     }
 
     pub fn assumed_cost_metadata_protocol_magic(&self, protocol_magic: impl AsRef<str>) -> Coin {
@@ -186,9 +198,7 @@ impl NetworkInfo {
             // when doing transactions
             ;
 
-        let bytes = BigNum::from_str(&len.to_string()).unwrap();
-        let fee = self.linear_fee();
-        fee.coefficient().checked_mul(&bytes).unwrap()
+        fee_for_extra_size(len, &self.linear_fee())
     }
 
     pub fn linear_fee(&self) -> LinearFee {
@@ -262,9 +272,30 @@ impl NetworkInfo {
     }
 }
 
+/// check an address against a [`NetworkInfo`]
+pub trait AddressNetworkExt {
+    fn is_valid_for(&self, network: &NetworkInfo) -> bool;
+}
+
+impl AddressNetworkExt for ParsedAddress {
+    fn is_valid_for(&self, network: &NetworkInfo) -> bool {
+        network.is_valid_address(self)
+    }
+}
+
+/// addresses that cannot be parsed are never valid
+impl AddressNetworkExt for Address {
+    fn is_valid_for(&self, network: &NetworkInfo) -> bool {
+        self.parse()
+            .map(|address| network.is_valid_address(&address))
+            .unwrap_or(false)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata::json_to_auxiliary_data;
     use cardano_multiplatform_lib::address::{BaseAddress, StakeCredential};
     use cardano_multiplatform_lib::crypto::{
         Ed25519KeyHash, Ed25519Signature, PublicKey, TransactionHash, Vkey, Vkeywitness,
@@ -272,13 +303,12 @@ mod tests {
     };
     use cardano_multiplatform_lib::ledger::alonzo::fees::min_no_script_fee;
     use cardano_multiplatform_lib::ledger::common::value::Value;
-    use cardano_multiplatform_lib::metadata::{
-        AuxiliaryData, GeneralTransactionMetadata, TransactionMetadatum,
-    };
+    use cardano_multiplatform_lib::metadata::MetadataJsonSchema;
     use cardano_multiplatform_lib::{
         AssetName, MultiAsset, PolicyID, Transaction, TransactionBody, TransactionInput,
         TransactionInputs, TransactionOutput, TransactionOutputs,
     };
+    use deps::serde_json;
 
     fn empty_transaction_cost(linear_fee: &LinearFee) -> Coin {
         let inputs = TransactionInputs::new();
@@ -292,22 +322,14 @@ mod tests {
     }
 
     fn protocol_magic_metadata_fee(linear_fee: &LinearFee, magic: &str) -> Coin {
-        let auxiliary_data = {
-            let mut auxiliary_data = AuxiliaryData::new();
-            let mut metadata = GeneralTransactionMetadata::new();
-            metadata.insert(
-                &BigNum::from_str("87").unwrap(),
-                &TransactionMetadatum::new_text(magic.to_string())
-                    .map_err(|error| {
-                        anyhow::anyhow!("Failed to encode the magic metadata: {}", error)
-                    })
-                    .unwrap(),
-            );
-            auxiliary_data.set_metadata(&metadata);
-            auxiliary_data
-        };
-        let size = BigNum::from_str(&auxiliary_data.to_bytes().len().to_string()).unwrap();
-        linear_fee.coefficient().checked_mul(&size).unwrap()
+        let auxiliary_data = json_to_auxiliary_data(
+            &serde_json::json!({ "87": magic }),
+            MetadataJsonSchema::NoConversions,
+            usize::MAX,
+        )
+        .unwrap()
+        .unwrap();
+        fee_for_extra_size(auxiliary_data.to_bytes().len(), linear_fee)
     }
 
     fn one_input_transaction_fee(linear_fee: &LinearFee) -> Coin {
@@ -467,6 +489,19 @@ mod tests {
         )
     }
 
+    #[test]
+    fn address_network() {
+        let mainnet =
+            Address::new_static("addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8");
+        let testnet =
+            Address::new_static("addr_test1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspjrlsz");
+
+        assert!(mainnet.is_valid_for(&NetworkInfo::Mainnet));
+        assert!(!mainnet.is_valid_for(&NetworkInfo::Testnet));
+        assert!(testnet.is_valid_for(&NetworkInfo::Testnet));
+        assert!(!Address::new_static("invalid").is_valid_for(&NetworkInfo::Mainnet));
+    }
+
     #[test]
     fn mainnet_assumed_costs() {
         test_assumed_cost(NetworkInfo::Mainnet);
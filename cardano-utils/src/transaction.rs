@@ -0,0 +1,195 @@
+use crate::metadata::json_to_auxiliary_data;
+use crate::multisig_plan::{MultisigPlan, ASSUMED_SIZE_OF_VALIDITY_BOUND};
+use crate::network_id::{
+    plan_size_overhead, ASSUMED_SIZE_EMPTY_TX, ASSUMED_SIZE_OF_ONE_INPUT,
+    ASSUMED_SIZE_OF_ONE_OUTPUT,
+};
+use crate::utxo::{pointer_to_cml_input, utxo_builder_to_cml_output};
+use anyhow::anyhow;
+use cardano_multiplatform_lib::ledger::common::value::{BigNum, Coin};
+use cardano_multiplatform_lib::metadata::MetadataJsonSchema;
+use cardano_multiplatform_lib::{TransactionBody, TransactionInputs, TransactionOutputs};
+use dcspark_core::tx::{TransactionDraft, UTxODetails};
+use deps::bigdecimal::ToPrimitive;
+use deps::serde_json;
+
+/// build the body of the cardano transaction from the [`TransactionDraft`]
+///
+/// The inputs and outputs are kept in the order of the draft (the fixed
+/// ones first). The metadata of the draft belongs to the auxiliary data
+/// and is not part of the body.
+pub fn draft_to_cml_body(draft: &TransactionDraft) -> anyhow::Result<TransactionBody> {
+    let mut inputs = TransactionInputs::new();
+    for input in draft.inputs() {
+        inputs.add(&pointer_to_cml_input(&input.pointer)?);
+    }
+
+    let mut outputs = TransactionOutputs::new();
+    for output in draft.outputs() {
+        outputs.add(&utxo_builder_to_cml_output(output)?);
+    }
+
+    let fee = Coin::from(
+        draft
+            .fee
+            .to_u64()
+            .ok_or_else(|| anyhow!("Can't convert fee {} to u64", draft.fee))?,
+    );
+    let ttl = draft.valid_until.map(|slot| BigNum::from(u64::from(slot)));
+
+    let mut body = TransactionBody::new(&inputs, &outputs, &fee, ttl);
+    if let Some(valid_from) = draft.valid_from {
+        body.set_validity_start_interval(&BigNum::from(u64::from(valid_from)));
+    }
+
+    Ok(body)
+}
+
+/// the assumed size of the input in the transaction, with the redeemer
+/// of its [`UTxODetails::script_spend`] if any
+pub fn estimate_input_size(input: &UTxODetails) -> usize {
+    ASSUMED_SIZE_OF_ONE_INPUT + estimate_redeemer_size(input)
+}
+
+/// the size of the redeemer of the [`UTxODetails::script_spend`] of the
+/// input, zero if the input is not spent from a script
+pub fn estimate_redeemer_size(input: &UTxODetails) -> usize {
+    input
+        .script_spend
+        .as_ref()
+        .map_or(0, |spend| spend.redeemer_size)
+}
+
+/// the size of the auxiliary data of the metadata, the JSON that can't be
+/// encoded is counted as its text (always longer than its CBOR encoding)
+fn estimate_metadata_size(metadata: &serde_json::Value) -> usize {
+    match json_to_auxiliary_data(metadata, MetadataJsonSchema::NoConversions, usize::MAX) {
+        Ok(Some(auxiliary_data)) => auxiliary_data.to_bytes().len(),
+        Ok(None) => 0,
+        Err(_) => metadata.to_string().len(),
+    }
+}
+
+/// the assumed size of the signed transaction of the draft spending from
+/// the `plan`
+///
+/// The size is accounted from the assumed sizes of the inputs, outputs and
+/// witnesses, without building nor signing the CML transaction. This is the
+/// size the fee estimators work with, see [`crate::network_id::fee_for_size`]
+/// for its fee.
+pub fn estimate_tx_size(draft: &TransactionDraft, plan: &MultisigPlan) -> usize {
+    let mut size = ASSUMED_SIZE_EMPTY_TX + plan_size_overhead(plan);
+
+    size += draft.inputs().map(estimate_input_size).sum::<usize>();
+    size += draft.outputs().count() * ASSUMED_SIZE_OF_ONE_OUTPUT;
+    size += estimate_metadata_size(&draft.metadata);
+
+    // the bounds of the plan timelock are already in its overhead
+    if draft.valid_from.is_some() && plan.valid_after.is_none() {
+        size += ASSUMED_SIZE_OF_VALIDITY_BOUND;
+    }
+    if draft.valid_until.is_some() && plan.valid_before.is_none() {
+        size += ASSUMED_SIZE_OF_VALIDITY_BOUND;
+    }
+
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_id::{fee_for_size, NetworkInfo};
+    use dcspark_core::tx::{ScriptSpend, TransactionId, UtxoPointer};
+    use dcspark_core::{Address, OutputIndex, SlotNumber, Value};
+
+    fn input() -> UTxODetails {
+        UTxODetails {
+            pointer: UtxoPointer {
+                transaction_id: TransactionId::new_static(
+                    "1c2d0b7b5b2b9a8ce7bd2a5bd0ae0d6d8d6f4fd1b5d1cf3e7d0e7e7f4a6e9b6c",
+                ),
+                output_index: OutputIndex::new(1),
+            },
+            address: Address::new_static("address"),
+            value: Value::from(2_000_000u64),
+            assets: vec![],
+            metadata: Default::default(),
+            datum: None,
+            script_ref: None,
+            extra: None,
+            creation_height: None,
+            script_spend: None,
+        }
+    }
+
+    fn plan() -> MultisigPlan {
+        serde_json::from_value(serde_json::json!({
+            "quorum": 2,
+            "keys": [
+                "00000000000000000000000000000000000000000000000000000000",
+                "00000000000000000000000000000000000000000000000000000001",
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn body_from_draft() {
+        let draft = TransactionDraft::new(vec![input()], vec![], Value::from(170_000u64))
+            .with_validity(Some(SlotNumber::new(10)), Some(SlotNumber::new(42)));
+
+        let body = draft_to_cml_body(&draft).unwrap();
+
+        assert_eq!(body.inputs().len(), 1);
+        assert_eq!(body.outputs().len(), 0);
+        assert_eq!(u64::from(body.fee()), 170_000);
+        assert_eq!(body.ttl().map(u64::from), Some(42));
+        assert_eq!(body.validity_start_interval().map(u64::from), Some(10));
+    }
+
+    #[test]
+    fn tx_size_from_draft() {
+        let plan = plan();
+        let empty = estimate_tx_size(&TransactionDraft::default(), &plan);
+        assert_eq!(
+            empty,
+            NetworkInfo::Mainnet.estimated_size_empty()
+                + NetworkInfo::Mainnet.estimate_size_overhead(&plan)
+        );
+
+        let draft = TransactionDraft::new(vec![input()], vec![], Value::from(170_000u64));
+        let size = estimate_tx_size(&draft, &plan);
+        assert_eq!(size, empty + ASSUMED_SIZE_OF_ONE_INPUT);
+        assert!(size >= draft_to_cml_body(&draft).unwrap().to_bytes().len());
+
+        let mut script_input = input();
+        script_input.script_spend = Some(ScriptSpend {
+            mem: 1000,
+            steps: 1000,
+            redeemer_size: 20,
+        });
+        let draft = TransactionDraft::new(vec![script_input], vec![], Value::from(170_000u64))
+            .with_validity(None, Some(SlotNumber::new(42)));
+        assert_eq!(
+            estimate_tx_size(&draft, &plan),
+            size + 20 + ASSUMED_SIZE_OF_VALIDITY_BOUND
+        );
+
+        let draft = draft.with_metadata(std::sync::Arc::new(
+            serde_json::json!({ "87": "mainnet.cardano-evm.c1" }),
+        ));
+        assert!(estimate_tx_size(&draft, &plan) > size + 20 + ASSUMED_SIZE_OF_VALIDITY_BOUND);
+    }
+
+    #[test]
+    fn fee_of_size() {
+        let params = NetworkInfo::Mainnet.linear_fee();
+
+        assert_eq!(u64::from(fee_for_size(0, &params)), 155_381);
+        assert_eq!(u64::from(fee_for_size(100, &params)), 155_381 + 44 * 100);
+        assert_eq!(
+            fee_for_size(NetworkInfo::Mainnet.estimated_size_empty(), &params),
+            NetworkInfo::Mainnet.assumed_empty_transaction()
+        );
+    }
+}
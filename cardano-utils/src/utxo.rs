@@ -1,11 +1,13 @@
-use crate::fingerprint;
 use crate::payment_credentials::CardanoPaymentCredentials;
 use anyhow::anyhow;
 use cardano_multiplatform_lib::builders::input_builder::{InputBuilderResult, SingleInputBuilder};
+use cardano_multiplatform_lib::builders::output_builder::SingleOutputBuilderResult;
 use cardano_multiplatform_lib::crypto::TransactionHash;
 use cardano_multiplatform_lib::ledger::common::value::{BigNum, Coin};
 use cardano_multiplatform_lib::plutus::ScriptRef;
-use cardano_multiplatform_lib::{Datum, MultiAsset, PolicyID, TransactionInput, TransactionOutput};
+use cardano_multiplatform_lib::{
+    Datum, MultiAsset, PolicyID, TransactionInput, TransactionOutput, TransactionUnspentOutput,
+};
 use dcspark_core::tx::{TransactionAsset, TransactionId, UTxOBuilder, UTxODetails, UtxoPointer};
 use dcspark_core::{Address, AssetName, OutputIndex, PolicyId, Regulated, TokenId, Value};
 use deps::bigdecimal::ToPrimitive;
@@ -20,39 +22,23 @@ pub struct CardanoUTxOExtra {
     datum: Option<Datum>,
 }
 
+/// the cardano input spending the UTxO at `pointer`
+pub fn pointer_to_cml_input(pointer: &UtxoPointer) -> anyhow::Result<TransactionInput> {
+    let transaction_id = TransactionHash::from_hex(pointer.transaction_id.as_ref())
+        .map_err(|err| anyhow!("can't convert input during hash conversion: {}", err))?;
+    let index = BigNum::from(u64::from(pointer.output_index));
+
+    Ok(TransactionInput::new(&transaction_id, &index))
+}
+
 pub fn utxo_details_to_cml_input(
     details: &UTxODetails,
     creds_kind: &CardanoPaymentCredentials,
 ) -> anyhow::Result<InputBuilderResult> {
-    let transaction_id = TransactionHash::from_hex(details.pointer.transaction_id.as_ref())
-        .map_err(|err| anyhow!("can't convert input during hash conversion: {}", err))?;
-    let index = BigNum::from(u64::from(details.pointer.output_index));
-
-    let address =
-        cardano_multiplatform_lib::address::Address::from_bech32(details.address.as_ref())
-            .map_err(|err| anyhow!("can't convert input during address conversion: {}", err))?;
-
-    let mut assets_map = HashMap::new();
-    details.assets.iter().for_each(|asset: &TransactionAsset| {
-        assets_map.insert(asset.fingerprint.clone(), asset.clone());
-    });
-
-    let value = tokens_to_csl_value(&details.value, &assets_map)
-        .map_err(|err| anyhow!("can't convert value: {}", err))?;
-
-    let mut output = TransactionOutput::new(&address, &value);
-    if let Some(extra) = &details.extra {
-        let utxo_extra: CardanoUTxOExtra = serde_json::from_str(extra)
-            .map_err(|err| anyhow!("can't parse cardano extra: {}", err))?;
-        if let Some(script_ref) = &utxo_extra.script_ref {
-            output.set_script_ref(script_ref);
-        }
-        if let Some(datum) = &utxo_extra.datum {
-            output.set_datum(datum);
-        }
-    }
+    let input = pointer_to_cml_input(&details.pointer)?;
+    let output = utxo_details_to_cml_output(details)?;
 
-    let builder = SingleInputBuilder::new(&TransactionInput::new(&transaction_id, &index), &output);
+    let builder = SingleInputBuilder::new(&input, &output);
 
     match creds_kind {
         CardanoPaymentCredentials::PaymentKey => builder
@@ -80,7 +66,7 @@ pub fn utxo_details_from_io(
     let (ada_value, tokens) = csl_value_to_tokens(&value.1.amount())?;
     Ok(UTxODetails {
         pointer: UtxoPointer {
-            transaction_id: TransactionId::new(value.0.transaction_id().to_string()),
+            transaction_id: TransactionId::new_unchecked(value.0.transaction_id().to_string()),
             output_index: OutputIndex::new(u64::from(value.0.index())),
         },
         address: Address::new(
@@ -93,6 +79,13 @@ pub fn utxo_details_from_io(
         value: ada_value,
         assets: tokens.values().cloned().collect::<Vec<_>>(),
         metadata: Arc::new(Default::default()),
+        datum: value.1.datum().as_ref().map(datum_from_cml).transpose()?,
+        script_ref: value
+            .1
+            .script_ref()
+            .as_ref()
+            .map(script_ref_from_cml)
+            .transpose()?,
         extra: Some(
             serde_json::to_string(&CardanoUTxOExtra {
                 script_ref: value.1.script_ref(),
@@ -100,16 +93,61 @@ pub fn utxo_details_from_io(
             })
             .map_err(|err| anyhow!("can't serialize extra: {}", err))?,
         ),
+        // the transaction output does not tell the block it was created in
+        creation_height: None,
+        script_spend: None,
     })
 }
 
+fn datum_from_cml(datum: &Datum) -> anyhow::Result<dcspark_core::tx::Datum> {
+    dcspark_core::tx::Datum::from_cbor(&datum.to_bytes())
+        .map_err(|err| anyhow!("can't convert datum: {}", err))
+}
+
+fn script_ref_from_cml(script_ref: &ScriptRef) -> anyhow::Result<dcspark_core::tx::ScriptRef> {
+    dcspark_core::tx::ScriptRef::from_cbor(&script_ref.to_bytes())
+        .map_err(|err| anyhow!("can't convert script reference: {}", err))
+}
+
+/// set the datum and the script reference of the output. The values
+/// from the cardano extra (if any) are applied afterward and take precedence.
+fn set_output_data(
+    output: &mut TransactionOutput,
+    datum: &Option<dcspark_core::tx::Datum>,
+    script_ref: &Option<dcspark_core::tx::ScriptRef>,
+) -> anyhow::Result<()> {
+    if let Some(datum) = datum {
+        let datum = Datum::from_bytes(datum.to_cbor())
+            .map_err(|err| anyhow!("can't convert datum: {}", err))?;
+        output.set_datum(&datum);
+    }
+    if let Some(script_ref) = script_ref {
+        let script_ref = ScriptRef::from_bytes(script_ref.to_cbor())
+            .map_err(|err| anyhow!("can't convert script reference: {}", err))?;
+        output.set_script_ref(&script_ref);
+    }
+    Ok(())
+}
+
+/// convert the value to the `u64` of the cardano ledger, failing if it is
+/// negative or above `u64::MAX`. The decimals are truncated.
+fn value_to_u64(value: &Value<Regulated>) -> anyhow::Result<u64> {
+    value
+        .to_u64()
+        .ok_or_else(|| anyhow!("Value {value} is not within the boundaries of a u64"))
+}
+
 fn value_to_csl_coin(value: &Value<Regulated>) -> anyhow::Result<Coin> {
-    Ok(Coin::from(value.to_u64().ok_or_else(|| {
-        anyhow!("Can't convert input balance to u64")
-    })?))
+    Ok(Coin::from(
+        value_to_u64(value).map_err(|err| anyhow!("Can't convert the coin: {err}"))?,
+    ))
 }
 
-fn tokens_to_csl_value(
+/// build the cardano value holding the `coin` and the `assets`, reverse
+/// of [`csl_value_to_tokens`]
+///
+/// The coin and every asset quantity must fit in a `u64`.
+pub fn tokens_to_csl_value(
     coin: &Value<Regulated>,
     assets: &HashMap<TokenId, TransactionAsset>,
 ) -> anyhow::Result<cardano_multiplatform_lib::ledger::common::value::Value> {
@@ -124,19 +162,19 @@ fn tokens_to_csl_value(
             let policy_id = PolicyID::from_bytes(decoded_policy_id)
                 .map_err(|error| anyhow!("Failed to decode the policy id: {error}"))?;
 
-            let decoded_asset_name = hex::decode(asset.asset_name.as_ref())
-                .map_err(|err| anyhow!("Failed to decode the asset name: hex error {err}"))?;
+            let decoded_asset_name = asset
+                .asset_name
+                .as_bytes()
+                .map_err(|err| anyhow!("Failed to decode the asset name: {err}"))?;
             let asset_name = cardano_multiplatform_lib::AssetName::new(decoded_asset_name)
                 .map_err(|err| anyhow!("Failed to decode asset name: {err}"))?;
 
-            let value =
-                BigNum::from_str(&asset.quantity.truncate().to_string()).map_err(|error| {
-                    anyhow!(
-                        "Value {value} ({fingerprint}) was not within the boundaries: {error}",
-                        value = asset.quantity.truncate(),
-                        fingerprint = asset.fingerprint,
-                    )
-                })?;
+            let value = BigNum::from(value_to_u64(&asset.quantity).map_err(|error| {
+                anyhow!(
+                    "Can't convert the quantity of {fingerprint}: {error}",
+                    fingerprint = asset.fingerprint,
+                )
+            })?);
 
             multi_assets.set_asset(&policy_id, &asset_name, &value);
         }
@@ -184,46 +222,52 @@ fn csl_coin_to_value(value: &Coin) -> anyhow::Result<Value<Regulated>> {
     Ok(value)
 }
 
-fn csl_value_to_tokens(
+/// split the cardano value in its coin and its assets, indexed by
+/// fingerprint
+pub fn csl_value_to_tokens(
     value: &cardano_multiplatform_lib::ledger::common::value::Value,
 ) -> anyhow::Result<(Value<Regulated>, HashMap<TokenId, TransactionAsset>)> {
     let coin = csl_coin_to_value(&value.coin())?;
     let mut tokens = HashMap::<TokenId, TransactionAsset>::new();
     multiasset_iter(value, |policy_id, asset_name, quantity| {
         let policy_id = PolicyId::new(hex::encode(policy_id.to_bytes()));
-        let asset_name = AssetName::new(hex::encode(asset_name.to_bytes()));
-        let fingerprint = fingerprint(&policy_id, &asset_name)
-            .map_err(|err| anyhow!("Can't create fingerprint {err}"))?;
+        let asset_name = AssetName::from_bytes(&asset_name.to_bytes())
+            .map_err(|err| anyhow!("Invalid asset name: {err}"))?;
         let quantity = quantity.ok_or_else(|| anyhow!("not found asset quantity"))?;
-        let asset = TransactionAsset {
-            policy_id,
-            asset_name,
-            fingerprint: fingerprint.clone(),
-            quantity: csl_coin_to_value(&quantity)?,
-        };
-        tokens.insert(fingerprint, asset);
+        let asset = TransactionAsset::new(policy_id, asset_name, csl_coin_to_value(&quantity)?)
+            .map_err(|err| anyhow!("Can't create fingerprint {err}"))?;
+        tokens.insert(asset.fingerprint.clone(), asset);
         Ok(())
     })?;
 
     Ok((coin, tokens))
 }
 
-pub fn utxo_builder_to_cml_output(builder: &UTxOBuilder) -> anyhow::Result<TransactionOutput> {
-    let address =
-        cardano_multiplatform_lib::address::Address::from_bech32(builder.address.as_ref())
-            .map_err(|err| anyhow!("can't convert output during address conversion: {}", err))?;
+/// the cardano output of the `address`, the values from the cardano
+/// extra (if any) take precedence over the `datum` and `script_ref`.
+fn to_cml_output(
+    address: &Address,
+    coin: &Value<Regulated>,
+    assets: &[TransactionAsset],
+    datum: &Option<dcspark_core::tx::Datum>,
+    script_ref: &Option<dcspark_core::tx::ScriptRef>,
+    extra: &Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let address = cardano_multiplatform_lib::address::Address::from_bech32(address.as_ref())
+        .map_err(|err| anyhow!("can't convert output during address conversion: {}", err))?;
 
     let mut assets_map = HashMap::new();
-    builder.assets.iter().for_each(|asset: &TransactionAsset| {
+    assets.iter().for_each(|asset: &TransactionAsset| {
         assets_map.insert(asset.fingerprint.clone(), asset.clone());
     });
 
-    let value = tokens_to_csl_value(&builder.value, &assets_map)
+    let value = tokens_to_csl_value(coin, &assets_map)
         .map_err(|err| anyhow!("can't convert value: {}", err))?;
 
     let mut output = TransactionOutput::new(&address, &value);
+    set_output_data(&mut output, datum, script_ref)?;
 
-    if let Some(extra) = &builder.extra {
+    if let Some(extra) = extra {
         let utxo_extra: CardanoUTxOExtra = serde_json::from_str(extra)
             .map_err(|err| anyhow!("can't parse cardano extra: {}", err))?;
         if let Some(script_ref) = &utxo_extra.script_ref {
@@ -237,6 +281,90 @@ pub fn utxo_builder_to_cml_output(builder: &UTxOBuilder) -> anyhow::Result<Trans
     Ok(output)
 }
 
+pub fn utxo_builder_to_cml_output(builder: &UTxOBuilder) -> anyhow::Result<TransactionOutput> {
+    to_cml_output(
+        &builder.address,
+        &builder.value,
+        &builder.assets,
+        &builder.datum,
+        &builder.script_ref,
+        &builder.extra,
+    )
+}
+
+/// the minimum value of ADA (in lovelace) the ledger requires in the output
+///
+/// The requirement depends on the serialized size of the output, its assets,
+/// datum and script reference included, times the `coinsPerUTxOByte`
+/// protocol parameter.
+pub fn min_ada_required(
+    output: &UTxOBuilder,
+    coins_per_utxo_byte: &BigNum,
+) -> anyhow::Result<Value<Regulated>> {
+    let min = min_ada_required_with_value(output, &output.value, coins_per_utxo_byte)?;
+    if min <= output.value {
+        return Ok(min);
+    }
+
+    // the encoding of the value itself is part of the size of the
+    // output: check again with the value topped up
+    min_ada_required_with_value(output, &min, coins_per_utxo_byte)
+}
+
+fn min_ada_required_with_value(
+    output: &UTxOBuilder,
+    value: &Value<Regulated>,
+    coins_per_utxo_byte: &BigNum,
+) -> anyhow::Result<Value<Regulated>> {
+    let mut output = output.clone();
+    output.value = value.clone();
+    let output = utxo_builder_to_cml_output(&output)?;
+
+    let lovelace = cardano_multiplatform_lib::ledger::babbage::min_ada::min_ada_required(
+        &output,
+        coins_per_utxo_byte,
+    )
+    .map_err(|err| anyhow!("Can't estimate min ada {}", err))?;
+
+    csl_coin_to_value(&lovelace)
+}
+
+/// the output ready to be added to a CML `TransactionBuilder`
+pub fn utxo_builder_to_cml_output_result(
+    builder: &UTxOBuilder,
+) -> anyhow::Result<SingleOutputBuilderResult> {
+    Ok(SingleOutputBuilderResult::new(&utxo_builder_to_cml_output(
+        builder,
+    )?))
+}
+
+/// the output holding the UTxO, see [`utxo_details_to_cml_utxo`] to keep
+/// its pointer
+pub fn utxo_details_to_cml_output(details: &UTxODetails) -> anyhow::Result<TransactionOutput> {
+    to_cml_output(
+        &details.address,
+        &details.value,
+        &details.assets,
+        &details.datum,
+        &details.script_ref,
+        &details.extra,
+    )
+}
+
+pub fn utxo_details_to_cml_utxo(details: &UTxODetails) -> anyhow::Result<TransactionUnspentOutput> {
+    Ok(TransactionUnspentOutput::new(
+        &pointer_to_cml_input(&details.pointer)?,
+        &utxo_details_to_cml_output(details)?,
+    ))
+}
+
+/// reverse of [`utxo_details_to_cml_utxo`], the metadata, the creation
+/// height and the script spend of the UTxO are not known from the ledger
+/// output and are left empty.
+pub fn utxo_details_from_cml_utxo(utxo: &TransactionUnspentOutput) -> anyhow::Result<UTxODetails> {
+    utxo_details_from_io((utxo.input(), utxo.output()))
+}
+
 pub fn utxo_builder_from_output(value: TransactionOutput) -> anyhow::Result<UTxOBuilder> {
     let (ada_value, tokens) = csl_value_to_tokens(&value.amount())?;
     Ok(UTxOBuilder {
@@ -248,6 +376,12 @@ pub fn utxo_builder_from_output(value: TransactionOutput) -> anyhow::Result<UTxO
         ),
         value: ada_value,
         assets: tokens.values().cloned().collect::<Vec<_>>(),
+        datum: value.datum().as_ref().map(datum_from_cml).transpose()?,
+        script_ref: value
+            .script_ref()
+            .as_ref()
+            .map(script_ref_from_cml)
+            .transpose()?,
         extra: Some(
             serde_json::to_string(&CardanoUTxOExtra {
                 script_ref: value.script_ref(),
@@ -257,3 +391,169 @@ pub fn utxo_builder_from_output(value: TransactionOutput) -> anyhow::Result<UTxO
         ),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deps::bigdecimal::BigDecimal;
+
+    fn asset(name: &str, quantity: Value<Regulated>) -> TransactionAsset {
+        TransactionAsset::new(
+            PolicyId::new("00000000000000000000000000000000000000000000000000000000"),
+            AssetName::from_utf8(name).unwrap(),
+            quantity,
+        )
+        .unwrap()
+    }
+
+    fn assets(assets: Vec<TransactionAsset>) -> HashMap<TokenId, TransactionAsset> {
+        assets
+            .into_iter()
+            .map(|asset| (asset.fingerprint.clone(), asset))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let coin = Value::from(1_500_000u64);
+        let tokens = assets(vec![
+            asset("tDRIP", Value::from(10u64)),
+            asset("tMILK", Value::from(u64::MAX)),
+        ]);
+
+        let value = tokens_to_csl_value(&coin, &tokens).unwrap();
+        assert_eq!(u64::from(value.coin()), 1_500_000);
+
+        let (round_coin, round_tokens) = csl_value_to_tokens(&value).unwrap();
+        assert_eq!(round_coin, coin);
+        assert_eq!(round_tokens, tokens);
+    }
+
+    fn details() -> UTxODetails {
+        UTxODetails {
+            pointer: UtxoPointer {
+                transaction_id: TransactionId::new_static(
+                    "1c2d0b7b5b2b9a8ce7bd2a5bd0ae0d6d8d6f4fd1b5d1cf3e7d0e7e7f4a6e9b6c",
+                ),
+                output_index: OutputIndex::new(1),
+            },
+            address: Address::new_static(
+                "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
+            ),
+            value: Value::from(2_000_000u64),
+            assets: vec![asset("tDRIP", Value::from(10u64))],
+            metadata: Default::default(),
+            datum: None,
+            script_ref: None,
+            extra: None,
+            creation_height: None,
+            script_spend: None,
+        }
+    }
+
+    #[test]
+    fn utxo_round_trip() {
+        let details = details();
+
+        let utxo = utxo_details_to_cml_utxo(&details).unwrap();
+        assert_eq!(u64::from(utxo.input().index()), 1);
+
+        let round = utxo_details_from_cml_utxo(&utxo).unwrap();
+        assert_eq!(round.pointer, details.pointer);
+        assert_eq!(round.address, details.address);
+        assert_eq!(round.value, details.value);
+        assert_eq!(round.assets, details.assets);
+        assert_eq!(round.datum, None);
+
+        let builder = UTxOBuilder::new(details.address.clone(), details.value.clone(), vec![]);
+        let output = utxo_builder_to_cml_output(&builder).unwrap();
+        assert_eq!(
+            utxo_builder_from_output(output).unwrap().value,
+            builder.value
+        );
+    }
+
+    #[test]
+    fn utxo_round_trip_with_datum_and_script_ref() {
+        let datum_hash = dcspark_core::tx::Datum::Hash(
+            dcspark_core::Hash32::from_hex(
+                "aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de",
+            )
+            .unwrap(),
+        );
+        // plutus data: the integer 42
+        let inline_datum = dcspark_core::tx::Datum::Inline(vec![0x18, 0x2a]);
+        // native script: `[0, [1, []]]` (all of no scripts)
+        let script_ref = dcspark_core::tx::ScriptRef::new(vec![0x82, 0x00, 0x82, 0x01, 0x80]);
+
+        for (datum, script_ref) in [
+            (Some(inline_datum), None),
+            (Some(datum_hash), None),
+            (None, Some(script_ref.clone())),
+            (
+                Some(dcspark_core::tx::Datum::Inline(vec![0x18, 0x2a])),
+                Some(script_ref),
+            ),
+        ] {
+            let mut details = details();
+            details.datum = datum.clone();
+            details.script_ref = script_ref.clone();
+
+            let utxo = utxo_details_to_cml_utxo(&details).unwrap();
+            let round = utxo_details_from_cml_utxo(&utxo).unwrap();
+            assert_eq!(round.datum, datum);
+            assert_eq!(round.script_ref, script_ref);
+
+            let mut builder =
+                UTxOBuilder::new(details.address.clone(), details.value.clone(), vec![]);
+            builder.datum = datum.clone();
+            builder.script_ref = script_ref.clone();
+            let round =
+                utxo_builder_from_output(utxo_builder_to_cml_output(&builder).unwrap()).unwrap();
+            assert_eq!(round.datum, datum);
+            assert_eq!(round.script_ref, script_ref);
+        }
+    }
+
+    #[test]
+    fn min_ada_for_output() {
+        let coins_per_utxo_byte = BigNum::from(4310u64);
+        let pure = UTxOBuilder::new(
+            Address::new_static("addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj"),
+            Value::zero(),
+            vec![],
+        );
+        let mut with_assets = pure.clone();
+        with_assets.assets = vec![asset("tDRIP", Value::from(10u64))];
+
+        let min = min_ada_required(&pure, &coins_per_utxo_byte).unwrap();
+        assert!(min > Value::zero());
+        assert!(min_ada_required(&with_assets, &coins_per_utxo_byte).unwrap() > min);
+
+        // the output topped up to the minimum is enough
+        let mut topped_up = pure;
+        topped_up.value = min.clone();
+        assert_eq!(
+            min_ada_required(&topped_up, &coins_per_utxo_byte).unwrap(),
+            min
+        );
+    }
+
+    #[test]
+    fn tokens_out_of_bounds() {
+        let above_u64 = Value::new(BigDecimal::from(u64::MAX) + BigDecimal::from(1u64));
+        let negative = Value::new(BigDecimal::from(-1i64));
+
+        assert!(tokens_to_csl_value(&above_u64, &HashMap::new()).is_err());
+        assert!(tokens_to_csl_value(
+            &Value::from(1_000_000u64),
+            &assets(vec![asset("tDRIP", above_u64)])
+        )
+        .is_err());
+        assert!(tokens_to_csl_value(
+            &Value::from(1_000_000u64),
+            &assets(vec![asset("tDRIP", negative)])
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::TokenId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// identify either the native asset of the blockchain (ada, ether...) or
+/// one of the tokens
+///
+/// Prefer this type over the [`TokenId::MAIN`] sentinel when a collection
+/// holds both the native asset and the tokens: the native asset has to be
+/// handled explicitly.
+///
+/// For compatibility it is serialized as a [`TokenId`], the native asset
+/// being the [`TokenId::MAIN`].
+///
+/// ```
+/// use dcspark_core::{AssetId, TokenId};
+///
+/// assert!(AssetId::from(TokenId::MAIN).is_native());
+///
+/// let token = AssetId::from(TokenId::new_static("asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc3"));
+/// assert!(!token.is_native());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetId {
+    #[default]
+    Native,
+    Token(TokenId),
+}
+
+impl AssetId {
+    pub fn is_native(&self) -> bool {
+        matches!(self, Self::Native)
+    }
+
+    /// the [`TokenId`] of the token, `None` for the native asset
+    pub fn token_id(&self) -> Option<&TokenId> {
+        match self {
+            Self::Native => None,
+            Self::Token(token_id) => Some(token_id),
+        }
+    }
+}
+
+impl From<TokenId> for AssetId {
+    fn from(token_id: TokenId) -> Self {
+        if token_id == TokenId::MAIN {
+            Self::Native
+        } else {
+            Self::Token(token_id)
+        }
+    }
+}
+
+impl<'a> From<&'a TokenId> for AssetId {
+    fn from(token_id: &'a TokenId) -> Self {
+        Self::from(token_id.clone())
+    }
+}
+
+impl From<AssetId> for TokenId {
+    fn from(asset_id: AssetId) -> Self {
+        match asset_id {
+            AssetId::Native => TokenId::MAIN,
+            AssetId::Token(token_id) => token_id,
+        }
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Native => f.write_str("native asset"),
+            Self::Token(token_id) => token_id.fmt(f),
+        }
+    }
+}
+
+impl Serialize for AssetId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Native => TokenId::MAIN.serialize(serializer),
+            Self::Token(token_id) => token_id.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        TokenId::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn main_token_is_native() {
+        assert_eq!(AssetId::from(TokenId::MAIN), AssetId::Native);
+        assert_eq!(TokenId::from(AssetId::Native), TokenId::MAIN);
+        assert_eq!(AssetId::default(), AssetId::Native);
+        assert_eq!(AssetId::Native.token_id(), None);
+    }
+
+    #[test]
+    fn serde_compatible_with_token_id() {
+        let token = TokenId::new_static("token");
+
+        for (asset_id, token_id) in [
+            (AssetId::Native, TokenId::MAIN),
+            (AssetId::Token(token.clone()), token),
+        ] {
+            let json = deps::serde_json::to_string(&asset_id).unwrap();
+
+            assert_eq!(json, deps::serde_json::to_string(&token_id).unwrap());
+            assert_eq!(
+                deps::serde_json::from_str::<AssetId>(&json).unwrap(),
+                asset_id
+            );
+        }
+    }
+}
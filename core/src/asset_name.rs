@@ -1,14 +1,37 @@
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, fmt};
+use thiserror::Error;
 
 /// identify an asset name through the protocol transfer
 ///
 /// asset name is always represented as `[0; n]` encoded
 /// in hexadecimal, n - is equal to the length of the set of bytes (there's no standard length)
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+///
+/// Prefer [`AssetName::from_hex`] and [`AssetName::from_utf8`] over
+/// [`AssetName::new`]: they make explicit how the name is encoded and
+/// store it in the canonical (lowercase) hexadecimal form.
+///
+/// ```
+/// use dcspark_core::AssetName;
+///
+/// let name = AssetName::from_utf8("PATATE").unwrap();
+/// assert_eq!(name, AssetName::from_hex("504154415445").unwrap());
+/// assert_eq!(name.as_bytes().unwrap(), b"PATATE");
+/// assert_eq!(name.to_utf8_lossy(), "PATATE");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 #[serde(transparent)]
 pub struct AssetName(Cow<'static, str>);
 
+#[derive(Debug, Error)]
+pub enum AssetNameError {
+    #[error("Asset name is not valid hexadecimal: {0}")]
+    InvalidHex(#[source] hex::FromHexError),
+
+    #[error("Asset name is too long: expected at most {max} bytes, got {length}", max = AssetName::MAX_LENGTH)]
+    TooLong { length: usize },
+}
+
 impl AssetName {
     /// default name of the main asset on cardano
     ///
@@ -25,6 +48,53 @@ impl AssetName {
     pub const fn new_static(asset_name: &'static str) -> Self {
         Self(Cow::Borrowed(asset_name))
     }
+
+    /// maximum length of an asset name, in bytes
+    pub const MAX_LENGTH: usize = 32;
+
+    /// create an [`AssetName`] from its hexadecimal representation
+    ///
+    /// the name is stored in lowercase so the same name always gives the
+    /// same fingerprint.
+    pub fn from_hex(asset_name: &str) -> Result<Self, AssetNameError> {
+        let bytes = hex::decode(asset_name).map_err(AssetNameError::InvalidHex)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// create an [`AssetName`] from the raw bytes of the name
+    pub fn from_bytes(asset_name: &[u8]) -> Result<Self, AssetNameError> {
+        if asset_name.len() > Self::MAX_LENGTH {
+            return Err(AssetNameError::TooLong {
+                length: asset_name.len(),
+            });
+        }
+
+        Ok(Self::new(hex::encode(asset_name)))
+    }
+
+    /// create an [`AssetName`] from a human readable name (i.e. `"PATATE"`)
+    pub fn from_utf8(asset_name: &str) -> Result<Self, AssetNameError> {
+        Self::from_bytes(asset_name.as_bytes())
+    }
+
+    /// decode the raw bytes of the name
+    ///
+    /// fails if the name was created with [`AssetName::new`] from a string
+    /// that is not hexadecimal.
+    pub fn as_bytes(&self) -> Result<Vec<u8>, AssetNameError> {
+        hex::decode(self.0.as_ref()).map_err(AssetNameError::InvalidHex)
+    }
+
+    /// human readable representation of the name, for logs and display
+    ///
+    /// the invalid UTF-8 sequences are replaced with `U+FFFD`. If the name
+    /// is not hexadecimal it is returned as is.
+    pub fn to_utf8_lossy(&self) -> String {
+        match self.as_bytes() {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => self.0.to_string(),
+        }
+    }
 }
 
 impl AsRef<str> for AssetName {
@@ -38,3 +108,54 @@ impl fmt::Display for AssetName {
         self.0.fmt(f)
     }
 }
+
+impl<'de> Deserialize<'de> for AssetName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let asset_name = String::deserialize(deserializer)?;
+
+        // existing data may hold uppercase hexadecimal or names that are not
+        // hexadecimal at all: the latter are kept as they are.
+        Ok(Self::from_hex(&asset_name).unwrap_or_else(|_| Self::new(asset_name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_hex() {
+        let name = AssetName::from_hex("50415441FF").unwrap();
+
+        assert_eq!(name.as_ref(), "50415441ff");
+        assert_eq!(name.as_bytes().unwrap(), [0x50, 0x41, 0x54, 0x41, 0xff]);
+        assert_eq!(name.to_utf8_lossy(), "PATA\u{FFFD}");
+        assert!(AssetName::from_hex("PATATE").is_err());
+        assert!(matches!(
+            AssetName::from_bytes(&[0; 33]),
+            Err(AssetNameError::TooLong { length: 33 })
+        ));
+    }
+
+    #[test]
+    fn serde_compatibility() {
+        for (json, expected) in [
+            (r#""504154415445""#, "504154415445"),
+            (r#""50415441FF""#, "50415441ff"),
+            (r#""not hex""#, "not hex"),
+        ] {
+            let name: AssetName = deps::serde_json::from_str(json).unwrap();
+            assert_eq!(name.as_ref(), expected);
+        }
+
+        let name = AssetName::from_utf8("PATATE").unwrap();
+        assert_eq!(
+            deps::serde_json::to_string(&name).unwrap(),
+            r#""504154415445""#
+        );
+        assert_eq!(AssetName::new("not hex").to_utf8_lossy(), "not hex");
+    }
+}
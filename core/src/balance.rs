@@ -1,7 +1,12 @@
-use crate::Value;
+use crate::{tx::TransactionAsset, AssetId, Regulated, Value};
+use deps::bigdecimal::{BigDecimal, Signed as _};
+use serde::{Deserialize, Serialize};
 use std::{
     any::type_name,
+    collections::{hash_map, HashMap},
     fmt,
+    iter::Sum,
+    marker::PhantomData,
     ops::{Add, AddAssign, Sub, SubAssign},
 };
 
@@ -31,6 +36,23 @@ impl<Rep> Balance<Rep> {
     pub fn in_excess(&self) -> bool {
         matches!(self, Self::Excess(_))
     }
+
+    /// combine the two balances together
+    ///
+    /// this is equivalent to `self.clone() + other.clone()`
+    pub fn combine(&self, other: &Self) -> Self {
+        self.clone() + other.clone()
+    }
+
+    fn from_signed(value: BigDecimal) -> Self {
+        if value.is_negative() {
+            Self::Debt(Value::new(value.abs()))
+        } else if value.is_positive() {
+            Self::Excess(Value::new(value))
+        } else {
+            Self::Balanced
+        }
+    }
 }
 
 impl<Rep> Default for Balance<Rep> {
@@ -168,6 +190,75 @@ impl<'a, Rep> SubAssign<&'a Value<Rep>> for Balance<Rep> {
     }
 }
 
+impl<Rep> Sum for Balance<Rep> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::Balanced, Add::add)
+    }
+}
+
+impl<'a, Rep> Sum<&'a Balance<Rep>> for Balance<Rep> {
+    fn sum<I: Iterator<Item = &'a Balance<Rep>>>(iter: I) -> Self {
+        iter.fold(Self::Balanced, |acc, balance| acc.combine(balance))
+    }
+}
+
+/// the balance is serialized as a signed decimal string: `"-10"` for a
+/// debt, `"0"` when balanced and `"+10"` for an excess.
+impl<Rep> Serialize for Balance<Rep> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, Rep> Deserialize<'de> for Balance<Rep> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(BalanceVisitor(PhantomData))
+    }
+}
+
+struct BalanceVisitor<Rep>(PhantomData<fn() -> Rep>);
+
+impl<'de, Rep> serde::de::Visitor<'de> for BalanceVisitor<Rep> {
+    type Value = Balance<Rep>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "to deserialise Balance<{}> from a signed numerical or string value",
+            type_name::<Rep>()
+        )
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(Balance::from_signed(BigDecimal::from(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(Balance::from_signed(BigDecimal::from(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse::<BigDecimal>()
+            .map(Balance::from_signed)
+            .map_err(E::custom)
+    }
+}
+
 impl<Rep> fmt::Display for Balance<Rep> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -197,10 +288,120 @@ impl<Rep> fmt::Debug for Balance<Rep> {
     }
 }
 
+/// per asset [`Balance`], the native asset included
+///
+/// Assets that are balanced are not kept in the sheet.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BalanceSheet(HashMap<AssetId, Balance<Regulated>>);
+
+impl BalanceSheet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// get the balance of the given token
+    pub fn get(&self, token: &AssetId) -> Balance<Regulated> {
+        self.0.get(token).cloned().unwrap_or_default()
+    }
+
+    /// check all the tokens of the sheet are balanced
+    pub fn is_balanced(&self) -> bool {
+        self.0.values().all(Balance::balanced)
+    }
+
+    pub fn in_debt(&self) -> impl Iterator<Item = (&AssetId, &Value<Regulated>)> {
+        self.0.iter().filter_map(|(token, balance)| match balance {
+            Balance::Debt(debt) => Some((token, debt)),
+            _ => None,
+        })
+    }
+
+    pub fn in_excess(&self) -> impl Iterator<Item = (&AssetId, &Value<Regulated>)> {
+        self.0.iter().filter_map(|(token, balance)| match balance {
+            Balance::Excess(excess) => Some((token, excess)),
+            _ => None,
+        })
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, AssetId, Balance<Regulated>> {
+        self.0.iter()
+    }
+
+    pub fn add_value(&mut self, token: &AssetId, value: &Value<Regulated>) {
+        self.update(token, |balance| *balance += value);
+    }
+
+    pub fn sub_value(&mut self, token: &AssetId, value: &Value<Regulated>) {
+        self.update(token, |balance| *balance -= value);
+    }
+
+    /// add the balances of the `other` sheet to this one
+    pub fn combine(&mut self, other: &Self) {
+        for (token, balance) in other.iter() {
+            self.update(token, |current| *current = current.combine(balance));
+        }
+    }
+
+    pub fn into_inner(self) -> HashMap<AssetId, Balance<Regulated>> {
+        self.0
+    }
+
+    fn update<F>(&mut self, token: &AssetId, f: F)
+    where
+        F: FnOnce(&mut Balance<Regulated>),
+    {
+        let balance = self.0.entry(token.clone()).or_default();
+        f(balance);
+
+        if balance.balanced() {
+            self.0.remove(token);
+        }
+    }
+}
+
+impl<'a> AddAssign<&'a TransactionAsset> for BalanceSheet {
+    fn add_assign(&mut self, asset: &'a TransactionAsset) {
+        self.add_value(&asset.asset_id(), &asset.quantity);
+    }
+}
+
+impl<'a> SubAssign<&'a TransactionAsset> for BalanceSheet {
+    fn sub_assign(&mut self, asset: &'a TransactionAsset) {
+        self.sub_value(&asset.asset_id(), &asset.quantity);
+    }
+}
+
+impl From<HashMap<AssetId, Balance<Regulated>>> for BalanceSheet {
+    fn from(balances: HashMap<AssetId, Balance<Regulated>>) -> Self {
+        Self(
+            balances
+                .into_iter()
+                .filter(|(_, balance)| !balance.balanced())
+                .collect(),
+        )
+    }
+}
+
+impl From<BalanceSheet> for HashMap<AssetId, Balance<Regulated>> {
+    fn from(sheet: BalanceSheet) -> Self {
+        sheet.0
+    }
+}
+
+impl IntoIterator for BalanceSheet {
+    type Item = (AssetId, Balance<Regulated>);
+    type IntoIter = hash_map::IntoIter<AssetId, Balance<Regulated>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cardano;
+    use crate::{cardano, TokenId};
 
     macro_rules! value {
         ($Value:literal) => {{
@@ -322,6 +523,80 @@ mod tests {
         assert_eq!(balance!(-2) + value!(1), balance!(-1));
     }
 
+    #[test]
+    fn sum() {
+        let balances = vec![
+            balance!(+ 10),
+            balance!(-3),
+            balance!(Balanced),
+            balance!(-7),
+        ];
+        assert_eq!(balances.iter().sum::<Balance<_>>(), balance!(Balanced));
+        assert_eq!(
+            balances.into_iter().skip(1).sum::<Balance<_>>(),
+            balance!(-10)
+        );
+    }
+
+    #[test]
+    fn combine() {
+        assert_eq!(balance!(+ 10).combine(&balance!(-3)), balance!(+ 7));
+        assert_eq!(balance!(-10).combine(&balance!(+ 3)), balance!(-7));
+        assert_eq!(balance!(Balanced).combine(&balance!(-3)), balance!(-3));
+    }
+
+    #[test]
+    fn serde() {
+        for (balance, json) in [
+            (balance!(+ 10), r#""+10""#),
+            (balance!(Balanced), r#""0""#),
+            (balance!(-10), r#""-10""#),
+        ] {
+            assert_eq!(deps::serde_json::to_string(&balance).unwrap(), json);
+            assert_eq!(
+                deps::serde_json::from_str::<Balance<cardano::Lovelace>>(json).unwrap(),
+                balance
+            );
+        }
+
+        assert_eq!(
+            deps::serde_json::from_str::<Balance<cardano::Lovelace>>("-3").unwrap(),
+            balance!(-3)
+        );
+    }
+
+    #[test]
+    fn balance_sheet() {
+        let mut asset = TransactionAsset::with_fingerprint(
+            crate::PolicyId::new_static("policy"),
+            crate::AssetName::new_static("name"),
+            TokenId::new_static("token"),
+        );
+        asset.quantity = Value::from(10u64);
+
+        let mut sheet = BalanceSheet::new();
+        sheet += &asset;
+        assert_eq!(
+            sheet.get(&asset.asset_id()),
+            Balance::Excess(Value::from(10u64))
+        );
+        assert_eq!(sheet.in_excess().count(), 1);
+
+        sheet -= &asset;
+        sheet -= &asset;
+        assert_eq!(
+            sheet.get(&asset.asset_id()),
+            Balance::Debt(Value::from(10u64))
+        );
+        assert_eq!(sheet.in_debt().count(), 1);
+
+        let mut other = BalanceSheet::new();
+        other += &asset;
+        sheet.combine(&other);
+        assert!(sheet.is_balanced());
+        assert!(sheet.iter().next().is_none());
+    }
+
     #[test]
     fn balance_sub() {
         assert_eq!(balance!(Balanced) - value!(0), balance!(Balanced));
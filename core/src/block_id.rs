@@ -1,3 +1,4 @@
+use crate::{Hash32, Hash32Error};
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, fmt, str};
 
@@ -11,11 +12,21 @@ use std::{borrow::Cow, fmt, str};
 pub struct BlockId(Cow<'static, str>);
 
 impl BlockId {
-    pub fn new<B>(block_id: B) -> Self
+    /// create a [`BlockId`] from the hexadecimal encoding of a 32 bytes
+    /// hash. The identifier is normalized to lower case hexadecimal.
+    pub fn from_hex(id: &str) -> Result<Self, Hash32Error> {
+        Hash32::from_hex(id).map(Self::from)
+    }
+
+    /// create a [`BlockId`] without checking it is a valid hash.
+    ///
+    /// Prefer [`BlockId::from_hex`] or [`BlockId::from`] a [`Hash32`], this
+    /// is meant for tests and for identifiers that are not 32 bytes hashes.
+    pub fn new_unchecked<B>(id: B) -> Self
     where
         B: Into<Cow<'static, str>>,
     {
-        Self(block_id.into())
+        Self(id.into())
     }
 
     /// create a static [`BlockId`]. Because we use a [`Cow`]
@@ -25,6 +36,11 @@ impl BlockId {
         Self(Cow::Borrowed(block_id))
     }
 
+    /// decode the compact 32 bytes representation of the identifier
+    pub fn to_hash32(&self) -> Result<Hash32, Hash32Error> {
+        Hash32::from_hex(&self.0)
+    }
+
     /// check the [`BlockId`] starts with the given `prefix`.
     ///
     /// This can be useful to check for partial [`BlockId`]
@@ -48,6 +64,12 @@ impl AsRef<[u8]> for BlockId {
     }
 }
 
+impl From<Hash32> for BlockId {
+    fn from(hash: Hash32) -> Self {
+        Self(Cow::Owned(hash.to_hex()))
+    }
+}
+
 impl fmt::Display for BlockId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -58,6 +80,18 @@ impl fmt::Display for BlockId {
 mod tests {
     use super::*;
 
+    const HASH: &str = "AA83ACBF5904C0EDFE4D79B3689D3D00FCFC553CF360FD2229B98D464C28E9DE";
+
+    #[test]
+    fn hash32_roundtrip() {
+        let id = BlockId::from_hex(HASH).unwrap();
+
+        assert_eq!(id.to_string(), HASH.to_lowercase());
+        assert_eq!(BlockId::from(id.to_hash32().unwrap()), id);
+        assert!(BlockId::from_hex("hello world").is_err());
+        assert!(BlockId::new_static("hello world").to_hash32().is_err());
+    }
+
     #[test]
     fn starts_with() {
         assert!(BlockId::new_static("hello world").starts_with("hello"));
@@ -74,6 +74,53 @@ impl BlockNumber {
     pub fn saturating_sub(self, rhs: u64) -> Self {
         Self(self.0.saturating_sub(rhs))
     }
+
+    /// Try to decrease by `1` the [`BlockNumber`]
+    ///
+    /// If the subtraction will underflow, the function will returns `None`.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    #[inline]
+    pub fn checked_prev(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+
+    /// Try to subtract the right hand side (`rhs`) value to the [`BlockNumber`].
+    ///
+    /// If the subtraction will underflow, the function will returns `None`.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    #[inline]
+    pub fn checked_sub(self, rhs: u64) -> Option<Self> {
+        self.0.checked_sub(rhs).map(Self)
+    }
+
+    /// the number of steps between the two [`BlockNumber`], regardless of
+    /// their order.
+    #[inline]
+    pub fn distance(self, other: Self) -> u64 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// iterate over all the [`BlockNumber`] from `self` to `end` (excluded)
+    ///
+    /// ```
+    /// # use dcspark_core::BlockNumber;
+    /// let numbers: Vec<_> = BlockNumber::new(1).range(BlockNumber::new(3)).collect();
+    /// assert_eq!(numbers, vec![BlockNumber::new(1), BlockNumber::new(2)]);
+    /// ```
+    pub fn range(self, end: Self) -> impl DoubleEndedIterator<Item = Self> {
+        (self.0..end.0).map(Self)
+    }
+
+    /// iterate over all the [`BlockNumber`] from `self` to `end` (included)
+    ///
+    /// ```
+    /// # use dcspark_core::BlockNumber;
+    /// let numbers: Vec<_> = BlockNumber::new(1).range_inclusive(BlockNumber::new(3)).collect();
+    /// assert_eq!(numbers.len(), 3);
+    /// ```
+    pub fn range_inclusive(self, end: Self) -> impl DoubleEndedIterator<Item = Self> {
+        (self.0..=end.0).map(Self)
+    }
 }
 
 impl fmt::Display for BlockNumber {
@@ -214,6 +261,44 @@ mod tests {
         )
     }
 
+    #[test]
+    fn checked_prev_underflow() {
+        assert_eq!(None, BlockNumber::MIN.checked_prev())
+    }
+
+    #[smoketest{ a: num::<u64>(), b: num::<u64>() }]
+    fn checked_sub(a: u64, b: u64) {
+        property::equal(
+            a.checked_sub(b).map(BlockNumber),
+            BlockNumber(a).checked_sub(b),
+        )
+    }
+
+    #[smoketest{ a: num::<u64>(), b: num::<u64>() }]
+    fn distance(a: u64, b: u64) {
+        property::equal(
+            BlockNumber(a).distance(BlockNumber(b)),
+            BlockNumber(b).distance(BlockNumber(a)),
+        )
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(BlockNumber(5).range(BlockNumber(5)).count(), 0);
+        assert_eq!(BlockNumber(5).range_inclusive(BlockNumber(5)).count(), 1);
+        assert_eq!(
+            BlockNumber(1)
+                .range_inclusive(BlockNumber(3))
+                .rev()
+                .collect::<Vec<_>>(),
+            vec![BlockNumber(3), BlockNumber(2), BlockNumber(1)]
+        );
+        assert_eq!(
+            BlockNumber::MAX.range_inclusive(BlockNumber::MAX).next(),
+            Some(BlockNumber::MAX)
+        );
+    }
+
     #[test]
     fn deserialize_from_number() {
         let expected = Sample {
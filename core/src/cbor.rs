@@ -0,0 +1,147 @@
+//! just enough of CBOR to read and write the few cardano structures
+//! (byron addresses, datums and script references) the core types
+//! need to understand.
+
+pub(crate) const MAJOR_UNSIGNED: u8 = 0x00;
+pub(crate) const MAJOR_BYTES: u8 = 0x40;
+pub(crate) const MAJOR_ARRAY: u8 = 0x80;
+pub(crate) const MAJOR_MAP: u8 = 0xA0;
+pub(crate) const MAJOR_TAG: u8 = 0xC0;
+
+/// tag of the CBOR encoded data items (`#6.24(bytes .cbor T)`)
+pub(crate) const TAG_ENCODED_CBOR: u64 = 24;
+
+pub(crate) struct CborReader<'a>(&'a [u8]);
+
+impl<'a> CborReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let (byte, remaining) = self.0.split_first()?;
+        self.0 = remaining;
+        Some(*byte)
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        if self.0.len() < length {
+            return None;
+        }
+        let (taken, remaining) = self.0.split_at(length);
+        self.0 = remaining;
+        Some(taken)
+    }
+
+    /// read a header of the given major type, returns its argument
+    pub(crate) fn header(&mut self, major_type: u8) -> Option<u64> {
+        let byte = self.byte()?;
+        if byte & 0xE0 != major_type {
+            return None;
+        }
+
+        let length = match byte & 0x1F {
+            argument @ 0..=23 => return Some(u64::from(argument)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return None,
+        };
+
+        Some(
+            self.take(length)?
+                .iter()
+                .fold(0, |value, byte| (value << 8) | u64::from(*byte)),
+        )
+    }
+
+    pub(crate) fn bytes(&mut self) -> Option<&'a [u8]> {
+        let length = self.header(MAJOR_BYTES)?;
+        self.take(usize::try_from(length).ok()?)
+    }
+
+    /// read an encoded CBOR data item: `#6.24(bytes)`
+    pub(crate) fn encoded_cbor(&mut self) -> Option<&'a [u8]> {
+        if self.header(MAJOR_TAG)? != TAG_ENCODED_CBOR {
+            return None;
+        }
+        self.bytes()
+    }
+}
+
+pub(crate) fn write_header(out: &mut Vec<u8>, major_type: u8, argument: u64) {
+    match argument {
+        0..=23 => out.push(major_type | argument as u8),
+        24..=0xFF => {
+            out.push(major_type | 24);
+            out.push(argument as u8);
+        }
+        0x100..=0xFFFF => {
+            out.push(major_type | 25);
+            out.extend((argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xFFFF_FFFF => {
+            out.push(major_type | 26);
+            out.extend((argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major_type | 27);
+            out.extend(argument.to_be_bytes());
+        }
+    }
+}
+
+pub(crate) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_header(out, MAJOR_BYTES, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn write_encoded_cbor(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_header(out, MAJOR_TAG, TAG_ENCODED_CBOR);
+    write_bytes(out, bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_roundtrip() {
+        for argument in [
+            0,
+            23,
+            24,
+            255,
+            256,
+            65_535,
+            65_536,
+            u32::MAX as u64,
+            u64::MAX,
+        ] {
+            let mut out = Vec::new();
+            write_header(&mut out, MAJOR_UNSIGNED, argument);
+
+            let mut reader = CborReader::new(&out);
+            assert_eq!(reader.header(MAJOR_UNSIGNED), Some(argument));
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn encoded_cbor_roundtrip() {
+        let mut out = Vec::new();
+        write_encoded_cbor(&mut out, &[0x01, 0x02]);
+
+        assert_eq!(out, [0xD8, 0x18, 0x42, 0x01, 0x02]);
+        assert_eq!(
+            CborReader::new(&out).encoded_cbor(),
+            Some([0x01, 0x02].as_slice())
+        );
+        assert_eq!(CborReader::new(&out).header(MAJOR_ARRAY), None);
+    }
+}
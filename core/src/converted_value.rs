@@ -0,0 +1,237 @@
+use crate::{Normalized, Regulated, Rule, Value};
+use serde::{Deserialize, Serialize};
+
+/// the side of the bridge the value is moved to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionDirection {
+    MainchainToSidechain,
+    SidechainToMainchain,
+}
+
+/// how the converted value is rounded when the target representation
+/// does not have enough decimals to hold the exact value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// the converted value is lower or equal to the source value, the
+    /// dust is what could not be moved
+    Down,
+    /// the converted value is greater or equal to the source value, the
+    /// dust is what was added to the source value
+    Up,
+}
+
+/// the result of converting a value between the mainchain and the
+/// sidechain representations with a [`Rule`], with the information
+/// required to audit the conversion
+///
+/// ```
+/// use dcspark_core::{ConvertedValue, Rounding, Rule, TokenId, Value};
+///
+/// let rule = Rule {
+///     asset: TokenId::MAIN,
+///     mainchain_decimal_precision: 6,
+///     sidechain_decimal_precision: 18,
+/// };
+///
+/// // 1.000000000123 lovelace worth of wei
+/// let wei = Value::from(1_000_000_000_123u64);
+/// let converted = ConvertedValue::sidechain_to_mainchain(&wei, &rule, Rounding::Down);
+///
+/// assert_eq!(converted.converted, Value::from(1u64));
+/// assert_eq!(converted.dust, "0.000000000000000123".parse().unwrap());
+/// assert!(converted.is_conserved());
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertedValue {
+    pub direction: ConversionDirection,
+    pub rule: Rule,
+    pub rounding: Rounding,
+    /// the value in the source representation
+    pub source: Value<Regulated>,
+    /// the value in the target representation, always integral
+    pub converted: Value<Regulated>,
+    /// the difference between the source and the converted value, it
+    /// is never negative
+    pub dust: Value<Normalized>,
+}
+
+impl ConvertedValue {
+    pub fn mainchain_to_sidechain(
+        source: &Value<Regulated>,
+        rule: &Rule,
+        rounding: Rounding,
+    ) -> Self {
+        Self::convert(
+            ConversionDirection::MainchainToSidechain,
+            source,
+            rule,
+            rounding,
+        )
+    }
+
+    pub fn sidechain_to_mainchain(
+        source: &Value<Regulated>,
+        rule: &Rule,
+        rounding: Rounding,
+    ) -> Self {
+        Self::convert(
+            ConversionDirection::SidechainToMainchain,
+            source,
+            rule,
+            rounding,
+        )
+    }
+
+    fn convert(
+        direction: ConversionDirection,
+        source: &Value<Regulated>,
+        rule: &Rule,
+        rounding: Rounding,
+    ) -> Self {
+        let normalized = Self::normalize_source(direction, source, rule);
+        let exact = match direction {
+            ConversionDirection::MainchainToSidechain => {
+                Value::from_normalized_to_sidechain(&normalized, rule)
+            }
+            ConversionDirection::SidechainToMainchain => {
+                Value::from_normalized_to_mainchain(&normalized, rule)
+            }
+        };
+
+        let truncated = exact.truncate();
+        let converted = if rounding == Rounding::Up && truncated != exact {
+            truncated + Value::one()
+        } else {
+            truncated
+        };
+
+        let mut converted_value = Self {
+            direction,
+            rule: rule.clone(),
+            rounding,
+            source: source.clone(),
+            converted,
+            dust: Value::zero(),
+        };
+        let converted = converted_value.normalized_converted();
+        converted_value.dust = match rounding {
+            Rounding::Down => &normalized - &converted,
+            Rounding::Up => &converted - &normalized,
+        };
+        converted_value
+    }
+
+    fn normalize_source(
+        direction: ConversionDirection,
+        value: &Value<Regulated>,
+        rule: &Rule,
+    ) -> Value<Normalized> {
+        match direction {
+            ConversionDirection::MainchainToSidechain => value.normalize_from_mainchain(rule),
+            ConversionDirection::SidechainToMainchain => value.normalize_from_sidechain(rule),
+        }
+    }
+
+    /// the source value, normalized
+    pub fn normalized_source(&self) -> Value<Normalized> {
+        Self::normalize_source(self.direction, &self.source, &self.rule)
+    }
+
+    /// the converted value, normalized
+    pub fn normalized_converted(&self) -> Value<Normalized> {
+        match self.direction {
+            ConversionDirection::MainchainToSidechain => {
+                self.converted.normalize_from_sidechain(&self.rule)
+            }
+            ConversionDirection::SidechainToMainchain => {
+                self.converted.normalize_from_mainchain(&self.rule)
+            }
+        }
+    }
+
+    /// check no value was created nor destroyed by the conversion: the
+    /// source and the converted value only differ by the dust
+    pub fn is_conserved(&self) -> bool {
+        if self.dust < Value::zero() {
+            return false;
+        }
+
+        let source = self.normalized_source();
+        let converted = self.normalized_converted();
+        match self.rounding {
+            Rounding::Down => source == converted + &self.dust,
+            Rounding::Up => converted == source + &self.dust,
+        }
+    }
+
+    /// the conversion was exact, there is no dust
+    pub fn is_exact(&self) -> bool {
+        self.dust == Value::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenId;
+
+    const ADA: Rule = Rule {
+        asset: TokenId::MAIN,
+        mainchain_decimal_precision: 6,
+        sidechain_decimal_precision: 18,
+    };
+
+    #[test]
+    fn mainchain_to_sidechain_is_exact() {
+        let lovelace = Value::from(1_500_000u64);
+        let converted = ConvertedValue::mainchain_to_sidechain(&lovelace, &ADA, Rounding::Down);
+
+        assert_eq!(
+            converted.converted,
+            Value::from(1_500_000_000_000_000_000u64)
+        );
+        assert!(converted.is_exact());
+        assert!(converted.is_conserved());
+    }
+
+    #[test]
+    fn sidechain_to_mainchain_rounding() {
+        let wei = Value::from(1_000_000_000_123u64);
+
+        let down = ConvertedValue::sidechain_to_mainchain(&wei, &ADA, Rounding::Down);
+        assert_eq!(down.converted, Value::from(1u64));
+        assert_eq!(down.dust, "0.000000000000000123".parse().unwrap());
+        assert!(down.is_conserved());
+
+        let up = ConvertedValue::sidechain_to_mainchain(&wei, &ADA, Rounding::Up);
+        assert_eq!(up.converted, Value::from(2u64));
+        assert_eq!(up.dust, "0.000000999999999877".parse().unwrap());
+        assert!(up.is_conserved());
+        assert!(!up.is_exact());
+    }
+
+    #[test]
+    fn tampered_conversion_is_not_conserved() {
+        let wei = Value::from(1_000_000_000_123u64);
+        let mut converted = ConvertedValue::sidechain_to_mainchain(&wei, &ADA, Rounding::Down);
+
+        converted.converted = Value::from(2u64);
+        assert!(!converted.is_conserved());
+    }
+
+    #[test]
+    fn serde() {
+        let wei = Value::from(1_000_000_000_123u64);
+        let converted = ConvertedValue::sidechain_to_mainchain(&wei, &ADA, Rounding::Down);
+
+        let json = deps::serde_json::to_value(&converted).unwrap();
+        assert_eq!(json["direction"], "sidechain_to_mainchain");
+        assert_eq!(json["rounding"], "down");
+
+        let decoded: ConvertedValue = deps::serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.dust, converted.dust);
+        assert!(decoded.is_conserved());
+    }
+}
@@ -0,0 +1,293 @@
+use bech32::{FromBase32 as _, ToBase32 as _};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+pub const CREDENTIAL_HASH_SIZE: usize = 28;
+
+const SCRIPT_HRP: &str = "script";
+const PAYMENT_KEY_HASH_HRP: &str = "addr_vkh";
+const STAKE_KEY_HASH_HRP: &str = "stake_vkh";
+
+/// hash of a key or of a script, used in the shelley addresses
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Credential {
+    Key([u8; CREDENTIAL_HASH_SIZE]),
+    Script(ScriptHash),
+}
+
+/// blake2b-224 hash of a native or plutus script
+///
+/// encoded in bech32 with the `script` human readable part
+/// (see [CIP-5](https://cips.cardano.org/cips/cip5/)).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct ScriptHash([u8; CREDENTIAL_HASH_SIZE]);
+
+/// the [`Credential`] controlling the spending of the funds of an address
+///
+/// encoded in bech32 with the `addr_vkh` or `script` human readable part
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct PaymentCredential(pub Credential);
+
+/// the [`Credential`] controlling the delegation of the funds of an address
+///
+/// encoded in bech32 with the `stake_vkh` or `script` human readable part
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct StakeCredential(pub Credential);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CredentialError {
+    #[error("Invalid hexadecimal encoding")]
+    InvalidHex(#[from] hex::FromHexError),
+    #[error("Invalid bech32 encoding")]
+    Bech32(#[from] bech32::Error),
+    #[error("Unexpected human readable part {hrp}")]
+    InvalidPrefix { hrp: String },
+    #[error("Invalid hash length {length}, expected 28 bytes")]
+    InvalidLength { length: usize },
+}
+
+fn hash_from_bytes(bytes: &[u8]) -> Result<[u8; CREDENTIAL_HASH_SIZE], CredentialError> {
+    bytes
+        .try_into()
+        .map_err(|_| CredentialError::InvalidLength {
+            length: bytes.len(),
+        })
+}
+
+fn encode_bech32(hrp: &str, bytes: &[u8]) -> String {
+    bech32::encode(hrp, bytes.to_base32(), bech32::Variant::Bech32)
+        .expect("the credential human readable parts are always valid")
+}
+
+/// decode a bech32 credential, returns a script credential if the
+/// human readable part is `script` or a key credential if it is `key_hrp`.
+fn decode_bech32(s: &str, key_hrp: &str) -> Result<Credential, CredentialError> {
+    let (hrp, data, _) = bech32::decode(s)?;
+    let hash = hash_from_bytes(&Vec::<u8>::from_base32(&data)?)?;
+
+    if hrp == SCRIPT_HRP {
+        Ok(Credential::Script(ScriptHash(hash)))
+    } else if hrp == key_hrp {
+        Ok(Credential::Key(hash))
+    } else {
+        Err(CredentialError::InvalidPrefix { hrp })
+    }
+}
+
+impl ScriptHash {
+    pub const fn new(hash: [u8; CREDENTIAL_HASH_SIZE]) -> Self {
+        Self(hash)
+    }
+
+    pub fn from_hex(hash: &str) -> Result<Self, CredentialError> {
+        hash_from_bytes(&hex::decode(hash)?).map(Self)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; CREDENTIAL_HASH_SIZE] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+impl fmt::Display for ScriptHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_bech32(SCRIPT_HRP, &self.0))
+    }
+}
+
+/// parse either the bech32 or the hexadecimal encoding of the script hash
+impl FromStr for ScriptHash {
+    type Err = CredentialError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(hash) = Self::from_hex(s) {
+            return Ok(hash);
+        }
+
+        let (hrp, data, _) = bech32::decode(s)?;
+        if hrp != SCRIPT_HRP {
+            return Err(CredentialError::InvalidPrefix { hrp });
+        }
+        hash_from_bytes(&Vec::<u8>::from_base32(&data)?).map(Self)
+    }
+}
+
+impl Credential {
+    pub fn key_from_hex(hash: &str) -> Result<Self, CredentialError> {
+        hash_from_bytes(&hex::decode(hash)?).map(Self::Key)
+    }
+
+    pub fn script_from_hex(hash: &str) -> Result<Self, CredentialError> {
+        ScriptHash::from_hex(hash).map(Self::Script)
+    }
+
+    pub fn hash(&self) -> &[u8; CREDENTIAL_HASH_SIZE] {
+        match self {
+            Self::Key(hash) => hash,
+            Self::Script(script) => script.as_bytes(),
+        }
+    }
+
+    pub fn is_script(&self) -> bool {
+        matches!(self, Self::Script(_))
+    }
+
+    pub fn script_hash(&self) -> Option<&ScriptHash> {
+        match self {
+            Self::Key(_) => None,
+            Self::Script(script) => Some(script),
+        }
+    }
+}
+
+/// the credential is displayed as the hexadecimal encoding of its hash
+impl fmt::Display for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        hex::encode(self.hash()).fmt(f)
+    }
+}
+
+macro_rules! credential_newtype {
+    ($Type:ident, $key_hrp:expr) => {
+        impl $Type {
+            pub fn credential(&self) -> &Credential {
+                &self.0
+            }
+        }
+
+        impl From<Credential> for $Type {
+            fn from(credential: Credential) -> Self {
+                Self(credential)
+            }
+        }
+
+        impl fmt::Display for $Type {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match &self.0 {
+                    Credential::Key(hash) => f.write_str(&encode_bech32($key_hrp, hash)),
+                    Credential::Script(script) => script.fmt(f),
+                }
+            }
+        }
+
+        impl FromStr for $Type {
+            type Err = CredentialError;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                decode_bech32(s, $key_hrp).map(Self)
+            }
+        }
+    };
+}
+
+credential_newtype!(PaymentCredential, PAYMENT_KEY_HASH_HRP);
+credential_newtype!(StakeCredential, STAKE_KEY_HASH_HRP);
+
+macro_rules! serde_from_str {
+    ($Type:ident, $expecting:literal) => {
+        impl Serialize for $Type {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $Type {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = $Type;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str($expecting)
+                    }
+
+                    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                    where
+                        E: serde::de::Error,
+                    {
+                        v.parse().map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_str(Visitor)
+            }
+        }
+    };
+}
+
+serde_from_str!(ScriptHash, "a bech32 or hexadecimal encoded script hash");
+serde_from_str!(PaymentCredential, "a bech32 encoded payment credential");
+serde_from_str!(StakeCredential, "a bech32 encoded stake credential");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_HASH: &str = "9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e";
+    const SCRIPT_HASH: &str = "c37b1b5dc0669f1d3c61a6fddb2e8fde96be87b881c60bce8e8d542f";
+
+    #[test]
+    fn script_hash_encoding() {
+        let hash = ScriptHash::from_hex(SCRIPT_HASH).unwrap();
+        let bech32 = "script1cda3khwqv60360rp5m7akt50m6ttapacs8rqhn5w342z7r35m37";
+
+        assert_eq!(hash.to_string(), bech32);
+        assert_eq!(hash.to_hex(), SCRIPT_HASH);
+        assert_eq!(bech32.parse::<ScriptHash>().unwrap(), hash);
+        assert_eq!(SCRIPT_HASH.parse::<ScriptHash>().unwrap(), hash);
+    }
+
+    #[test]
+    fn invalid_length() {
+        assert_eq!(
+            ScriptHash::from_hex("00"),
+            Err(CredentialError::InvalidLength { length: 1 })
+        );
+    }
+
+    #[test]
+    fn payment_credential() {
+        let key = PaymentCredential(Credential::key_from_hex(KEY_HASH).unwrap());
+        let bech32 = "addr_vkh1jjfnzhxe966a33psfenm0ct2udkkr569qf55v4uprgkgu8zsvmg";
+
+        assert_eq!(key.to_string(), bech32);
+        assert_eq!(bech32.parse::<PaymentCredential>().unwrap(), key);
+
+        let script = PaymentCredential(Credential::script_from_hex(SCRIPT_HASH).unwrap());
+        assert_eq!(
+            script.to_string().parse::<PaymentCredential>().unwrap(),
+            script
+        );
+        assert!(script.credential().is_script());
+    }
+
+    #[test]
+    fn stake_credential_rejects_payment_prefix() {
+        assert!(matches!(
+            "addr_vkh1jjfnzhxe966a33psfenm0ct2udkkr569qf55v4uprgkgu8zsvmg"
+                .parse::<StakeCredential>(),
+            Err(CredentialError::InvalidPrefix { .. })
+        ));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let stake = StakeCredential(Credential::key_from_hex(KEY_HASH).unwrap());
+        let json = serde_json::to_string(&stake).unwrap();
+
+        assert!(json.starts_with("\"stake_vkh1"));
+        assert_eq!(
+            serde_json::from_str::<StakeCredential>(&json).unwrap(),
+            stake
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{SlotNumber, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// parameters of a blockchain era, used to convert between slots,
+/// epochs and time.
+///
+/// ```
+/// use dcspark_core::{EraParams, SlotNumber, Timestamp};
+///
+/// // cardano mainnet, shelley era
+/// let era = EraParams {
+///     first_slot: SlotNumber::new(4492800),
+///     first_epoch: 208,
+///     system_start: Timestamp::new(1596059091),
+///     slot_length: 1,
+///     epoch_length: 432000,
+/// };
+///
+/// assert_eq!(era.slot_to_epoch(SlotNumber::new(99100852)), Some(427));
+/// assert_eq!(
+///     era.slot_to_timestamp(SlotNumber::new(4492801)),
+///     Some(Timestamp::new(1596059092)),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EraParams {
+    /// the first slot of the era
+    pub first_slot: SlotNumber,
+    /// the epoch of the first slot of the era
+    pub first_epoch: u64,
+    /// the time of the first slot of the era, in seconds since UNIX epoch
+    pub system_start: Timestamp,
+    /// the duration of a slot, in seconds
+    pub slot_length: u64,
+    /// the number of slots in an epoch
+    pub epoch_length: u64,
+}
+
+impl EraParams {
+    /// the number of slots since the start of the era, `None` if the
+    /// slot is before the era.
+    fn relative_slot(&self, slot: SlotNumber) -> Option<u64> {
+        u64::from(slot).checked_sub(u64::from(self.first_slot))
+    }
+
+    /// compute the time at which the slot starts
+    ///
+    /// Returns `None` if the slot is before the era or if the time
+    /// would overflow.
+    pub fn slot_to_timestamp(&self, slot: SlotNumber) -> Option<Timestamp> {
+        self.relative_slot(slot)?
+            .checked_mul(self.slot_length)?
+            .checked_add(self.system_start.into_inner())
+            .map(Timestamp::new)
+    }
+
+    /// compute the slot in progress at the given time
+    ///
+    /// Returns `None` if the time is before the era.
+    pub fn timestamp_to_slot(&self, timestamp: Timestamp) -> Option<SlotNumber> {
+        let elapsed = timestamp
+            .into_inner()
+            .checked_sub(self.system_start.into_inner())?;
+        let relative_slot = elapsed.checked_div(self.slot_length)?;
+        self.first_slot.checked_add(relative_slot)
+    }
+
+    /// compute the epoch of the given slot
+    ///
+    /// Returns `None` if the slot is before the era.
+    pub fn slot_to_epoch(&self, slot: SlotNumber) -> Option<u64> {
+        let epochs = self.relative_slot(slot)?.checked_div(self.epoch_length)?;
+        self.first_epoch.checked_add(epochs)
+    }
+
+    /// compute the position of the slot within its epoch
+    ///
+    /// Returns `None` if the slot is before the era.
+    pub fn slot_in_epoch(&self, slot: SlotNumber) -> Option<u64> {
+        self.relative_slot(slot)?.checked_rem(self.epoch_length)
+    }
+
+    /// compute the first slot of the given epoch
+    ///
+    /// Returns `None` if the epoch is before the era.
+    pub fn epoch_first_slot(&self, epoch: u64) -> Option<SlotNumber> {
+        let slots = epoch
+            .checked_sub(self.first_epoch)?
+            .checked_mul(self.epoch_length)?;
+        self.first_slot.checked_add(slots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAINNET: EraParams = EraParams {
+        first_slot: SlotNumber::new(4492800),
+        first_epoch: 208,
+        system_start: Timestamp::new(1596059091),
+        slot_length: 1,
+        epoch_length: 432000,
+    };
+
+    const PREVIEW: EraParams = EraParams {
+        first_slot: SlotNumber::new(0),
+        first_epoch: 0,
+        system_start: Timestamp::new(1666656000),
+        slot_length: 1,
+        epoch_length: 86400,
+    };
+
+    #[test]
+    fn slot_to_epoch() {
+        assert_eq!(MAINNET.slot_to_epoch(SlotNumber::new(4492799)), None);
+        assert_eq!(MAINNET.slot_to_epoch(SlotNumber::new(4492800)), Some(208));
+        assert_eq!(MAINNET.slot_to_epoch(SlotNumber::new(97507251)), Some(423));
+        assert_eq!(MAINNET.slot_to_epoch(SlotNumber::new(99100793)), Some(426));
+        assert_eq!(MAINNET.slot_to_epoch(SlotNumber::new(99100852)), Some(427));
+        assert_eq!(MAINNET.slot_in_epoch(SlotNumber::new(99100852)), Some(52));
+
+        assert_eq!(PREVIEW.slot_to_epoch(SlotNumber::new(24278356)), Some(280));
+        assert_eq!(
+            PREVIEW.slot_in_epoch(SlotNumber::new(24278356)),
+            Some(86356)
+        );
+    }
+
+    #[test]
+    fn timestamp_roundtrip() {
+        let slot = SlotNumber::new(24278356);
+        let timestamp = PREVIEW.slot_to_timestamp(slot).unwrap();
+
+        assert_eq!(timestamp, Timestamp::new(1690934356));
+        assert_eq!(PREVIEW.timestamp_to_slot(timestamp), Some(slot));
+        assert_eq!(PREVIEW.timestamp_to_slot(Timestamp::new(1666655999)), None);
+    }
+
+    #[test]
+    fn epoch_first_slot() {
+        assert_eq!(MAINNET.epoch_first_slot(207), None);
+        assert_eq!(
+            MAINNET.epoch_first_slot(209),
+            Some(SlotNumber::new(4492800 + 432000))
+        );
+        assert_eq!(
+            MAINNET.slot_to_epoch(MAINNET.epoch_first_slot(427).unwrap()),
+            Some(427)
+        );
+    }
+
+    #[test]
+    fn slower_slots() {
+        let era = EraParams {
+            slot_length: 20,
+            ..PREVIEW
+        };
+
+        assert_eq!(
+            era.slot_to_timestamp(SlotNumber::new(3)),
+            Some(Timestamp::new(1666656060))
+        );
+        assert_eq!(
+            era.timestamp_to_slot(Timestamp::new(1666656079)),
+            Some(SlotNumber::new(3))
+        );
+    }
+}
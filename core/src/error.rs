@@ -19,3 +19,113 @@ macro_rules! critical_error {
         }
     };
 }
+
+/// machine readable classification of an error
+///
+/// it is attached to an [`anyhow::Error`] as a context so the callers can
+/// decide what to do with the error (abort, retry, reject the input)
+/// without matching on the error messages:
+///
+/// ```
+/// use anyhow::Context as _;
+/// use dcspark_core::error::{is_retryable, ErrorClass};
+///
+/// let error = std::fs::read("/does/not/exist")
+///     .context(ErrorClass::Retryable)
+///     .unwrap_err();
+///
+/// assert!(is_retryable(&error));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// the error leaves the application in an inconsistent state, it
+    /// should not be retried
+    Critical,
+    /// transient error (network, resource temporarily unavailable...),
+    /// the operation may succeed if it is retried
+    Retryable,
+    /// the input of the operation is invalid, retrying with the same
+    /// input will fail again
+    InvalidInput,
+}
+
+impl std::fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Critical => f.write_str("critical level error"),
+            Self::Retryable => f.write_str("retryable error"),
+            Self::InvalidInput => f.write_str("invalid input"),
+        }
+    }
+}
+
+/// the class of the error, `None` if the error was not classified
+///
+/// an error with a [`CriticalError`] context is always critical, otherwise
+/// the outermost [`ErrorClass`] context is used.
+pub fn error_class(error: &anyhow::Error) -> Option<ErrorClass> {
+    if error.downcast_ref::<CriticalError>().is_some() {
+        return Some(ErrorClass::Critical);
+    }
+
+    error.downcast_ref::<ErrorClass>().copied()
+}
+
+pub fn is_critical(error: &anyhow::Error) -> bool {
+    error_class(error) == Some(ErrorClass::Critical)
+}
+
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    error_class(error) == Some(ErrorClass::Retryable)
+}
+
+pub fn is_invalid_input(error: &anyhow::Error) -> bool {
+    error_class(error) == Some(ErrorClass::InvalidInput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context as _};
+
+    #[test]
+    fn unclassified() {
+        let error = anyhow!("some error");
+
+        assert_eq!(error_class(&error), None);
+        assert!(!is_critical(&error));
+        assert!(!is_retryable(&error));
+    }
+
+    #[test]
+    fn classified_context() {
+        let error = Err::<(), _>(anyhow!("connection reset"))
+            .context(ErrorClass::Retryable)
+            .context("failed to pull the next block")
+            .unwrap_err();
+
+        assert!(is_retryable(&error));
+        assert!(!is_invalid_input(&error));
+    }
+
+    #[test]
+    fn outermost_class() {
+        let error = Err::<(), _>(anyhow!("connection reset"))
+            .context(ErrorClass::Retryable)
+            .context(ErrorClass::InvalidInput)
+            .unwrap_err();
+
+        assert_eq!(error_class(&error), Some(ErrorClass::InvalidInput));
+    }
+
+    #[test]
+    fn critical_error_is_critical() {
+        let error = Err::<(), _>(anyhow!("invalid block"))
+            .context(crate::critical_error!())
+            .context(ErrorClass::Retryable)
+            .unwrap_err();
+
+        assert!(is_critical(&error));
+        assert!(!is_retryable(&error));
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str};
+use thiserror::Error;
+
+pub const HASH32_SIZE: usize = 32;
+
+/// 32 bytes hash, the compact representation of the
+/// [`BlockId`](crate::BlockId) and [`TransactionId`](crate::tx::TransactionId)
+///
+/// Unlike the identifiers this type is `Copy` and always holds a valid
+/// hash. It is encoded in hexadecimal in its human readable forms.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct Hash32([u8; HASH32_SIZE]);
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Hash32Error {
+    #[error("Invalid hexadecimal encoding")]
+    InvalidHex(#[from] hex::FromHexError),
+    #[error("Invalid hash length {length}, expected 32 bytes")]
+    InvalidLength { length: usize },
+}
+
+impl Hash32 {
+    pub const fn new(bytes: [u8; HASH32_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn from_hex(hash: &str) -> Result<Self, Hash32Error> {
+        let bytes = hex::decode(hash)?;
+        Self::try_from(bytes.as_slice())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; HASH32_SIZE] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+impl From<[u8; HASH32_SIZE]> for Hash32 {
+    fn from(bytes: [u8; HASH32_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Hash32 {
+    type Error = Hash32Error;
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| Hash32Error::InvalidLength {
+                length: bytes.len(),
+            })
+    }
+}
+
+impl AsRef<[u8]> for Hash32 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl str::FromStr for Hash32 {
+    type Err = Hash32Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Serialize for Hash32 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash32 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let hash = String::deserialize(deserializer)?;
+        hash.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de";
+
+    #[test]
+    fn hex_roundtrip() {
+        let hash = Hash32::from_hex(HASH).unwrap();
+
+        assert_eq!(hash.to_string(), HASH);
+        assert_eq!(hash.as_bytes()[0], 0xaa);
+        assert_eq!(
+            Hash32::from_hex(&HASH.to_uppercase()).unwrap(),
+            hash,
+            "upper case hexadecimal is accepted"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            Hash32::from_hex("hello world"),
+            Err(Hash32Error::InvalidHex(_))
+        ));
+        assert_eq!(
+            Hash32::from_hex(&HASH[2..]),
+            Err(Hash32Error::InvalidLength { length: 31 })
+        );
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let hash = Hash32::from_hex(HASH).unwrap();
+        let json = serde_json::to_string(&hash).unwrap();
+
+        assert_eq!(json, format!("\"{HASH}\""));
+        assert_eq!(serde_json::from_str::<Hash32>(&json).unwrap(), hash);
+    }
+}
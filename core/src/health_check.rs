@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// state of a long running service (blockchain source, store, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// the service is up to date and operating normally
+    Ready,
+    /// the service is operating but catching up (i.e. syncing the chain)
+    Syncing,
+    /// the service is running but not able to make progress (i.e. lost
+    /// the connection to the node and is trying to reconnect)
+    Degraded,
+    /// the service is stopped and will not make progress anymore
+    Stopped,
+}
+
+/// status of a service, as reported by [`HealthCheck::status`]
+///
+/// the `details` are free form information for the operators, they can be
+/// exposed as is in a health endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub state: ServiceState,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+}
+
+pub trait HealthCheck {
+    fn status(&self) -> ServiceStatus;
+}
+
+impl ServiceStatus {
+    pub fn new(state: ServiceState) -> Self {
+        Self {
+            state,
+            details: BTreeMap::new(),
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_detail(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.details.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// the service is making progress: it is either ready or syncing
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.state, ServiceState::Ready | ServiceState::Syncing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde() {
+        let status = ServiceStatus::new(ServiceState::Syncing).with_detail("tip", 42);
+        let json = serde_json::to_string(&status).unwrap();
+
+        assert_eq!(json, r#"{"state":"syncing","details":{"tip":"42"}}"#);
+        assert_eq!(
+            serde_json::from_str::<ServiceStatus>(r#"{"state":"stopped"}"#).unwrap(),
+            ServiceStatus::new(ServiceState::Stopped)
+        );
+    }
+
+    #[test]
+    fn healthy() {
+        assert!(ServiceStatus::new(ServiceState::Ready).is_healthy());
+        assert!(ServiceStatus::new(ServiceState::Syncing).is_healthy());
+        assert!(!ServiceStatus::new(ServiceState::Degraded).is_healthy());
+        assert!(!ServiceStatus::new(ServiceState::Stopped).is_healthy());
+    }
+}
@@ -1,12 +1,22 @@
 mod address;
+mod asset_id;
 mod asset_name;
 mod balance;
 mod block_id;
 mod block_number;
+mod cbor;
+mod converted_value;
+mod credential;
+mod era_params;
 pub mod error;
+mod hash32;
+mod health_check;
+mod newtype;
 mod number_visitor;
 mod output_index;
+mod parsed_address;
 mod policy_id;
+mod rule_registry;
 mod slot_number;
 mod stoppable_service;
 mod timestamp;
@@ -16,16 +26,30 @@ mod utxo_store;
 mod value;
 
 pub use address::*;
+pub use asset_id::*;
 pub use asset_name::*;
 pub use balance::*;
 pub use block_id::*;
 pub use block_number::*;
+pub use converted_value::*;
+pub use credential::*;
+pub use era_params::*;
+pub use hash32::*;
+pub use health_check::*;
 pub use number_visitor::*;
 pub use output_index::*;
+pub use parsed_address::*;
 pub use policy_id::*;
+pub use rule_registry::*;
 pub use slot_number::*;
-pub use stoppable_service::StoppableService;
+pub use stoppable_service::*;
 pub use timestamp::*;
 pub use token_id::*;
 pub use utxo_store::*;
 pub use value::*;
+
+#[doc(hidden)]
+pub mod __private {
+    //! re-exports used by the exported macros, not part of the public API
+    pub use serde;
+}
@@ -0,0 +1,278 @@
+//! macros to define the identifier newtypes with the same behavior as
+//! the ones of this crate.
+//!
+//! They are exported so the chain specific crates can define their own
+//! identifiers (round numbers, transaction hashes...) without copying
+//! the boilerplate.
+
+/// define a string identifier backed by a `Cow<'static, str>`, like
+/// [`PolicyId`](crate::PolicyId)
+///
+/// the identifier has `new`/`new_static` constructors, implements
+/// `AsRef<str>`, `Display`, `FromStr` and is serialized as a string.
+///
+/// ```
+/// dcspark_core::define_string_id! {
+///     /// hash of an EVM transaction
+///     pub struct EvmTxHash;
+/// }
+///
+/// const GENESIS: EvmTxHash = EvmTxHash::new_static("0x00");
+///
+/// let hash = EvmTxHash::new(String::from("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"));
+/// assert_eq!(GENESIS.as_ref(), "0x00");
+/// assert_eq!(hash.to_string(), hash.as_ref());
+/// ```
+#[macro_export]
+macro_rules! define_string_id {
+    ($(#[$meta:meta])* $vis:vis struct $Name:ident;) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $Name(::std::borrow::Cow<'static, str>);
+
+        impl $Name {
+            #[inline]
+            pub fn new(id: impl Into<::std::borrow::Cow<'static, str>>) -> Self {
+                Self(id.into())
+            }
+
+            #[doc = concat!("create a static [`", stringify!($Name), "`]. Because we use a [`Cow`](std::borrow::Cow)")]
+            /// internally this allows us to defined pre-defined static
+            /// values without having to do extra allocations etc.
+            pub const fn new_static(id: &'static str) -> Self {
+                Self(::std::borrow::Cow::Borrowed(id))
+            }
+        }
+
+        impl ::std::convert::AsRef<str> for $Name {
+            fn as_ref(&self) -> &str {
+                self.0.as_ref()
+            }
+        }
+
+        impl ::std::fmt::Display for $Name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                ::std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl ::std::str::FromStr for $Name {
+            type Err = ::std::convert::Infallible;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self::new(s.to_owned()))
+            }
+        }
+
+        impl $crate::__private::serde::Serialize for $Name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::__private::serde::Serializer,
+            {
+                serializer.serialize_str(self.0.as_ref())
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $Name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: $crate::__private::serde::Deserializer<'de>,
+            {
+                <String as $crate::__private::serde::Deserialize>::deserialize(deserializer)
+                    .map(Self::new)
+            }
+        }
+    };
+}
+
+/// define a `u64` number identifier, like [`BlockNumber`](crate::BlockNumber)
+///
+/// the number has the `MIN`/`MAX` constants, the checked and saturating
+/// operations, implements the formatting traits and `FromStr`. It is
+/// serialized as a number and deserialized from either a number or a
+/// string (see [`NumberVisitor`](crate::NumberVisitor)).
+///
+/// ```
+/// dcspark_core::define_number_id! {
+///     /// round of the algorand blockchain
+///     pub struct RoundNumber(u64);
+/// }
+///
+/// let round = RoundNumber::new(41).checked_next().unwrap();
+/// assert_eq!(round.into_inner(), 42);
+/// assert_eq!(RoundNumber::MAX.checked_next(), None);
+/// ```
+#[macro_export]
+macro_rules! define_number_id {
+    (@fmt $Name:ident: $($Trait:ident),*) => {
+        $(
+            impl ::std::fmt::$Trait for $Name {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    ::std::fmt::$Trait::fmt(&self.0, f)
+                }
+            }
+        )*
+    };
+    ($(#[$meta:meta])* $vis:vis struct $Name:ident(u64);) => {
+        $(#[$meta])*
+        #[derive(Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+        $vis struct $Name(u64);
+
+        impl $Name {
+            #[doc = concat!("the largest value a [`", stringify!($Name), "`] can be")]
+            pub const MAX: Self = Self::new(u64::MAX);
+
+            #[doc = concat!("the smallest value a [`", stringify!($Name), "`] can be")]
+            pub const MIN: Self = Self::new(u64::MIN);
+
+            #[inline(always)]
+            pub const fn new(number: u64) -> Self {
+                Self(number)
+            }
+
+            #[inline(always)]
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            pub fn into_inner(self) -> u64 {
+                self.0
+            }
+
+            /// Try to increase by `1` the value
+            ///
+            /// If the addition will overflow, the function will returns `None`.
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn checked_next(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+
+            /// Increase by `1` the value
+            ///
+            /// If the addition will overflow, the function will returns [`Self::MAX`].
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn saturating_next(self) -> Self {
+                self.saturating_add(1)
+            }
+
+            /// Try to add the right hand side (`rhs`) value.
+            ///
+            /// If the addition will overflow, the function will returns `None`.
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn checked_add(self, rhs: u64) -> Option<Self> {
+                self.0.checked_add(rhs).map(Self)
+            }
+
+            /// Add the right hand side (`rhs`) value.
+            ///
+            /// If the addition will overflow we returns the [`Self::MAX`].
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn saturating_add(self, rhs: u64) -> Self {
+                Self(self.0.saturating_add(rhs))
+            }
+
+            /// Try to subtract the right hand side (`rhs`) value.
+            ///
+            /// If the subtraction will underflow, the function will returns `None`.
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn checked_sub(self, rhs: u64) -> Option<Self> {
+                self.0.checked_sub(rhs).map(Self)
+            }
+
+            /// Subtract the right hand side (`rhs`) value.
+            ///
+            /// If the subtraction will underflow we returns the [`Self::MIN`].
+            #[must_use = "The function does not modify the state, the new value is returned"]
+            #[inline]
+            pub fn saturating_sub(self, rhs: u64) -> Self {
+                Self(self.0.saturating_sub(rhs))
+            }
+        }
+
+        $crate::define_number_id!(@fmt $Name: Display, Binary, Octal, LowerHex, UpperHex, LowerExp, UpperExp);
+
+        impl ::std::str::FromStr for $Name {
+            type Err = ::std::num::ParseIntError;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+
+        impl ::std::convert::From<u64> for $Name {
+            fn from(number: u64) -> Self {
+                Self(number)
+            }
+        }
+
+        impl ::std::convert::From<$Name> for u64 {
+            fn from($Name(number): $Name) -> Self {
+                number
+            }
+        }
+
+        impl $crate::__private::serde::Serialize for $Name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::__private::serde::Serializer,
+            {
+                serializer.serialize_u64(self.0)
+            }
+        }
+
+        impl<'de> $crate::__private::serde::Deserialize<'de> for $Name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: $crate::__private::serde::Deserializer<'de>,
+            {
+                deserializer.deserialize_any($crate::NumberVisitor::<$Name>::default())
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    // the tests do not use all the generated functions
+    #![allow(dead_code)]
+
+    define_string_id! {
+        /// test string identifier
+        struct StringId;
+    }
+
+    define_number_id! {
+        /// test number identifier
+        struct NumberId(u64);
+    }
+
+    #[test]
+    fn string_id_serde() {
+        let id = StringId::new_static("id");
+        let json = serde_json::to_string(&id).unwrap();
+
+        assert_eq!(json, r#""id""#);
+        assert_eq!(serde_json::from_str::<StringId>(&json).unwrap(), id);
+        assert_eq!("id".parse::<StringId>().unwrap(), id);
+    }
+
+    #[test]
+    fn number_id_serde() {
+        let number = NumberId::new(42);
+        let json = serde_json::to_string(&number).unwrap();
+
+        assert_eq!(json, "42");
+        assert_eq!(serde_json::from_str::<NumberId>(&json).unwrap(), number);
+        assert_eq!(serde_json::from_str::<NumberId>(r#""42""#).unwrap(), number);
+        assert_eq!(format!("{number:x}"), "2a");
+    }
+
+    #[test]
+    fn number_id_operations() {
+        assert_eq!(NumberId::MAX.checked_next(), None);
+        assert_eq!(NumberId::MIN.checked_sub(1), None);
+        assert_eq!(NumberId::MAX.saturating_next(), NumberId::MAX);
+        assert_eq!(NumberId::MIN.saturating_sub(1), NumberId::MIN);
+        assert_eq!(u64::from(NumberId::from(7).saturating_add(3)), 10);
+    }
+}
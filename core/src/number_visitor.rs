@@ -1,4 +1,4 @@
-use deps::bigdecimal::BigDecimal;
+use deps::bigdecimal::{num_bigint::BigInt, BigDecimal};
 use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::{any, fmt};
@@ -41,13 +41,66 @@ where
         T::try_from(value).map_err(E::custom)
     }
 
-    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_i128(i128::from(value))
+    }
+
+    fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
     {
-        let v: u64 = value.parse::<u64>().map_err(E::custom)?;
+        let v = u64::try_from(value).map_err(|_| out_of_range::<T, E>(value))?;
         self.visit_u64(v)
     }
+
+    fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if value.is_negative() {
+            return Err(negative::<T, E>(value));
+        }
+        self.visit_u128(value.unsigned_abs())
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        match value.parse::<u64>() {
+            Ok(v) => self.visit_u64(v),
+            // give a precise error if the value is a valid integer
+            // but is negative or too large
+            Err(error) => match value.parse::<i128>() {
+                Ok(v) => self.visit_i128(v),
+                Err(_) if value.parse::<u128>().is_ok() => Err(out_of_range::<T, E>(value)),
+                Err(_) => Err(E::custom(error)),
+            },
+        }
+    }
+}
+
+fn negative<T, E>(value: impl fmt::Display) -> E
+where
+    E: serde::de::Error,
+{
+    E::custom(format!(
+        "negative value {value} cannot be deserialised as the unsigned {}",
+        any::type_name::<T>()
+    ))
+}
+
+fn out_of_range<T, E>(value: impl fmt::Display) -> E
+where
+    E: serde::de::Error,
+{
+    E::custom(format!(
+        "value {value} is out of range for {}",
+        any::type_name::<T>()
+    ))
 }
 
 pub struct BigDecimalVisitor<T> {
@@ -86,10 +139,40 @@ where
         T::try_from(v).map_err(E::custom)
     }
 
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let v: BigDecimal = BigDecimal::from(v);
+        T::try_from(v).map_err(E::custom)
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let v: BigDecimal = BigDecimal::new(BigInt::from(v), 0);
+        T::try_from(v).map_err(E::custom)
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let v: BigDecimal = BigDecimal::new(BigInt::from(v), 0);
+        T::try_from(v).map_err(E::custom)
+    }
+
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
     {
+        // most of the values are integers that fit in a u64, parsing
+        // them directly is a lot cheaper than going through the BigDecimal parser
+        if let Ok(v) = value.parse::<u64>() {
+            return self.visit_u64(v);
+        }
+
         let v: BigDecimal = value.parse::<BigDecimal>().map_err(E::custom)?;
         T::try_from(v).map_err(E::custom)
     }
@@ -98,6 +181,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::de::{value::U128Deserializer, Deserialize as _, IntoDeserializer as _};
     use serde::Serialize;
     use smoke::{generator::num, property};
     use smoke_macros::smoketest;
@@ -120,6 +204,80 @@ mod tests {
         }
     }
 
+    #[derive(Debug, PartialEq)]
+    struct Decimal(BigDecimal);
+
+    impl From<BigDecimal> for Decimal {
+        fn from(number: BigDecimal) -> Self {
+            Self(number)
+        }
+    }
+
+    impl<'de> serde::de::Deserialize<'de> for Decimal {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+        {
+            deserializer.deserialize_any(BigDecimalVisitor::<Decimal>::default())
+        }
+    }
+
+    #[test]
+    fn number_visitor_negative() {
+        let error = deps::serde_json::from_str::<Sample>("-1").unwrap_err();
+        assert!(error.to_string().starts_with("negative value -1"));
+
+        let error = deps::serde_json::from_str::<Sample>("\"-42\"").unwrap_err();
+        assert!(error.to_string().starts_with("negative value -42"));
+    }
+
+    #[test]
+    fn number_visitor_out_of_range() {
+        let too_large = (u128::from(u64::MAX) + 1).to_string();
+
+        let error = deps::serde_json::from_str::<Sample>(&format!("\"{too_large}\"")).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with(&format!("value {too_large} is out of range")));
+
+        let error = deps::serde_json::from_str::<Sample>("\"not a number\"").unwrap_err();
+        assert!(error.to_string().starts_with("invalid digit"));
+    }
+
+    #[test]
+    fn big_decimal_visitor_u128() {
+        let value = u128::MAX;
+        let expected = Decimal(value.to_string().parse().unwrap());
+
+        assert_eq!(
+            deps::serde_json::from_str::<Decimal>(&format!("\"{value}\"")).unwrap(),
+            expected
+        );
+
+        let deserializer: U128Deserializer<serde::de::value::Error> = value.into_deserializer();
+        assert_eq!(Decimal::deserialize(deserializer).unwrap(), expected);
+    }
+
+    #[test]
+    fn big_decimal_visitor_negative() {
+        assert_eq!(
+            deps::serde_json::from_str::<Decimal>("-1").unwrap(),
+            Decimal(BigDecimal::from(-1i64))
+        );
+        assert_eq!(
+            deps::serde_json::from_str::<Decimal>("\"-0.5\"").unwrap(),
+            Decimal("-0.5".parse().unwrap())
+        );
+
+        let value = i128::MIN;
+        let deserializer: serde::de::value::I128Deserializer<serde::de::value::Error> =
+            value.into_deserializer();
+        assert_eq!(
+            Decimal::deserialize(deserializer).unwrap(),
+            Decimal(value.to_string().parse().unwrap())
+        );
+    }
+
     #[smoketest{ n: num::<u64>() }]
     fn check_visitor_from_struct(n: u64) {
         let input = Sample(n);
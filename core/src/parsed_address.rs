@@ -0,0 +1,529 @@
+use crate::cbor::{CborReader, MAJOR_ARRAY, MAJOR_MAP, MAJOR_UNSIGNED};
+use crate::{
+    Address, Credential, PaymentCredential, ScriptHash, StakeCredential, CREDENTIAL_HASH_SIZE,
+};
+use bech32::FromBase32 as _;
+use thiserror::Error;
+
+const HASH_SIZE: usize = CREDENTIAL_HASH_SIZE;
+const MAINNET_PROTOCOL_MAGIC: u32 = 764824073;
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// pointer to the stake registration certificate of a pointer address
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct StakePointer {
+    pub slot: u64,
+    pub transaction_index: u64,
+    pub certificate_index: u64,
+}
+
+/// decoded [`Address`], see [CIP-19] for the details of the different
+/// types of addresses.
+///
+/// [CIP-19]: https://cips.cardano.org/cips/cip19/
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum ParsedAddress {
+    Base {
+        network_id: u8,
+        payment: PaymentCredential,
+        stake: StakeCredential,
+    },
+    Enterprise {
+        network_id: u8,
+        payment: PaymentCredential,
+    },
+    Pointer {
+        network_id: u8,
+        payment: PaymentCredential,
+        pointer: StakePointer,
+    },
+    Reward {
+        network_id: u8,
+        stake: StakeCredential,
+    },
+    /// legacy bootstrap address. The `protocol_magic` is only
+    /// set for the addresses that are not for the mainnet.
+    Byron { protocol_magic: Option<u32> },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AddressError {
+    #[error("The address is not valid bech32, hexadecimal or base58")]
+    InvalidEncoding,
+    #[error("Invalid bech32 address")]
+    Bech32(#[from] bech32::Error),
+    #[error("Unexpected human readable part {hrp} for the address type")]
+    InvalidPrefix { hrp: String },
+    #[error("Unexpected human readable part {hrp} for the network id {network_id}")]
+    NetworkMismatch { hrp: String, network_id: u8 },
+    #[error("The address is empty")]
+    Empty,
+    #[error("Unsupported address type {0:#x}")]
+    UnsupportedType(u8),
+    #[error("Invalid length {length} for the address type")]
+    InvalidLength { length: usize },
+    #[error("Invalid stake pointer")]
+    InvalidPointer,
+    #[error("Invalid byron address")]
+    InvalidByron,
+    #[error(
+        "Invalid checksum of the byron address, expected {expected:#010x} but found {found:#010x}"
+    )]
+    InvalidByronChecksum { expected: u32, found: u64 },
+    #[error("Unexpected bytes after the byron address")]
+    TrailingBytes,
+}
+
+impl ParsedAddress {
+    /// decode the address from its bytes representation
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AddressError> {
+        let header = *bytes.first().ok_or(AddressError::Empty)?;
+        let address_type = header >> 4;
+        let network_id = header & 0x0F;
+        let payload = &bytes[1..];
+
+        let expect_length = |length: usize| {
+            if payload.len() == length {
+                Ok(())
+            } else {
+                Err(AddressError::InvalidLength {
+                    length: bytes.len(),
+                })
+            }
+        };
+
+        match address_type {
+            0b0000..=0b0011 => {
+                expect_length(2 * HASH_SIZE)?;
+                Ok(Self::Base {
+                    network_id,
+                    payment: decode_credential(&payload[..HASH_SIZE], address_type & 0b01 != 0),
+                    stake: decode_credential(&payload[HASH_SIZE..], address_type & 0b10 != 0),
+                })
+            }
+            0b0100 | 0b0101 => {
+                if payload.len() <= HASH_SIZE {
+                    return Err(AddressError::InvalidLength {
+                        length: bytes.len(),
+                    });
+                }
+                Ok(Self::Pointer {
+                    network_id,
+                    payment: decode_credential(&payload[..HASH_SIZE], address_type == 0b0101),
+                    pointer: StakePointer::decode(&payload[HASH_SIZE..])?,
+                })
+            }
+            0b0110 | 0b0111 => {
+                expect_length(HASH_SIZE)?;
+                Ok(Self::Enterprise {
+                    network_id,
+                    payment: decode_credential(payload, address_type == 0b0111),
+                })
+            }
+            0b1000 => decode_byron(bytes),
+            0b1110 | 0b1111 => {
+                expect_length(HASH_SIZE)?;
+                Ok(Self::Reward {
+                    network_id,
+                    stake: decode_credential(payload, address_type == 0b1111),
+                })
+            }
+            _ => Err(AddressError::UnsupportedType(header)),
+        }
+    }
+
+    /// network id of the shelley addresses, `None` for the
+    /// byron addresses.
+    pub fn network_id(&self) -> Option<u8> {
+        match self {
+            Self::Base { network_id, .. }
+            | Self::Enterprise { network_id, .. }
+            | Self::Pointer { network_id, .. }
+            | Self::Reward { network_id, .. } => Some(*network_id),
+            Self::Byron { .. } => None,
+        }
+    }
+
+    pub fn payment_credential(&self) -> Option<&PaymentCredential> {
+        match self {
+            Self::Base { payment, .. }
+            | Self::Enterprise { payment, .. }
+            | Self::Pointer { payment, .. } => Some(payment),
+            Self::Reward { .. } | Self::Byron { .. } => None,
+        }
+    }
+
+    pub fn stake_credential(&self) -> Option<&StakeCredential> {
+        match self {
+            Self::Base { stake, .. } | Self::Reward { stake, .. } => Some(stake),
+            Self::Enterprise { .. } | Self::Pointer { .. } | Self::Byron { .. } => None,
+        }
+    }
+
+    pub fn is_byron(&self) -> bool {
+        matches!(self, Self::Byron { .. })
+    }
+
+    /// check the address belongs to the network identified by the
+    /// `network_id` (shelley addresses) and the `protocol_magic`
+    /// (byron addresses).
+    pub fn is_valid_for(&self, network_id: u8, protocol_magic: u32) -> bool {
+        match self {
+            Self::Byron {
+                protocol_magic: None,
+            } => protocol_magic == MAINNET_PROTOCOL_MAGIC,
+            Self::Byron {
+                protocol_magic: Some(magic),
+            } => *magic == protocol_magic,
+            shelley => shelley.network_id() == Some(network_id),
+        }
+    }
+}
+
+impl StakePointer {
+    fn decode(mut bytes: &[u8]) -> Result<Self, AddressError> {
+        let slot = decode_variable_length(&mut bytes)?;
+        let transaction_index = decode_variable_length(&mut bytes)?;
+        let certificate_index = decode_variable_length(&mut bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(AddressError::InvalidPointer);
+        }
+
+        Ok(Self {
+            slot,
+            transaction_index,
+            certificate_index,
+        })
+    }
+}
+
+impl Address {
+    /// parse the address, it can be either bech32 encoded (shelley
+    /// addresses), base58 encoded (byron addresses) or the
+    /// hexadecimal encoding of the address bytes.
+    ///
+    /// ```
+    /// use dcspark_core::{Address, ParsedAddress};
+    ///
+    /// let address = Address::new_static("addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8");
+    /// let parsed = address.parse().unwrap();
+    ///
+    /// assert!(matches!(parsed, ParsedAddress::Enterprise { .. }));
+    /// assert_eq!(parsed.network_id(), Some(1));
+    /// ```
+    pub fn parse(&self) -> Result<ParsedAddress, AddressError> {
+        let address = self.as_ref();
+
+        if let Ok(bytes) = hex::decode(address) {
+            return ParsedAddress::from_bytes(&bytes);
+        }
+
+        if address.starts_with("addr") || address.starts_with("stake") {
+            let (hrp, data, _) = bech32::decode(address)?;
+            let bytes = Vec::<u8>::from_base32(&data)?;
+            let parsed = ParsedAddress::from_bytes(&bytes)?;
+            match (hrp.as_str(), &parsed) {
+                ("stake" | "stake_test", ParsedAddress::Reward { .. }) => (),
+                ("addr" | "addr_test", ParsedAddress::Reward { .. }) => {
+                    return Err(AddressError::InvalidPrefix { hrp })
+                }
+                ("addr" | "addr_test", ParsedAddress::Byron { .. }) => {
+                    return Err(AddressError::InvalidPrefix { hrp })
+                }
+                ("addr" | "addr_test", _) => (),
+                _ => return Err(AddressError::InvalidPrefix { hrp }),
+            }
+
+            // the mainnet prefixes are only for the network id 1
+            let mainnet = matches!(hrp.as_str(), "addr" | "stake");
+            return match parsed.network_id() {
+                Some(network_id) if (network_id == 1) != mainnet => {
+                    Err(AddressError::NetworkMismatch { hrp, network_id })
+                }
+                _ => Ok(parsed),
+            };
+        }
+
+        let bytes = decode_base58(address).ok_or(AddressError::InvalidEncoding)?;
+        decode_byron(&bytes)
+    }
+}
+
+fn decode_credential<C: From<Credential>>(bytes: &[u8], script: bool) -> C {
+    let mut hash = [0; HASH_SIZE];
+    hash.copy_from_slice(bytes);
+    if script {
+        Credential::Script(ScriptHash::new(hash)).into()
+    } else {
+        Credential::Key(hash).into()
+    }
+}
+
+/// decode the variable length natural numbers of the stake pointers:
+/// big endian, 7 bits per byte with the most significant bit set on
+/// all the bytes but the last one.
+fn decode_variable_length(bytes: &mut &[u8]) -> Result<u64, AddressError> {
+    let mut value: u64 = 0;
+    loop {
+        let (byte, remaining) = bytes.split_first().ok_or(AddressError::InvalidPointer)?;
+        *bytes = remaining;
+
+        value =
+            value.checked_mul(128).ok_or(AddressError::InvalidPointer)? | u64::from(byte & 0x7F);
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn decode_base58(input: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+
+    for c in input.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let leading_zeros = input.bytes().take_while(|c| *c == b'1').count();
+    let mut decoded = vec![0; leading_zeros];
+    decoded.extend(bytes);
+    Some(decoded)
+}
+
+/// byron addresses are CBOR encoded: `[ #6.24(bytes .cbor payload), crc32 ]`
+/// with the payload `[ root, attributes, type ]` and the crc32 of the
+/// payload bytes. The protocol magic, if any, is the attribute `2`.
+fn decode_byron(bytes: &[u8]) -> Result<ParsedAddress, AddressError> {
+    let mut cbor = CborReader::new(bytes);
+
+    if cbor.header(MAJOR_ARRAY) != Some(2) {
+        return Err(AddressError::InvalidByron);
+    }
+    let payload = cbor.encoded_cbor().ok_or(AddressError::InvalidByron)?;
+    let found = cbor
+        .header(MAJOR_UNSIGNED)
+        .ok_or(AddressError::InvalidByron)?;
+    if !cbor.is_empty() {
+        return Err(AddressError::TrailingBytes);
+    }
+
+    let expected = crc32fast::hash(payload);
+    if u64::from(expected) != found {
+        return Err(AddressError::InvalidByronChecksum { expected, found });
+    }
+
+    decode_byron_protocol_magic(payload)
+        .map(|protocol_magic| ParsedAddress::Byron { protocol_magic })
+        .ok_or(AddressError::InvalidByron)
+}
+
+fn decode_byron_protocol_magic(payload: &[u8]) -> Option<Option<u32>> {
+    let mut payload = CborReader::new(payload);
+
+    if payload.header(MAJOR_ARRAY)? != 3 || payload.bytes()?.len() != HASH_SIZE {
+        return None;
+    }
+
+    let mut protocol_magic = None;
+    let attributes = payload.header(MAJOR_MAP)?;
+    for _ in 0..attributes {
+        let key = payload.header(MAJOR_UNSIGNED)?;
+        let value = payload.bytes()?;
+        if key == 2 {
+            let magic = CborReader::new(value).header(MAJOR_UNSIGNED)?;
+            protocol_magic = Some(u32::try_from(magic).ok()?);
+        }
+    }
+    payload.header(MAJOR_UNSIGNED)?;
+
+    payload.is_empty().then_some(protocol_magic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYMENT: &str = "9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e";
+    const STAKE: &str = "337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251";
+
+    fn key<C: From<Credential>>(hash: &str) -> C {
+        Credential::key_from_hex(hash).unwrap().into()
+    }
+
+    #[test]
+    fn base() {
+        let parsed = Address::new_static("addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x").parse().unwrap();
+
+        assert_eq!(
+            parsed,
+            ParsedAddress::Base {
+                network_id: 1,
+                payment: key(PAYMENT),
+                stake: key(STAKE),
+            }
+        );
+        assert!(parsed.is_valid_for(1, MAINNET_PROTOCOL_MAGIC));
+        assert!(!parsed.is_valid_for(0, 1097911063));
+    }
+
+    #[test]
+    fn enterprise() {
+        let parsed =
+            Address::new_static("addr_test1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspjrlsz")
+                .parse()
+                .unwrap();
+
+        assert_eq!(parsed.network_id(), Some(0));
+        assert_eq!(parsed.payment_credential(), Some(&key(PAYMENT)));
+        assert_eq!(parsed.stake_credential(), None);
+    }
+
+    #[test]
+    fn enterprise_script() {
+        let parsed =
+            Address::new_static("addr1w8phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtcyjy7wx")
+                .parse()
+                .unwrap();
+
+        assert!(parsed
+            .payment_credential()
+            .unwrap()
+            .credential()
+            .is_script());
+    }
+
+    #[test]
+    fn pointer() {
+        let parsed = Address::new_static(
+            "addr1gx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer5pnz75xxcrzqf96k",
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            parsed,
+            ParsedAddress::Pointer {
+                network_id: 1,
+                payment: key(PAYMENT),
+                pointer: StakePointer {
+                    slot: 2498243,
+                    transaction_index: 27,
+                    certificate_index: 3,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn reward() {
+        let parsed =
+            Address::new_static("stake1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw")
+                .parse()
+                .unwrap();
+
+        assert_eq!(parsed.stake_credential(), Some(&key(STAKE)));
+        assert_eq!(parsed.payment_credential(), None);
+    }
+
+    #[test]
+    fn hexadecimal() {
+        let parsed = Address::new(format!("61{PAYMENT}")).parse().unwrap();
+
+        assert_eq!(
+            parsed,
+            ParsedAddress::Enterprise {
+                network_id: 1,
+                payment: key(PAYMENT),
+            }
+        );
+    }
+
+    #[test]
+    fn byron() {
+        let parsed =
+            Address::new_static("Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAi")
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            parsed,
+            ParsedAddress::Byron {
+                protocol_magic: None
+            }
+        );
+        assert!(parsed.is_byron());
+        assert!(parsed.is_valid_for(1, MAINNET_PROTOCOL_MAGIC));
+        assert!(!parsed.is_valid_for(0, 1097911063));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Address::new_static("not an address").parse(),
+            Err(AddressError::InvalidEncoding)
+        );
+        assert_eq!(
+            Address::new(format!("61{}", &PAYMENT[2..])).parse(),
+            Err(AddressError::InvalidLength { length: 28 })
+        );
+        assert!(matches!(
+            Address::new_static("addr1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw")
+                .parse(),
+            Err(AddressError::Bech32(_))
+        ));
+    }
+
+    #[test]
+    fn byron_invalid_checksum() {
+        // the CIP-19 byron address with the last byte of its crc32 changed
+        assert!(matches!(
+            Address::new_static("Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAh")
+                .parse(),
+            Err(AddressError::InvalidByronChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn byron_trailing_bytes() {
+        let mut bytes =
+            decode_base58("Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAi").unwrap();
+        bytes.push(0);
+
+        assert_eq!(
+            ParsedAddress::from_bytes(&bytes),
+            Err(AddressError::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn network_mismatch() {
+        // header 0x61: enterprise address of the network 1
+        assert_eq!(
+            Address::new_static("addr_test1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzerspqnws9")
+                .parse(),
+            Err(AddressError::NetworkMismatch {
+                hrp: "addr_test".to_string(),
+                network_id: 1
+            })
+        );
+        // header 0x60: enterprise address of the network 0
+        assert_eq!(
+            Address::new_static("addr1vz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers6g8jlq")
+                .parse(),
+            Err(AddressError::NetworkMismatch {
+                hrp: "addr".to_string(),
+                network_id: 0
+            })
+        );
+    }
+}
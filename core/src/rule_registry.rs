@@ -0,0 +1,278 @@
+use crate::{Normalized, Regulated, Rule, TokenId, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// registry of the conversion [`Rule`] of every known token
+///
+/// The registry can be loaded from a configuration file:
+///
+/// ```
+/// use dcspark_core::{RuleRegistry, TokenId};
+///
+/// let registry: RuleRegistry = deps::serde_json::from_str(r#"{
+///     "default": {
+///         "asset": "0000000000000000000000000000000000000000000000000000000000000000",
+///         "mainchain_decimal_precision": 6,
+///         "sidechain_decimal_precision": 18
+///     },
+///     "rules": [
+///         {
+///             "asset": "asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92",
+///             "mainchain_decimal_precision": 0,
+///             "sidechain_decimal_precision": 0
+///         }
+///     ]
+/// }"#).unwrap();
+///
+/// let rule = registry
+///     .rule_for(&TokenId::new_static("asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92"))
+///     .unwrap();
+/// assert_eq!(rule.mainchain_decimal_precision, 0);
+///
+/// // unknown tokens fallback on the default rule
+/// let rule = registry.rule_for(&TokenId::new_static("unknown")).unwrap();
+/// assert_eq!(rule.mainchain_decimal_precision, 6);
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "RuleRegistryConfig", into = "RuleRegistryConfig")]
+pub struct RuleRegistry {
+    default: Option<Rule>,
+    rules: HashMap<TokenId, Rule>,
+}
+
+#[derive(Debug, Error)]
+pub enum RuleRegistryError {
+    #[error("No rule registered for the token {token}")]
+    UnknownToken { token: TokenId },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleRegistryConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<Rule>,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+impl RuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// create a registry that will fallback on the given `rule` for
+    /// the tokens that do not have a rule registered.
+    pub fn with_default(rule: Rule) -> Self {
+        Self {
+            default: Some(rule),
+            rules: HashMap::new(),
+        }
+    }
+
+    pub fn set_default(&mut self, rule: Rule) -> Option<Rule> {
+        self.default.replace(rule)
+    }
+
+    pub fn default_rule(&self) -> Option<&Rule> {
+        self.default.as_ref()
+    }
+
+    /// register the rule for the [`Rule::asset`], returns the previously
+    /// registered rule for this asset (if any).
+    pub fn insert(&mut self, rule: Rule) -> Option<Rule> {
+        self.rules.insert(rule.asset.clone(), rule)
+    }
+
+    /// get the rule registered for the given token, without falling back
+    /// on the default rule.
+    pub fn get(&self, token: &TokenId) -> Option<&Rule> {
+        self.rules.get(token)
+    }
+
+    /// get the rule to apply for the given token, falling back on the
+    /// default rule if the token has no rule registered.
+    pub fn rule_for(&self, token: &TokenId) -> Result<&Rule, RuleRegistryError> {
+        self.rules
+            .get(token)
+            .or(self.default.as_ref())
+            .ok_or_else(|| RuleRegistryError::UnknownToken {
+                token: token.clone(),
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.values()
+    }
+}
+
+impl FromIterator<Rule> for RuleRegistry {
+    fn from_iter<I: IntoIterator<Item = Rule>>(iter: I) -> Self {
+        let mut registry = Self::new();
+        for rule in iter {
+            registry.insert(rule);
+        }
+        registry
+    }
+}
+
+impl From<RuleRegistryConfig> for RuleRegistry {
+    fn from(config: RuleRegistryConfig) -> Self {
+        let mut registry: Self = config.rules.into_iter().collect();
+        registry.default = config.default;
+        registry
+    }
+}
+
+impl From<RuleRegistry> for RuleRegistryConfig {
+    fn from(registry: RuleRegistry) -> Self {
+        let mut rules: Vec<Rule> = registry.rules.into_values().collect();
+        rules.sort_by(|a, b| a.asset.cmp(&b.asset));
+
+        Self {
+            default: registry.default,
+            rules,
+        }
+    }
+}
+
+impl Value<Regulated> {
+    /// see [`Value::normalize_from_mainchain`], the rule is looked up in the registry
+    pub fn normalize_from_mainchain_with(
+        &self,
+        registry: &RuleRegistry,
+        token: &TokenId,
+    ) -> Result<Value<Normalized>, RuleRegistryError> {
+        registry
+            .rule_for(token)
+            .map(|rule| self.normalize_from_mainchain(rule))
+    }
+
+    /// see [`Value::normalize_from_sidechain`], the rule is looked up in the registry
+    pub fn normalize_from_sidechain_with(
+        &self,
+        registry: &RuleRegistry,
+        token: &TokenId,
+    ) -> Result<Value<Normalized>, RuleRegistryError> {
+        registry
+            .rule_for(token)
+            .map(|rule| self.normalize_from_sidechain(rule))
+    }
+
+    /// see [`Value::from_normalized_to_mainchain`], the rule is looked up in the registry
+    pub fn from_normalized_to_mainchain_with(
+        normalized: &Value<Normalized>,
+        registry: &RuleRegistry,
+        token: &TokenId,
+    ) -> Result<Self, RuleRegistryError> {
+        registry
+            .rule_for(token)
+            .map(|rule| Self::from_normalized_to_mainchain(normalized, rule))
+    }
+
+    /// see [`Value::from_normalized_to_sidechain`], the rule is looked up in the registry
+    pub fn from_normalized_to_sidechain_with(
+        normalized: &Value<Normalized>,
+        registry: &RuleRegistry,
+        token: &TokenId,
+    ) -> Result<Self, RuleRegistryError> {
+        registry
+            .rule_for(token)
+            .map(|rule| Self::from_normalized_to_sidechain(normalized, rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADA: Rule = Rule {
+        asset: TokenId::MAIN,
+        mainchain_decimal_precision: 6,
+        sidechain_decimal_precision: 18,
+    };
+
+    const TOKEN: Rule = Rule {
+        asset: TokenId::new_static("token"),
+        mainchain_decimal_precision: 2,
+        sidechain_decimal_precision: 2,
+    };
+
+    #[test]
+    fn lookup_with_fallback() {
+        let mut registry = RuleRegistry::new();
+        registry.insert(TOKEN);
+
+        assert!(registry.get(&TokenId::MAIN).is_none());
+        assert!(matches!(
+            registry.rule_for(&TokenId::MAIN),
+            Err(RuleRegistryError::UnknownToken { .. })
+        ));
+
+        registry.set_default(ADA);
+        assert!(registry.get(&TokenId::MAIN).is_none());
+        assert_eq!(
+            registry
+                .rule_for(&TokenId::MAIN)
+                .unwrap()
+                .mainchain_decimal_precision,
+            6
+        );
+        assert_eq!(
+            registry
+                .rule_for(&TOKEN.asset)
+                .unwrap()
+                .mainchain_decimal_precision,
+            2
+        );
+    }
+
+    #[test]
+    fn normalize_with() {
+        let registry: RuleRegistry = [ADA, TOKEN].into_iter().collect();
+
+        let value = Value::<Regulated>::from(1_000_000u64);
+        let normalized = value
+            .normalize_from_mainchain_with(&registry, &TokenId::MAIN)
+            .unwrap();
+        assert_eq!(normalized, Value::from(1u64));
+
+        let normalized = value
+            .normalize_from_mainchain_with(&registry, &TOKEN.asset)
+            .unwrap();
+        assert_eq!(normalized, Value::from(10_000u64));
+
+        let back = Value::<Regulated>::from_normalized_to_mainchain_with(
+            &normalized,
+            &registry,
+            &TOKEN.asset,
+        )
+        .unwrap();
+        assert_eq!(back, value);
+
+        assert!(value
+            .normalize_from_mainchain_with(&registry, &TokenId::new_static("unknown"))
+            .is_err());
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let mut registry = RuleRegistry::with_default(ADA);
+        registry.insert(TOKEN);
+
+        let json = deps::serde_json::to_string(&registry).unwrap();
+        let decoded: RuleRegistry = deps::serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            decoded.default_rule().unwrap().sidechain_decimal_precision,
+            18
+        );
+        assert_eq!(
+            decoded
+                .get(&TOKEN.asset)
+                .unwrap()
+                .mainchain_decimal_precision,
+            2
+        );
+    }
+}
@@ -58,6 +58,68 @@ impl SlotNumber {
     pub fn saturating_add(self, rhs: u64) -> Self {
         Self(self.0.saturating_add(rhs))
     }
+
+    #[inline(always)]
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn into_inner(self) -> u64 {
+        self.0
+    }
+
+    /// Try to decrease by `1` the [`SlotNumber`]
+    ///
+    /// If the subtraction will underflow, the function will returns `None`.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    #[inline]
+    pub fn checked_prev(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+
+    /// Try to subtract the right hand side (`rhs`) value to the [`SlotNumber`].
+    ///
+    /// If the subtraction will underflow, the function will returns `None`.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    #[inline]
+    pub fn checked_sub(self, rhs: u64) -> Option<Self> {
+        self.0.checked_sub(rhs).map(Self)
+    }
+
+    /// Subtract the right hand side (`rhs`) value to the [`SlotNumber`].
+    ///
+    /// If the subtraction will overflow we returns the [`Self::MIN`].
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    #[inline]
+    pub fn saturating_sub(self, rhs: u64) -> Self {
+        Self(self.0.saturating_sub(rhs))
+    }
+
+    /// the number of steps between the two [`SlotNumber`], regardless of
+    /// their order.
+    #[inline]
+    pub fn distance(self, other: Self) -> u64 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// iterate over all the [`SlotNumber`] from `self` to `end` (excluded)
+    ///
+    /// ```
+    /// # use dcspark_core::SlotNumber;
+    /// let numbers: Vec<_> = SlotNumber::new(1).range(SlotNumber::new(3)).collect();
+    /// assert_eq!(numbers, vec![SlotNumber::new(1), SlotNumber::new(2)]);
+    /// ```
+    pub fn range(self, end: Self) -> impl DoubleEndedIterator<Item = Self> {
+        (self.0..end.0).map(Self)
+    }
+
+    /// iterate over all the [`SlotNumber`] from `self` to `end` (included)
+    ///
+    /// ```
+    /// # use dcspark_core::SlotNumber;
+    /// let numbers: Vec<_> = SlotNumber::new(1).range_inclusive(SlotNumber::new(3)).collect();
+    /// assert_eq!(numbers.len(), 3);
+    /// ```
+    pub fn range_inclusive(self, end: Self) -> impl DoubleEndedIterator<Item = Self> {
+        (self.0..=end.0).map(Self)
+    }
 }
 
 impl fmt::Display for SlotNumber {
@@ -198,6 +260,44 @@ mod tests {
         )
     }
 
+    #[test]
+    fn checked_prev_underflow() {
+        assert_eq!(None, SlotNumber::MIN.checked_prev())
+    }
+
+    #[smoketest{ a: num::<u64>(), b: num::<u64>() }]
+    fn checked_sub(a: u64, b: u64) {
+        property::equal(
+            a.checked_sub(b).map(SlotNumber),
+            SlotNumber(a).checked_sub(b),
+        )
+    }
+
+    #[smoketest{ a: num::<u64>(), b: num::<u64>() }]
+    fn distance(a: u64, b: u64) {
+        property::equal(
+            SlotNumber(a).distance(SlotNumber(b)),
+            SlotNumber(b).distance(SlotNumber(a)),
+        )
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(SlotNumber(5).range(SlotNumber(5)).count(), 0);
+        assert_eq!(SlotNumber(5).range_inclusive(SlotNumber(5)).count(), 1);
+        assert_eq!(
+            SlotNumber(1)
+                .range_inclusive(SlotNumber(3))
+                .rev()
+                .collect::<Vec<_>>(),
+            vec![SlotNumber(3), SlotNumber(2), SlotNumber(1)]
+        );
+        assert_eq!(
+            SlotNumber::MAX.range_inclusive(SlotNumber::MAX).next(),
+            Some(SlotNumber::MAX)
+        );
+    }
+
     #[test]
     fn deserialize_from_number() {
         let expected = Sample {
@@ -1,4 +1,293 @@
+use futures::future::{join_all, BoxFuture};
+use std::{collections::HashSet, time::Duration};
+use thiserror::Error;
+
 #[async_trait::async_trait]
 pub trait StoppableService {
     async fn stop(self) -> anyhow::Result<()>;
 }
+
+#[derive(Debug, Error)]
+pub enum StopError {
+    #[error("the service did not stop within {0:?}")]
+    Timeout(Duration),
+    #[error("the service failed to stop")]
+    Failed(#[source] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+#[error("{} service(s) failed to stop", .failures.len())]
+pub struct ShutdownError {
+    /// the services that failed to stop, with their error, in the order
+    /// they were stopped
+    pub failures: Vec<(String, StopError)>,
+}
+
+impl ShutdownError {
+    pub fn failed_services(&self) -> Vec<&str> {
+        self.failures
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+/// stop the service, giving up if it takes longer than `timeout`
+pub async fn stop_with_timeout<S>(service: S, timeout: Duration) -> Result<(), StopError>
+where
+    S: StoppableService,
+{
+    match tokio::time::timeout(timeout, service.stop()).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(StopError::Failed(error)),
+        Err(_) => Err(StopError::Timeout(timeout)),
+    }
+}
+
+/// stop all the services concurrently, each of them has `timeout` to stop
+///
+/// the results are returned in the same order as the services.
+pub async fn stop_all<S>(services: Vec<S>, timeout: Duration) -> Vec<Result<(), StopError>>
+where
+    S: StoppableService,
+{
+    join_all(
+        services
+            .into_iter()
+            .map(|service| stop_with_timeout(service, timeout)),
+    )
+    .await
+}
+
+struct RegisteredService {
+    name: String,
+    depends_on: Vec<String>,
+    stop: BoxFuture<'static, Result<(), StopError>>,
+}
+
+/// stops the services of an application in dependency order
+///
+/// a service is stopped before the services it depends on: if the
+/// application's consumer depends on the blockchain source, the consumer
+/// is stopped first so it does not pull from a stopped source. The
+/// services that do not depend on each other are stopped concurrently.
+///
+/// ```
+/// # use dcspark_core::{ShutdownCoordinator, StoppableService};
+/// # use std::time::Duration;
+/// # struct Service;
+/// # #[async_trait::async_trait]
+/// # impl StoppableService for Service {
+/// #     async fn stop(self) -> anyhow::Result<()> { Ok(()) }
+/// # }
+/// # async fn shutdown(source: Service, consumer: Service) -> anyhow::Result<()> {
+/// let mut coordinator = ShutdownCoordinator::new(Duration::from_secs(10));
+/// coordinator
+///     .register("source", source, &[])
+///     .register("consumer", consumer, &["source"]);
+///
+/// coordinator.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ShutdownCoordinator {
+    timeout: Duration,
+    services: Vec<RegisteredService>,
+}
+
+impl ShutdownCoordinator {
+    /// create a coordinator where every service has `timeout` to stop
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            services: Vec::new(),
+        }
+    }
+
+    /// register a service under the given `name`, it will be stopped before
+    /// the services it `depends_on`.
+    ///
+    /// the dependencies that are not registered are ignored.
+    pub fn register<S>(
+        &mut self,
+        name: impl Into<String>,
+        service: S,
+        depends_on: &[&str],
+    ) -> &mut Self
+    where
+        S: StoppableService + Send + 'static,
+    {
+        let timeout = self.timeout;
+        self.services.push(RegisteredService {
+            name: name.into(),
+            depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+            stop: Box::pin(stop_with_timeout(service, timeout)),
+        });
+        self
+    }
+
+    /// stop all the registered services
+    ///
+    /// all the services are stopped even if some fail to stop. If there is
+    /// a dependency cycle, the services of the cycle are stopped concurrently.
+    pub async fn shutdown(self) -> Result<(), ShutdownError> {
+        let mut pending = self.services;
+        let mut failures = Vec::new();
+
+        while !pending.is_empty() {
+            // the services nothing pending depends on can be stopped now
+            let depended_on: HashSet<String> = pending
+                .iter()
+                .flat_map(|service| service.depends_on.iter().cloned())
+                .collect();
+            let (mut stage, remaining): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|service| !depended_on.contains(&service.name));
+            pending = remaining;
+
+            if stage.is_empty() {
+                stage = std::mem::take(&mut pending);
+            }
+
+            let (names, stops): (Vec<_>, Vec<_>) = stage
+                .into_iter()
+                .map(|service| (service.name, service.stop))
+                .unzip();
+
+            for (name, result) in names.into_iter().zip(join_all(stops).await) {
+                if let Err(error) = result {
+                    failures.push((name, error));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ShutdownError { failures })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    enum Behaviour {
+        Stop,
+        Fail,
+        Hang,
+    }
+
+    struct Service {
+        name: &'static str,
+        behaviour: Behaviour,
+        stopped: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl StoppableService for Service {
+        async fn stop(self) -> anyhow::Result<()> {
+            match self.behaviour {
+                Behaviour::Stop => {
+                    self.stopped.lock().unwrap().push(self.name);
+                    Ok(())
+                }
+                Behaviour::Fail => Err(anyhow::anyhow!("{} failed", self.name)),
+                Behaviour::Hang => std::future::pending().await,
+            }
+        }
+    }
+
+    fn service(
+        name: &'static str,
+        behaviour: Behaviour,
+        stopped: &Arc<Mutex<Vec<&'static str>>>,
+    ) -> Service {
+        Service {
+            name,
+            behaviour,
+            stopped: Arc::clone(stopped),
+        }
+    }
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn stop_all_reports_each_service() {
+        let stopped = Arc::default();
+        let results = stop_all(
+            vec![
+                service("a", Behaviour::Stop, &stopped),
+                service("b", Behaviour::Fail, &stopped),
+                service("c", Behaviour::Hang, &stopped),
+            ],
+            TIMEOUT,
+        )
+        .await;
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(StopError::Failed(_))));
+        assert!(matches!(results[2], Err(StopError::Timeout(TIMEOUT))));
+        assert_eq!(*stopped.lock().unwrap(), ["a"]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_in_dependency_order() {
+        let stopped = Arc::default();
+        let mut coordinator = ShutdownCoordinator::new(TIMEOUT);
+        coordinator
+            .register(
+                "storage",
+                service("storage", Behaviour::Stop, &stopped),
+                &[],
+            )
+            .register(
+                "source",
+                service("source", Behaviour::Stop, &stopped),
+                &["storage"],
+            )
+            .register(
+                "consumer",
+                service("consumer", Behaviour::Stop, &stopped),
+                &["source", "storage"],
+            );
+
+        coordinator.shutdown().await.unwrap();
+
+        assert_eq!(*stopped.lock().unwrap(), ["consumer", "source", "storage"]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_reports_failures() {
+        let stopped = Arc::default();
+        let mut coordinator = ShutdownCoordinator::new(TIMEOUT);
+        coordinator
+            .register("source", service("source", Behaviour::Stop, &stopped), &[])
+            .register(
+                "consumer",
+                service("consumer", Behaviour::Hang, &stopped),
+                &["source"],
+            )
+            .register("api", service("api", Behaviour::Fail, &stopped), &[]);
+
+        let error = coordinator.shutdown().await.unwrap_err();
+
+        assert_eq!(error.failed_services(), ["consumer", "api"]);
+        assert_eq!(*stopped.lock().unwrap(), ["source"]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_with_cycle() {
+        let stopped = Arc::default();
+        let mut coordinator = ShutdownCoordinator::new(TIMEOUT);
+        coordinator
+            .register("a", service("a", Behaviour::Stop, &stopped), &["b"])
+            .register("b", service("b", Behaviour::Stop, &stopped), &["a"]);
+
+        coordinator.shutdown().await.unwrap();
+
+        assert_eq!(stopped.lock().unwrap().len(), 2);
+    }
+}
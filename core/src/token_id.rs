@@ -1,11 +1,19 @@
 use std::{borrow::Cow, fmt, str};
 
+use crate::{AssetName, PolicyId};
+use bech32::ToBase32 as _;
+use cryptoxide::hashing::blake2b::Blake2b;
 use serde::{Deserialize, Serialize};
 
 /// identify a token through the protocol transfer
 ///
 /// Token identifier is the unique representation of a specific token
-/// for cardano it is the output of the CIP14 hashing, 0 padded.
+/// for cardano it is the CIP14 asset fingerprint (`asset1...`), see
+/// [`TokenId::cip14`].
+///
+/// The cardano token ids used to be the hex encoded CIP14 hash, padded
+/// with `0` to 64 characters. The stored token ids in that legacy format
+/// can be converted with [`TokenId::migrate_legacy_cip14`].
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct TokenId(Cow<'static, str>);
@@ -28,6 +36,115 @@ impl TokenId {
     pub const fn new_static(token_id: &'static str) -> Self {
         Self(Cow::Borrowed(token_id))
     }
+
+    /// compute the [CIP14] asset fingerprint of the given asset.
+    ///
+    /// Both the [`PolicyId`] and the [`AssetName`] are expected to be
+    /// hex encoded. The result is the bech32 encoded (`asset` human readable
+    /// part) blake2b-160 hash of the policy id bytes followed by the asset
+    /// name bytes.
+    ///
+    /// ```
+    /// use dcspark_core::{AssetName, PolicyId, TokenId};
+    ///
+    /// let token_id = TokenId::cip14(
+    ///     &PolicyId::new_static("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373"),
+    ///     &AssetName::new_static("504154415445"),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(token_id.as_ref(), "asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92");
+    /// ```
+    ///
+    /// [CIP14]: https://cips.cardano.org/cips/cip14/
+    pub fn cip14(policy_id: &PolicyId, asset_name: &AssetName) -> Result<Self, Cip14Error> {
+        const POLICY_ID_SIZE: usize = 28;
+
+        let policy_id = hex::decode(policy_id.as_ref()).map_err(Cip14Error::InvalidPolicyId)?;
+        if policy_id.len() != POLICY_ID_SIZE {
+            return Err(Cip14Error::InvalidPolicyIdLength {
+                length: policy_id.len(),
+            });
+        }
+        let asset_name = hex::decode(asset_name.as_ref()).map_err(Cip14Error::InvalidAssetName)?;
+
+        let mut hash = [0; CIP14_HASH_SIZE];
+        Blake2b::<{ CIP14_HASH_SIZE * 8 }>::new()
+            .update(&policy_id)
+            .update(&asset_name)
+            .finalize_at(&mut hash);
+
+        Ok(Self::new(encode_cip14(&hash)))
+    }
+
+    /// the CIP14 hash of the legacy cardano token ids: the hex encoded
+    /// hash padded with `0` to 64 characters
+    fn legacy_cip14_hash(&self) -> Option<[u8; CIP14_HASH_SIZE]> {
+        const LEGACY_SIZE: usize = 64;
+
+        if self.0.len() != LEGACY_SIZE || *self == Self::MAIN {
+            return None;
+        }
+        let (hash, padding) = self.0.split_at(CIP14_HASH_SIZE * 2);
+        if !padding.bytes().all(|c| c == b'0') {
+            return None;
+        }
+
+        let mut bytes = [0; CIP14_HASH_SIZE];
+        hex::decode_to_slice(hash, &mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    /// check the token id is in the legacy format of the cardano token ids
+    /// (see [`Self::migrate_legacy_cip14`])
+    pub fn is_legacy_cip14(&self) -> bool {
+        self.legacy_cip14_hash().is_some()
+    }
+
+    /// convert a token id in the legacy format (the hex encoded CIP14 hash,
+    /// padded with `0` to 64 characters) to its CIP14 asset fingerprint.
+    /// The other token ids, including [`Self::MAIN`], are returned as is.
+    ///
+    /// The stored token ids (and the maps keyed by them) have to be
+    /// converted to match the fingerprints computed by [`Self::cip14`].
+    ///
+    /// ```
+    /// use dcspark_core::TokenId;
+    ///
+    /// let legacy = TokenId::new_static(
+    ///     "bad5e7a31f5b9b2b1c3e0b1ad02b5a8a0c4c20f3000000000000000000000000",
+    /// );
+    ///
+    /// assert!(legacy.is_legacy_cip14());
+    /// assert!(legacy.migrate_legacy_cip14().as_ref().starts_with("asset1"));
+    /// assert_eq!(TokenId::MAIN.migrate_legacy_cip14(), TokenId::MAIN);
+    /// ```
+    pub fn migrate_legacy_cip14(&self) -> Self {
+        match self.legacy_cip14_hash() {
+            Some(hash) => Self::new(encode_cip14(&hash)),
+            None => self.clone(),
+        }
+    }
+}
+
+const CIP14_HASH_SIZE: usize = 20;
+
+fn encode_cip14(hash: &[u8; CIP14_HASH_SIZE]) -> String {
+    bech32::encode(CIP14_HRP, hash.to_base32(), bech32::Variant::Bech32)
+        .expect("the CIP14 human readable part is always valid")
+}
+
+const CIP14_HRP: &str = "asset";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Cip14Error {
+    #[error("Failed to decode the policy id: {0}")]
+    InvalidPolicyId(#[source] hex::FromHexError),
+
+    #[error("Invalid policy id length: expected 28 bytes, got {length}")]
+    InvalidPolicyIdLength { length: usize },
+
+    #[error("Failed to decode the asset name: {0}")]
+    InvalidAssetName(#[source] hex::FromHexError),
 }
 
 impl AsRef<str> for TokenId {
@@ -55,3 +172,85 @@ impl str::FromStr for TokenId {
         Ok(Self::new(s.to_owned()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cip14_test_vectors() {
+        let vectors = [
+            (
+                "7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373",
+                "",
+                "asset1rjklcrnsdzqp65wjgrg55sy9723kw09mlgvlc3",
+            ),
+            (
+                "1e349c9bdea19fd6c147626a5260bc44b71635f398b67c59881df209",
+                "504154415445",
+                "asset1hv4p5tv2a837mzqrst04d0dcptdjmluqvdx9k3",
+            ),
+            (
+                "7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "asset1pkpwyknlvul7az0xx8czhl60pyel45rpje4z8w",
+            ),
+        ];
+
+        for (policy_id, asset_name, expected) in vectors {
+            let token_id = TokenId::cip14(
+                &PolicyId::new_static(policy_id),
+                &AssetName::new_static(asset_name),
+            )
+            .unwrap();
+
+            assert_eq!(token_id.as_ref(), expected);
+        }
+    }
+
+    #[test]
+    fn cip14_invalid_inputs() {
+        let policy_id =
+            PolicyId::new_static("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373");
+
+        assert!(matches!(
+            TokenId::cip14(&PolicyId::new_static("not hex"), &AssetName::new_static("")),
+            Err(Cip14Error::InvalidPolicyId(_))
+        ));
+        assert!(matches!(
+            TokenId::cip14(&PolicyId::new_static("7eae"), &AssetName::new_static("")),
+            Err(Cip14Error::InvalidPolicyIdLength { length: 2 })
+        ));
+        assert!(matches!(
+            TokenId::cip14(&policy_id, &AssetName::new_static("0")),
+            Err(Cip14Error::InvalidAssetName(_))
+        ));
+    }
+
+    #[test]
+    fn migrate_legacy_cip14() {
+        let policy_id =
+            PolicyId::new_static("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373");
+        let asset_name = AssetName::new_static("504154415445");
+
+        // the format computed before the CIP14 fingerprints
+        let mut hash = [0; CIP14_HASH_SIZE];
+        Blake2b::<{ CIP14_HASH_SIZE * 8 }>::new()
+            .update(&hex::decode(policy_id.as_ref()).unwrap())
+            .update(&hex::decode(asset_name.as_ref()).unwrap())
+            .finalize_at(&mut hash);
+        let legacy = TokenId::new(format!("{:0<64}", hex::encode(hash)));
+
+        assert!(legacy.is_legacy_cip14());
+        assert_eq!(
+            legacy.migrate_legacy_cip14(),
+            TokenId::cip14(&policy_id, &asset_name).unwrap()
+        );
+
+        let fingerprint = TokenId::new_static("asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92");
+        assert!(!fingerprint.is_legacy_cip14());
+        assert_eq!(fingerprint.migrate_legacy_cip14(), fingerprint);
+        assert!(!TokenId::MAIN.is_legacy_cip14());
+        assert_eq!(TokenId::MAIN.migrate_legacy_cip14(), TokenId::MAIN);
+    }
+}
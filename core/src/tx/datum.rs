@@ -0,0 +1,226 @@
+use crate::cbor::{
+    write_bytes, write_encoded_cbor, write_header, CborReader, MAJOR_ARRAY, MAJOR_UNSIGNED,
+};
+use crate::Hash32;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// datum attached to a transaction output
+///
+/// the CBOR encoding is the babbage `datum_option`:
+/// `[0, datum_hash] / [1, #6.24(bytes .cbor plutus_data)]`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Datum {
+    /// hash of the datum, the datum itself is provided when
+    /// spending the output
+    Hash(Hash32),
+    /// CBOR encoded plutus data, inlined in the output
+    Inline(#[serde(with = "hex_bytes")] Vec<u8>),
+}
+
+/// script attached to a transaction output, so it can be referenced
+/// by other transactions instead of being provided in the witnesses
+///
+/// holds the CBOR encoded script: `[language, script]`. The CBOR encoding
+/// of the reference itself is `#6.24(bytes .cbor script)`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ScriptRef(#[serde(with = "hex_bytes")] Vec<u8>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptLanguage {
+    Native,
+    PlutusV1,
+    PlutusV2,
+    PlutusV3,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OutputCborError {
+    #[error("Invalid CBOR encoding of the datum")]
+    InvalidDatum,
+    #[error("Invalid CBOR encoding of the script reference")]
+    InvalidScriptRef,
+}
+
+impl Datum {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, OutputCborError> {
+        Self::decode(bytes).ok_or(OutputCborError::InvalidDatum)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut cbor = CborReader::new(bytes);
+
+        if cbor.header(MAJOR_ARRAY)? != 2 {
+            return None;
+        }
+
+        let datum = match cbor.header(MAJOR_UNSIGNED)? {
+            0 => Self::Hash(Hash32::try_from(cbor.bytes()?).ok()?),
+            1 => Self::Inline(cbor.encoded_cbor()?.to_vec()),
+            _ => return None,
+        };
+
+        cbor.is_empty().then_some(datum)
+    }
+
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_header(&mut out, MAJOR_ARRAY, 2);
+        match self {
+            Self::Hash(hash) => {
+                write_header(&mut out, MAJOR_UNSIGNED, 0);
+                write_bytes(&mut out, hash.as_bytes());
+            }
+            Self::Inline(data) => {
+                write_header(&mut out, MAJOR_UNSIGNED, 1);
+                write_encoded_cbor(&mut out, data);
+            }
+        }
+        out
+    }
+
+    pub fn hash(&self) -> Option<&Hash32> {
+        match self {
+            Self::Hash(hash) => Some(hash),
+            Self::Inline(_) => None,
+        }
+    }
+
+    pub fn inline_data(&self) -> Option<&[u8]> {
+        match self {
+            Self::Hash(_) => None,
+            Self::Inline(data) => Some(data),
+        }
+    }
+}
+
+impl ScriptRef {
+    /// create the reference from the CBOR encoded script
+    pub fn new(script: Vec<u8>) -> Self {
+        Self(script)
+    }
+
+    /// decode the CBOR encoding of the script reference (`#6.24(bytes .cbor script)`)
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, OutputCborError> {
+        let mut cbor = CborReader::new(bytes);
+        let script = cbor
+            .encoded_cbor()
+            .filter(|_| cbor.is_empty())
+            .ok_or(OutputCborError::InvalidScriptRef)?;
+        Ok(Self(script.to_vec()))
+    }
+
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_encoded_cbor(&mut out, &self.0);
+        out
+    }
+
+    /// the CBOR encoded script
+    pub fn script(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// language of the script, `None` if the script is not a valid
+    /// CBOR `[language, script]` array.
+    pub fn language(&self) -> Option<ScriptLanguage> {
+        let mut cbor = CborReader::new(&self.0);
+        if cbor.header(MAJOR_ARRAY)? != 2 {
+            return None;
+        }
+
+        match cbor.header(MAJOR_UNSIGNED)? {
+            0 => Some(ScriptLanguage::Native),
+            1 => Some(ScriptLanguage::PlutusV1),
+            2 => Some(ScriptLanguage::PlutusV2),
+            3 => Some(ScriptLanguage::PlutusV3),
+            _ => None,
+        }
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize as _, Deserializer, Serializer};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = String::deserialize(deserializer)?;
+        hex::decode(bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATUM_HASH: &str = "aa83acbf5904c0edfe4d79b3689d3d00fcfc553cf360fd2229b98d464c28e9de";
+
+    #[test]
+    fn datum_hash_cbor() {
+        let datum = Datum::Hash(Hash32::from_hex(DATUM_HASH).unwrap());
+        let cbor = datum.to_cbor();
+
+        assert_eq!(hex::encode(&cbor), format!("82005820{DATUM_HASH}"));
+        assert_eq!(Datum::from_cbor(&cbor).unwrap(), datum);
+    }
+
+    #[test]
+    fn inline_datum_cbor() {
+        // plutus data: the integer 42
+        let datum = Datum::Inline(vec![0x18, 0x2a]);
+        let cbor = datum.to_cbor();
+
+        assert_eq!(hex::encode(&cbor), "8201d81842182a");
+        assert_eq!(Datum::from_cbor(&cbor).unwrap(), datum);
+        assert_eq!(datum.inline_data(), Some([0x18, 0x2a].as_slice()));
+    }
+
+    #[test]
+    fn invalid_datum() {
+        assert_eq!(
+            Datum::from_cbor(&[0x82, 0x02, 0x00]),
+            Err(OutputCborError::InvalidDatum)
+        );
+        assert_eq!(Datum::from_cbor(&[]), Err(OutputCborError::InvalidDatum));
+    }
+
+    #[test]
+    fn script_ref_cbor() {
+        // native script: `[0, [1, []]]` (all of no scripts)
+        let script_ref = ScriptRef::new(vec![0x82, 0x00, 0x82, 0x01, 0x80]);
+        let cbor = script_ref.to_cbor();
+
+        assert_eq!(hex::encode(&cbor), "d818458200820180");
+        assert_eq!(ScriptRef::from_cbor(&cbor).unwrap(), script_ref);
+        assert_eq!(script_ref.language(), Some(ScriptLanguage::Native));
+    }
+
+    #[test]
+    fn serde() {
+        let datum = Datum::Inline(vec![0x18, 0x2a]);
+        let json = serde_json::to_string(&datum).unwrap();
+
+        assert_eq!(json, r#"{"inline":"182a"}"#);
+        assert_eq!(serde_json::from_str::<Datum>(&json).unwrap(), datum);
+
+        let script_ref = ScriptRef::new(vec![0x82, 0x00]);
+        let json = serde_json::to_string(&script_ref).unwrap();
+
+        assert_eq!(json, r#""8200""#);
+        assert_eq!(
+            serde_json::from_str::<ScriptRef>(&json).unwrap(),
+            script_ref
+        );
+    }
+}
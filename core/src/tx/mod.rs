@@ -1,9 +1,15 @@
+mod datum;
+mod multi_asset_value;
 mod transaction;
 mod transaction_asset;
+mod transaction_draft;
 mod transaction_id;
 mod utxo;
 
+pub use datum::*;
+pub use multi_asset_value::*;
 pub use transaction::*;
 pub use transaction_asset::*;
+pub use transaction_draft::*;
 pub use transaction_id::*;
 pub use utxo::*;
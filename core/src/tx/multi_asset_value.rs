@@ -0,0 +1,375 @@
+use crate::tx::TransactionAsset;
+use crate::{Regulated, TokenId, Value};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{hash_map, HashMap};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// bundle of native assets, indexed by their [`TokenId`]
+///
+/// Assets with a zero quantity are never kept in the bundle, so two
+/// bundles holding the same quantities are always equal.
+///
+/// Like [`Value`], the arithmetic operators do not check for underflow:
+/// use [`MultiAssetValue::checked_sub`] when the result must not contain
+/// negative quantities.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct MultiAssetValue(HashMap<TokenId, TransactionAsset>);
+
+impl MultiAssetValue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// number of different assets in the bundle
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn get(&self, token: &TokenId) -> Option<&TransactionAsset> {
+        self.0.get(token)
+    }
+
+    pub fn contains(&self, token: &TokenId) -> bool {
+        self.0.contains_key(token)
+    }
+
+    /// quantity of the given asset held in the bundle, zero if the
+    /// asset is not present.
+    pub fn quantity_of(&self, token: &TokenId) -> Value<Regulated> {
+        self.0
+            .get(token)
+            .map(|asset| asset.quantity.clone())
+            .unwrap_or_else(Value::zero)
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &TokenId> {
+        self.0.keys()
+    }
+
+    pub fn assets(&self) -> impl Iterator<Item = &TransactionAsset> {
+        self.0.values()
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, TokenId, TransactionAsset> {
+        self.0.iter()
+    }
+
+    /// add the quantity of `asset` to the bundle
+    pub fn add_asset(&mut self, asset: &TransactionAsset) {
+        self.update(asset, |current| *current += &asset.quantity);
+    }
+
+    /// subtract the quantity of `asset` from the bundle. The resulting
+    /// quantity may be negative.
+    pub fn sub_asset(&mut self, asset: &TransactionAsset) {
+        self.update(asset, |current| *current -= &asset.quantity);
+    }
+
+    pub fn remove(&mut self, token: &TokenId) -> Option<TransactionAsset> {
+        self.0.remove(token)
+    }
+
+    /// subtract `other` from `self`, returns `None` if any of the
+    /// resulting quantities would be negative.
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if self.is_superset_of(other) {
+            Some(self - other)
+        } else {
+            None
+        }
+    }
+
+    /// check that `self` holds at least the quantity of every asset of `other`
+    pub fn is_superset_of(&self, other: &Self) -> bool {
+        other
+            .iter()
+            .all(|(token, asset)| self.quantity_of(token) >= asset.quantity)
+    }
+
+    pub fn into_inner(self) -> HashMap<TokenId, TransactionAsset> {
+        self.0
+    }
+
+    fn update<F>(&mut self, asset: &TransactionAsset, f: F)
+    where
+        F: FnOnce(&mut Value<Regulated>),
+    {
+        let current = self.0.entry(asset.fingerprint.clone()).or_insert_with(|| {
+            TransactionAsset::with_fingerprint(
+                asset.policy_id.clone(),
+                asset.asset_name.clone(),
+                asset.fingerprint.clone(),
+            )
+        });
+
+        f(&mut current.quantity);
+
+        if current.quantity == Value::zero() {
+            self.0.remove(&asset.fingerprint);
+        }
+    }
+}
+
+impl From<HashMap<TokenId, TransactionAsset>> for MultiAssetValue {
+    fn from(assets: HashMap<TokenId, TransactionAsset>) -> Self {
+        assets.into_values().collect()
+    }
+}
+
+/// deserialized through [`From<HashMap>`] so the assets with a zero
+/// quantity are dropped
+impl<'de> Deserialize<'de> for MultiAssetValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        HashMap::<TokenId, TransactionAsset>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl From<MultiAssetValue> for HashMap<TokenId, TransactionAsset> {
+    fn from(assets: MultiAssetValue) -> Self {
+        assets.0
+    }
+}
+
+impl FromIterator<TransactionAsset> for MultiAssetValue {
+    fn from_iter<I: IntoIterator<Item = TransactionAsset>>(iter: I) -> Self {
+        let mut assets = Self::new();
+        assets.extend(iter);
+        assets
+    }
+}
+
+impl<'a> FromIterator<&'a TransactionAsset> for MultiAssetValue {
+    fn from_iter<I: IntoIterator<Item = &'a TransactionAsset>>(iter: I) -> Self {
+        let mut assets = Self::new();
+        assets.extend(iter);
+        assets
+    }
+}
+
+impl Extend<TransactionAsset> for MultiAssetValue {
+    fn extend<I: IntoIterator<Item = TransactionAsset>>(&mut self, iter: I) {
+        for asset in iter {
+            self.add_asset(&asset);
+        }
+    }
+}
+
+impl<'a> Extend<&'a TransactionAsset> for MultiAssetValue {
+    fn extend<I: IntoIterator<Item = &'a TransactionAsset>>(&mut self, iter: I) {
+        for asset in iter {
+            self.add_asset(asset);
+        }
+    }
+}
+
+impl IntoIterator for MultiAssetValue {
+    type Item = (TokenId, TransactionAsset);
+    type IntoIter = hash_map::IntoIter<TokenId, TransactionAsset>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MultiAssetValue {
+    type Item = (&'a TokenId, &'a TransactionAsset);
+    type IntoIter = hash_map::Iter<'a, TokenId, TransactionAsset>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> AddAssign<&'a MultiAssetValue> for MultiAssetValue {
+    fn add_assign(&mut self, rhs: &'a MultiAssetValue) {
+        self.extend(rhs.assets());
+    }
+}
+
+impl AddAssign for MultiAssetValue {
+    fn add_assign(&mut self, rhs: Self) {
+        *self += &rhs;
+    }
+}
+
+impl<'a> SubAssign<&'a MultiAssetValue> for MultiAssetValue {
+    fn sub_assign(&mut self, rhs: &'a MultiAssetValue) {
+        for asset in rhs.assets() {
+            self.sub_asset(asset);
+        }
+    }
+}
+
+impl SubAssign for MultiAssetValue {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self -= &rhs;
+    }
+}
+
+impl Add for MultiAssetValue {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += &rhs;
+        self
+    }
+}
+
+impl<'a> Add<&'a MultiAssetValue> for MultiAssetValue {
+    type Output = Self;
+
+    fn add(mut self, rhs: &'a MultiAssetValue) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl Add for &MultiAssetValue {
+    type Output = MultiAssetValue;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.clone() + rhs
+    }
+}
+
+impl Sub for MultiAssetValue {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        self -= &rhs;
+        self
+    }
+}
+
+impl<'a> Sub<&'a MultiAssetValue> for MultiAssetValue {
+    type Output = Self;
+
+    fn sub(mut self, rhs: &'a MultiAssetValue) -> Self::Output {
+        self -= rhs;
+        self
+    }
+}
+
+impl Sub for &MultiAssetValue {
+    type Output = MultiAssetValue;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.clone() - rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssetName, PolicyId};
+
+    fn asset(name: &'static str, quantity: u64) -> TransactionAsset {
+        let mut asset = TransactionAsset::with_fingerprint(
+            PolicyId::new_static(name),
+            AssetName::new_static(name),
+            TokenId::new_static(name),
+        );
+        asset.quantity = Value::from(quantity);
+        asset
+    }
+
+    #[test]
+    fn add_merges_quantities() {
+        let a: MultiAssetValue = [asset("a", 1), asset("b", 2)].into_iter().collect();
+        let b: MultiAssetValue = [asset("b", 3), asset("c", 4)].into_iter().collect();
+
+        let sum = &a + &b;
+
+        assert_eq!(sum.len(), 3);
+        assert_eq!(
+            sum.quantity_of(&TokenId::new_static("a")),
+            Value::from(1u64)
+        );
+        assert_eq!(
+            sum.quantity_of(&TokenId::new_static("b")),
+            Value::from(5u64)
+        );
+        assert_eq!(
+            sum.quantity_of(&TokenId::new_static("c")),
+            Value::from(4u64)
+        );
+    }
+
+    #[test]
+    fn sub_removes_empty_assets() {
+        let a: MultiAssetValue = [asset("a", 1), asset("b", 2)].into_iter().collect();
+        let b: MultiAssetValue = [asset("a", 1)].into_iter().collect();
+
+        let diff = a - b;
+
+        assert_eq!(diff, [asset("b", 2)].into_iter().collect());
+        assert!(!diff.contains(&TokenId::new_static("a")));
+    }
+
+    #[test]
+    fn checked_sub() {
+        let a: MultiAssetValue = [asset("a", 1), asset("b", 2)].into_iter().collect();
+        let b: MultiAssetValue = [asset("b", 3)].into_iter().collect();
+        let c: MultiAssetValue = [asset("c", 1)].into_iter().collect();
+
+        assert!(a.checked_sub(&b).is_none());
+        assert!(a.checked_sub(&c).is_none());
+        assert_eq!(
+            b.checked_sub(&[asset("b", 1)].into_iter().collect()),
+            Some([asset("b", 2)].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn is_superset_of() {
+        let a: MultiAssetValue = [asset("a", 1), asset("b", 2)].into_iter().collect();
+        let b: MultiAssetValue = [asset("b", 2)].into_iter().collect();
+
+        assert!(a.is_superset_of(&b));
+        assert!(!b.is_superset_of(&a));
+        assert!(a.is_superset_of(&MultiAssetValue::new()));
+    }
+
+    #[test]
+    fn hashmap_roundtrip() {
+        let map = HashMap::from([
+            (TokenId::new_static("a"), asset("a", 1)),
+            (TokenId::new_static("b"), asset("b", 0)),
+        ]);
+
+        let assets = MultiAssetValue::from(map);
+        assert_eq!(assets.len(), 1);
+
+        let map: HashMap<_, _> = assets.into();
+        assert_eq!(map.get(&TokenId::new_static("a")), Some(&asset("a", 1)));
+    }
+
+    #[test]
+    fn deserialize_drops_empty_assets() {
+        let map = HashMap::from([
+            (TokenId::new_static("a"), asset("a", 1)),
+            (TokenId::new_static("b"), asset("b", 0)),
+        ]);
+        let json = deps::serde_json::to_string(&map).unwrap();
+
+        let assets: MultiAssetValue = deps::serde_json::from_str(&json).unwrap();
+
+        assert_eq!(assets, [asset("a", 1)].into_iter().collect());
+        assert_eq!(
+            deps::serde_json::to_string(&assets).unwrap(),
+            deps::serde_json::to_string(&HashMap::from([(
+                TokenId::new_static("a"),
+                asset("a", 1)
+            )]))
+            .unwrap()
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::policy_id::PolicyId;
-use crate::{AssetName, Regulated, TokenId, Value};
+use crate::{AssetId, AssetName, Cip14Error, Regulated, TokenId, Value};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Ord, PartialOrd)]
 #[serde(deny_unknown_fields)]
@@ -12,8 +13,42 @@ pub struct TransactionAsset {
     pub quantity: Value<Regulated>,
 }
 
+#[derive(Debug, Error)]
+pub enum MergeAssetError {
+    #[error(
+        "Asset {fingerprint} is found with different identifiers: {policy_id}.{asset_name} and {other_policy_id}.{other_asset_name}"
+    )]
+    MismatchedIdentifiers {
+        fingerprint: TokenId,
+        policy_id: PolicyId,
+        asset_name: AssetName,
+        other_policy_id: PolicyId,
+        other_asset_name: AssetName,
+    },
+}
+
 impl TransactionAsset {
+    /// create a new [`TransactionAsset`] with the given `quantity`, the
+    /// `fingerprint` is derived from the `policy_id` and the `asset_name`
+    /// (see [`TokenId::cip14`]).
     pub fn new(
+        policy_id: PolicyId,
+        asset_name: AssetName,
+        quantity: Value<Regulated>,
+    ) -> Result<TransactionAsset, Cip14Error> {
+        let fingerprint = TokenId::cip14(&policy_id, &asset_name)?;
+
+        Ok(Self {
+            policy_id,
+            asset_name,
+            fingerprint,
+            quantity,
+        })
+    }
+
+    /// create an empty [`TransactionAsset`] (zero quantity) reusing an
+    /// already known `fingerprint`.
+    pub fn with_fingerprint(
         policy_id: PolicyId,
         asset_name: AssetName,
         fingerprint: TokenId,
@@ -25,4 +60,178 @@ impl TransactionAsset {
             quantity: Default::default(),
         }
     }
+
+    /// the [`AssetId`] of the token, [`AssetId::Native`] for the
+    /// [`TokenId::MAIN`] fingerprint
+    pub fn asset_id(&self) -> AssetId {
+        AssetId::from(&self.fingerprint)
+    }
+
+    /// check both assets have the same `fingerprint`, `policy_id` and
+    /// `asset_name`, regardless of their quantity
+    pub fn is_same_asset(&self, other: &Self) -> bool {
+        self.fingerprint == other.fingerprint
+            && self.policy_id == other.policy_id
+            && self.asset_name == other.asset_name
+    }
+
+    /// Try to add the quantity of `rhs` to this asset
+    ///
+    /// returns `None` if `rhs` is not the same asset (see
+    /// [`Self::is_same_asset`]) or if the resulting quantity would be negative.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        self.checked_op(rhs, Value::checked_add)
+    }
+
+    /// Try to subtract the quantity of `rhs` from this asset
+    ///
+    /// returns `None` if `rhs` is not the same asset (see
+    /// [`Self::is_same_asset`]) or if the resulting quantity would be negative.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        self.checked_op(rhs, Value::checked_sub)
+    }
+
+    fn checked_op<F>(&self, rhs: &Self, op: F) -> Option<Self>
+    where
+        F: FnOnce(&Value<Regulated>, &Value<Regulated>) -> Option<Value<Regulated>>,
+    {
+        if !self.is_same_asset(rhs) {
+            return None;
+        }
+
+        Some(Self {
+            quantity: op(&self.quantity, &rhs.quantity)?,
+            ..self.clone()
+        })
+    }
+
+    /// merge the assets with the same `fingerprint` together, summing
+    /// their quantities
+    ///
+    /// The assets are returned in the order their `fingerprint` first
+    /// appears. Fails if two assets share a `fingerprint` but not the
+    /// `policy_id` or the `asset_name`.
+    ///
+    /// ```
+    /// # use dcspark_core::{AssetName, PolicyId, TokenId, Value};
+    /// # use dcspark_core::tx::TransactionAsset;
+    /// let mut asset = TransactionAsset::with_fingerprint(
+    ///     PolicyId::new_static("policy"),
+    ///     AssetName::new_static("name"),
+    ///     TokenId::new_static("token"),
+    /// );
+    /// asset.quantity = Value::from(2u64);
+    ///
+    /// let merged = TransactionAsset::merge([asset.clone(), asset]).unwrap();
+    /// assert_eq!(merged.len(), 1);
+    /// assert_eq!(merged[0].quantity, Value::from(4u64));
+    /// ```
+    pub fn merge<I>(assets: I) -> Result<Vec<Self>, MergeAssetError>
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        let mut merged: Vec<Self> = Vec::new();
+
+        for asset in assets {
+            match merged
+                .iter_mut()
+                .find(|current| current.fingerprint == asset.fingerprint)
+            {
+                Some(current) if current.is_same_asset(&asset) => {
+                    current.quantity += asset.quantity;
+                }
+                Some(current) => {
+                    return Err(MergeAssetError::MismatchedIdentifiers {
+                        fingerprint: asset.fingerprint,
+                        policy_id: current.policy_id.clone(),
+                        asset_name: current.asset_name.clone(),
+                        other_policy_id: asset.policy_id,
+                        other_asset_name: asset.asset_name,
+                    });
+                }
+                None => merged.push(asset),
+            }
+        }
+
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_derives_the_fingerprint() {
+        let asset = TransactionAsset::new(
+            PolicyId::new_static("7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373"),
+            AssetName::new_static("504154415445"),
+            Value::new(10u64.into()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            asset.fingerprint,
+            TokenId::new_static("asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92")
+        );
+        assert_eq!(asset.quantity, Value::new(10u64.into()));
+    }
+
+    fn asset(fingerprint: &'static str, name: &'static str, quantity: u64) -> TransactionAsset {
+        let mut asset = TransactionAsset::with_fingerprint(
+            PolicyId::new_static("policy"),
+            AssetName::new_static(name),
+            TokenId::new_static(fingerprint),
+        );
+        asset.quantity = Value::from(quantity);
+        asset
+    }
+
+    #[test]
+    fn checked_operations() {
+        let a = asset("a", "a", 3);
+
+        assert_eq!(a.checked_add(&asset("a", "a", 2)), Some(asset("a", "a", 5)));
+        assert_eq!(a.checked_sub(&asset("a", "a", 2)), Some(asset("a", "a", 1)));
+        assert_eq!(a.checked_sub(&asset("a", "a", 4)), None);
+        assert_eq!(a.checked_add(&asset("b", "b", 1)), None);
+        assert_eq!(a.checked_add(&asset("a", "b", 1)), None);
+    }
+
+    #[test]
+    fn merge_by_fingerprint() {
+        let merged =
+            TransactionAsset::merge([asset("a", "a", 1), asset("b", "b", 2), asset("a", "a", 3)])
+                .unwrap();
+
+        assert_eq!(merged, [asset("a", "a", 4), asset("b", "b", 2)]);
+        assert!(TransactionAsset::merge([]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_mismatched_identifiers() {
+        let error = TransactionAsset::merge([asset("a", "a", 1), asset("a", "b", 1)]).unwrap_err();
+
+        assert!(matches!(
+            error,
+            MergeAssetError::MismatchedIdentifiers { fingerprint, .. } if fingerprint == TokenId::new_static("a")
+        ));
+    }
+
+    #[test]
+    fn asset_id() {
+        assert_eq!(
+            asset("asset1", "name", 1).asset_id(),
+            AssetId::Token(TokenId::new_static("asset1"))
+        );
+
+        let main = TransactionAsset::with_fingerprint(
+            PolicyId::new_static(""),
+            AssetName::new_static(""),
+            TokenId::MAIN,
+        );
+        assert_eq!(main.asset_id(), AssetId::Native);
+    }
 }
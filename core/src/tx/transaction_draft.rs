@@ -0,0 +1,185 @@
+use crate::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use crate::{Regulated, SlotNumber, Value};
+use deps::serde_json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// a transaction that is balanced but not yet serialized nor signed
+///
+/// This is the output of the input selection and it is chain agnostic:
+/// the chain specific crates are responsible to serialize it (see
+/// `cardano_utils::transaction` for cardano).
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDraft {
+    /// the inputs the transaction was requested to spend
+    pub fixed_inputs: Vec<UTxODetails>,
+    /// the outputs the transaction was requested to create
+    pub fixed_outputs: Vec<UTxOBuilder>,
+
+    /// the inputs added by the input selection to fund the transaction
+    #[serde(default)]
+    pub chosen_inputs: Vec<UTxODetails>,
+    /// the outputs returning the excess of the inputs
+    #[serde(default)]
+    pub changes: Vec<UTxOBuilder>,
+
+    pub fee: Value<Regulated>,
+
+    #[serde(default)]
+    pub metadata: Arc<serde_json::Value>,
+
+    /// the transaction is not valid before this slot
+    #[serde(default)]
+    pub valid_from: Option<SlotNumber>,
+    /// the transaction is not valid from this slot onward
+    #[serde(default)]
+    pub valid_until: Option<SlotNumber>,
+}
+
+impl TransactionDraft {
+    pub fn new(
+        fixed_inputs: Vec<UTxODetails>,
+        fixed_outputs: Vec<UTxOBuilder>,
+        fee: Value<Regulated>,
+    ) -> Self {
+        Self {
+            fixed_inputs,
+            fixed_outputs,
+            fee,
+            ..Self::default()
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_metadata(mut self, metadata: Arc<serde_json::Value>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// set the validity window of the transaction, `None` leaves the
+    /// bound open
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_validity(
+        mut self,
+        valid_from: Option<SlotNumber>,
+        valid_until: Option<SlotNumber>,
+    ) -> Self {
+        self.valid_from = valid_from;
+        self.valid_until = valid_until;
+        self
+    }
+
+    /// all the inputs of the transaction, the fixed ones first
+    pub fn inputs(&self) -> impl Iterator<Item = &UTxODetails> {
+        self.fixed_inputs.iter().chain(self.chosen_inputs.iter())
+    }
+
+    /// all the outputs of the transaction, the changes last
+    pub fn outputs(&self) -> impl Iterator<Item = &UTxOBuilder> {
+        self.fixed_outputs.iter().chain(self.changes.iter())
+    }
+
+    pub fn input_balance(&self) -> Value<Regulated> {
+        self.inputs().map(|input| &input.value).sum()
+    }
+
+    pub fn output_balance(&self) -> Value<Regulated> {
+        self.outputs().map(|output| &output.value).sum()
+    }
+
+    pub fn input_asset_balance(&self) -> MultiAssetValue {
+        self.inputs()
+            .flat_map(|input| input.assets.iter())
+            .collect()
+    }
+
+    pub fn output_asset_balance(&self) -> MultiAssetValue {
+        self.outputs()
+            .flat_map(|output| output.assets.iter())
+            .collect()
+    }
+
+    /// check the inputs pay exactly for the outputs and the fee, for the
+    /// main asset and every token
+    pub fn is_balanced(&self) -> bool {
+        self.input_balance() == &self.output_balance() + &self.fee
+            && self.input_asset_balance() == self.output_asset_balance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::{TransactionAsset, TransactionId, UtxoPointer};
+    use crate::{Address, AssetName, OutputIndex, PolicyId, TokenId};
+
+    fn token(quantity: u64) -> TransactionAsset {
+        let mut asset = TransactionAsset::with_fingerprint(
+            PolicyId::new_static("policy"),
+            AssetName::new_static("name"),
+            TokenId::new_static("token"),
+        );
+        asset.quantity = Value::from(quantity);
+        asset
+    }
+
+    fn input(index: u64, value: u64, assets: Vec<TransactionAsset>) -> UTxODetails {
+        UTxODetails {
+            pointer: UtxoPointer {
+                transaction_id: TransactionId::new_static("tx"),
+                output_index: OutputIndex::new(index),
+            },
+            address: Address::new_static("address"),
+            value: Value::from(value),
+            assets,
+            metadata: Default::default(),
+            datum: None,
+            script_ref: None,
+            extra: None,
+            creation_height: None,
+            script_spend: None,
+        }
+    }
+
+    fn output(value: u64, assets: Vec<TransactionAsset>) -> UTxOBuilder {
+        UTxOBuilder::new(Address::new_static("address"), Value::from(value), assets)
+    }
+
+    #[test]
+    fn balanced() {
+        let mut draft = TransactionDraft::new(
+            vec![input(0, 10, vec![token(5)])],
+            vec![output(5, vec![token(3)])],
+            Value::from(1u64),
+        );
+        assert!(!draft.is_balanced());
+
+        draft.chosen_inputs.push(input(1, 2, vec![]));
+        draft.changes.push(output(6, vec![token(2)]));
+
+        assert_eq!(draft.inputs().count(), 2);
+        assert_eq!(draft.outputs().count(), 2);
+        assert_eq!(draft.input_balance(), Value::from(12u64));
+        assert!(draft.is_balanced());
+    }
+
+    #[test]
+    fn serde_defaults() {
+        let draft = TransactionDraft::new(vec![], vec![], Value::from(1u64))
+            .with_validity(None, Some(SlotNumber::new(42)));
+
+        let json = serde_json::to_value(&draft).unwrap();
+        assert_eq!(json["validUntil"], 42);
+        assert_eq!(
+            serde_json::from_value::<TransactionDraft>(json).unwrap(),
+            draft
+        );
+
+        let draft: TransactionDraft =
+            serde_json::from_str(r#"{"fixedInputs": [], "fixedOutputs": [], "fee": "1"}"#).unwrap();
+        assert_eq!(draft.valid_from, None);
+        assert!(draft.changes.is_empty());
+    }
+}
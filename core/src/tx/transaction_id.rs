@@ -1,3 +1,4 @@
+use crate::{Hash32, Hash32Error};
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, fmt, str};
 
@@ -23,11 +24,21 @@ impl TransactionId {
     /// ```
     pub const ZERO: Self = Self::new_static("N/A");
 
-    pub fn new<B>(block_id: B) -> Self
+    /// create a [`TransactionId`] from the hexadecimal encoding of a 32 bytes
+    /// hash. The identifier is normalized to lower case hexadecimal.
+    pub fn from_hex(id: &str) -> Result<Self, Hash32Error> {
+        Hash32::from_hex(id).map(Self::from)
+    }
+
+    /// create a [`TransactionId`] without checking it is a valid hash.
+    ///
+    /// Prefer [`TransactionId::from_hex`] or [`TransactionId::from`] a [`Hash32`], this
+    /// is meant for tests and for identifiers that are not 32 bytes hashes.
+    pub fn new_unchecked<B>(id: B) -> Self
     where
         B: Into<Cow<'static, str>>,
     {
-        Self(block_id.into())
+        Self(id.into())
     }
 
     /// create a static [`TransactionId`]. Because we use a [`Cow`]
@@ -37,6 +48,11 @@ impl TransactionId {
         Self(Cow::Borrowed(block_id))
     }
 
+    /// decode the compact 32 bytes representation of the identifier
+    pub fn to_hash32(&self) -> Result<Hash32, Hash32Error> {
+        Hash32::from_hex(&self.0)
+    }
+
     /// check the [`TransactionId`] starts with the given `prefix`.
     ///
     /// This can be useful to check for partial [`TransactionId`]
@@ -60,6 +76,12 @@ impl AsRef<[u8]> for TransactionId {
     }
 }
 
+impl From<Hash32> for TransactionId {
+    fn from(hash: Hash32) -> Self {
+        Self(Cow::Owned(hash.to_hex()))
+    }
+}
+
 impl fmt::Display for TransactionId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -70,6 +92,20 @@ impl fmt::Display for TransactionId {
 mod tests {
     use super::*;
 
+    const HASH: &str = "AA83ACBF5904C0EDFE4D79B3689D3D00FCFC553CF360FD2229B98D464C28E9DE";
+
+    #[test]
+    fn hash32_roundtrip() {
+        let id = TransactionId::from_hex(HASH).unwrap();
+
+        assert_eq!(id.to_string(), HASH.to_lowercase());
+        assert_eq!(TransactionId::from(id.to_hash32().unwrap()), id);
+        assert!(TransactionId::from_hex("hello world").is_err());
+        assert!(TransactionId::new_static("hello world")
+            .to_hash32()
+            .is_err());
+    }
+
     #[test]
     fn starts_with() {
         assert!(TransactionId::new_static("hello world").starts_with("hello"));
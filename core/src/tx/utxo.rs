@@ -1,33 +1,144 @@
-use crate::tx::{TransactionAsset, TransactionId};
-use crate::{Address, OutputIndex, Regulated, Value};
+use crate::tx::{Datum, ScriptRef, TransactionAsset, TransactionId};
+use crate::{
+    Address, BlockNumber, OutputIndex, PaymentCredential, Regulated, StakeCredential, Value,
+};
 
 use deps::serde_json;
 use serde::{Deserialize, Serialize};
 
-use std::fmt;
 use std::sync::Arc;
+use std::{fmt, str};
 
 /// Points to particular UTxO for some ['TransactionId'].
 /// We can have multiple pointers with different indexes for the same transaction.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
-#[serde(rename_all = "camelCase")]
+///
+/// The canonical string representation follows the `cardano-cli` convention:
+/// `<transaction_id>#<output_index>`. This is the format used by [`fmt::Display`],
+/// [`str::FromStr`] and by the serde implementation.
+///
+/// ```
+/// use dcspark_core::{tx::{TransactionId, UtxoPointer}, OutputIndex};
+///
+/// let pointer: UtxoPointer = "0d47cbae2bbd3b3f52fc4c1da4d3d3c1e7a1a4d4f2a0e2c7c4c1b0f6d7c8a9b0#1"
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(pointer.output_index, OutputIndex::new(1));
+/// assert_eq!(
+///     pointer.to_string(),
+///     "0d47cbae2bbd3b3f52fc4c1da4d3d3c1e7a1a4d4f2a0e2c7c4c1b0f6d7c8a9b0#1",
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct UtxoPointer {
     pub transaction_id: TransactionId,
     pub output_index: OutputIndex,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum UtxoPointerFromStrError {
+    #[error("Missing '#' separator between the transaction id and the output index")]
+    MissingSeparator,
+
+    #[error("Empty transaction id")]
+    EmptyTransactionId,
+
+    #[error("Invalid output index: {0}")]
+    InvalidOutputIndex(#[from] std::num::ParseIntError),
+}
+
 impl fmt::Display for UtxoPointer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{hash}@{index}",
+            "{hash}#{index}",
             hash = self.transaction_id,
             index = self.output_index,
         )
     }
 }
 
+impl str::FromStr for UtxoPointer {
+    type Err = UtxoPointerFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (transaction_id, output_index) = s
+            .rsplit_once('#')
+            .ok_or(UtxoPointerFromStrError::MissingSeparator)?;
+
+        if transaction_id.is_empty() {
+            return Err(UtxoPointerFromStrError::EmptyTransactionId);
+        }
+
+        Ok(Self {
+            transaction_id: TransactionId::new_unchecked(transaction_id.to_owned()),
+            output_index: output_index.parse()?,
+        })
+    }
+}
+
+impl Serialize for UtxoPointer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// legacy representation of the [`UtxoPointer`], still accepted when
+/// deserializing so previously stored data remains readable.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+struct UtxoPointerFields {
+    transaction_id: TransactionId,
+    output_index: OutputIndex,
+}
+
+impl<'de> Deserialize<'de> for UtxoPointer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct UtxoPointerVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for UtxoPointerVisitor {
+            type Value = UtxoPointer;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a utxo pointer (`<transaction_id>#<output_index>`)")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let UtxoPointerFields {
+                    transaction_id,
+                    output_index,
+                } = UtxoPointerFields::deserialize(serde::de::value::MapAccessDeserializer::new(
+                    map,
+                ))?;
+
+                Ok(UtxoPointer {
+                    transaction_id,
+                    output_index,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(UtxoPointerVisitor)
+    }
+}
+
 /// list the details of the UTxO
 ///
 /// this is the information that we will collect int he UTxO store
@@ -39,18 +150,73 @@ pub struct UTxODetails {
 
     #[serde(default)]
     pub assets: Vec<TransactionAsset>,
+    /// `null` if left out
+    #[serde(default)]
     pub metadata: Arc<serde_json::Value>,
 
+    #[serde(default)]
+    pub datum: Option<Datum>,
+    #[serde(default)]
+    pub script_ref: Option<ScriptRef>,
+
     #[serde(default)]
     pub extra: Option<String>,
+
+    /// the block the UTxO was created in, if known
+    ///
+    /// Used to prefer the older UTxOs (coin-age) during the selection.
+    #[serde(default)]
+    pub creation_height: Option<BlockNumber>,
+
+    /// the cost of spending the UTxO if it is locked by a Plutus script,
+    /// `None` for the UTxOs spent with a key or a native script
+    #[serde(default)]
+    pub script_spend: Option<ScriptSpend>,
+}
+
+/// the execution units and the redeemer spending a script-locked UTxO
+///
+/// The fee estimators add the price of the execution units and the size of
+/// the redeemer to the fee of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScriptSpend {
+    /// the memory units of the script execution
+    pub mem: u64,
+    /// the CPU steps of the script execution
+    pub steps: u64,
+    /// the size in bytes of the redeemer in the witness set
+    #[serde(default)]
+    pub redeemer_size: usize,
+}
+
+impl UTxODetails {
+    /// the [`PaymentCredential`] of the [`UTxODetails::address`]
+    ///
+    /// `None` if the address cannot be parsed or has no payment credential.
+    pub fn payment_credential(&self) -> Option<PaymentCredential> {
+        payment_credential(&self.address)
+    }
+
+    /// the [`StakeCredential`] of the [`UTxODetails::address`]
+    ///
+    /// `None` if the address cannot be parsed or has no stake credential.
+    pub fn stake_credential(&self) -> Option<StakeCredential> {
+        stake_credential(&self.address)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct UTxOBuilder {
     pub address: Address,
     pub value: Value<Regulated>,
+    #[serde(default)]
     pub assets: Vec<TransactionAsset>,
 
+    #[serde(default)]
+    pub datum: Option<Datum>,
+    #[serde(default)]
+    pub script_ref: Option<ScriptRef>,
+
     #[serde(default)]
     pub extra: Option<String>,
 }
@@ -61,7 +227,117 @@ impl UTxOBuilder {
             address,
             value,
             assets,
+            datum: None,
+            script_ref: None,
             extra: None,
         }
     }
+
+    /// see [`UTxODetails::payment_credential`]
+    pub fn payment_credential(&self) -> Option<PaymentCredential> {
+        payment_credential(&self.address)
+    }
+
+    /// see [`UTxODetails::stake_credential`]
+    pub fn stake_credential(&self) -> Option<StakeCredential> {
+        stake_credential(&self.address)
+    }
+}
+
+fn payment_credential(address: &Address) -> Option<PaymentCredential> {
+    address.parse().ok()?.payment_credential().copied()
+}
+
+fn stake_credential(address: &Address) -> Option<StakeCredential> {
+    address.parse().ok()?.stake_credential().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TX_ID: &str = "0d47cbae2bbd3b3f52fc4c1da4d3d3c1e7a1a4d4f2a0e2c7c4c1b0f6d7c8a9b0";
+
+    fn pointer(index: u64) -> UtxoPointer {
+        UtxoPointer {
+            transaction_id: TransactionId::new_static(TX_ID),
+            output_index: OutputIndex::new(index),
+        }
+    }
+
+    #[test]
+    fn display_from_str_roundtrip() {
+        let p = pointer(42);
+        let s = p.to_string();
+
+        assert_eq!(s, format!("{TX_ID}#42"));
+        assert_eq!(s.parse::<UtxoPointer>().unwrap(), p);
+    }
+
+    #[test]
+    fn from_str_errors() {
+        assert!(matches!(
+            TX_ID.parse::<UtxoPointer>(),
+            Err(UtxoPointerFromStrError::MissingSeparator)
+        ));
+        assert!(matches!(
+            "#1".parse::<UtxoPointer>(),
+            Err(UtxoPointerFromStrError::EmptyTransactionId)
+        ));
+        assert!(matches!(
+            format!("{TX_ID}#-1").parse::<UtxoPointer>(),
+            Err(UtxoPointerFromStrError::InvalidOutputIndex(_))
+        ));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let p = pointer(3);
+        let json = serde_json::to_string(&p).unwrap();
+
+        assert_eq!(json, format!("\"{TX_ID}#3\""));
+        assert_eq!(serde_json::from_str::<UtxoPointer>(&json).unwrap(), p);
+    }
+
+    #[test]
+    fn deserialize_legacy_struct() {
+        let json = format!(r#"{{"transactionId":"{TX_ID}","outputIndex":3}}"#);
+
+        assert_eq!(
+            serde_json::from_str::<UtxoPointer>(&json).unwrap(),
+            pointer(3)
+        );
+    }
+
+    #[test]
+    fn credentials() {
+        let builder = UTxOBuilder::new(
+            Address::new_static("addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x"),
+            Value::zero(),
+            Vec::new(),
+        );
+
+        assert_eq!(
+            builder.payment_credential().unwrap().to_string(),
+            "addr_vkh1jjfnzhxe966a33psfenm0ct2udkkr569qf55v4uprgkgu8zsvmg"
+        );
+        assert_eq!(
+            builder.stake_credential().unwrap().to_string(),
+            "stake_vkh1xdak9nllvsp6q636e0p5lrzxqq7xnlne5d3gemafc3e9z3v4vud"
+        );
+
+        let builder = UTxOBuilder::new(Address::new_static("invalid"), Value::zero(), Vec::new());
+        assert!(builder.payment_credential().is_none());
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(pointer(1) < pointer(2));
+
+        let other = UtxoPointer {
+            transaction_id: TransactionId::new_static("ff"),
+            output_index: OutputIndex::new(0),
+        };
+        assert!(pointer(10) < other);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::tx::{UTxODetails, UtxoPointer};
-use crate::{AssetName, PolicyId, Regulated, TokenId, Value};
+use crate::{AssetId, AssetName, PolicyId, Regulated, TokenId, Value};
 use anyhow::anyhow;
 use imbl::{hashmap::Entry, HashMap};
 use std::collections::BTreeMap;
@@ -14,7 +14,7 @@ use std::sync::Arc;
 #[derive(Default, Clone)]
 pub struct UTxOStore {
     utxos: UTxOSet,
-    by_policy_id: HashMap<TokenId, UTxOSet>,
+    by_policy_id: HashMap<AssetId, UTxOSet>,
 
     /// keep the hashmap of the known TokenId/AssetName
     ///
@@ -23,7 +23,7 @@ pub struct UTxOStore {
 
 #[derive(Default, Clone)]
 struct UTxOSet {
-    token_id: TokenId,
+    asset_id: AssetId,
     balance: Value<Regulated>,
     set: HashMap<UtxoPointer, Arc<UTxODetails>>,
     ordered_by_value: BTreeMap<Value<Regulated>, HashMap<UtxoPointer, Arc<UTxODetails>>>,
@@ -31,7 +31,7 @@ struct UTxOSet {
 
 pub struct UTxOStoreMut {
     utxos: UTxOSet,
-    by_policy_id: HashMap<TokenId, UTxOSet>,
+    by_policy_id: HashMap<AssetId, UTxOSet>,
     dictionary: HashMap<TokenId, (PolicyId, AssetName)>,
 }
 
@@ -39,15 +39,15 @@ impl UTxOSet {
     pub fn remove_from_asset(&mut self, pointer: &UtxoPointer) -> Option<Arc<UTxODetails>> {
         let utxo: Arc<UTxODetails> = self.set.remove(pointer)?;
 
-        let value = if self.token_id == TokenId::MAIN {
-            utxo.value.clone()
-        } else {
-            utxo.assets
+        let value = match &self.asset_id {
+            AssetId::Native => utxo.value.clone(),
+            AssetId::Token(token_id) => utxo
+                .assets
                 .iter()
-                .find(|ta| ta.fingerprint == self.token_id)
+                .find(|ta| &ta.fingerprint == token_id)
                 .map(|ta| &ta.quantity)
                 .cloned()
-                .expect("We know we already have the value")
+                .expect("We know we already have the value"),
         };
 
         self.finish_remove(value, pointer);
@@ -68,8 +68,8 @@ impl UTxOSet {
 }
 
 impl UTxOSet {
-    pub fn add_value(&mut self, token: &TokenId, value: Value<Regulated>, utxo: Arc<UTxODetails>) {
-        self.token_id = token.clone();
+    pub fn add_value(&mut self, asset: &AssetId, value: Value<Regulated>, utxo: Arc<UTxODetails>) {
+        self.asset_id = asset.clone();
         self.balance.add_assign(value.clone());
         self.set.insert(utxo.pointer.clone(), utxo.clone());
         match self.ordered_by_value.entry(value) {
@@ -154,10 +154,10 @@ impl UTxOStore {
         self.utxos.len()
     }
 
-    /// Get the number of UTxOs for the given token.
+    /// Get the number of UTxOs for the given asset.
     ///
     #[inline]
-    pub fn number_utxos_for_token(&self, token: &TokenId) -> usize {
+    pub fn number_utxos_for_token(&self, token: &AssetId) -> usize {
         self.by_token_id(token)
             .map(|set| set.len())
             .unwrap_or_default()
@@ -169,10 +169,10 @@ impl UTxOStore {
         self.utxos.contains_key(utxo)
     }
 
-    /// check if the State contains a given [`TokenId`]
+    /// check if the State contains a given [`AssetId`]
     #[inline]
-    pub fn contains_token(&self, token_id: &TokenId) -> bool {
-        token_id == &self.utxos.token_id || self.by_policy_id.contains_key(token_id)
+    pub fn contains_token(&self, asset_id: &AssetId) -> bool {
+        asset_id == &self.utxos.asset_id || self.by_policy_id.contains_key(asset_id)
     }
 
     /// retrieve the asset identifier from the TokenId
@@ -208,58 +208,58 @@ impl UTxOStore {
         self.utxos.ordered_utxo_iterator()
     }
 
-    /// list all UTxO that are associated to the given [`TokenId`]
+    /// list all UTxO that are associated to the given [`AssetId`]
     ///
-    /// The iterator may be empty if there is no [`TokenId`] present
+    /// The iterator may be empty if there is no [`AssetId`] present
     /// in the store
     #[inline]
     pub fn iter_token(
         &self,
-        token_id: &TokenId,
+        asset_id: &AssetId,
     ) -> impl Iterator<Item = (&UtxoPointer, &Arc<UTxODetails>)> {
-        self.by_token_id(token_id)
+        self.by_token_id(asset_id)
             .into_iter()
             .flat_map(|c| c.iter())
     }
 
-    /// list all UTxO that are associated to the given [`TokenId`] ordered by total balance, in
+    /// list all UTxO that are associated to the given [`AssetId`] ordered by total balance, in
     /// *ascending* way.
     ///
-    /// The iterator may be empty if there is no [`TokenId`] present in the store
+    /// The iterator may be empty if there is no [`AssetId`] present in the store
     #[inline]
     pub fn iter_token_ordered_by_value(
         &self,
-        token_id: &TokenId,
+        asset_id: &AssetId,
     ) -> impl Iterator<Item = &UTxODetails> {
-        self.by_token_id(token_id)
+        self.by_token_id(asset_id)
             .into_iter()
             .flat_map(|set| set.ordered_utxo_iterator())
     }
 
-    /// list all UTxO that are associated to the given [`TokenId`] ordered by total balance, in
+    /// list all UTxO that are associated to the given [`AssetId`] ordered by total balance, in
     /// *descending* way.
     ///
-    /// The iterator may be empty if there is no [`TokenId`] present in the store
+    /// The iterator may be empty if there is no [`AssetId`] present in the store
     #[inline]
     pub fn iter_token_ordered_by_value_rev(
         &self,
-        token_id: &TokenId,
+        asset_id: &AssetId,
     ) -> impl Iterator<Item = &UTxODetails> {
-        self.by_token_id(token_id)
+        self.by_token_id(asset_id)
             .into_iter()
             .flat_map(|set| set.ordered_utxo_iterator_rev())
     }
 
     /// get the balance of a given asset
     #[inline]
-    pub fn get_balance_of(&self, token: &TokenId) -> Option<Value<Regulated>> {
+    pub fn get_balance_of(&self, token: &AssetId) -> Option<Value<Regulated>> {
         self.by_token_id(token).map(|set| set.balance.clone())
     }
 
-    /// get the utxo set for the given token, considering both the primary/main token and the
-    /// assets
+    /// get the utxo set for the given asset, considering both the native asset and the
+    /// tokens
     #[inline]
-    fn by_token_id(&self, token: &TokenId) -> Option<&UTxOSet> {
+    fn by_token_id(&self, token: &AssetId) -> Option<&UTxOSet> {
         self.by_policy_id.get(token)
     }
 }
@@ -271,10 +271,10 @@ impl UTxOStoreMut {
             for policy_id in value
                 .assets
                 .iter()
-                .map(|a| &a.fingerprint)
-                .chain(&[TokenId::MAIN])
+                .map(|a| a.asset_id())
+                .chain([AssetId::Native])
             {
-                let entry = self.by_policy_id.entry(policy_id.clone()).and_modify(|c| {
+                let entry = self.by_policy_id.entry(policy_id).and_modify(|c| {
                     c.remove_from_asset(utxo);
                 });
 
@@ -311,26 +311,23 @@ impl UTxOStoreMut {
             let value = utxo_details.value.clone();
 
             self.utxos
-                .add_value(&TokenId::MAIN, value, utxo_details.clone());
+                .add_value(&AssetId::Native, value, utxo_details.clone());
 
             self.by_policy_id
-                .entry(TokenId::MAIN)
+                .entry(AssetId::Native)
                 .or_default()
                 .add_value(
-                    &TokenId::MAIN,
+                    &AssetId::Native,
                     utxo_details.value.clone(),
                     utxo_details.clone(),
                 );
 
             for asset in utxo_details.assets.iter() {
+                let asset_id = asset.asset_id();
                 self.by_policy_id
-                    .entry(asset.fingerprint.clone())
+                    .entry(asset_id.clone())
                     .or_default()
-                    .add_value(
-                        &asset.fingerprint,
-                        asset.quantity.clone(),
-                        utxo_details.clone(),
-                    );
+                    .add_value(&asset_id, asset.quantity.clone(), utxo_details.clone());
 
                 // We want to populate the dictionary only if we don't
                 // already have the entry
@@ -342,9 +339,9 @@ impl UTxOStoreMut {
         }
     }
 
-    pub fn token_balance(&self, token_id: &TokenId) -> Option<Value<Regulated>> {
+    pub fn token_balance(&self, asset_id: &AssetId) -> Option<Value<Regulated>> {
         self.by_policy_id
-            .get(token_id)
+            .get(asset_id)
             .map(|set| set.balance.clone())
     }
 
@@ -371,7 +368,8 @@ mod tests {
     use crate::tx::{TransactionAsset, TransactionId, UTxODetails, UtxoPointer};
     use crate::utxo_store::UTxOSet;
     use crate::{
-        cardano, Address, AssetName, OutputIndex, PolicyId, Regulated, TokenId, UTxOStore, Value,
+        cardano, Address, AssetId, AssetName, OutputIndex, PolicyId, Regulated, TokenId, UTxOStore,
+        Value,
     };
     use deps::bigdecimal::BigDecimal;
     use rand::{thread_rng, RngCore};
@@ -412,15 +410,19 @@ mod tests {
                 },
             ],
             metadata: Default::default(),
+            datum: None,
+            script_ref: None,
             extra: None,
+            creation_height: None,
+            script_spend: None,
         };
         assert!(mut_store.insert(utxo).is_ok());
         assert_eq!(
-            mut_store.token_balance(&shib_token_id),
+            mut_store.token_balance(&AssetId::Token(shib_token_id.clone())),
             Some(shib_quantity.clone()),
         );
         assert_eq!(
-            mut_store.token_balance(&sushi_token_id),
+            mut_store.token_balance(&AssetId::Token(sushi_token_id.clone())),
             Some(sushi_quantity.clone()),
         );
         assert_eq!(mut_store.balance(), ada_quantity.to_regulated());
@@ -438,14 +440,21 @@ mod tests {
                 quantity: sushi_quantity.clone(),
             }],
             metadata: Default::default(),
+            datum: None,
+            script_ref: None,
             extra: None,
+            creation_height: None,
+            script_spend: None,
         };
         assert!(mut_store.insert(new_utxo).is_ok());
         assert_eq!(
-            mut_store.token_balance(&sushi_token_id),
+            mut_store.token_balance(&AssetId::Token(sushi_token_id.clone())),
             Some(sushi_quantity.clone() + sushi_quantity.clone())
         );
-        assert_eq!(mut_store.token_balance(&shib_token_id), Some(shib_quantity));
+        assert_eq!(
+            mut_store.token_balance(&AssetId::Token(shib_token_id.clone())),
+            Some(shib_quantity)
+        );
         assert_eq!(
             mut_store.balance(),
             (ada_quantity.clone() + ada_quantity.clone()).to_regulated()
@@ -453,18 +462,24 @@ mod tests {
 
         assert!(mut_store.remove(&first_pointer).is_ok());
         assert_eq!(
-            mut_store.token_balance(&sushi_token_id),
+            mut_store.token_balance(&AssetId::Token(sushi_token_id.clone())),
             Some(sushi_quantity.clone())
         );
-        assert_eq!(mut_store.token_balance(&shib_token_id), None);
+        assert_eq!(
+            mut_store.token_balance(&AssetId::Token(shib_token_id.clone())),
+            None
+        );
         assert_eq!(mut_store.balance(), ada_quantity.to_regulated());
         let frozen = mut_store.freeze();
         let mut_store = frozen.thaw();
         assert_eq!(
-            mut_store.token_balance(&sushi_token_id),
+            mut_store.token_balance(&AssetId::Token(sushi_token_id.clone())),
             Some(sushi_quantity)
         );
-        assert_eq!(mut_store.token_balance(&shib_token_id), None);
+        assert_eq!(
+            mut_store.token_balance(&AssetId::Token(shib_token_id.clone())),
+            None
+        );
         assert_eq!(mut_store.balance(), ada_quantity.to_regulated());
     }
 
@@ -490,10 +505,14 @@ mod tests {
                 value: value.to_lovelace().to_regulated(),
                 assets: vec![],
                 metadata: Default::default(),
+                datum: None,
+                script_ref: None,
                 extra: None,
+                creation_height: None,
+                script_spend: None,
             };
             utxo_set.add_value(
-                &TokenId::MAIN,
+                &AssetId::Native,
                 value.to_lovelace().to_regulated(),
                 Arc::new(utxo),
             );
@@ -165,22 +165,167 @@ impl<Rep> Value<Rep> {
         let value = if value < &BigDecimal::one() {
             BigDecimal::from(0u64)
         } else {
-            let value = value.to_string();
-            let mut split = value.split('.');
-
-            if let Some(integer) = split.next() {
-                if let Ok(parsed) = integer.parse() {
-                    parsed
-                } else {
-                    BigDecimal::zero()
-                }
-            } else {
-                BigDecimal::zero()
-            }
+            // dropping the decimal digits by rescaling is a lot cheaper
+            // than going through the string representation.
+            value.with_scale(0)
         };
 
         Self::new(value)
     }
+
+    /// Try to add `rhs` to the [`Value`]
+    ///
+    /// returns `None` if the result would be negative (which can only
+    /// happen if one of the operand is already negative).
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(1u64);
+    /// assert_eq!(value.checked_add(&Value::from(2u64)), Some(Value::from(3u64)));
+    /// ```
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Self::non_negative(&self.value + &rhs.value)
+    }
+
+    /// Try to subtract `rhs` from the [`Value`]
+    ///
+    /// returns `None` if the result would be negative.
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(2u64);
+    /// assert_eq!(value.checked_sub(&Value::from(1u64)), Some(Value::from(1u64)));
+    /// assert_eq!(value.checked_sub(&Value::from(3u64)), None);
+    /// ```
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        Self::non_negative(&self.value - &rhs.value)
+    }
+
+    /// subtract `rhs` from the [`Value`], stopping at zero instead of
+    /// going negative.
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(2u64);
+    /// assert_eq!(value.saturating_sub(&Value::from(3u64)), Value::zero());
+    /// ```
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn saturating_sub(&self, rhs: &Self) -> Self {
+        self.checked_sub(rhs).unwrap_or_else(Self::zero)
+    }
+
+    /// absolute difference between the two values
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(2u64);
+    /// assert_eq!(value.abs_diff(&Value::from(5u64)), Value::from(3u64));
+    /// ```
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn abs_diff(&self, rhs: &Self) -> Self {
+        Self::new((&self.value - &rhs.value).abs())
+    }
+
+    /// split the [`Value`] in parts proportional to the given `weights`
+    ///
+    /// Every part is truncated to the precision of the value (i.e. an
+    /// integral value is split in integral parts). The truncation leftover
+    /// is added to the first part with a non zero weight so the parts
+    /// always sum up exactly to the original value. If all the weights
+    /// are zero, the whole value goes to the first part.
+    ///
+    /// If `weights` is empty, the returned vector is empty.
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(10u64);
+    /// let parts = value.split_weighted(&[1, 1, 1]);
+    ///
+    /// assert_eq!(
+    ///     parts,
+    ///     vec![Value::from(4u64), Value::from(3u64), Value::from(3u64)],
+    /// );
+    /// ```
+    pub fn split_weighted(&self, weights: &[u64]) -> Vec<Self> {
+        if weights.is_empty() {
+            return Vec::new();
+        }
+
+        let total: u128 = weights.iter().map(|w| u128::from(*w)).sum();
+        let first = weights.iter().position(|w| *w != 0).unwrap_or(0);
+
+        if total == 0 {
+            return (0..weights.len())
+                .map(|i| {
+                    if i == first {
+                        self.clone()
+                    } else {
+                        Self::zero()
+                    }
+                })
+                .collect();
+        }
+
+        let (integer, scale) = self.integer_and_scale();
+        let total = BigInt::from(total);
+
+        let mut parts: Vec<Self> = weights
+            .iter()
+            .map(|weight| {
+                let part = &integer * BigInt::from(*weight) / &total;
+                Self::new(BigDecimal::new(part, scale))
+            })
+            .collect();
+
+        let distributed: Self = parts.iter().sum();
+        parts[first] += self - &distributed;
+
+        parts
+    }
+
+    /// compute `numerator / denominator` of the [`Value`]
+    ///
+    /// The result is truncated to the precision of the value (i.e. the
+    /// percentage of an integral value is integral).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `denominator` is zero.
+    ///
+    /// ```
+    /// # use dcspark_core::{Value, Regulated};
+    /// let value = Value::<Regulated>::from(999u64);
+    /// assert_eq!(value.percent(50, 100), Value::from(499u64));
+    /// ```
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn percent(&self, numerator: u64, denominator: u64) -> Self {
+        assert!(denominator != 0, "percent with a zero denominator");
+
+        let (integer, scale) = self.integer_and_scale();
+        let part = integer * BigInt::from(numerator) / BigInt::from(denominator);
+
+        Self::new(BigDecimal::new(part, scale))
+    }
+
+    /// the value as an integer and its scale, with a non negative scale so
+    /// integer operations never lose precision below the units.
+    fn integer_and_scale(&self) -> (BigInt, i64) {
+        if self.value.is_integer() {
+            self.value.with_scale(0).into_bigint_and_exponent()
+        } else {
+            self.value.clone().into_bigint_and_exponent()
+        }
+    }
+
+    fn non_negative(value: BigDecimal) -> Option<Self> {
+        if value.is_negative() {
+            None
+        } else {
+            Some(Self::new(value))
+        }
+    }
 }
 
 impl<Rep> ToPrimitive for Value<Rep> {
@@ -586,7 +731,148 @@ impl<Rep> Serialize for Value<Rep> {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_newtype_struct("Value", &self.to_string())
+        serializer.serialize_newtype_struct("Value", &DisplayAsStr(&self.value))
+    }
+}
+
+/// serialize the displayed value as a string without requiring the
+/// intermediate allocation when the serializer supports it.
+struct DisplayAsStr<'a, T>(&'a T);
+
+impl<'a, T: fmt::Display> Serialize for DisplayAsStr<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self.0)
+    }
+}
+
+/// opt-in serde helpers to serialize a [`Value`] as a JSON number
+/// instead of the default string representation.
+///
+/// Only integral values can be serialized, any other value is rejected
+/// with an error. The values above `2^53 - 1` (the `Number.MAX_SAFE_INTEGER`
+/// of javascript) are serialized as a string instead: JSON parsers reading
+/// numbers as `f64` would silently lose their precision.
+/// When deserializing, numbers (integers or floats without a fractional
+/// part in the exact range of a `f64`) and strings are accepted.
+///
+/// ```
+/// use dcspark_core::{Value, cardano::Lovelace};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Output {
+///     #[serde(with = "dcspark_core::value_as_number")]
+///     value: Value<Lovelace>,
+/// }
+/// ```
+pub mod value_as_number {
+    use super::Value;
+    use deps::bigdecimal::{num_bigint::BigInt, BigDecimal, ToPrimitive as _};
+    use serde::{Deserializer, Serializer};
+    use std::{any, fmt, marker::PhantomData, str::FromStr};
+
+    /// largest integer a `f64` can represent without losing precision
+    const MAX_SAFE_INTEGER: u64 = 9_007_199_254_740_991;
+    const MAX_SAFE_F64_INTEGER: f64 = MAX_SAFE_INTEGER as f64;
+
+    pub fn serialize<Rep, S>(value: &Value<Rep>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::Error as _;
+
+        if !value.value.is_integer() {
+            return Err(S::Error::custom(format!(
+                "value {value} is not an integer and cannot be serialized as a number"
+            )));
+        }
+
+        match value.value.to_u64() {
+            Some(number) if number <= MAX_SAFE_INTEGER => serializer.serialize_u64(number),
+            _ => serializer.collect_str(value),
+        }
+    }
+
+    pub fn deserialize<'de, Rep, D>(deserializer: D) -> Result<Value<Rep>, D::Error>
+    where
+        D: Deserializer<'de>,
+        Value<Rep>: FromStr,
+        <Value<Rep> as FromStr>::Err: fmt::Display,
+    {
+        deserializer.deserialize_any(NumberVisitor(PhantomData))
+    }
+
+    struct NumberVisitor<Rep>(PhantomData<fn() -> Rep>);
+
+    impl<'de, Rep> serde::de::Visitor<'de> for NumberVisitor<Rep>
+    where
+        Value<Rep>: FromStr,
+        <Value<Rep> as FromStr>::Err: fmt::Display,
+    {
+        type Value = Value<Rep>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "to deserialise Value<{}> from a numerical or a string value",
+                any::type_name::<Rep>()
+            )
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(Value::from(v))
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            u64::try_from(v)
+                .map(Value::from)
+                .map_err(|_| E::custom(format!("value {v} cannot be negative")))
+        }
+
+        fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(Value::new(BigDecimal::new(BigInt::from(v), 0)))
+        }
+
+        fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            u128::try_from(v)
+                .map_err(|_| E::custom(format!("value {v} cannot be negative")))
+                .and_then(|v| self.visit_u128(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            if v.fract() != 0.0 || !(0.0..=MAX_SAFE_F64_INTEGER).contains(&v) {
+                return Err(E::custom(format!(
+                    "value {v} is not an integer in the safe range of a number"
+                )));
+            }
+
+            Ok(Value::new(BigDecimal::from(v as u64)))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            v.parse().map_err(E::custom)
+        }
     }
 }
 
@@ -680,6 +966,19 @@ impl<'a, Rep> Div<usize> for &'a Value<Rep> {
     type Output = Value<Rep>;
 
     fn div(self, rhs: usize) -> Self::Output {
+        // fast path: exact division of integral values (the common case
+        // for the zero decimals representations such as `Lovelace` or `Wei`)
+        // can be done on the integers directly, avoiding the arbitrary
+        // precision division.
+        if self.value.is_integer() {
+            let (integer, _) = self.value.with_scale(0).into_bigint_and_exponent();
+            let rhs = BigInt::from(rhs);
+
+            if (&integer % &rhs).is_zero() {
+                return Value::new(BigDecimal::new(integer / rhs, 0));
+            }
+        }
+
         let value = &self.value
             / BigDecimal::from_usize(rhs).expect("Usize should always fit in a big number");
         Value::new(value)
@@ -1029,6 +1328,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn checked_add() {
+        assert_eq!(value!(1).checked_add(&value!(1)), Some(value!(2)));
+        assert_eq!(value!(0).checked_add(&value!(0)), Some(value!(0)));
+        assert_eq!((value!(0) - value!(2)).checked_add(&value!(1)), None);
+    }
+
+    #[test]
+    fn checked_sub() {
+        assert_eq!(value!(2).checked_sub(&value!(1)), Some(value!(1)));
+        assert_eq!(value!(1).checked_sub(&value!(1)), Some(value!(0)));
+        assert_eq!(value!(1).checked_sub(&value!(2)), None);
+    }
+
+    #[test]
+    fn saturating_sub() {
+        assert_eq!(value!(2).saturating_sub(&value!(1)), value!(1));
+        assert_eq!(value!(1).saturating_sub(&value!(2)), value!(0));
+    }
+
+    #[test]
+    fn abs_diff() {
+        assert_eq!(value!(2).abs_diff(&value!(5)), value!(3));
+        assert_eq!(value!(5).abs_diff(&value!(2)), value!(3));
+        assert_eq!(value!(5).abs_diff(&value!(5)), value!(0));
+    }
+
     #[test]
     fn div() {
         assert_eq!(value!(1) / 1, value!(1));
@@ -1039,6 +1365,206 @@ mod tests {
         assert_eq!((value!(4) / 3).truncate(), value!(1));
     }
 
+    #[test]
+    fn split_weighted() {
+        assert_eq!(value!(10).split_weighted(&[]), vec![]);
+        assert_eq!(
+            value!(10).split_weighted(&[1, 1]),
+            vec![value!(5), value!(5)]
+        );
+        assert_eq!(
+            value!(11).split_weighted(&[1, 1]),
+            vec![value!(6), value!(5)]
+        );
+        assert_eq!(
+            value!(10).split_weighted(&[0, 1, 2]),
+            vec![value!(0), value!(4), value!(6)]
+        );
+        assert_eq!(
+            value!(10).split_weighted(&[0, 0]),
+            vec![value!(10), value!(0)]
+        );
+
+        let value: Value<Normalized> = "1.05".parse().unwrap();
+        let parts = value.split_weighted(&[1, 1]);
+        assert_eq!(parts[1], "0.52".parse().unwrap());
+        assert_eq!(parts.into_iter().sum::<Value<Normalized>>(), value);
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(value!(100).percent(50, 100), value!(50));
+        assert_eq!(value!(99).percent(50, 100), value!(49));
+        assert_eq!(value!(99).percent(0, 100), value!(0));
+        assert_eq!(value!(99).percent(1, 1), value!(99));
+    }
+
+    #[test]
+    #[should_panic]
+    fn percent_zero_denominator() {
+        let _ = value!(99).percent(1, 0);
+    }
+
+    quickcheck! {
+        fn split_weighted_sums_to_original(lovelace: Value<cardano::Lovelace>, weights: Vec<u64>) -> bool {
+            let parts = lovelace.split_weighted(&weights);
+
+            weights.is_empty() || (parts.len() == weights.len() && parts.into_iter().sum::<Value<_>>() == lovelace)
+        }
+    }
+
+    #[test]
+    fn div_fast_path_matches_decimal_division() {
+        for (value, rhs) in [
+            (0u64, 1usize),
+            (10, 2),
+            (9, 3),
+            (9, 2),
+            (1, 3),
+            (45_000_000, 7),
+        ] {
+            let expected = Value::<cardano::Lovelace>::new(
+                BigDecimal::from(value) / BigDecimal::from_usize(rhs).unwrap(),
+            );
+
+            assert_eq!(Value::<cardano::Lovelace>::from(value) / rhs, expected);
+        }
+
+        let value = Value::<Normalized>::new(BigDecimal::new(BigInt::from(3u64), -2));
+        assert_eq!(value / 3, Value::from(100u64));
+    }
+
+    #[test]
+    fn truncate_integral_values() {
+        let value = Value::<Normalized>::new(BigDecimal::new(BigInt::from(42u64), -3));
+        assert_eq!(value.truncate(), Value::from(42_000u64));
+
+        let value = Value::<Normalized>::new(BigDecimal::new(BigInt::from(42_000u64), 3));
+        assert_eq!(value.truncate(), Value::from(42u64));
+
+        let value: Value<Normalized> = "123456789012345678901234567890.999".parse().unwrap();
+        assert_eq!(
+            value.truncate().to_string(),
+            "123456789012345678901234567890"
+        );
+    }
+
+    #[test]
+    fn serialize_as_string() {
+        let value: Value<Normalized> = "1.5".parse().unwrap();
+        let json = deps::serde_json::to_string(&value).unwrap();
+
+        assert_eq!(json, "\"1.5\"");
+        assert_eq!(
+            deps::serde_json::from_str::<Value<Normalized>>(&json).unwrap(),
+            value
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct AsNumber {
+        #[serde(with = "value_as_number")]
+        value: Value<cardano::Lovelace>,
+    }
+
+    #[test]
+    fn value_as_number_roundtrip() {
+        let input = AsNumber { value: value!(42) };
+        let json = deps::serde_json::to_string(&input).unwrap();
+
+        assert_eq!(json, r#"{"value":42}"#);
+        assert_eq!(
+            deps::serde_json::from_str::<AsNumber>(&json).unwrap(),
+            input
+        );
+    }
+
+    #[test]
+    fn value_as_number_accepts_strings_and_floats() {
+        let decoded: AsNumber = deps::serde_json::from_str(r#"{"value":"42"}"#).unwrap();
+        assert_eq!(decoded.value, value!(42));
+
+        let decoded: AsNumber = deps::serde_json::from_str(r#"{"value":42.0}"#).unwrap();
+        assert_eq!(decoded.value, value!(42));
+
+        assert!(deps::serde_json::from_str::<AsNumber>(r#"{"value":42.5}"#).is_err());
+        assert!(deps::serde_json::from_str::<AsNumber>(r#"{"value":-1}"#).is_err());
+    }
+
+    #[test]
+    fn value_as_number_unsafe_integers_as_strings() {
+        let input = AsNumber {
+            value: value!(9_007_199_254_740_991),
+        };
+        assert_eq!(
+            deps::serde_json::to_string(&input).unwrap(),
+            r#"{"value":9007199254740991}"#
+        );
+
+        for value in [
+            value!(9_007_199_254_740_992),
+            Value::<cardano::Lovelace>::from(u64::MAX) + value!(1),
+        ] {
+            let input = AsNumber { value };
+            let json = deps::serde_json::to_string(&input).unwrap();
+            assert_eq!(json, format!(r#"{{"value":"{}"}}"#, input.value));
+            assert_eq!(
+                deps::serde_json::from_str::<AsNumber>(&json).unwrap(),
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn value_as_number_rejects_decimals() {
+        let input = AsNumber {
+            value: Value::new("1.5".parse().unwrap()),
+        };
+        assert!(deps::serde_json::to_string(&input).is_err());
+    }
+
+    #[test]
+    fn deserialize_wei_overflowing_u64() {
+        // 1 million ether
+        const WEI: &str = "1000000000000000000000000";
+        let expected: Value<evm::Wei> = WEI.parse().unwrap();
+
+        let value: Value<evm::Wei> = deps::serde_json::from_str(&format!("\"{WEI}\"")).unwrap();
+        assert_eq!(value, expected);
+        assert_eq!(value.to_ether(), "1000000".parse().unwrap());
+
+        let wei: u128 = WEI.parse().unwrap();
+        let deserializer: serde::de::value::U128Deserializer<serde::de::value::Error> =
+            serde::de::IntoDeserializer::into_deserializer(wei);
+        assert_eq!(
+            Value::<evm::Wei>::deserialize(deserializer).unwrap(),
+            expected
+        );
+
+        let deserializer: serde::de::value::U128Deserializer<serde::de::value::Error> =
+            serde::de::IntoDeserializer::into_deserializer(u128::MAX);
+        assert_eq!(
+            Value::<evm::Wei>::deserialize(deserializer).unwrap(),
+            u128::MAX.to_string().parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_negative_value() {
+        let value: Value<evm::Wei> = Value::new("-1000000000000000000000000".parse().unwrap());
+        let json = deps::serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"-1000000000000000000000000\"");
+        assert_eq!(
+            deps::serde_json::from_str::<Value<evm::Wei>>(&json).unwrap(),
+            value
+        );
+
+        assert_eq!(
+            deps::serde_json::from_str::<Value<evm::Wei>>("-1").unwrap(),
+            Value::new(BigDecimal::from(-1i64))
+        );
+    }
+
     #[test]
     fn normalized() {
         const RULE: Rule = Rule {
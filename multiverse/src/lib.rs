@@ -136,6 +136,15 @@ pub struct BestBlock<K> {
     pub discarded: HashSet<EntryRef<K>>,
 }
 
+/// Structure returned by [`Multiverse::verify`] function.
+pub struct Verification {
+    /// number of entries persisted in the domain
+    pub entries: usize,
+    /// the storage keys of the corrupted entries: the entries that could
+    /// not be decoded or that are not stored under the key of their value.
+    pub corrupted: Vec<Vec<u8>>,
+}
+
 impl<K, V> Multiverse<K, V>
 where
     K: Eq + Hash,
@@ -144,6 +153,11 @@ where
     pub fn tips(&self) -> HashSet<Arc<K>> {
         self.tips.iter().map(|e| Arc::clone(&e.key)).collect()
     }
+
+    /// list all the roots of the Multiverse, the entries without parent
+    pub fn roots(&self) -> HashSet<Arc<K>> {
+        self.roots.iter().map(|e| Arc::clone(&e.key)).collect()
+    }
 }
 
 impl<K, V> Multiverse<K, V>
@@ -303,6 +317,47 @@ where
         Ok(multiverse)
     }
 
+    /// check the entries persisted in the `domain` of the [`sled::Db`]
+    /// without loading them: every entry is expected to be decoded and to
+    /// be stored under the key of its block number and id.
+    ///
+    /// If `repair` is set the corrupted entries are removed from the
+    /// storage so the multiverse can be loaded again with [`Self::load_from`].
+    ///
+    #[tracing::instrument(skip(db), level = "debug")]
+    pub fn verify(
+        db: &sled::Db,
+        domain: &str,
+        repair: bool,
+    ) -> Result<Verification, MultiverseError> {
+        let tree = db.open_tree(domain)?;
+        let mut verification = Verification {
+            entries: 0,
+            corrupted: Vec::new(),
+        };
+
+        for entry in tree.iter() {
+            let (key, value) = entry?;
+            verification.entries += 1;
+
+            let valid = deps::serde_json::from_slice::<V>(&value)
+                .map(|variant| mk_sled_key(variant.block_number(), variant.id()) == key.as_ref())
+                .unwrap_or(false);
+            if !valid {
+                tracing::warn!(key = ?key, "corrupted entry");
+                verification.corrupted.push(key.to_vec());
+            }
+        }
+
+        if repair {
+            for key in verification.corrupted.iter() {
+                tree.remove(key)?;
+            }
+        }
+
+        Ok(verification)
+    }
+
     /// open the multiverse, loading an existing persisted multiverse
     ///
     /// the `domain` is the sub[`sled::Tree`] in the [`sled::Db`] that
@@ -840,6 +895,43 @@ mod tests {
             .expect("entries were not restored from db");
     }
 
+    #[test]
+    fn verify_and_repair_corrupted_entries() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        let blockchain = declare_blockchain! { "Root" <= "1" <= "2" };
+
+        let mut multiverse = Multiverse::new_with(db.clone(), "temporary", BlockNumber::MIN);
+
+        for block in blockchain {
+            multiverse.insert(block).unwrap();
+        }
+
+        std::mem::drop(multiverse);
+
+        // an entry that can't be decoded and an entry stored under the key
+        // of another block
+        let tree = db.open_tree("temporary").unwrap();
+        tree.insert(b"garbage", b"not json".to_vec()).unwrap();
+        let three = deps::serde_json::to_vec(&V::new("3", 3)).unwrap();
+        tree.insert(mk_sled_key(BlockNumber::new(4u64), "3"), three).unwrap();
+
+        let verification = Multiverse::<K, V>::verify(&db, "temporary", false).unwrap();
+        assert_eq!(verification.entries, 5);
+        assert_eq!(verification.corrupted.len(), 2);
+        assert!(Multiverse::<K, V>::load_from(db.clone(), "temporary", BlockNumber::MIN).is_err());
+
+        Multiverse::<K, V>::verify(&db, "temporary", true).unwrap();
+        let verification = Multiverse::<K, V>::verify(&db, "temporary", false).unwrap();
+        assert_eq!(verification.entries, 3);
+        assert!(verification.corrupted.is_empty());
+
+        let multiverse: Multiverse<K, V> =
+            Multiverse::load_from(db, "temporary", BlockNumber::MIN).unwrap();
+        assert_eq!(multiverse.len(), 3);
+        assert_eq!(multiverse.roots().len(), 1);
+    }
+
     struct Simulation {
         multiverse: Multiverse<K, V>,
         selection_rule: BestBlockSelectionRule,
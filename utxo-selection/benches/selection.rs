@@ -0,0 +1,172 @@
+//! Compare the selection algorithms on generated UTxO sets
+//!
+//! Every algorithm pays the same output from sets of 10², 10⁴ and 10⁶
+//! UTxOs, with and without native assets. Criterion measures the runtime
+//! of the selection, the number of inputs chosen and the fee of each
+//! selection are printed once before the measurements.
+//!
+//! ```sh
+//! cargo bench -p utxo-selection --bench selection
+//! ```
+
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_utils::multisig_plan::MultisigPlan;
+use cardano_utils::network_id::NetworkInfo;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use dcspark_core::tx::{TransactionAsset, TransactionId, UTxOBuilder, UTxODetails, UtxoPointer};
+use dcspark_core::{
+    Address, AssetName, OutputIndex, PolicyId, Regulated, TokenId, UTxOStore, Value,
+};
+use deps::serde_json;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use utxo_selection::algorithms::{
+    Knapsack, LargestFirst, LargestFirstMultiAsset, RandomImprove, SingleRandomDraw, Thermostat,
+    ThermostatAlgoConfig,
+};
+use utxo_selection::estimators::ThermostatFeeEstimator;
+use utxo_selection::{InputOutputSetup, InputSelectionAlgorithm, UTxOStoreSupport};
+
+const SIZES: [usize; 3] = [100, 10_000, 1_000_000];
+const ADDRESS: &str = "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj";
+const TOKEN: &str = "tDRIP";
+
+fn token(quantity: u64) -> TransactionAsset {
+    TransactionAsset {
+        policy_id: PolicyId::new("00000000000000000000000000000000000000000000000000000000"),
+        asset_name: AssetName::new("74445249"),
+        fingerprint: TokenId::new(TOKEN),
+        quantity: Value::from(quantity),
+    }
+}
+
+/// `len` UTxOs of 1 to 1000 ADA, one in three holding tokens if `assets`
+fn utxos(len: usize, assets: bool) -> UTxOStore {
+    let mut rng = StdRng::seed_from_u64(len as u64);
+    let mut store = UTxOStore::new().thaw();
+    for index in 0..len {
+        let assets = if assets && index % 3 == 0 {
+            vec![token(rng.gen_range(1..1_000_000))]
+        } else {
+            vec![]
+        };
+        store
+            .insert(UTxODetails {
+                pointer: UtxoPointer {
+                    transaction_id: TransactionId::new_unchecked(format!("{index:064}")),
+                    output_index: OutputIndex::new(0),
+                },
+                address: Address::new(ADDRESS),
+                value: Value::from(rng.gen_range(1_000_000..1_000_000_000)),
+                assets,
+                metadata: Arc::new(serde_json::Value::Null),
+                datum: None,
+                script_ref: None,
+                extra: None,
+                creation_height: None,
+                script_spend: None,
+            })
+            .expect("the pointers are unique");
+    }
+    store.freeze()
+}
+
+fn setup(assets: bool) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+    let output = UTxOBuilder::new(
+        Address::new(ADDRESS),
+        Value::<Regulated>::from(50_000_000),
+        if assets { vec![token(1_000)] } else { vec![] },
+    );
+    InputOutputSetup::from_fixed_inputs_and_outputs(
+        vec![],
+        vec![output],
+        Some(Address::new(ADDRESS)),
+    )
+}
+
+fn estimator() -> ThermostatFeeEstimator {
+    let plan: MultisigPlan = serde_json::from_value(serde_json::json! {
+        {
+            "quorum": 1u8,
+            "keys": ["00000000000000000000000000000000000000000000000000000000"]
+        }
+    })
+    .expect("valid plan");
+    ThermostatFeeEstimator::new(NetworkInfo::Testnet, &plan, BigNum::from(4310))
+}
+
+fn bench_algorithm<Algorithm, Make>(
+    c: &mut Criterion,
+    name: &str,
+    utxos: &UTxOStore,
+    assets: bool,
+    make: Make,
+) where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    Make: Fn(UTxOStore) -> Algorithm,
+{
+    let group_name = if assets {
+        format!("{name}/assets")
+    } else {
+        name.to_string()
+    };
+    let len = utxos.len();
+
+    let setup = setup(assets);
+    match make(utxos.clone()).select_inputs(&mut estimator(), setup.clone()) {
+        Ok(result) => println!(
+            "{group_name}/{len}: {} inputs, fee {}",
+            result.chosen_inputs.len(),
+            result.fee
+        ),
+        Err(error) => println!("{group_name}/{len}: failed: {error}"),
+    }
+
+    let mut group = c.benchmark_group(group_name);
+    group.sample_size(10);
+    group.bench_with_input(BenchmarkId::from_parameter(len), utxos, |b, utxos| {
+        b.iter_batched(
+            || (make(utxos.clone()), estimator(), setup.clone()),
+            |(mut algorithm, mut estimator, setup)| algorithm.select_inputs(&mut estimator, setup),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn selection(c: &mut Criterion) {
+    for assets in [false, true] {
+        for len in SIZES {
+            let utxos = utxos(len, assets);
+
+            bench_algorithm(c, "thermostat", &utxos, assets, |utxos| {
+                let mut thermostat = Thermostat::new(ThermostatAlgoConfig::default());
+                thermostat.set_available_utxos(utxos).expect("valid utxos");
+                thermostat
+            });
+            bench_algorithm(c, "largest_first", &utxos, assets, |utxos| {
+                LargestFirst::try_from(utxos).expect("valid utxos")
+            });
+            bench_algorithm(c, "largest_first_multi_asset", &utxos, assets, |utxos| {
+                LargestFirstMultiAsset::try_from(utxos).expect("valid utxos")
+            });
+            bench_algorithm(c, "random_improve", &utxos, assets, |utxos| {
+                RandomImprove::try_from(utxos)
+                    .expect("valid utxos")
+                    .with_seed(0)
+            });
+            bench_algorithm(c, "single_random_draw", &utxos, assets, |utxos| {
+                SingleRandomDraw::try_from(utxos)
+                    .expect("valid utxos")
+                    .with_seed(0)
+            });
+            bench_algorithm(c, "knapsack", &utxos, assets, |utxos| {
+                Knapsack::try_from(utxos).expect("valid utxos")
+            });
+        }
+    }
+}
+
+criterion_group!(benches, selection);
+criterion_main!(benches);
@@ -1,6 +1,7 @@
 use crate::common::{InputOutputSetup, InputSelectionResult};
 use crate::estimate::TransactionFeeEstimator;
-use dcspark_core::UTxOStore;
+use dcspark_core::tx::UTxODetails;
+use dcspark_core::{Address, UTxOStore};
 
 pub trait InputSelectionAlgorithm {
     type InputUtxo: Clone;
@@ -20,9 +21,111 @@ pub trait InputSelectionAlgorithm {
     ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>>;
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo>;
+
+    /// the checkpoint of the change address provider of the algorithm (see
+    /// [`ChangeAddressProvider::checkpoint`](crate::ChangeAddressProvider::checkpoint)),
+    /// `0` if the algorithm has none
+    fn change_addresses_checkpoint(&self) -> u64 {
+        0
+    }
+
+    /// rewind the change address provider of the algorithm to the
+    /// `checkpoint`, when the result of a selection is dropped
+    fn rewind_change_addresses(&mut self, _checkpoint: u64) {}
+
+    /// the next address of the change address provider of the algorithm,
+    /// `None` if the algorithm has none
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        Ok(None)
+    }
 }
 
+/// the available inputs given as a [`UTxOStore`]
+///
+/// This is the preferred interface: the store is shared with the wallet
+/// instead of copying every UTxO in a `Vec` for each transaction. All the
+/// algorithms of this crate support it, use the [`UTxOStoreAdapter`] for
+/// the others.
 pub trait UTxOStoreSupport {
     fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()>;
     fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore>;
 }
+
+/// give the [`UTxOStoreSupport`] interface to an algorithm only taking its
+/// inputs as a `Vec`
+///
+/// The inputs are converted on every call, the algorithms of this crate
+/// implement [`UTxOStoreSupport`] directly.
+pub struct UTxOStoreAdapter<Algorithm> {
+    algorithm: Algorithm,
+}
+
+impl<Algorithm> UTxOStoreAdapter<Algorithm> {
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self { algorithm }
+    }
+
+    pub fn into_inner(self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+impl<Algorithm> UTxOStoreSupport for UTxOStoreAdapter<Algorithm>
+where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails>,
+{
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.algorithm.set_available_inputs(
+            utxos
+                .iter()
+                .map(|(_, utxo)| utxo.as_ref().clone())
+                .collect(),
+        )
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        let mut store = UTxOStore::new().thaw();
+        for input in self.algorithm.available_inputs() {
+            store.insert(input)?;
+        }
+        Ok(store.freeze())
+    }
+}
+
+impl<Algorithm: InputSelectionAlgorithm> InputSelectionAlgorithm for UTxOStoreAdapter<Algorithm> {
+    type InputUtxo = Algorithm::InputUtxo;
+    type OutputUtxo = Algorithm::OutputUtxo;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.algorithm.set_available_inputs(available_inputs)
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        self.algorithm.select_inputs(estimator, input_output_setup)
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.algorithm.available_inputs()
+    }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        self.algorithm.change_addresses_checkpoint()
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        self.algorithm.rewind_change_addresses(checkpoint)
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        self.algorithm.next_change_address()
+    }
+}
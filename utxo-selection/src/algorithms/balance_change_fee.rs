@@ -82,7 +82,9 @@ impl InputSelectionAlgorithm for FeeChangeBalancer {
             chosen_inputs: vec![],
             changes: vec![],
             fee,
-        })
+            trace: None,
+        }
+        .into_stable_order())
     }
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo> {
@@ -134,6 +136,8 @@ mod tests {
                         vec![],
                     )],
                     change_address: None,
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -156,6 +160,8 @@ mod tests {
                     fixed_inputs: result.chosen_inputs,
                     fixed_outputs: result.fixed_outputs,
                     change_address: Some(Address::new("kek")),
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
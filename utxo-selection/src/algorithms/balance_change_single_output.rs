@@ -1,24 +1,81 @@
+use crate::algorithms::select_largest_input_for;
+use crate::change_address::{assign_change_addresses, checkpoint, next_provided_address, rewind};
 use crate::{
-    calculate_asset_balance, calculate_main_token_balance, InputOutputSetup,
-    InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator, UTxOStoreSupport,
+    assets_in_selection_order, calculate_main_token_balance, restore_utxos,
+    BoxedChangeAddressProvider, ChangeAddressProvider, ChangeStrategy, InputOutputSetup,
+    InputSelectionAlgorithm, InputSelectionResult, SingleChange, TransactionFeeEstimator,
+    UTxOStoreSupport,
 };
 use anyhow::anyhow;
-use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
-use dcspark_core::{Balance, Regulated, UTxOStore, Value};
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, AssetId, Balance, Regulated, UTxOStore, Value};
 
-#[derive(Default)]
-pub struct SingleOutputChangeBalancer {
+/// balance the transaction with the change, a single change output unless
+/// another [`ChangeStrategy`] is set with
+/// [`SingleOutputChangeBalancer::with_change_strategy`]
+///
+/// The excess tokens are returned in the change with the main asset, the
+/// tokens missing in the inputs are selected from the available inputs.
+/// The changes are sent to the `change_address` of the setup, or to the
+/// addresses set with [`SingleOutputChangeBalancer::with_change_addresses`].
+pub struct SingleOutputChangeBalancer<S = SingleChange> {
     available_inputs: UTxOStore,
     extra: Option<String>,
+    change_strategy: S,
+    change_addresses: Option<BoxedChangeAddressProvider>,
 }
 
-impl SingleOutputChangeBalancer {
+impl Default for SingleOutputChangeBalancer {
+    fn default() -> Self {
+        Self {
+            available_inputs: Default::default(),
+            extra: None,
+            change_strategy: SingleChange,
+            change_addresses: None,
+        }
+    }
+}
+
+impl<S: ChangeStrategy> SingleOutputChangeBalancer<S> {
     pub fn set_extra(&mut self, extra: String) {
         self.extra = Some(extra);
     }
+
+    /// split the change with the given strategy
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_strategy<S2: ChangeStrategy>(
+        self,
+        change_strategy: S2,
+    ) -> SingleOutputChangeBalancer<S2> {
+        SingleOutputChangeBalancer {
+            available_inputs: self.available_inputs,
+            extra: self.extra,
+            change_strategy,
+            change_addresses: self.change_addresses,
+        }
+    }
+
+    /// send each change to the next address of the `provider`
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_addresses<P: ChangeAddressProvider + Send + 'static>(
+        self,
+        provider: P,
+    ) -> Self {
+        Self {
+            change_addresses: Some(Box::new(provider)),
+            ..self
+        }
+    }
+
+    /// the provider set with
+    /// [`SingleOutputChangeBalancer::with_change_addresses`], e.g. to persist
+    /// its [`ChangeAddressProvider::next_index`] after a selection
+    pub fn change_addresses(&self) -> Option<&(dyn ChangeAddressProvider + Send)> {
+        self.change_addresses.as_deref()
+    }
 }
 
-impl UTxOStoreSupport for SingleOutputChangeBalancer {
+impl<S: ChangeStrategy> UTxOStoreSupport for SingleOutputChangeBalancer<S> {
     fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
         self.available_inputs = utxos;
         Ok(())
@@ -29,7 +86,7 @@ impl UTxOStoreSupport for SingleOutputChangeBalancer {
     }
 }
 
-impl InputSelectionAlgorithm for SingleOutputChangeBalancer {
+impl<S: ChangeStrategy> InputSelectionAlgorithm for SingleOutputChangeBalancer<S> {
     type InputUtxo = UTxODetails;
     type OutputUtxo = UTxOBuilder;
 
@@ -52,44 +109,47 @@ impl InputSelectionAlgorithm for SingleOutputChangeBalancer {
         estimator: &mut Estimate,
         input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
     ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let (mut utxos, excluded) = input_output_setup.split_selectable(&self.available_inputs)?;
+
         let change_address = if let Some(address) = input_output_setup.change_address {
             address
         } else {
             return Err(anyhow!("change address is not provided"));
         };
 
-        let asset_balances = calculate_asset_balance(
-            &input_output_setup.input_asset_balance,
-            &input_output_setup.output_asset_balance,
-        );
-        let mut change_assets = vec![];
-        for (token, asset_balance) in asset_balances.into_iter() {
-            match asset_balance {
-                Balance::Debt(d) => {
-                    return Err(anyhow!(
-                        "there's lack of assets selected, can't balance change: {}",
-                        d
-                    ));
-                }
-                Balance::Balanced => {}
-                Balance::Excess(excess) => {
-                    let mut asset = input_output_setup
-                        .input_asset_balance
-                        .get(&token)
-                        .ok_or_else(|| anyhow!("asset {} must be presented in the inputs", token))?
-                        .clone();
-                    asset.quantity = excess;
-                    change_assets.push(asset)
-                }
+        let mut fee = estimator.min_required_fee()?;
+        let mut input_balance = input_output_setup.input_balance;
+        let mut input_asset_balance = input_output_setup.input_asset_balance;
+        let mut chosen_inputs = vec![];
+
+        // the tokens missing in the inputs are selected first, the other
+        // tokens of these inputs go to the change
+        for output_asset in assets_in_selection_order(&input_output_setup.output_asset_balance) {
+            let token = &output_asset.fingerprint;
+            while input_asset_balance.quantity_of(token) < output_asset.quantity {
+                let (input, remaining_utxos) = select_largest_input_for(
+                    utxos,
+                    &AssetId::Token(token.clone()),
+                )
+                .map_err(|error| {
+                    anyhow!("there's lack of {token} selected, can't balance change: {error}")
+                })?;
+                utxos = remaining_utxos;
+
+                fee += &estimator.fee_for_input(&input)?;
+                estimator.add_input(input.clone())?;
+                input_balance += &input.value;
+                input_asset_balance.extend(input.assets.iter());
+                chosen_inputs.push(input);
             }
         }
 
-        let mut fee = estimator.min_required_fee()?;
-        let current_balance = calculate_main_token_balance(
-            &input_output_setup.input_balance,
-            &input_output_setup.output_balance,
-            &fee,
-        );
+        let mut change_assets: MultiAssetValue = input_asset_balance
+            .checked_sub(&input_output_setup.output_asset_balance)
+            .ok_or_else(|| anyhow!("there's lack of assets selected, can't balance change"))?;
+
+        let current_balance =
+            calculate_main_token_balance(&input_balance, &input_output_setup.output_balance, &fee);
 
         let value: Value<Regulated> = match current_balance {
             Balance::Debt(d) => {
@@ -102,42 +162,83 @@ impl InputSelectionAlgorithm for SingleOutputChangeBalancer {
             Balance::Excess(excess) => excess,
         };
 
-        let mut change = UTxOBuilder {
-            address: change_address,
-            value,
-            assets: change_assets,
-            extra: self.extra.clone(),
-        };
+        // the change must hold the minimum value required by the ledger,
+        // it is topped up with the largest available inputs otherwise
+        let mut value = value;
+        let excess = loop {
+            let excess = UTxOBuilder {
+                address: change_address.clone(),
+                value: value.clone(),
+                assets: change_assets
+                    .clone()
+                    .into_iter()
+                    .map(|(_, asset)| asset)
+                    .collect(),
+                datum: None,
+                script_ref: None,
+                extra: self.extra.clone(),
+            };
 
-        let fee_for_change = estimator.fee_for_output(&change)?;
-        change.value -= &fee_for_change;
-        fee += &fee_for_change;
+            let fee_for_change = estimator.fee_for_output(&excess)?;
+            if let Some(remaining) = excess.value.checked_sub(&fee_for_change) {
+                let mut change = excess.clone();
+                change.value = remaining;
+                let min_value = estimator.min_value_for_output(change)?;
+                if remaining >= min_value {
+                    break excess;
+                }
+            }
 
-        estimator.add_output(change.clone())?;
+            let (input, remaining_utxos) = select_largest_input_for(utxos, &AssetId::Native)
+                .map_err(|error| {
+                    anyhow!(
+                        "not enough main token in the change ({}) to pay for its fee ({}) and the minimum UTxO value: {}",
+                        value,
+                        fee_for_change,
+                        error
+                    )
+                })?;
+            utxos = remaining_utxos;
 
-        let output_balance = &input_output_setup.output_balance + &change.value;
+            let fee_for_input = estimator.fee_for_input(&input)?;
+            estimator.add_input(input.clone())?;
+            fee += &fee_for_input;
+            value = (&value + &input.value).saturating_sub(&fee_for_input);
+            input_balance += &input.value;
+            input_asset_balance.extend(input.assets.iter());
+            change_assets.extend(input.assets.iter());
+            chosen_inputs.push(input);
+        };
+
+        let changes = self
+            .change_strategy
+            .build_changes(estimator, excess.clone())?;
+        let changes = assign_change_addresses(estimator, &mut self.change_addresses, changes)?;
+        let mut output_balance = input_output_setup.output_balance;
         let mut output_asset_balance = input_output_setup.output_asset_balance;
-        for asset in change.assets.iter() {
-            output_asset_balance
-                .entry(asset.fingerprint.clone())
-                .or_insert(TransactionAsset::new(
-                    asset.policy_id.clone(),
-                    asset.asset_name.clone(),
-                    asset.fingerprint.clone(),
-                ))
-                .quantity += &asset.quantity;
+        for change in changes.iter() {
+            estimator.add_output(change.clone())?;
+            output_balance += &change.value;
+            output_asset_balance.extend(change.assets.iter());
         }
+        let changes_value: Value<Regulated> = changes.iter().map(|change| &change.value).sum();
+        fee += &excess.value - &changes_value;
+
+        self.available_inputs = restore_utxos(utxos, excluded)?;
+
         Ok(InputSelectionResult {
-            input_balance: input_output_setup.input_balance,
-            input_asset_balance: input_output_setup.input_asset_balance,
+            input_balance,
+            input_asset_balance,
             output_balance,
             output_asset_balance,
             fixed_inputs: input_output_setup.fixed_inputs,
             fixed_outputs: input_output_setup.fixed_outputs,
-            chosen_inputs: vec![],
-            changes: vec![change],
+            chosen_inputs,
+            changes,
             fee,
-        })
+            trace: None,
+        }
+        .into_stable_order())
     }
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo> {
@@ -146,6 +247,18 @@ impl InputSelectionAlgorithm for SingleOutputChangeBalancer {
             .map(|(_, v)| v.as_ref().clone())
             .collect::<Vec<_>>()
     }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        checkpoint(&self.change_addresses)
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        rewind(&mut self.change_addresses, checkpoint);
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        next_provided_address(&mut self.change_addresses)
+    }
 }
 
 #[cfg(test)]
@@ -153,7 +266,7 @@ mod tests {
     use crate::algorithms::test_utils::{create_asset, create_utxo};
     use crate::algorithms::{LargestFirst, SingleOutputChangeBalancer};
     use crate::estimators::dummy_estimator::DummyFeeEstimate;
-    use crate::{InputOutputSetup, InputSelectionAlgorithm};
+    use crate::{InputOutputSetup, InputSelectionAlgorithm, SplitChange};
     use dcspark_core::tx::UTxOBuilder;
     use dcspark_core::{Address, Regulated, TokenId, UTxOStore, Value};
     use std::collections::HashMap;
@@ -252,7 +365,7 @@ mod tests {
                     input_balance: Default::default(),
                     input_asset_balance: Default::default(),
                     output_balance: Value::from(24),
-                    output_asset_balance: output_balance.clone(),
+                    output_asset_balance: output_balance.clone().into(),
                     fixed_inputs: vec![],
                     fixed_outputs: vec![UTxOBuilder::new(
                         Address::new("unwrap"),
@@ -260,6 +373,8 @@ mod tests {
                         output_balance.values().cloned().collect(),
                     )],
                     change_address: None,
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -300,6 +415,8 @@ mod tests {
                     fixed_inputs: result.chosen_inputs,
                     fixed_outputs: result.fixed_outputs,
                     change_address: Some(Address::new("kek")),
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -332,4 +449,153 @@ mod tests {
             create_asset("1".to_string(), Value::from(90))
         );
     }
+
+    #[test]
+    fn change_topped_up_to_min_value() {
+        let mut balance_change = SingleOutputChangeBalancer::default();
+        balance_change
+            .set_available_inputs(vec![
+                create_utxo(1, 0, "0".to_string(), Value::<Regulated>::from(5), vec![]),
+                create_utxo(1, 1, "0".to_string(), Value::<Regulated>::from(2), vec![]),
+            ])
+            .unwrap();
+
+        let result = balance_change
+            .select_inputs(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(3)),
+                InputOutputSetup {
+                    input_balance: Value::from(10),
+                    output_balance: Value::from(9),
+                    change_address: Some(Address::new("kek")),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(result.changes[0].value, Value::<Regulated>::from(6));
+        assert!(result.is_balanced());
+        assert_eq!(balance_change.available_inputs().len(), 1);
+
+        let error = balance_change
+            .select_inputs(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(4)),
+                InputOutputSetup {
+                    input_balance: Value::from(10),
+                    output_balance: Value::from(9),
+                    change_address: Some(Address::new("kek")),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("minimum UTxO value"));
+    }
+
+    #[test]
+    fn change_split_by_strategy() {
+        let mut balance_change =
+            SingleOutputChangeBalancer::default().with_change_strategy(SplitChange::new(2));
+
+        let result = balance_change
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    input_balance: Value::from(30),
+                    output_balance: Value::from(9),
+                    change_address: Some(Address::new("kek")),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.changes.len(), 2);
+        assert_eq!(result.changes[0].value, Value::<Regulated>::from(11));
+        assert_eq!(result.changes[1].value, Value::<Regulated>::from(10));
+        assert!(result.is_balanced());
+    }
+
+    #[test]
+    fn excess_tokens_only() {
+        let mut balance_change = SingleOutputChangeBalancer::default();
+        balance_change
+            .set_available_inputs(vec![create_utxo(
+                1,
+                0,
+                "0".to_string(),
+                Value::<Regulated>::from(5),
+                vec![],
+            )])
+            .unwrap();
+
+        // the main asset is balanced but the change holding the tokens
+        // requires the minimum value
+        let result = balance_change
+            .select_inputs(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(2)),
+                InputOutputSetup {
+                    input_balance: Value::from(10),
+                    input_asset_balance: vec![create_asset("kek".to_string(), Value::from(7))]
+                        .into_iter()
+                        .collect(),
+                    output_balance: Value::from(10),
+                    change_address: Some(Address::new("kek")),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].value, Value::<Regulated>::from(5));
+        assert_eq!(
+            result.changes[0].assets,
+            vec![create_asset("kek".to_string(), Value::from(7))]
+        );
+        assert!(result.is_balanced());
+    }
+
+    #[test]
+    fn missing_tokens_selected() {
+        let mut balance_change = SingleOutputChangeBalancer::default();
+        balance_change
+            .set_available_inputs(vec![
+                create_utxo(1, 0, "0".to_string(), Value::<Regulated>::from(5), vec![]),
+                create_utxo(
+                    1,
+                    1,
+                    "0".to_string(),
+                    Value::<Regulated>::from(2),
+                    vec![
+                        create_asset("kek".to_string(), Value::from(10)),
+                        create_asset("lol".to_string(), Value::from(1)),
+                    ],
+                ),
+            ])
+            .unwrap();
+
+        let result = balance_change
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    input_balance: Value::from(10),
+                    output_balance: Value::from(9),
+                    output_asset_balance: vec![create_asset("kek".to_string(), Value::from(4))]
+                        .into_iter()
+                        .collect(),
+                    change_address: Some(Address::new("kek")),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(result.changes[0].value, Value::<Regulated>::from(3));
+        assert!(result.changes[0]
+            .assets
+            .contains(&create_asset("kek".to_string(), Value::from(6))));
+        assert!(result.changes[0]
+            .assets
+            .contains(&create_asset("lol".to_string(), Value::from(1))));
+        assert!(result.is_balanced());
+    }
 }
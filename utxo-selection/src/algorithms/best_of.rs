@@ -0,0 +1,201 @@
+use crate::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
+    UTxOStoreSupport, WasteMetric,
+};
+use anyhow::anyhow;
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, UTxOStore};
+
+/// run two algorithms and keep the result with the lowest waste (see
+/// [`WasteMetric`]), the first algorithm wins the ties
+///
+/// More algorithms are compared by nesting the selectors:
+/// `BestOfSelector::new(a, BestOfSelector::new(b, c))`. Both algorithms are
+/// given the same available inputs, the inputs chosen by the winner are
+/// removed from both.
+///
+/// The algorithms run on copies of the estimator (see
+/// [`TransactionFeeEstimator::fork`]), the estimator of the winner replaces
+/// the one given to the selection. The change addresses taken by the loser
+/// are given back to its provider.
+///
+/// The checkpoint of the change addresses holds the checkpoints of both
+/// algorithms, each on 32 bits. In nested selectors, the checkpoint of the
+/// first algorithm of the inner selector is lost: it can't take its change
+/// addresses from a provider.
+pub struct BestOfSelector<First, Second> {
+    first: First,
+    second: Second,
+    waste: WasteMetric,
+    /// the algorithm giving the next change address, the winner of the
+    /// last selection
+    first_won: bool,
+}
+
+impl<First, Second> BestOfSelector<First, Second> {
+    pub fn new(first: First, second: Second) -> Self {
+        Self {
+            first,
+            second,
+            waste: WasteMetric::default(),
+            first_won: true,
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_waste_metric(self, waste: WasteMetric) -> Self {
+        Self { waste, ..self }
+    }
+
+    pub fn into_inner(self) -> (First, Second) {
+        (self.first, self.second)
+    }
+}
+
+impl<First: UTxOStoreSupport, Second: UTxOStoreSupport> UTxOStoreSupport
+    for BestOfSelector<First, Second>
+{
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.second.set_available_utxos(utxos.clone())?;
+        self.first.set_available_utxos(utxos)
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        self.first.get_available_utxos()
+    }
+}
+
+impl<First, Second> InputSelectionAlgorithm for BestOfSelector<First, Second>
+where
+    First: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+    Second: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+{
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.second.set_available_inputs(available_inputs.clone())?;
+        self.first.set_available_inputs(available_inputs)
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let fork = || {
+            estimator
+                .fork()
+                .ok_or_else(|| anyhow!("The estimator can't be copied to compare the algorithms"))
+        };
+
+        let first_checkpoint = self.first.change_addresses_checkpoint();
+        let second_checkpoint = self.second.change_addresses_checkpoint();
+        let mut first_estimator = fork()?;
+        let first = self
+            .first
+            .select_inputs(&mut first_estimator, input_output_setup.clone());
+        let mut second_estimator = fork()?;
+        let second = self
+            .second
+            .select_inputs(&mut second_estimator, input_output_setup);
+
+        // the error of the first algorithm is returned if both fail
+        let first_wins = match (&first, &second) {
+            (Ok(first), Ok(second)) => {
+                self.waste.waste(&first_estimator, first)?
+                    <= self.waste.waste(&second_estimator, second)?
+            }
+            (_, Err(_)) => true,
+            (Err(_), Ok(_)) => false,
+        };
+
+        self.first_won = first_wins;
+        if first_wins {
+            *estimator = first_estimator;
+            self.second.rewind_change_addresses(second_checkpoint);
+            self.second
+                .set_available_utxos(self.first.get_available_utxos()?)?;
+            first
+        } else {
+            *estimator = second_estimator;
+            self.first.rewind_change_addresses(first_checkpoint);
+            self.first
+                .set_available_utxos(self.second.get_available_utxos()?)?;
+            second
+        }
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.first.available_inputs()
+    }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        let first = self.first.change_addresses_checkpoint() as u32;
+        let second = self.second.change_addresses_checkpoint() as u32;
+        (u64::from(first) << 32) | u64::from(second)
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        self.first.rewind_change_addresses(checkpoint >> 32);
+        self.second
+            .rewind_change_addresses(checkpoint & u64::from(u32::MAX));
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        if self.first_won {
+            self.first.next_change_address()
+        } else {
+            self.second.next_change_address()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::{Knapsack, LargestFirst};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use dcspark_core::{Regulated, Value};
+
+    fn inputs() -> Vec<UTxODetails> {
+        vec![
+            create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(100), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(10), vec![]),
+        ]
+    }
+
+    #[test]
+    fn lowest_waste_wins() {
+        // the largest first picks the input of 100, the knapsack the input
+        // of 10 matching the output without excess
+        let mut selector = BestOfSelector::new(
+            LargestFirst::try_from(inputs()).unwrap(),
+            Knapsack::try_from(inputs()).unwrap(),
+        );
+
+        let result = selector
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    output_balance: Value::from(10),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(result.input_balance, Value::from(10));
+        let (first, second) = selector.into_inner();
+        assert_eq!(first.available_inputs().len(), 1);
+        assert_eq!(first.available_inputs(), second.available_inputs());
+    }
+}
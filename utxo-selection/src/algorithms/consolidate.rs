@@ -0,0 +1,547 @@
+//! Consolidation of the small UTxOs
+//!
+//! The algorithm selects the UTxOs below a configured threshold, the
+//! smallest first, until the maximum number of inputs or the size limit of
+//! the transaction is reached. Everything that is not paid to the outputs
+//! is returned to the change address with one change output per token and
+//! the changes of the main asset built by the [`ChangeStrategy`] (a single
+//! change by default), the main asset goes to the fee instead when it is
+//! below the minimum value of an output.
+//!
+//! This is meant for scheduled maintenance transactions (usually without
+//! any output) keeping the number of UTxOs, and so the accumulators of the
+//! [`Thermostat`](super::Thermostat), under control.
+
+use crate::change_address::{checkpoint, next_change_address, next_provided_address, rewind};
+use crate::change_strategy::build_excess_changes;
+use crate::{
+    calculate_main_token_balance, restore_utxos, BoxedChangeAddressProvider, ChangeAddressProvider,
+    ChangeStrategy, InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, SingleChange,
+    TransactionFeeEstimator, UTxOStoreSupport,
+};
+use anyhow::{anyhow, bail};
+use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, Balance, Regulated, UTxOStore, Value};
+use deps::bigdecimal::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ConsolidateConfigFields")]
+pub struct ConsolidateConfig {
+    /// only the UTxOs with a main asset value strictly below this
+    /// threshold are consolidated
+    threshold: Value<Regulated>,
+    max_inputs: usize,
+}
+
+/// the fields of [`ConsolidateConfig`] before validation
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConsolidateConfigFields {
+    threshold: Value<Regulated>,
+    max_inputs: usize,
+}
+
+impl TryFrom<ConsolidateConfigFields> for ConsolidateConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: ConsolidateConfigFields) -> Result<Self, Self::Error> {
+        let config = ConsolidateConfig {
+            threshold: fields.threshold,
+            max_inputs: fields.max_inputs,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl Default for ConsolidateConfig {
+    fn default() -> Self {
+        ConsolidateConfig {
+            threshold: Value::<Regulated>::from(5_000_000),
+            max_inputs: 100,
+        }
+    }
+}
+
+impl ConsolidateConfig {
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_threshold(self, threshold: Value<Regulated>) -> Self {
+        Self { threshold, ..self }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_max_inputs(self, max_inputs: usize) -> Self {
+        Self { max_inputs, ..self }
+    }
+
+    /// check the settings are consistent, this is done when the config is
+    /// deserialized
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.threshold == Value::zero() {
+            bail!("The consolidation threshold must be above zero");
+        }
+        if self.max_inputs < 2 {
+            bail!("At least 2 inputs are required to consolidate");
+        }
+        Ok(())
+    }
+}
+
+pub struct Consolidate<S = SingleChange> {
+    available_inputs: UTxOStore,
+    config: ConsolidateConfig,
+    change_strategy: S,
+    change_addresses: Option<BoxedChangeAddressProvider>,
+}
+
+impl Consolidate {
+    pub fn new(config: ConsolidateConfig) -> Self {
+        Self {
+            available_inputs: UTxOStore::new(),
+            config,
+            change_strategy: SingleChange,
+            change_addresses: None,
+        }
+    }
+}
+
+impl<S: ChangeStrategy> Consolidate<S> {
+    /// return the main asset with the given strategy, the tokens are still
+    /// returned in one change per token
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_strategy<S2: ChangeStrategy>(self, change_strategy: S2) -> Consolidate<S2> {
+        Consolidate {
+            available_inputs: self.available_inputs,
+            config: self.config,
+            change_strategy,
+            change_addresses: self.change_addresses,
+        }
+    }
+
+    /// send each change to the next address of the `provider` instead of
+    /// the change address of the setup
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_addresses<P: ChangeAddressProvider + Send + 'static>(
+        self,
+        provider: P,
+    ) -> Self {
+        Self {
+            change_addresses: Some(Box::new(provider)),
+            ..self
+        }
+    }
+
+    /// the provider set with [`Consolidate::with_change_addresses`], e.g. to
+    /// persist its [`ChangeAddressProvider::next_index`] after a selection
+    pub fn change_addresses(&self) -> Option<&(dyn ChangeAddressProvider + Send)> {
+        self.change_addresses.as_deref()
+    }
+
+    /// the UTxOs to consolidate, the smallest first
+    fn candidates(&self, utxos: &UTxOStore) -> Vec<UTxODetails> {
+        let mut candidates = utxos
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .filter(|utxo| utxo.value < self.config.threshold)
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| {
+            a.value
+                .cmp(&b.value)
+                .then_with(|| a.pointer.cmp(&b.pointer))
+        });
+        candidates
+    }
+
+    /// create one change per token with the minimum value for the output and
+    /// the changes of the remaining main asset with the change strategy
+    ///
+    /// The remaining main asset is added to the fee if it can't pay for the
+    /// fee and the minimum value of a change.
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        change_address: &Address,
+        input_balance: &Value<Regulated>,
+        output_balance: &Value<Regulated>,
+        excess_assets: Vec<TransactionAsset>,
+        fee: &mut Value<Regulated>,
+    ) -> anyhow::Result<Vec<UTxOBuilder>> {
+        let mut output_balance = output_balance.clone();
+        let mut changes = vec![];
+        for asset in excess_assets {
+            let mut change = UTxOBuilder::new(
+                next_change_address(&mut self.change_addresses, change_address)?,
+                Value::zero(),
+                vec![asset],
+            );
+            change.value = estimator.min_value_for_output(change.clone())?;
+            *fee += estimator.fee_for_output(&change)?;
+            estimator.add_output(change.clone())?;
+
+            output_balance += &change.value;
+            changes.push(change);
+        }
+
+        let excess = match calculate_main_token_balance(input_balance, &output_balance, fee) {
+            Balance::Debt(debt) => bail!(
+                "not enough main asset to create the token changes and pay the fee: {debt} missing"
+            ),
+            Balance::Balanced => Value::zero(),
+            Balance::Excess(excess) => excess,
+        };
+
+        if excess > Value::zero() {
+            let main_changes = build_excess_changes(
+                &self.change_strategy,
+                estimator,
+                &mut self.change_addresses,
+                UTxOBuilder::new(change_address.clone(), excess.clone(), vec![]),
+            )?;
+            let main_value: Value<Regulated> =
+                main_changes.iter().map(|change| &change.value).sum();
+            *fee += &excess - &main_value;
+            changes.extend(main_changes);
+        }
+
+        Ok(changes)
+    }
+}
+
+fn to_usize(value: Value<Regulated>) -> anyhow::Result<usize> {
+    value
+        .to_usize()
+        .ok_or_else(|| anyhow!("can't estimate the size of the transaction"))
+}
+
+impl TryFrom<UTxOStore> for Consolidate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
+        Ok(Self {
+            available_inputs: value,
+            config: ConsolidateConfig::default(),
+            change_strategy: SingleChange,
+            change_addresses: None,
+        })
+    }
+}
+
+impl TryFrom<Vec<UTxODetails>> for Consolidate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
+        let mut store = UTxOStore::new().thaw();
+        for val in value {
+            store.insert(val)?;
+        }
+        Self::try_from(store.freeze())
+    }
+}
+
+impl<S: ChangeStrategy> UTxOStoreSupport for Consolidate<S> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.available_inputs = utxos;
+        Ok(())
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        Ok(self.available_inputs.clone())
+    }
+}
+
+impl<S: ChangeStrategy> InputSelectionAlgorithm for Consolidate<S> {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        let mut utxo_store = UTxOStore::new().thaw();
+        for input in available_inputs.into_iter() {
+            utxo_store.insert(input)?;
+        }
+        self.available_inputs = utxo_store.freeze();
+        Ok(())
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let (selectable, excluded) = input_output_setup.split_selectable(&self.available_inputs)?;
+
+        let change_address = input_output_setup
+            .change_address
+            .clone()
+            .ok_or_else(|| anyhow!("change address is not provided"))?;
+
+        let mut input_balance = input_output_setup.input_balance;
+        let mut input_asset_balance = input_output_setup.input_asset_balance;
+        let mut fee = estimator.min_required_fee()?;
+
+        // reserve the room for the changes, one per token and one for the
+        // main asset, estimated from the size of a pure change output
+        let size_of_one_output = to_usize(estimator.fee_for_output(&UTxOBuilder::new(
+            change_address.clone(),
+            Value::zero(),
+            vec![],
+        ))?)?;
+
+        let mut chosen_inputs = vec![];
+        let mut utxos = selectable.thaw();
+        for candidate in self.candidates(&selectable) {
+            if chosen_inputs.len() >= self.config.max_inputs {
+                break;
+            }
+
+            let mut tokens = input_asset_balance.len();
+            for asset in candidate.assets.iter() {
+                if !input_asset_balance.contains(&asset.fingerprint) {
+                    tokens += 1;
+                }
+            }
+            let reserved_room = size_of_one_output * (tokens + 1);
+            let size_of_input = to_usize(estimator.fee_for_input(&candidate)?)?;
+            let required_size = estimator
+                .current_size()?
+                .saturating_add(size_of_input)
+                .saturating_add(reserved_room);
+            if required_size > estimator.max_size()? {
+                break;
+            }
+
+            fee += estimator.fee_for_input(&candidate)?;
+            estimator.add_input(candidate.clone())?;
+            input_balance += &candidate.value;
+            input_asset_balance.extend(candidate.assets.iter());
+            utxos.remove(&candidate.pointer)?;
+            chosen_inputs.push(candidate);
+        }
+
+        if input_output_setup.fixed_inputs.len() + chosen_inputs.len() < 2 {
+            bail!(
+                "Not enough UTxOs below {threshold} to consolidate",
+                threshold = self.config.threshold
+            );
+        }
+
+        let mut excess_assets = input_asset_balance
+            .checked_sub(&input_output_setup.output_asset_balance)
+            .ok_or_else(|| anyhow!("there's lack of assets selected, can't consolidate"))?
+            .into_iter()
+            .map(|(_, asset)| asset)
+            .filter(|asset| asset.quantity > Value::zero())
+            .collect::<Vec<_>>();
+        excess_assets.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+        let start = checkpoint(&self.change_addresses);
+        let changes = match self.build_changes(
+            estimator,
+            &change_address,
+            &input_balance,
+            &input_output_setup.output_balance,
+            excess_assets,
+            &mut fee,
+        ) {
+            Ok(changes) => changes,
+            Err(error) => {
+                rewind(&mut self.change_addresses, start);
+                return Err(error);
+            }
+        };
+        let mut output_balance = input_output_setup.output_balance;
+        let mut output_asset_balance = input_output_setup.output_asset_balance;
+        for change in changes.iter() {
+            output_balance += &change.value;
+            output_asset_balance.extend(change.assets.iter());
+        }
+
+        self.available_inputs = restore_utxos(utxos.freeze(), excluded)?;
+
+        Ok(InputSelectionResult {
+            fixed_inputs: input_output_setup.fixed_inputs,
+            fixed_outputs: input_output_setup.fixed_outputs,
+            chosen_inputs,
+            changes,
+            input_balance,
+            output_balance,
+            fee,
+
+            input_asset_balance,
+            output_asset_balance,
+            trace: None,
+        }
+        .into_stable_order())
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.available_inputs
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>()
+    }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        checkpoint(&self.change_addresses)
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        rewind(&mut self.change_addresses, checkpoint);
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        next_provided_address(&mut self.change_addresses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{RoundRobinChangeAddresses, SplitChange};
+    use dcspark_core::{Address, TokenId};
+
+    fn inputs() -> Vec<UTxODetails> {
+        vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(3), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(1), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::from(2), vec![]),
+            create_utxo(0, 3, "0".to_string(), Value::from(100), vec![]),
+            create_utxo(
+                0,
+                4,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(5))],
+            ),
+            create_utxo(
+                0,
+                5,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(7))],
+            ),
+        ]
+    }
+
+    fn config(threshold: u64, max_inputs: usize) -> Consolidate {
+        let mut algorithm = Consolidate::new(ConsolidateConfig {
+            threshold: Value::from(threshold),
+            max_inputs,
+        });
+        algorithm.set_available_inputs(inputs()).unwrap();
+        algorithm
+    }
+
+    fn setup() -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup {
+            change_address: Some(Address::new("change")),
+            ..InputOutputSetup::default()
+        }
+    }
+
+    #[test]
+    fn one_change_per_token() {
+        let mut algorithm = config(10, 100);
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup())
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 5);
+        assert!(result
+            .chosen_inputs
+            .iter()
+            .all(|input| input.value < Value::from(100)));
+        assert_eq!(algorithm.available_inputs().len(), 1);
+
+        assert_eq!(result.changes.len(), 2);
+        assert_eq!(result.changes[0].assets.len(), 1);
+        assert_eq!(result.changes[0].assets[0].fingerprint, TokenId::new("kek"));
+        assert_eq!(result.changes[0].assets[0].quantity, Value::from(12));
+        assert!(result.changes[1].assets.is_empty());
+        assert_eq!(result.changes[1].value, Value::from(10));
+        assert_eq!(result.input_balance, result.output_balance);
+    }
+
+    #[test]
+    fn rotate_change_addresses() {
+        let addresses =
+            RoundRobinChangeAddresses::new(vec![Address::new("a"), Address::new("b")]).unwrap();
+        let mut algorithm = config(10, 100).with_change_addresses(addresses);
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup())
+            .unwrap();
+
+        assert_eq!(result.changes[0].address, Address::new("a"));
+        assert_eq!(result.changes[1].address, Address::new("b"));
+    }
+
+    #[test]
+    fn maximum_inputs() {
+        let mut algorithm = config(10, 2);
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup())
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 2);
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].value, Value::from(3));
+    }
+
+    #[test]
+    fn change_strategy() {
+        let mut algorithm = config(10, 100).with_change_strategy(SplitChange::new(2));
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup())
+            .unwrap();
+
+        // one change for kek, the main asset split in two
+        assert_eq!(result.changes.len(), 3);
+        assert_eq!(result.changes[0].assets[0].quantity, Value::from(12));
+        assert_eq!(result.changes[1].value, Value::from(5));
+        assert_eq!(result.changes[2].value, Value::from(5));
+        assert_eq!(result.input_balance, result.output_balance);
+    }
+
+    #[test]
+    fn main_change_below_minimum_goes_to_fee() {
+        let addresses =
+            RoundRobinChangeAddresses::new(vec![Address::new("a"), Address::new("b")]).unwrap();
+        let mut algorithm = config(10, 2).with_change_addresses(addresses);
+
+        let result = algorithm
+            .select_inputs(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(4)),
+                setup(),
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 2);
+        assert!(result.changes.is_empty());
+        assert_eq!(result.fee, Value::from(3));
+        assert_eq!(algorithm.change_addresses().unwrap().checkpoint(), 0);
+    }
+
+    #[test]
+    fn nothing_to_consolidate() {
+        let mut algorithm = config(2, 100);
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup())
+            .unwrap_err();
+        assert!(error.to_string().contains("Not enough UTxOs below 2"));
+        assert_eq!(algorithm.available_inputs().len(), inputs().len());
+    }
+}
@@ -0,0 +1,623 @@
+//! Knapsack selection: select the subset of the available UTxOs whose
+//! value is the closest above the target, so the change is minimal.
+//!
+//! The tokens of the outputs are covered first with the largest UTxOs
+//! holding them. The remaining main asset target is then searched with a
+//! depth first branch and bound over the UTxOs sorted by their value net
+//! of the input fee. The search is bounded:
+//!
+//! * by `max_iterations`, the best selection found so far is used once
+//!   the budget is exhausted;
+//! * by `max_inputs`, the number of inputs the transaction may hold;
+//! * by bucketing: the UTxOs whose value falls into the same bucket of
+//!   `bucket_size` are considered equivalent and only one of them is tried
+//!   at each step of the search;
+//! * by the [`SelectionBudget`], the selection fails once it is cancelled
+//!   or past its deadline.
+//!
+//! Unlike [`Thermostat`](super::Thermostat) the algorithm does not care
+//! about the shape of the UTxO set, it only minimizes the change (and so
+//! the fee of the transaction).
+
+use crate::algorithm::InputSelectionAlgorithm;
+use crate::change_strategy::balance_with_changes;
+use crate::common::{InputOutputSetup, InputSelectionResult};
+use crate::estimate::TransactionFeeEstimator;
+use crate::{
+    calculate_main_token_balance, ChangeStrategy, SelectionBudget, SingleChange, UTxOStoreSupport,
+};
+use anyhow::{anyhow, bail};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Regulated, TokenId, UTxOStore, Value};
+use deps::bigdecimal::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "KnapsackConfigFields")]
+pub struct KnapsackConfig {
+    max_inputs: usize,
+    max_iterations: usize,
+    bucket_size: u64,
+}
+
+/// the fields of [`KnapsackConfig`] before validation
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KnapsackConfigFields {
+    max_inputs: usize,
+    max_iterations: usize,
+    bucket_size: u64,
+}
+
+impl TryFrom<KnapsackConfigFields> for KnapsackConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: KnapsackConfigFields) -> Result<Self, Self::Error> {
+        let config = KnapsackConfig {
+            max_inputs: fields.max_inputs,
+            max_iterations: fields.max_iterations,
+            bucket_size: fields.bucket_size,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl KnapsackConfig {
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_max_inputs(self, max_inputs: usize) -> Self {
+        Self { max_inputs, ..self }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_max_iterations(self, max_iterations: usize) -> Self {
+        Self {
+            max_iterations,
+            ..self
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_bucket_size(self, bucket_size: u64) -> Self {
+        Self {
+            bucket_size,
+            ..self
+        }
+    }
+
+    /// check the settings are consistent, this is done when the config is
+    /// deserialized
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.max_inputs == 0 {
+            bail!("The knapsack requires at least one input");
+        }
+        if self.max_iterations == 0 {
+            bail!("The knapsack requires at least one iteration");
+        }
+        if self.bucket_size == 0 {
+            bail!("The bucket size of the knapsack must be above zero");
+        }
+        Ok(())
+    }
+}
+
+impl Default for KnapsackConfig {
+    fn default() -> Self {
+        KnapsackConfig {
+            max_inputs: 20,
+            max_iterations: 100_000,
+            bucket_size: 1,
+        }
+    }
+}
+
+/// the knapsack selection, the change is left to the balancer of the
+/// [`SelectionPipeline`](crate::SelectionPipeline) unless a
+/// [`ChangeStrategy`] is set with [`Knapsack::with_change_strategy`]
+pub struct Knapsack<S = SingleChange> {
+    available_inputs: Vec<UTxODetails>,
+    config: KnapsackConfig,
+    budget: SelectionBudget,
+    change_strategy: Option<S>,
+}
+
+impl Knapsack {
+    pub fn new(config: KnapsackConfig) -> Self {
+        Self {
+            available_inputs: vec![],
+            config,
+            budget: SelectionBudget::default(),
+            change_strategy: None,
+        }
+    }
+}
+
+impl<S: ChangeStrategy> Knapsack<S> {
+    /// stop the search once the `budget` is exhausted
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_budget(self, budget: SelectionBudget) -> Self {
+        Self { budget, ..self }
+    }
+
+    /// return the excess in the changes built with the given strategy, the
+    /// result of the selection is balanced
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_strategy<S2: ChangeStrategy>(self, change_strategy: S2) -> Knapsack<S2> {
+        Knapsack {
+            available_inputs: self.available_inputs,
+            config: self.config,
+            budget: self.budget,
+            change_strategy: Some(change_strategy),
+        }
+    }
+}
+
+/// the number of iterations of the search between two checks of the budget
+const BUDGET_CHECK_INTERVAL: usize = 1024;
+
+/// a UTxO considered by the search, with its value net of the input fee
+struct Candidate {
+    index: usize,
+    effective_value: u64,
+}
+
+/// the state of the branch and bound search over the candidates
+struct Search<'a> {
+    candidates: &'a [Candidate],
+    /// `remaining[i]` is the sum of the effective values of `candidates[i..]`
+    remaining: Vec<u64>,
+    target: u64,
+    max_depth: usize,
+    bucket_size: u64,
+    iterations: usize,
+    max_iterations: usize,
+    budget: &'a SelectionBudget,
+    exhausted: bool,
+
+    current: Vec<usize>,
+    /// the best selection so far, with its excess over the target
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        candidates: &'a [Candidate],
+        target: u64,
+        max_depth: usize,
+        config: &KnapsackConfig,
+        budget: &'a SelectionBudget,
+    ) -> Self {
+        let mut remaining = vec![0; candidates.len() + 1];
+        for (i, candidate) in candidates.iter().enumerate().rev() {
+            remaining[i] = remaining[i + 1].saturating_add(candidate.effective_value);
+        }
+
+        Self {
+            candidates,
+            remaining,
+            target,
+            max_depth,
+            bucket_size: config.bucket_size.max(1),
+            iterations: 0,
+            max_iterations: config.max_iterations,
+            budget,
+            exhausted: false,
+            current: vec![],
+            best: None,
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        matches!(self.best, Some((0, _)))
+    }
+
+    fn run(&mut self, start: usize, sum: u64) {
+        let mut last_bucket = None;
+
+        for position in start..self.candidates.len() {
+            if self.exhausted || self.iterations >= self.max_iterations || self.is_exact() {
+                return;
+            }
+            self.iterations += 1;
+            if self.iterations % BUDGET_CHECK_INTERVAL == 0 && self.budget.is_exhausted() {
+                self.exhausted = true;
+                return;
+            }
+
+            // the candidates are sorted, the ones left can't reach the target
+            if sum.saturating_add(self.remaining[position]) < self.target {
+                return;
+            }
+
+            let candidate = &self.candidates[position];
+            let bucket = candidate.effective_value / self.bucket_size;
+            if last_bucket == Some(bucket) {
+                continue;
+            }
+            last_bucket = Some(bucket);
+
+            let next = sum.saturating_add(candidate.effective_value);
+            self.current.push(position);
+            if next >= self.target {
+                let excess = next - self.target;
+                let is_better = match &self.best {
+                    None => true,
+                    Some((best_excess, best)) => {
+                        excess < *best_excess
+                            || (excess == *best_excess && self.current.len() < best.len())
+                    }
+                };
+                if is_better {
+                    self.best = Some((excess, self.current.clone()));
+                }
+            } else if self.current.len() < self.max_depth {
+                self.run(position + 1, next);
+            }
+            self.current.pop();
+        }
+    }
+}
+
+fn to_u64(value: &Value<Regulated>) -> anyhow::Result<u64> {
+    value
+        .to_u64()
+        .ok_or_else(|| anyhow!("Can't convert {value} to u64"))
+}
+
+impl TryFrom<UTxOStore> for Knapsack {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
+        // the store does not iterate in a stable order: sort the inputs so
+        // the selection is reproducible
+        let mut inputs = value
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>();
+        inputs.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        Self::try_from(inputs)
+    }
+}
+
+impl TryFrom<Vec<UTxODetails>> for Knapsack {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            available_inputs: value,
+            config: KnapsackConfig::default(),
+            budget: SelectionBudget::default(),
+            change_strategy: None,
+        })
+    }
+}
+
+impl<S: ChangeStrategy> UTxOStoreSupport for Knapsack<S> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        let mut inputs = utxos
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>();
+        inputs.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        self.available_inputs = inputs;
+        Ok(())
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        let mut store = UTxOStore::new().thaw();
+        for input in self.available_inputs.iter() {
+            store.insert(input.clone())?;
+        }
+        Ok(store.freeze())
+    }
+}
+
+impl<S: ChangeStrategy> InputSelectionAlgorithm for Knapsack<S> {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.available_inputs = available_inputs;
+        Ok(())
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        self.budget.check()?;
+
+        let selectable = self
+            .available_inputs
+            .iter()
+            .map(|input| input_output_setup.is_selectable(&input.pointer))
+            .collect::<Vec<_>>();
+
+        let mut input_balance = input_output_setup.input_balance;
+        let output_balance = input_output_setup.output_balance;
+        let mut fee = estimator.min_required_fee()?;
+
+        let mut asset_input_balance = input_output_setup.input_asset_balance;
+        let asset_output_balance = input_output_setup.output_asset_balance;
+
+        let mut chosen = vec![false; self.available_inputs.len()];
+        let mut chosen_inputs = vec![];
+
+        // cover the tokens first, with the largest UTxOs holding them
+        let mut tokens: Vec<&TokenId> = asset_output_balance.tokens().collect();
+        tokens.sort();
+        for token in tokens {
+            let required = asset_output_balance.quantity_of(token);
+            while asset_input_balance.quantity_of(token) < required {
+                let (index, input) = self
+                    .available_inputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, input)| {
+                        selectable[*index]
+                            && !chosen[*index]
+                            && input.assets.iter().any(|a| &a.fingerprint == token)
+                    })
+                    .max_by_key(|(_, input)| {
+                        input
+                            .assets
+                            .iter()
+                            .filter(|a| &a.fingerprint == token)
+                            .map(|a| &a.quantity)
+                            .sum::<Value<Regulated>>()
+                    })
+                    .ok_or_else(|| anyhow!("UTxO Balance Insufficient for {token}"))?;
+
+                chosen[index] = true;
+                fee += estimator.fee_for_input(input)?;
+                estimator.add_input(input.clone())?;
+                input_balance += &input.value;
+                asset_input_balance.extend(input.assets.iter());
+                chosen_inputs.push(input.clone());
+            }
+        }
+
+        // then search the main asset for the minimal change
+        let balance = calculate_main_token_balance(&input_balance, &output_balance, &fee);
+        if balance.in_debt() {
+            let target = to_u64(&(&output_balance + &fee).saturating_sub(&input_balance))?;
+
+            let mut candidates = vec![];
+            for (index, input) in self.available_inputs.iter().enumerate() {
+                if !selectable[index] || chosen[index] {
+                    continue;
+                }
+                let value = to_u64(&input.value)?;
+                let input_fee = to_u64(&estimator.fee_for_input(input)?)?;
+                if value > input_fee {
+                    candidates.push(Candidate {
+                        index,
+                        effective_value: value - input_fee,
+                    });
+                }
+            }
+            candidates.sort_by(|a, b| b.effective_value.cmp(&a.effective_value));
+
+            let max_depth = self.config.max_inputs.saturating_sub(chosen_inputs.len());
+            let mut search =
+                Search::new(&candidates, target, max_depth, &self.config, &self.budget);
+            search.run(0, 0);
+            if search.exhausted {
+                self.budget.check()?;
+            }
+
+            let (_, positions) = match search.best {
+                Some(best) => best,
+                None if search.remaining[0] < target => bail!("UTxO Balance Insufficient"),
+                None => bail!(
+                    "No selection found within {max_inputs} inputs",
+                    max_inputs = self.config.max_inputs
+                ),
+            };
+
+            for position in positions {
+                let index = candidates[position].index;
+                let input = &self.available_inputs[index];
+
+                chosen[index] = true;
+                fee += estimator.fee_for_input(input)?;
+                estimator.add_input(input.clone())?;
+                input_balance += &input.value;
+                asset_input_balance.extend(input.assets.iter());
+                chosen_inputs.push(input.clone());
+            }
+        }
+
+        let result = InputSelectionResult {
+            fixed_inputs: input_output_setup.fixed_inputs,
+            fixed_outputs: input_output_setup.fixed_outputs,
+            chosen_inputs,
+            changes: vec![],
+            input_balance,
+            output_balance,
+            fee,
+
+            input_asset_balance: asset_input_balance,
+            output_asset_balance: asset_output_balance,
+            trace: None,
+        };
+        let result = match &self.change_strategy {
+            Some(strategy) => balance_with_changes(
+                strategy,
+                estimator,
+                input_output_setup.change_address,
+                result,
+            )?,
+            None => result,
+        };
+
+        // the fixed inputs are spent by the transaction as well
+        let mut chosen = chosen.into_iter();
+        self.available_inputs.retain(|input| {
+            !chosen.next().unwrap_or_default()
+                && !result
+                    .fixed_inputs
+                    .iter()
+                    .any(|fixed| fixed.pointer == input.pointer)
+        });
+
+        Ok(result.into_stable_order())
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.available_inputs.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::SplitChange;
+    use dcspark_core::Address;
+
+    fn inputs() -> Vec<UTxODetails> {
+        vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(7), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::from(5), vec![]),
+            create_utxo(0, 3, "0".to_string(), Value::from(3), vec![]),
+            create_utxo(
+                0,
+                4,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(5))],
+            ),
+        ]
+    }
+
+    fn setup(
+        output_balance: u64,
+        assets: Vec<(&str, u64)>,
+    ) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup {
+            output_balance: Value::from(output_balance),
+            output_asset_balance: assets
+                .into_iter()
+                .map(|(token, quantity)| create_asset(token.to_string(), Value::from(quantity)))
+                .collect(),
+            ..InputOutputSetup::default()
+        }
+    }
+
+    fn selected_indices(result: &InputSelectionResult<UTxODetails, UTxOBuilder>) -> Vec<u64> {
+        let mut indices: Vec<u64> = result
+            .chosen_inputs
+            .iter()
+            .map(|input| u64::from(input.pointer.output_index))
+            .collect();
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn exact_match() {
+        let mut algorithm = Knapsack::try_from(inputs()).unwrap();
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(8, vec![]))
+            .unwrap();
+
+        // 5 + 3, no change
+        assert_eq!(selected_indices(&result), [2, 3]);
+        assert_eq!(result.input_balance, Value::from(8));
+        assert_eq!(algorithm.available_inputs().len(), inputs().len() - 2);
+    }
+
+    #[test]
+    fn tokens_are_covered_first() {
+        let mut algorithm = Knapsack::try_from(inputs()).unwrap();
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(9, vec![("kek", 5)]))
+            .unwrap();
+
+        // the kek UTxO brings 2, 7 completes the target exactly
+        assert_eq!(selected_indices(&result), [1, 4]);
+        assert_eq!(result.input_balance, Value::from(9));
+    }
+
+    #[test]
+    fn maximum_inputs() {
+        let mut algorithm = Knapsack::new(KnapsackConfig {
+            max_inputs: 1,
+            ..KnapsackConfig::default()
+        });
+        algorithm.set_available_inputs(inputs()).unwrap();
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(8, vec![]))
+            .unwrap();
+        assert_eq!(selected_indices(&result), [0]);
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(9, vec![]))
+            .unwrap_err();
+        assert!(error.to_string().contains("No selection found within 1"));
+    }
+
+    #[test]
+    fn balance_insufficient() {
+        let mut algorithm = Knapsack::try_from(inputs()).unwrap();
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(28, vec![]))
+            .unwrap_err();
+        assert!(error.to_string().contains("UTxO Balance Insufficient"));
+        assert_eq!(algorithm.available_inputs().len(), inputs().len());
+    }
+
+    #[test]
+    fn change_strategy() {
+        let mut algorithm = Knapsack::try_from(inputs())
+            .unwrap()
+            .with_change_strategy(SplitChange::new(2));
+        let setup = InputOutputSetup {
+            change_address: Some(Address::new("change")),
+            ..setup(6, vec![("kek", 1)])
+        };
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup)
+            .unwrap();
+
+        // the kek UTxO and 5 leave 1 and 4 kek, split in two changes
+        assert!(result.is_balanced());
+        assert_eq!(result.changes.len(), 2);
+        let tokens: Value<Regulated> = result
+            .changes
+            .iter()
+            .flat_map(|change| change.assets.iter())
+            .map(|asset| &asset.quantity)
+            .sum();
+        assert_eq!(tokens, Value::from(4));
+    }
+
+    #[test]
+    fn cancelled_budget() {
+        let budget = SelectionBudget::new();
+        let mut algorithm = Knapsack::try_from(inputs())
+            .unwrap()
+            .with_budget(budget.clone());
+        budget.cancel();
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(8, vec![]))
+            .unwrap_err();
+        assert!(error.downcast_ref::<crate::SelectionCancelled>().is_some());
+        assert_eq!(algorithm.available_inputs().len(), inputs().len());
+    }
+}
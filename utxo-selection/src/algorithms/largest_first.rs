@@ -1,14 +1,32 @@
 use crate::algorithm::InputSelectionAlgorithm;
 use crate::common::{InputOutputSetup, InputSelectionResult};
 use crate::estimate::TransactionFeeEstimator;
-use crate::{calculate_main_token_balance, UTxOStoreSupport};
+use crate::{
+    assets_in_selection_order, calculate_main_token_balance, restore_utxos, CandidateScorer,
+    UTxOStoreSupport,
+};
 use anyhow::anyhow;
-use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
-use dcspark_core::{Regulated, TokenId, UTxOStore};
-use std::collections::HashMap;
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{AssetId, Regulated, TokenId, UTxOStore};
 
 pub struct LargestFirst {
     available_inputs: UTxOStore,
+    scorer: Option<Box<dyn CandidateScorer + Send + Sync>>,
+}
+
+impl LargestFirst {
+    /// select the candidates of highest score first, the largest of them
+    /// on a tie
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_scorer<S>(self, scorer: S) -> Self
+    where
+        S: CandidateScorer + Send + Sync + 'static,
+    {
+        Self {
+            scorer: Some(Box::new(scorer)),
+            ..self
+        }
+    }
 }
 
 impl TryFrom<UTxOStore> for LargestFirst {
@@ -17,6 +35,7 @@ impl TryFrom<UTxOStore> for LargestFirst {
     fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
         Ok(Self {
             available_inputs: value,
+            scorer: None,
         })
     }
 }
@@ -31,6 +50,7 @@ impl TryFrom<Vec<UTxODetails>> for LargestFirst {
         }
         Ok(Self {
             available_inputs: store.freeze(),
+            scorer: None,
         })
     }
 }
@@ -69,6 +89,8 @@ impl InputSelectionAlgorithm for LargestFirst {
         estimator: &mut Estimate,
         input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
     ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let (mut utxos, excluded) = input_output_setup.split_selectable(&self.available_inputs)?;
+
         let mut input_balance = input_output_setup.input_balance;
         let output_balance = input_output_setup.output_balance;
         let mut fee = estimator.min_required_fee()?;
@@ -78,23 +100,19 @@ impl InputSelectionAlgorithm for LargestFirst {
 
         let mut selected_inputs: Vec<UTxODetails> = vec![];
 
-        let mut utxos = self.available_inputs.clone();
-
-        for (token, token_output_balance) in asset_output_balance.iter() {
-            let mut token_input_balance = asset_input_balance
-                .entry(token.clone())
-                .or_insert(TransactionAsset::new(
-                    token_output_balance.policy_id.clone(),
-                    token_output_balance.asset_name.clone(),
-                    token_output_balance.fingerprint.clone(),
-                ))
-                .quantity
-                .clone();
+        // the inputs selected for the previous assets may already hold
+        // some of the next ones
+        for token_output_balance in assets_in_selection_order(&asset_output_balance) {
+            let token = &token_output_balance.fingerprint;
+            let mut token_input_balance = asset_input_balance.quantity_of(token);
 
             while token_input_balance < token_output_balance.quantity {
                 let (new_selected_inputs, new_utxos) = select_input_and_update_balances(
                     token,
                     utxos.clone(),
+                    self.scorer.as_deref(),
+                    &input_output_setup.fixed_inputs,
+                    &selected_inputs,
                     estimator,
                     &mut asset_input_balance,
                     &mut token_input_balance,
@@ -109,6 +127,9 @@ impl InputSelectionAlgorithm for LargestFirst {
         while calculate_main_token_balance(&input_balance, &output_balance, &fee).in_debt() {
             let (new_selected_inputs, new_utxos) = select_input_and_update_balances_for_main(
                 utxos.clone(),
+                self.scorer.as_deref(),
+                &input_output_setup.fixed_inputs,
+                &selected_inputs,
                 estimator,
                 &mut asset_input_balance,
                 &mut input_balance,
@@ -118,7 +139,7 @@ impl InputSelectionAlgorithm for LargestFirst {
             utxos = new_utxos;
         }
 
-        self.available_inputs = utxos;
+        self.available_inputs = restore_utxos(utxos, excluded)?;
 
         Ok(InputSelectionResult {
             fixed_inputs: input_output_setup.fixed_inputs,
@@ -131,7 +152,9 @@ impl InputSelectionAlgorithm for LargestFirst {
 
             input_asset_balance: asset_input_balance,
             output_asset_balance: asset_output_balance,
-        })
+            trace: None,
+        }
+        .into_stable_order())
     }
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo> {
@@ -142,36 +165,38 @@ impl InputSelectionAlgorithm for LargestFirst {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn select_input_and_update_balances<
     Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
 >(
     token: &TokenId,
     utxos: UTxOStore,
+    scorer: Option<&(dyn CandidateScorer + Send + Sync)>,
+    fixed_inputs: &[UTxODetails],
+    selected_inputs: &[UTxODetails],
     estimator: &mut Estimate,
-    asset_input_balance: &mut HashMap<TokenId, TransactionAsset>,
+    asset_input_balance: &mut MultiAssetValue,
     input_token_balance: &mut dcspark_core::Value<Regulated>,
     input_total: &mut dcspark_core::Value<Regulated>,
     fee: &mut dcspark_core::Value<Regulated>,
 ) -> anyhow::Result<(Vec<UTxODetails>, UTxOStore)> {
     let mut selected_inputs: Vec<UTxODetails> = vec![];
 
-    let (selected, new_utxos) = select_largest_input_for(utxos, token)?;
+    let (selected, new_utxos) = select_preferred_input_for(
+        utxos,
+        &AssetId::Token(token.clone()),
+        scorer,
+        fixed_inputs,
+        selected_inputs,
+    )?;
 
     *input_total += &selected.value;
     for asset in selected.assets.iter() {
-        if token != &TokenId::MAIN && &asset.fingerprint == token {
+        if &asset.fingerprint == token {
             *input_token_balance += &asset.quantity;
         }
-
-        let current_input_asset = asset_input_balance
-            .entry(asset.fingerprint.clone())
-            .or_insert(TransactionAsset::new(
-                asset.policy_id.clone(),
-                asset.asset_name.clone(),
-                asset.fingerprint.clone(),
-            ));
-        current_input_asset.quantity += &asset.quantity;
     }
+    asset_input_balance.extend(selected.assets.iter());
 
     *fee += estimator.fee_for_input(&selected)?;
     selected_inputs.push(selected.clone());
@@ -180,30 +205,31 @@ fn select_input_and_update_balances<
     Ok((selected_inputs, new_utxos))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn select_input_and_update_balances_for_main<
     Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
 >(
     utxos: UTxOStore,
+    scorer: Option<&(dyn CandidateScorer + Send + Sync)>,
+    fixed_inputs: &[UTxODetails],
+    selected_inputs: &[UTxODetails],
     estimator: &mut Estimate,
-    asset_input_balance: &mut HashMap<TokenId, TransactionAsset>,
+    asset_input_balance: &mut MultiAssetValue,
     input_total: &mut dcspark_core::Value<Regulated>,
     fee: &mut dcspark_core::Value<Regulated>,
 ) -> anyhow::Result<(Vec<UTxODetails>, UTxOStore)> {
     let mut selected_inputs: Vec<UTxODetails> = vec![];
 
-    let (selected, new_utxos) = select_largest_input_for(utxos, &TokenId::MAIN)?;
+    let (selected, new_utxos) = select_preferred_input_for(
+        utxos,
+        &AssetId::Native,
+        scorer,
+        fixed_inputs,
+        selected_inputs,
+    )?;
 
     *input_total += &selected.value;
-    for asset in selected.assets.iter() {
-        let current_input_asset = asset_input_balance
-            .entry(asset.fingerprint.clone())
-            .or_insert(TransactionAsset::new(
-                asset.policy_id.clone(),
-                asset.asset_name.clone(),
-                asset.fingerprint.clone(),
-            ));
-        current_input_asset.quantity += &asset.quantity;
-    }
+    asset_input_balance.extend(selected.assets.iter());
 
     *fee += estimator.fee_for_input(&selected)?;
     selected_inputs.push(selected.clone());
@@ -214,7 +240,7 @@ fn select_input_and_update_balances_for_main<
 
 pub fn select_largest_input_for(
     utxos: UTxOStore,
-    asset: &TokenId,
+    asset: &AssetId,
 ) -> anyhow::Result<(UTxODetails, UTxOStore)> {
     let utxo = utxos
         // here we take the largest available UTxO for this given
@@ -229,14 +255,53 @@ pub fn select_largest_input_for(
     Ok((utxo, utxos.freeze()))
 }
 
+/// the largest input for `asset` among the ones of highest score
+fn select_preferred_input_for(
+    utxos: UTxOStore,
+    asset: &AssetId,
+    scorer: Option<&(dyn CandidateScorer + Send + Sync)>,
+    fixed_inputs: &[UTxODetails],
+    selected_inputs: &[UTxODetails],
+) -> anyhow::Result<(UTxODetails, UTxOStore)> {
+    let scorer = match scorer {
+        Some(scorer) => scorer,
+        None => return select_largest_input_for(utxos, asset),
+    };
+
+    let selected = fixed_inputs
+        .iter()
+        .chain(selected_inputs)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut preferred: Option<(u64, &UTxODetails)> = None;
+    for utxo in utxos.iter_token_ordered_by_value_rev(asset) {
+        let score = scorer.score(utxo, &selected);
+        // strictly higher: the first (largest) one is kept on a tie
+        let better = match preferred {
+            Some((best, _)) => score > best,
+            None => true,
+        };
+        if better {
+            preferred = Some((score, utxo));
+        }
+    }
+    let utxo = preferred
+        .map(|(_, utxo)| utxo.clone())
+        .ok_or_else(|| anyhow!("No more input to select for {asset}"))?;
+
+    let mut utxos = utxos.thaw();
+    utxos.remove(&utxo.pointer)?;
+    Ok((utxo, utxos.freeze()))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::algorithms::test_utils::{create_asset, create_utxo};
     use crate::algorithms::LargestFirst;
     use crate::estimators::dummy_estimator::DummyFeeEstimate;
-    use crate::{InputOutputSetup, InputSelectionAlgorithm};
+    use crate::{CoinAgeScorer, InputOutputSetup, InputSelectionAlgorithm};
     use dcspark_core::tx::TransactionAsset;
-    use dcspark_core::{OutputIndex, Regulated, TokenId, UTxOStore, Value};
+    use dcspark_core::{BlockNumber, OutputIndex, Regulated, TokenId, UTxOStore, Value};
     use std::collections::HashMap;
 
     #[test]
@@ -284,6 +349,8 @@ mod tests {
                     fixed_inputs: vec![],
                     fixed_outputs: vec![],
                     change_address: None,
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -420,10 +487,12 @@ mod tests {
                     input_balance: Default::default(),
                     input_asset_balance: Default::default(),
                     output_balance: Value::from(24),
-                    output_asset_balance,
+                    output_asset_balance: output_asset_balance.into(),
                     fixed_inputs: vec![],
                     fixed_outputs: vec![],
                     change_address: None,
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -432,11 +501,119 @@ mod tests {
         assert!(result.input_balance >= result.output_balance);
         assert!(result
             .input_asset_balance
-            .values()
+            .assets()
             .any(|asset: &TransactionAsset| asset.quantity == Value::from(402)));
         assert!(result
             .input_asset_balance
-            .values()
+            .assets()
             .any(|asset: &TransactionAsset| asset.quantity == Value::from(502)));
     }
+
+    #[test]
+    fn excluded_inputs_remain_available() {
+        let mut largest_first = LargestFirst::try_from(vec![
+            create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::<Regulated>::from(11), vec![]),
+        ])
+        .unwrap();
+        let excluded = create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]);
+
+        let result = largest_first
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    output_balance: Value::from(1),
+                    excluded_inputs: [excluded.pointer.clone()].into_iter().collect(),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(
+            result.chosen_inputs.first().unwrap().pointer.output_index,
+            OutputIndex::new(2)
+        );
+        assert!(largest_first
+            .available_inputs()
+            .iter()
+            .any(|input| input.pointer == excluded.pointer));
+        assert_eq!(largest_first.available_inputs().len(), 2);
+    }
+
+    #[test]
+    fn older_inputs_first() {
+        let mut old = create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(10), vec![]);
+        old.creation_height = Some(BlockNumber::new(10));
+        let mut recent = create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]);
+        recent.creation_height = Some(BlockNumber::new(90));
+        let mut largest_first = LargestFirst::try_from(vec![old, recent])
+            .unwrap()
+            .with_scorer(CoinAgeScorer::new(BlockNumber::new(100)));
+
+        let result = largest_first
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    output_balance: Value::from(5),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(
+            result.chosen_inputs.first().unwrap().pointer.output_index,
+            OutputIndex::new(0)
+        );
+    }
+
+    #[test]
+    fn input_covers_several_assets() {
+        let mut largest_first = LargestFirst::try_from(vec![
+            create_utxo(
+                0,
+                0,
+                "0".to_string(),
+                Value::<Regulated>::from(2),
+                vec![
+                    create_asset("kek".to_string(), Value::from(5)),
+                    create_asset("lol".to_string(), Value::from(5)),
+                ],
+            ),
+            create_utxo(
+                0,
+                1,
+                "0".to_string(),
+                Value::<Regulated>::from(2),
+                vec![create_asset("lol".to_string(), Value::from(3))],
+            ),
+            create_utxo(0, 2, "0".to_string(), Value::<Regulated>::from(20), vec![]),
+        ])
+        .unwrap();
+
+        let result = largest_first
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    output_balance: Value::from(2),
+                    output_asset_balance: vec![
+                        create_asset("kek".to_string(), Value::from(4)),
+                        create_asset("lol".to_string(), Value::from(4)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                    ..InputOutputSetup::default()
+                },
+            )
+            .unwrap();
+
+        // the input selected for kek holds enough lol and main asset
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(
+            result.chosen_inputs[0].pointer.output_index,
+            OutputIndex::new(0)
+        );
+    }
 }
@@ -0,0 +1,434 @@
+//! Largest-First selection following the multi-asset rules of
+//! [CIP-2](https://cips.cardano.org/cips/cip2/).
+//!
+//! For every asset of the outputs, the available UTxOs holding this asset
+//! are considered in descending order of quantity and selected until the
+//! asset is covered. The main asset is covered last, so the inputs already
+//! selected for the tokens contribute to it.
+//!
+//! Divergences from the specification:
+//!
+//! * the main asset must cover the outputs **and** the fee returned by the
+//!   [`TransactionFeeEstimator`], CIP-2 leaves the fee to a later stage;
+//! * no change output is generated, this is left to the `balance_change_*`
+//!   algorithms (CIP-2 generates the change as part of the selection),
+//!   unless a [`ChangeStrategy`] is set with
+//!   [`LargestFirstMultiAsset::with_change_strategy`];
+//! * the tokens are covered in [`TokenId`] order so the selection is
+//!   reproducible, CIP-2 does not specify an order.
+
+use crate::algorithm::InputSelectionAlgorithm;
+use crate::algorithms::select_largest_input_for;
+use crate::change_strategy::balance_with_changes;
+use crate::common::{InputOutputSetup, InputSelectionResult};
+use crate::estimate::TransactionFeeEstimator;
+use crate::{
+    calculate_main_token_balance, restore_utxos, ChangeStrategy, SingleChange, UTxOStoreSupport,
+};
+use anyhow::{bail, Context as _};
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{AssetId, Regulated, TokenId, UTxOStore, Value};
+
+pub struct LargestFirstMultiAsset<S = SingleChange> {
+    available_inputs: UTxOStore,
+    max_input_count: Option<usize>,
+    change_strategy: Option<S>,
+}
+
+/// the inputs selected so far and the resulting balances
+struct Selection {
+    chosen_inputs: Vec<UTxODetails>,
+    input_balance: Value<Regulated>,
+    input_asset_balance: MultiAssetValue,
+    fee: Value<Regulated>,
+}
+
+impl<S: ChangeStrategy> LargestFirstMultiAsset<S> {
+    /// limit the number of inputs the algorithm may select, the
+    /// selection fails with `Maximum input count exceeded` otherwise
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_max_input_count(mut self, max_input_count: usize) -> Self {
+        self.max_input_count = Some(max_input_count);
+        self
+    }
+
+    /// return the excess in the changes built with the given strategy, the
+    /// result of the selection is balanced
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_strategy<S2: ChangeStrategy>(
+        self,
+        change_strategy: S2,
+    ) -> LargestFirstMultiAsset<S2> {
+        LargestFirstMultiAsset {
+            available_inputs: self.available_inputs,
+            max_input_count: self.max_input_count,
+            change_strategy: Some(change_strategy),
+        }
+    }
+
+    fn select_largest<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        asset: &AssetId,
+        utxos: UTxOStore,
+        estimator: &mut Estimate,
+        selection: &mut Selection,
+    ) -> anyhow::Result<UTxOStore> {
+        if let Some(max_input_count) = self.max_input_count {
+            if selection.chosen_inputs.len() >= max_input_count {
+                bail!("Maximum input count ({max_input_count}) exceeded while covering {asset}");
+            }
+        }
+
+        let (selected, utxos) = select_largest_input_for(utxos, asset)?;
+
+        selection.input_balance += &selected.value;
+        selection.input_asset_balance.extend(selected.assets.iter());
+        selection.fee += estimator.fee_for_input(&selected)?;
+        selection.chosen_inputs.push(selected.clone());
+        estimator.add_input(selected)?;
+
+        Ok(utxos)
+    }
+}
+
+/// check the available UTxOs hold enough of every asset of the outputs
+/// before selecting anything
+fn check_balance_sufficient(
+    utxos: &UTxOStore,
+    setup: &InputOutputSetup<UTxODetails, UTxOBuilder>,
+) -> anyhow::Result<()> {
+    for (token, output) in setup.output_asset_balance.iter() {
+        let available = utxos
+            .get_balance_of(&AssetId::Token(token.clone()))
+            .unwrap_or_default()
+            + setup.input_asset_balance.quantity_of(token);
+        if available < output.quantity {
+            bail!(
+                "UTxO balance insufficient for {token}: {available} available, {required} required",
+                required = output.quantity
+            );
+        }
+    }
+
+    let available =
+        utxos.get_balance_of(&AssetId::Native).unwrap_or_default() + &setup.input_balance;
+    if available < setup.output_balance {
+        bail!(
+            "UTxO balance insufficient: {available} available, {required} required",
+            required = setup.output_balance
+        );
+    }
+
+    Ok(())
+}
+
+impl TryFrom<UTxOStore> for LargestFirstMultiAsset {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
+        Ok(Self {
+            available_inputs: value,
+            max_input_count: None,
+            change_strategy: None,
+        })
+    }
+}
+
+impl TryFrom<Vec<UTxODetails>> for LargestFirstMultiAsset {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
+        let mut store = UTxOStore::new().thaw();
+        for val in value {
+            store.insert(val)?;
+        }
+        Self::try_from(store.freeze())
+    }
+}
+
+impl<S: ChangeStrategy> UTxOStoreSupport for LargestFirstMultiAsset<S> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.available_inputs = utxos;
+        Ok(())
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        Ok(self.available_inputs.clone())
+    }
+}
+
+impl<S: ChangeStrategy> InputSelectionAlgorithm for LargestFirstMultiAsset<S> {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        let mut utxo_store = UTxOStore::new().thaw();
+        for input in available_inputs.into_iter() {
+            utxo_store.insert(input)?;
+        }
+        self.available_inputs = utxo_store.freeze();
+        Ok(())
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let (mut utxos, excluded) = input_output_setup.split_selectable(&self.available_inputs)?;
+        check_balance_sufficient(&utxos, &input_output_setup)?;
+
+        let mut selection = Selection {
+            chosen_inputs: vec![],
+            input_balance: input_output_setup.input_balance,
+            input_asset_balance: input_output_setup.input_asset_balance,
+            fee: estimator.min_required_fee()?,
+        };
+
+        let mut tokens: Vec<&TokenId> = input_output_setup.output_asset_balance.tokens().collect();
+        tokens.sort();
+
+        for token in tokens {
+            let required = input_output_setup.output_asset_balance.quantity_of(token);
+            let asset = AssetId::Token(token.clone());
+
+            while selection.input_asset_balance.quantity_of(token) < required {
+                utxos = self.select_largest(&asset, utxos, estimator, &mut selection)?;
+            }
+        }
+
+        // coin covering: the main asset is selected last
+        while calculate_main_token_balance(
+            &selection.input_balance,
+            &input_output_setup.output_balance,
+            &selection.fee,
+        )
+        .in_debt()
+        {
+            utxos = self
+                .select_largest(&AssetId::Native, utxos, estimator, &mut selection)
+                .context("Could not cover the fee")?;
+        }
+
+        let result = InputSelectionResult {
+            fixed_inputs: input_output_setup.fixed_inputs,
+            fixed_outputs: input_output_setup.fixed_outputs,
+            chosen_inputs: selection.chosen_inputs,
+            changes: vec![],
+            input_balance: selection.input_balance,
+            output_balance: input_output_setup.output_balance,
+            fee: selection.fee,
+
+            input_asset_balance: selection.input_asset_balance,
+            output_asset_balance: input_output_setup.output_asset_balance,
+            trace: None,
+        };
+        let result = match &self.change_strategy {
+            Some(strategy) => balance_with_changes(
+                strategy,
+                estimator,
+                input_output_setup.change_address,
+                result,
+            )?,
+            None => result,
+        };
+
+        self.available_inputs = restore_utxos(utxos, excluded)?;
+
+        Ok(result.into_stable_order())
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.available_inputs
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{
+        ConstrainedEstimator, ConstraintViolation, DenominationChange, SelectionConstraints,
+    };
+    use dcspark_core::Address;
+
+    fn store() -> Vec<UTxODetails> {
+        vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(30), vec![]),
+            create_utxo(
+                0,
+                2,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(5))],
+            ),
+            create_utxo(
+                0,
+                3,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(50))],
+            ),
+            create_utxo(
+                0,
+                4,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("lol".to_string(), Value::from(7))],
+            ),
+        ]
+    }
+
+    fn setup(
+        output_balance: u64,
+        assets: Vec<(&str, u64)>,
+    ) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup {
+            output_balance: Value::from(output_balance),
+            output_asset_balance: assets
+                .into_iter()
+                .map(|(token, quantity)| create_asset(token.to_string(), Value::from(quantity)))
+                .collect(),
+            ..InputOutputSetup::default()
+        }
+    }
+
+    fn selected_indices(result: &InputSelectionResult<UTxODetails, UTxOBuilder>) -> Vec<u64> {
+        result
+            .chosen_inputs
+            .iter()
+            .map(|input| u64::from(input.pointer.output_index))
+            .collect()
+    }
+
+    #[test]
+    fn covers_each_asset_with_the_largest_first() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store()).unwrap();
+
+        let result = algorithm
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                setup(3, vec![("kek", 10), ("lol", 7)]),
+            )
+            .unwrap();
+
+        // kek is covered by the largest kek UTxO alone, the main asset is
+        // already covered by the token inputs
+        assert_eq!(selected_indices(&result), [3, 4]);
+        assert!(result.input_balance >= result.output_balance);
+        assert_eq!(
+            result.input_asset_balance.quantity_of(&TokenId::new("kek")),
+            Value::from(50)
+        );
+        assert_eq!(
+            algorithm.available_inputs().len(),
+            store().len() - result.chosen_inputs.len()
+        );
+    }
+
+    #[test]
+    fn coin_covering() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store()).unwrap();
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(35, vec![("lol", 1)]))
+            .unwrap();
+
+        assert_eq!(selected_indices(&result), [4, 1, 0]);
+        assert_eq!(result.input_balance, Value::from(42));
+    }
+
+    #[test]
+    fn coin_covering_constraint_violation() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store()).unwrap();
+        let mut estimator = ConstrainedEstimator::new(
+            DummyFeeEstimate::new(),
+            SelectionConstraints {
+                max_inputs: Some(1),
+                ..SelectionConstraints::default()
+            },
+        );
+
+        let error = algorithm
+            .select_inputs(&mut estimator, setup(35, vec![("lol", 1)]))
+            .unwrap_err();
+        assert!(error.to_string().contains("Could not cover the fee"));
+        assert_eq!(
+            error.downcast_ref::<ConstraintViolation>(),
+            Some(&ConstraintViolation::TooManyInputs { max: 1 })
+        );
+    }
+
+    #[test]
+    fn change_strategy() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store())
+            .unwrap()
+            .with_change_strategy(DenominationChange::new(Value::from(5), 10));
+        let setup = InputOutputSetup {
+            change_address: Some(Address::new("change")),
+            ..setup(35, vec![("lol", 1)])
+        };
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup)
+            .unwrap();
+
+        // the excess of 7 is returned in a change of 5 and the remainder
+        // holding the 6 lol
+        assert!(result.is_balanced());
+        assert_eq!(result.changes.len(), 2);
+        assert!(result
+            .changes
+            .iter()
+            .any(|change| change.value == Value::from(5) && change.assets.is_empty()));
+        assert!(result
+            .changes
+            .iter()
+            .any(|change| change.value == Value::from(2)
+                && change.assets[0].quantity == Value::from(6)));
+    }
+
+    #[test]
+    fn balance_insufficient() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store()).unwrap();
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(1, vec![("kek", 56)]))
+            .unwrap_err();
+        assert!(error.to_string().contains("UTxO balance insufficient"));
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(47, vec![]))
+            .unwrap_err();
+        assert!(error.to_string().contains("UTxO balance insufficient"));
+
+        // nothing was selected
+        assert_eq!(algorithm.available_inputs().len(), store().len());
+    }
+
+    #[test]
+    fn maximum_input_count() {
+        let mut algorithm = LargestFirstMultiAsset::try_from(store())
+            .unwrap()
+            .with_max_input_count(1);
+
+        let error = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(1, vec![("kek", 55)]))
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Maximum input count (1) exceeded"));
+    }
+}
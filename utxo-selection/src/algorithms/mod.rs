@@ -1,12 +1,24 @@
 mod balance_change_fee;
 mod balance_change_single_output;
+mod best_of;
+mod consolidate;
+mod knapsack;
 mod largest_first;
+mod largest_first_multi_asset;
+#[cfg(test)]
+pub(crate) mod properties;
 mod random_improve;
-mod test_utils;
+mod single_random_draw;
+pub(crate) mod test_utils;
 mod thermostat;
 
 pub use balance_change_fee::*;
 pub use balance_change_single_output::*;
+pub use best_of::*;
+pub use consolidate::*;
+pub use knapsack::*;
 pub use largest_first::*;
+pub use largest_first_multi_asset::*;
 pub use random_improve::*;
+pub use single_random_draw::*;
 pub use thermostat::*;
@@ -0,0 +1,432 @@
+//! Properties every selection algorithm must hold
+//!
+//! The [`Scenario`]s are random wallets and payments, [`check_selection`]
+//! runs an algorithm on a scenario (followed by the change balancer, see
+//! [`SelectionPipeline`]) and checks the result:
+//!
+//! * the result is balanced, see [`check_balanced`];
+//! * no input is used twice, nor left available after the selection;
+//! * the fee covers the minimum fee of the estimator;
+//! * the changes hold the minimum value required for an output.
+//!
+//! The scenarios are discarded only when the algorithm runs out of inputs
+//! (see [`is_infeasible`]), any other error fails the property. The
+//! collateral selection is checked by [`check_collateral`].
+//!
+//! A new algorithm is covered by adding its property at the end of the file.
+
+use crate::algorithms::test_utils::{create_asset, create_utxo};
+use crate::algorithms::{
+    Consolidate, ConsolidateConfig, Knapsack, LargestFirst, LargestFirstMultiAsset, RandomImprove,
+    SingleOutputChangeBalancer, SingleRandomDraw, Thermostat, ThermostatAlgoConfig,
+};
+use crate::verify::check_balanced;
+use crate::{
+    select_collateral, CollateralSetup, InputOutputSetup, InputSelectionAlgorithm,
+    InputSelectionResult, SelectionPipeline, TransactionFeeEstimator, UTxOStoreSupport,
+};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, Regulated, TokenId, UTxOStore, Value};
+use deps::bigdecimal::ToPrimitive;
+use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+use std::collections::HashSet;
+
+const TOKENS: [&str; 3] = ["tokenA", "tokenB", "tokenC"];
+const BASE_FEE: u64 = 10;
+const FEE_PER_UTXO: u64 = 2;
+const MIN_VALUE_FOR_OUTPUT: u64 = 5;
+
+/// a fee of [`BASE_FEE`] plus [`FEE_PER_UTXO`] for every input and output
+/// added, every output requires [`MIN_VALUE_FOR_OUTPUT`]
+#[derive(Debug, Clone, Default)]
+pub struct LinearFeeEstimate {
+    utxos: u64,
+}
+
+impl TransactionFeeEstimator for LinearFeeEstimate {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
+        Ok(Value::from(BASE_FEE + FEE_PER_UTXO * self.utxos))
+    }
+
+    fn fee_for_input(&self, _input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
+        Ok(Value::from(FEE_PER_UTXO))
+    }
+
+    fn add_input(&mut self, _input: Self::InputUtxo) -> anyhow::Result<()> {
+        self.utxos += 1;
+        Ok(())
+    }
+
+    fn fee_for_output(&self, _output: &Self::OutputUtxo) -> anyhow::Result<Value<Regulated>> {
+        Ok(Value::from(FEE_PER_UTXO))
+    }
+
+    fn add_output(&mut self, _output: Self::OutputUtxo) -> anyhow::Result<()> {
+        self.utxos += 1;
+        Ok(())
+    }
+
+    fn min_value_for_output(
+        &mut self,
+        _output: Self::OutputUtxo,
+    ) -> anyhow::Result<Value<Regulated>> {
+        Ok(Value::from(MIN_VALUE_FOR_OUTPUT))
+    }
+
+    fn current_size(&self) -> anyhow::Result<usize> {
+        Ok(self.utxos as usize)
+    }
+
+    fn max_size(&self) -> anyhow::Result<usize> {
+        Ok(usize::MAX)
+    }
+
+    fn fork(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+/// a wallet and the payments to make from it
+///
+/// The payments spend at most half of the wallet: most scenarios can be
+/// paid, the others are discarded by [`check_selection`].
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub utxos: Vec<UTxODetails>,
+    pub outputs: Vec<UTxOBuilder>,
+    pub seed: u64,
+}
+
+impl Scenario {
+    pub fn store(&self) -> UTxOStore {
+        let mut store = UTxOStore::new().thaw();
+        for utxo in self.utxos.iter() {
+            store.insert(utxo.clone()).expect("the pointers are unique");
+        }
+        store.freeze()
+    }
+
+    pub fn setup(&self) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup::from_fixed_inputs_and_outputs(
+            vec![],
+            self.outputs.clone(),
+            Some(Address::new("change")),
+        )
+    }
+}
+
+impl Arbitrary for Scenario {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let len = 1 + u64::arbitrary(g) % 20;
+        let mut total = 0;
+        let mut token_totals = [0; TOKENS.len()];
+        let utxos = (0..len)
+            .map(|index| {
+                let value = 20 + u64::arbitrary(g) % 1_000;
+                total += value;
+                let mut assets = vec![];
+                for (token, token_total) in TOKENS.iter().zip(token_totals.iter_mut()) {
+                    if u8::arbitrary(g) % 3 == 0 {
+                        let quantity = 1 + u64::arbitrary(g) % 100;
+                        *token_total += quantity;
+                        assets.push(create_asset(token.to_string(), Value::from(quantity)));
+                    }
+                }
+                create_utxo(index, 0, "wallet".to_string(), Value::from(value), assets)
+            })
+            .collect();
+
+        let count = 1 + u64::arbitrary(g) % 3;
+        let outputs = (0..count)
+            .map(|_| {
+                let value = MIN_VALUE_FOR_OUTPUT + u64::arbitrary(g) % (total / (2 * count));
+                let mut assets = vec![];
+                for (token, token_total) in TOKENS.iter().zip(token_totals.iter()) {
+                    let max = token_total / (2 * count);
+                    if max > 0 && bool::arbitrary(g) {
+                        let quantity = 1 + u64::arbitrary(g) % max;
+                        assets.push(create_asset(token.to_string(), Value::from(quantity)));
+                    }
+                }
+                UTxOBuilder::new(Address::new("output"), Value::from(value), assets)
+            })
+            .collect();
+
+        Self {
+            utxos,
+            outputs,
+            seed: u64::arbitrary(g),
+        }
+    }
+}
+
+/// a valid thermostat config with few accumulators and a band of main
+/// asset in the range of the scenarios, so the changes are split
+#[derive(Debug, Clone)]
+pub struct ThermostatScenarioConfig(pub ThermostatAlgoConfig);
+
+impl Arbitrary for ThermostatScenarioConfig {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let min = u64::arbitrary(g) % 500;
+        let max = min + u64::arbitrary(g) % 1_000;
+        Self(
+            ThermostatAlgoConfig::default()
+                .with_num_accumulators(1 + usize::arbitrary(g) % 4)
+                .with_num_accumulators_assets(1 + usize::arbitrary(g) % 4)
+                .with_native_utxo_thermostat(Value::from(min), Value::from(max))
+                .with_cleanup_mixed_utxos(bool::arbitrary(g)),
+        )
+    }
+}
+
+/// the errors of the algorithms running out of inputs for the payment
+const INFEASIBLE: [&str; 6] = [
+    "utxo balance insufficient",
+    "no more input to select",
+    "could not get inputs to fund",
+    "there's lack of",
+    "not enough",
+    "maximum input count",
+];
+
+/// the scenario can't be paid with the available inputs, as opposed to an
+/// error of the algorithm
+pub fn is_infeasible(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let cause = cause.to_string().to_lowercase();
+        INFEASIBLE.iter().any(|message| cause.contains(message))
+    })
+}
+
+/// check the `result` of a selection with the `estimator` it ran with, see
+/// the module documentation
+pub fn check_invariants<
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+>(
+    estimator: &mut Estimate,
+    result: &InputSelectionResult<UTxODetails, UTxOBuilder>,
+) -> Result<(), String> {
+    check_balanced(result)
+        .into_result()
+        .map_err(|error| error.to_string())?;
+
+    let mut pointers = HashSet::new();
+    for input in result
+        .fixed_inputs
+        .iter()
+        .chain(result.chosen_inputs.iter())
+    {
+        if !pointers.insert(&input.pointer) {
+            return Err(format!("The input {:?} is used twice", input.pointer));
+        }
+    }
+
+    let min_fee = estimator.min_required_fee().map_err(|e| e.to_string())?;
+    if result.fee < min_fee {
+        return Err(format!(
+            "The fee {} is below the minimum fee {min_fee}",
+            result.fee
+        ));
+    }
+
+    for change in result.changes.iter() {
+        let min_value = estimator
+            .min_value_for_output(change.clone())
+            .map_err(|e| e.to_string())?;
+        if change.value < min_value {
+            return Err(format!(
+                "The change {} is below the minimum value {min_value}",
+                change.value
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// run the `primary` algorithm, given the UTxOs of the `scenario`, and
+/// check the invariants of its result
+///
+/// The scenarios the algorithm can't pay are discarded, the other errors
+/// fail the property.
+pub fn check_selection<Primary>(primary: Primary, scenario: &Scenario) -> TestResult
+where
+    Primary: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+{
+    let mut estimator = LinearFeeEstimate::default();
+    for output in scenario.outputs.iter() {
+        estimator
+            .add_output(output.clone())
+            .expect("the estimator accepts any output");
+    }
+
+    let mut pipeline = SelectionPipeline::new(primary, SingleOutputChangeBalancer::default());
+    let result = match pipeline.select(&mut estimator, scenario.setup()) {
+        Ok(result) => result,
+        Err(error) if is_infeasible(&error) => return TestResult::discard(),
+        Err(error) => return TestResult::error(format!("{error:#}")),
+    };
+
+    if let Err(error) = check_invariants(&mut estimator, &result) {
+        return TestResult::error(error);
+    }
+
+    let available = pipeline.available_inputs();
+    if let Some(input) = result
+        .chosen_inputs
+        .iter()
+        .find(|input| available.iter().any(|utxo| utxo.pointer == input.pointer))
+    {
+        return TestResult::error(format!(
+            "The input {:?} is still available after the selection",
+            input.pointer
+        ));
+    }
+    if available.len() + result.chosen_inputs.len() != scenario.utxos.len() {
+        return TestResult::error("Some inputs were lost by the selection");
+    }
+
+    TestResult::passed()
+}
+
+/// select the collateral of a transaction paying `fee` among the UTxOs of
+/// the `scenario` and check the collateral covers the fee
+pub fn check_collateral(scenario: &Scenario, fee: u64, setup: &CollateralSetup) -> TestResult {
+    let collateral = match select_collateral(&scenario.utxos, &Value::from(fee), setup) {
+        Ok(collateral) => collateral,
+        Err(error) if is_infeasible(&error) => return TestResult::discard(),
+        Err(error) => return TestResult::error(format!("{error:#}")),
+    };
+
+    if collateral.inputs.is_empty() || collateral.inputs.len() > setup.max_collateral_inputs {
+        return TestResult::error(format!(
+            "{} collateral inputs selected, at most {} allowed",
+            collateral.inputs.len(),
+            setup.max_collateral_inputs
+        ));
+    }
+    let mut pointers = HashSet::new();
+    for input in collateral.inputs.iter() {
+        let in_wallet = scenario
+            .utxos
+            .iter()
+            .any(|utxo| utxo.pointer == input.pointer);
+        if !input.assets.is_empty() || !in_wallet {
+            return TestResult::error(format!(
+                "The collateral input {:?} is not a pure UTxO of the wallet",
+                input.pointer
+            ));
+        }
+        if !pointers.insert(&input.pointer) {
+            return TestResult::error(format!("The input {:?} is used twice", input.pointer));
+        }
+    }
+
+    let total_collateral = collateral
+        .total_collateral
+        .to_u64()
+        .expect("the collateral is below the value of the wallet");
+    if total_collateral * 100 < fee * setup.percentage {
+        return TestResult::error(format!(
+            "The collateral {} does not cover {}% of the fee {fee}",
+            collateral.total_collateral, setup.percentage
+        ));
+    }
+    let total: Value<Regulated> = collateral.inputs.iter().map(|input| &input.value).sum();
+    let returned = collateral
+        .collateral_return
+        .as_ref()
+        .map(|output| output.value.clone())
+        .unwrap_or_else(Value::zero);
+    if &collateral.total_collateral + &returned != total {
+        return TestResult::error(format!(
+            "The collateral {} and its return {returned} do not match the inputs {total}",
+            collateral.total_collateral
+        ));
+    }
+
+    TestResult::passed()
+}
+
+#[test]
+fn scenarios_hold_tokens() {
+    let mut g = Gen::new(100);
+    let token = TokenId::new(TOKENS[0]);
+    let paid = (0..100).any(|_| {
+        Scenario::arbitrary(&mut g)
+            .outputs
+            .iter()
+            .any(|output| output.assets.iter().any(|asset| asset.fingerprint == token))
+    });
+    assert!(paid);
+}
+
+quickcheck! {
+    fn largest_first(scenario: Scenario) -> TestResult {
+        check_selection(LargestFirst::try_from(scenario.store()).unwrap(), &scenario)
+    }
+
+    fn largest_first_multi_asset(scenario: Scenario) -> TestResult {
+        check_selection(
+            LargestFirstMultiAsset::try_from(scenario.store()).unwrap(),
+            &scenario,
+        )
+    }
+
+    fn random_improve(scenario: Scenario) -> TestResult {
+        check_selection(
+            RandomImprove::try_from(scenario.store())
+                .unwrap()
+                .with_seed(scenario.seed),
+            &scenario,
+        )
+    }
+
+    fn single_random_draw(scenario: Scenario) -> TestResult {
+        check_selection(
+            SingleRandomDraw::try_from(scenario.store())
+                .unwrap()
+                .with_seed(scenario.seed),
+            &scenario,
+        )
+    }
+
+    fn knapsack(scenario: Scenario) -> TestResult {
+        check_selection(Knapsack::try_from(scenario.store()).unwrap(), &scenario)
+    }
+
+    fn thermostat(scenario: Scenario, config: ThermostatScenarioConfig) -> TestResult {
+        let mut thermostat = Thermostat::new(config.0);
+        thermostat.set_available_utxos(scenario.store()).unwrap();
+        check_selection(thermostat, &scenario)
+    }
+
+    fn consolidate(scenario: Scenario, threshold: u64, max_inputs: usize) -> TestResult {
+        let mut consolidate = Consolidate::new(
+            ConsolidateConfig::default()
+                .with_threshold(Value::from(1 + threshold % 1_000))
+                .with_max_inputs(2 + max_inputs % 20),
+        );
+        consolidate.set_available_utxos(scenario.store()).unwrap();
+        check_selection(consolidate, &scenario)
+    }
+
+    fn collateral(
+        scenario: Scenario,
+        fee: u64,
+        percentage: u64,
+        max_inputs: usize,
+        with_return: bool
+    ) -> TestResult {
+        let setup = CollateralSetup {
+            percentage: 100 + percentage % 200,
+            max_collateral_inputs: 1 + max_inputs % 3,
+            return_address: with_return.then(|| Address::new("return")),
+        };
+        check_collateral(&scenario, fee % 1_000, &setup)
+    }
+}
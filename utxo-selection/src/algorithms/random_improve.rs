@@ -1,64 +1,105 @@
 use crate::algorithm::InputSelectionAlgorithm;
 use crate::common::{InputOutputSetup, InputSelectionResult};
 use crate::estimate::TransactionFeeEstimator;
+use crate::scorer::draw_candidate;
+use crate::{assets_in_selection_order, CandidateScorer, UTxOStoreSupport};
 use anyhow::anyhow;
-use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
-use dcspark_core::{AssetName, PolicyId, Regulated, TokenId, UTxOStore};
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{Regulated, UTxOStore};
 use deps::bigdecimal::ToPrimitive;
-use rand::Rng;
-use std::cmp::Ordering;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
 
-pub struct RandomImprove {
-    available_inputs: Vec<UTxODetails>,
+/// CIP-2 Random-Improve selection
+///
+/// The random number generator is seeded from the entropy of the system
+/// by default, use [`RandomImprove::with_seed`] or
+/// [`RandomImprove::with_rng`] to make the selection reproducible.
+///
+/// The candidates are the UTxOs of the [`UTxOStore`] given to the
+/// algorithm, shared with it rather than copied: give the inputs with
+/// [`UTxOStoreSupport`] to avoid converting them on every transaction.
+pub struct RandomImprove<R = StdRng> {
+    /// the candidates in the order of their pointers, indexed by
+    /// `available_indices`
+    available_inputs: Vec<Arc<UTxODetails>>,
     available_indices: BTreeSet<usize>,
+    /// the available inputs, the chosen ones are removed after each
+    /// selection
+    available_utxos: UTxOStore,
+    rng: R,
+    scorer: Option<Box<dyn CandidateScorer + Send + Sync>>,
 }
 
-impl TryFrom<UTxOStore> for RandomImprove {
-    type Error = anyhow::Error;
-
-    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
-        RandomImprove::try_from(
-            value
-                .iter()
-                .map(|(_, v)| v.as_ref().clone())
-                .collect::<Vec<_>>(),
-        )
+impl RandomImprove {
+    /// seed the random number generator so the same inputs always give
+    /// the same selection
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(StdRng::seed_from_u64(seed))
     }
 }
 
-impl TryFrom<Vec<UTxODetails>> for RandomImprove {
-    type Error = anyhow::Error;
+impl<R: Rng> RandomImprove<R> {
+    /// use the given random number generator for the selection
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_rng<R2: Rng>(self, rng: R2) -> RandomImprove<R2> {
+        RandomImprove {
+            available_inputs: self.available_inputs,
+            available_indices: self.available_indices,
+            available_utxos: self.available_utxos,
+            rng,
+            scorer: self.scorer,
+        }
+    }
 
-    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
-        let available_indices = BTreeSet::from_iter(0..value.len());
-        Ok(Self {
-            available_inputs: value,
-            available_indices,
-        })
+    /// draw the candidates with a probability proportional to their score
+    /// plus one, instead of uniformly
+    ///
+    /// The improvement phase still draws the replacements uniformly.
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_scorer<S>(self, scorer: S) -> Self
+    where
+        S: CandidateScorer + Send + Sync + 'static,
+    {
+        Self {
+            scorer: Some(Box::new(scorer)),
+            ..self
+        }
     }
-}
 
-impl InputSelectionAlgorithm for RandomImprove {
-    type InputUtxo = UTxODetails;
-    type OutputUtxo = UTxOBuilder;
+    fn set_utxos(&mut self, utxos: UTxOStore) {
+        self.available_inputs = indexed_inputs(&utxos);
+        self.available_indices = BTreeSet::from_iter(0..self.available_inputs.len());
+        self.available_utxos = utxos;
+    }
 
-    fn set_available_inputs(
-        &mut self,
-        available_inputs: Vec<Self::InputUtxo>,
-    ) -> anyhow::Result<()> {
-        let _available_indices = BTreeSet::from_iter(0..available_inputs.len());
-        self.available_inputs = available_inputs;
-        Ok(())
+    /// the inputs spent by the transaction so far, given to the scorer
+    fn spent_inputs(
+        &self,
+        fixed_inputs: &[UTxODetails],
+        chosen_indices: &HashSet<usize>,
+    ) -> Vec<UTxODetails> {
+        fixed_inputs
+            .iter()
+            .cloned()
+            .chain(
+                chosen_indices
+                    .iter()
+                    .map(|i| self.available_inputs[*i].as_ref().clone()),
+            )
+            .collect()
     }
 
-    fn select_inputs<
-        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    fn select<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
     >(
         &mut self,
         estimator: &mut Estimate,
-        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
-    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    ) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>> {
         let mut input_balance = input_output_setup.input_balance;
         let output_balance = input_output_setup.output_balance;
         let mut fee = estimator.min_required_fee()?;
@@ -70,29 +111,22 @@ impl InputSelectionAlgorithm for RandomImprove {
 
         let mut chosen_indices = HashSet::<usize>::new();
 
-        let mut rng = rand::thread_rng();
-        let mut policy_ids_to_asset_names = asset_output_balance
-            .values()
-            .map(|asset: &TransactionAsset| (asset.policy_id.clone(), asset.asset_name.clone()))
-            .collect::<Vec<(PolicyId, AssetName)>>();
-        policy_ids_to_asset_names.sort_by(
-            |left: &(PolicyId, AssetName), right: &(PolicyId, AssetName)| match left.0.cmp(&right.0)
-            {
-                Ordering::Equal => left.1.cmp(&right.1),
-                x => x,
-            },
-        );
-
-        for (policy_id, asset_name) in policy_ids_to_asset_names.iter() {
-            let token = TokenId::new(format!("{policy_id}:{asset_name}"));
+        let output_assets = assets_in_selection_order(&asset_output_balance);
+        for token in output_assets.iter().map(|asset| asset.fingerprint.clone()) {
+            // the inputs selected for the previous assets may already hold some of this one
+            let already_covered = asset_input_balance.quantity_of(&token);
+            let spent = self.spent_inputs(&input_output_setup.fixed_inputs, &chosen_indices);
             let asset_chosen_indices = select_input_and_update_balances(
                 &self.available_inputs,
                 &mut self.available_indices,
                 &explicit_outputs,
+                self.scorer.as_deref(),
+                &spent,
                 estimator,
                 &mut asset_input_balance,
                 &mut input_balance,
                 &mut fee,
+                already_covered,
                 |value: &UTxODetails| {
                     value
                         .assets
@@ -107,24 +141,29 @@ impl InputSelectionAlgorithm for RandomImprove {
                         .find(|asset| asset.fingerprint == token)
                         .map(|asset| asset.quantity.clone())
                 },
-                &mut rng,
+                &mut self.rng,
             )?;
 
             chosen_indices.extend(asset_chosen_indices);
         }
 
         // add in remaining ADA
+        let already_covered = input_balance.clone();
+        let spent = self.spent_inputs(&input_output_setup.fixed_inputs, &chosen_indices);
         let ada_chosen_indices = select_input_and_update_balances(
             &self.available_inputs,
             &mut self.available_indices,
             &explicit_outputs,
+            self.scorer.as_deref(),
+            &spent,
             estimator,
             &mut asset_input_balance,
             &mut input_balance,
             &mut fee,
+            already_covered,
             |value: &UTxODetails| Some(value.value.clone()),
             |value: &UTxOBuilder| Some(value.value.clone()),
-            &mut rng,
+            &mut self.rng,
         )?;
         chosen_indices.extend(ada_chosen_indices);
 
@@ -135,26 +174,22 @@ impl InputSelectionAlgorithm for RandomImprove {
             if self.available_indices.is_empty() {
                 return Err(anyhow!("UTxO Balance Insufficient[x]"));
             }
-            let i = *self
-                .available_indices
-                .iter()
-                .nth(rng.gen_range(0..self.available_indices.len()))
-                .unwrap();
+            let spent = self.spent_inputs(&input_output_setup.fixed_inputs, &chosen_indices);
+            let position = draw_candidate(
+                &mut self.rng,
+                self.available_indices
+                    .iter()
+                    .map(|i| self.available_inputs[*i].as_ref()),
+                self.scorer.as_deref(),
+                &spent,
+            );
+            let i = *self.available_indices.iter().nth(position).unwrap();
             self.available_indices.remove(&i);
-            let input = &self.available_inputs[i];
+            let input = self.available_inputs[i].as_ref();
             let input_fee = estimator.fee_for_input(input)?;
             estimator.add_input(input.clone())?;
             input_balance += &input.value;
-            for asset in input.assets.iter() {
-                asset_input_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset::new(
-                        asset.policy_id.clone(),
-                        asset.asset_name.clone(),
-                        asset.fingerprint.clone(),
-                    ))
-                    .quantity += &asset.quantity;
-            }
+            asset_input_balance.extend(input.assets.iter());
             fee += input_fee;
             chosen_indices.insert(i);
         }
@@ -162,9 +197,13 @@ impl InputSelectionAlgorithm for RandomImprove {
         Ok(InputSelectionResult {
             fixed_inputs: input_output_setup.fixed_inputs,
             fixed_outputs: input_output_setup.fixed_outputs,
+            // sorted so the order of the inputs does not depend on the
+            // iteration order of the set
             chosen_inputs: chosen_indices
                 .into_iter()
-                .map(|i| self.available_inputs[i].clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|i| self.available_inputs[i].as_ref().clone())
                 .collect(),
             changes: vec![],
             input_balance,
@@ -173,13 +212,131 @@ impl InputSelectionAlgorithm for RandomImprove {
 
             input_asset_balance: asset_input_balance,
             output_asset_balance: asset_output_balance,
-        })
+            trace: None,
+        }
+        .into_stable_order())
+    }
+}
+
+impl TryFrom<UTxOStore> for RandomImprove {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
+        let mut random_improve = Self {
+            available_inputs: vec![],
+            available_indices: BTreeSet::new(),
+            available_utxos: UTxOStore::new(),
+            rng: StdRng::from_entropy(),
+            scorer: None,
+        };
+        random_improve.set_utxos(value);
+        Ok(random_improve)
+    }
+}
+
+impl<R: Rng> UTxOStoreSupport for RandomImprove<R> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.set_utxos(utxos);
+        Ok(())
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        Ok(self.available_utxos.clone())
+    }
+}
+
+/// the UTxOs of the store, shared with it
+///
+/// The store does not iterate in a stable order: sort the inputs so a
+/// seeded selection is reproducible.
+fn indexed_inputs(utxos: &UTxOStore) -> Vec<Arc<UTxODetails>> {
+    let mut inputs = utxos
+        .iter()
+        .map(|(_, utxo)| utxo.clone())
+        .collect::<Vec<_>>();
+    inputs.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+    inputs
+}
+
+fn store_of(inputs: Vec<UTxODetails>) -> anyhow::Result<UTxOStore> {
+    let mut store = UTxOStore::new().thaw();
+    for input in inputs {
+        store.insert(input)?;
+    }
+    Ok(store.freeze())
+}
+
+impl TryFrom<Vec<UTxODetails>> for RandomImprove {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
+        RandomImprove::try_from(store_of(value)?)
+    }
+}
+
+impl<R: Rng> InputSelectionAlgorithm for RandomImprove<R> {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.set_utxos(store_of(available_inputs)?);
+        Ok(())
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        // the excluded and the fixed inputs are set aside for the selection,
+        // the excluded ones remain available afterwards
+        let set_aside = self
+            .available_indices
+            .iter()
+            .copied()
+            .filter(|i| !input_output_setup.is_selectable(&self.available_inputs[*i].pointer))
+            .collect::<Vec<_>>();
+        for i in set_aside.iter() {
+            self.available_indices.remove(i);
+        }
+        let excluded = set_aside
+            .iter()
+            .copied()
+            .filter(|i| {
+                input_output_setup
+                    .excluded_inputs
+                    .contains(&self.available_inputs[*i].pointer)
+            })
+            .collect::<Vec<_>>();
+
+        match self.select(estimator, input_output_setup) {
+            Ok(result) => {
+                self.available_indices.extend(excluded);
+                // only the chosen inputs are removed from the store, the
+                // candidates are not copied back
+                let mut utxos = self.available_utxos.thaw();
+                for input in result.chosen_inputs.iter() {
+                    utxos.remove(&input.pointer)?;
+                }
+                self.available_utxos = utxos.freeze();
+                Ok(result)
+            }
+            Err(error) => {
+                self.available_indices.extend(set_aside);
+                Err(error)
+            }
+        }
     }
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo> {
         self.available_indices
             .iter()
-            .map(|index| self.available_inputs[*index].clone())
+            .map(|index| self.available_inputs[*index].as_ref().clone())
             .collect::<Vec<_>>()
     }
 }
@@ -191,13 +348,16 @@ fn select_input_and_update_balances<
     ByOutput,
     R: Rng + ?Sized,
 >(
-    available_inputs: &[UTxODetails],
+    available_inputs: &[Arc<UTxODetails>],
     available_indices: &mut BTreeSet<usize>,
     explicit_outputs: &[UTxOBuilder],
+    scorer: Option<&(dyn CandidateScorer + Send + Sync)>,
+    spent: &[UTxODetails],
     estimator: &mut Estimate,
-    asset_input_balance: &mut HashMap<TokenId, TransactionAsset>,
+    asset_input_balance: &mut MultiAssetValue,
     input_total: &mut dcspark_core::Value<Regulated>,
     fee: &mut dcspark_core::Value<Regulated>,
+    already_covered: dcspark_core::Value<Regulated>,
     by_input: ByInput,
     by_output: ByOutput,
     rng: &mut R,
@@ -210,44 +370,50 @@ where
 
     let mut relevant_indices = available_indices
         .iter()
-        .filter(|i| by_input(&available_inputs[**i]).is_some())
+        .filter(|i| by_input(available_inputs[**i].as_ref()).is_some())
         .cloned()
         .collect::<Vec<usize>>();
 
     let mut associated_indices: BTreeMap<UTxOBuilder, Vec<usize>> = BTreeMap::new();
+    // the part of each output covered by the inputs selected before this phase
+    let mut carried_over: BTreeMap<UTxOBuilder, dcspark_core::Value<Regulated>> = BTreeMap::new();
     let mut outputs = explicit_outputs
         .iter()
         .filter(|output| by_output(output).is_some())
         .cloned()
         .collect::<Vec<UTxOBuilder>>();
     outputs.sort_by_key(|output| by_output(output).expect("filtered above"));
+
+    // Phase 1: Random Selection
+    //
+    // The selection is done per asset, the inputs already selected (for the
+    // previous assets or the fixed inputs) are credited to the outputs first
+    // so the same quantity is not selected twice.
+    let mut spent = spent.to_vec();
+    let mut carried = already_covered;
     for output in outputs.iter().rev() {
-        // TODO: how should we adapt this to inputs being associated when running for other assets?
-        // if we do these two phases for each asset and don't take into account the other runs for other assets
-        // then we over-add (and potentially fail if we don't have plenty of inputs)
-        // On the other hand, the improvement phase it difficult to determine if a change is an improvement
-        // if we're trying to improve for multiple assets at a time without knowing how important each input is
-        // e.g. maybe we have lots of asset A but not much of B
-        // For now I will just have this be entirely separate per-asset but we might want to in a later commit
-        // consider the improvements separately and have it take some kind of dot product / distance for assets
-        // during the improvement phase and have the improvement phase target multiple asset types at once.
-        // One issue with that is how to scale in between different assets. We could maybe normalize them by
-        // dividing each asset type by the sum of the required asset type in all outputs.
-        // Another possibility for adapting this to multiassets is when associating an input x for asset type a
-        // we try and subtract all other assets b != a from the outputs we're trying to cover.
-        // It might make sense to diverge further and not consider it per-output and to instead just match against
-        // the sum of all outputs as one single value.
-        let mut added = dcspark_core::Value::zero();
         let needed = by_output(output)
             .ok_or_else(|| anyhow!("Transaction output proper amount is not found"))?;
+        let mut added = std::cmp::min(carried.clone(), needed.clone());
+        carried = carried.saturating_sub(&added);
+        carried_over.insert(output.clone(), added.clone());
+
         while added < needed {
             if relevant_indices.is_empty() {
                 return Err(anyhow!("UTxO Balance Insufficient"));
             }
-            let random_index = rng.gen_range(0..relevant_indices.len());
+            let random_index = draw_candidate(
+                rng,
+                relevant_indices
+                    .iter()
+                    .map(|i| available_inputs[*i].as_ref()),
+                scorer,
+                &spent,
+            );
             let i = relevant_indices.swap_remove(random_index);
             available_indices.remove(&i);
-            let input = &available_inputs[i];
+            let input = available_inputs[i].as_ref();
+            spent.push(input.clone());
             added +=
                 &by_input(input).expect("do not call on asset types that aren't in the output");
             associated_indices
@@ -259,33 +425,41 @@ where
 
     if !relevant_indices.is_empty() {
         // Phase 2: Improvement
-        for output in outputs.iter_mut() {
-            let associated = associated_indices
-                .get_mut(output)
-                .ok_or_else(|| anyhow!("Associated index by output key not found"))?;
+        //
+        // an input is replaced if the total of the asset for the output moves
+        // closer to the ideal (twice the output) without exceeding the
+        // maximum (three times the output) nor going under the output
+        for output in outputs.iter() {
+            let associated = match associated_indices.get_mut(output) {
+                Some(associated) => associated,
+                // the output is entirely covered by the previous phases
+                None => continue,
+            };
+            let min = to_u64(&by_output(output).expect("filtered above"))? as i128;
+            let ideal = 2 * min;
+            let max = 3 * min;
+            let mut total = to_u64(carried_over.get(output).expect("inserted above"))? as i128;
+            for i in associated.iter() {
+                total += quantity_of(&by_input, available_inputs[*i].as_ref())?;
+            }
+
             for i in associated.iter_mut() {
                 let random_index = rng.gen_range(0..relevant_indices.len());
                 let j: &mut usize = relevant_indices
                     .get_mut(random_index)
                     .ok_or_else(|| anyhow!("Relevant index by random index not found"))?;
-                let should_improve = {
-                    let input = &available_inputs[*i];
-                    let new_input = &available_inputs[*j];
-                    let cur = input.value.raw().to_u64().unwrap();
-                    let new = new_input.value.raw().to_u64().unwrap();
-                    let min = output.value.raw().to_u64().unwrap();
-                    let ideal = 2 * min;
-                    let max = 3 * min;
-                    let move_closer =
-                        (ideal as i128 - new as i128).abs() < (ideal as i128 - cur as i128).abs();
-                    let not_exceed_max = new < max;
-
-                    move_closer && not_exceed_max
-                };
-                if should_improve {
+
+                let cur = quantity_of(&by_input, available_inputs[*i].as_ref())?;
+                let new = quantity_of(&by_input, available_inputs[*j].as_ref())?;
+                let new_total = total - cur + new;
+
+                let move_closer = (ideal - new_total).abs() < (ideal - total).abs();
+                let within_bounds = min <= new_total && new_total <= max;
+                if move_closer && within_bounds {
                     available_indices.insert(*i);
                     available_indices.remove(j);
                     std::mem::swap(i, j);
+                    total = new_total;
                 }
             }
         }
@@ -293,25 +467,12 @@ where
 
     // after finalizing the improvement we need to actually add these results to the builder
     for output in outputs.iter() {
-        for i in associated_indices
-            .get(output)
-            .ok_or_else(|| anyhow!("Transaction output key not found"))?
-            .iter()
-        {
-            let input = &available_inputs[*i];
+        for i in associated_indices.get(output).into_iter().flatten() {
+            let input = available_inputs[*i].as_ref();
             let input_fee = &estimator.fee_for_input(input)?;
             estimator.add_input(input.clone())?;
             *input_total += &input.value;
-            for asset in input.assets.iter() {
-                asset_input_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset::new(
-                        asset.policy_id.clone(),
-                        asset.asset_name.clone(),
-                        asset.fingerprint.clone(),
-                    ))
-                    .quantity += &asset.quantity;
-            }
+            asset_input_balance.extend(input.assets.iter());
             *fee += input_fee;
             chosen_indices.insert(*i);
         }
@@ -320,14 +481,40 @@ where
     Ok(chosen_indices)
 }
 
+fn to_u64(value: &dcspark_core::Value<Regulated>) -> anyhow::Result<u64> {
+    value
+        .to_u64()
+        .ok_or_else(|| anyhow!("Can't convert {value} to u64"))
+}
+
+fn quantity_of<ByInput>(by_input: &ByInput, input: &UTxODetails) -> anyhow::Result<i128>
+where
+    ByInput: Fn(&UTxODetails) -> Option<dcspark_core::Value<Regulated>>,
+{
+    Ok(to_u64(&by_input(input).unwrap_or_default())? as i128)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
     use crate::algorithms::RandomImprove;
     use crate::estimators::dummy_estimator::DummyFeeEstimate;
     use crate::{InputOutputSetup, InputSelectionAlgorithm};
-    use dcspark_core::tx::UTxOBuilder;
-    use dcspark_core::{Address, Regulated, UTxOStore, Value};
+    use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+    use dcspark_core::{Address, Regulated, TokenId, UTxOStore, Value};
+
+    fn multi_asset_setup(
+        value: u64,
+        token: &str,
+        quantity: u64,
+    ) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        let output = UTxOBuilder::new(
+            Address::new(""),
+            Value::from(value),
+            vec![create_asset(token.to_string(), Value::from(quantity))],
+        );
+        InputOutputSetup::from_fixed_inputs_and_outputs(vec![], vec![output], None)
+    }
 
     #[test]
     fn try_select_dummy_fee() {
@@ -374,6 +561,8 @@ mod tests {
                     fixed_inputs: vec![],
                     fixed_outputs: vec![UTxOBuilder::new(Address::new(""), Value::from(1), vec![])],
                     change_address: None,
+                    excluded_inputs: Default::default(),
+                    collateral: None,
                 },
             )
             .unwrap();
@@ -381,4 +570,168 @@ mod tests {
         assert_eq!(result.fee, Value::zero());
         assert!(result.output_balance <= result.input_balance);
     }
+
+    #[test]
+    fn seeded_selection_is_reproducible() {
+        let inputs: Vec<_> = (0..20)
+            .map(|index| {
+                create_utxo(
+                    0,
+                    index,
+                    "0".to_string(),
+                    Value::<Regulated>::from(index + 1),
+                    vec![],
+                )
+            })
+            .collect();
+
+        let select = |seed| {
+            RandomImprove::try_from(inputs.clone())
+                .unwrap()
+                .with_seed(seed)
+                .select_inputs(
+                    &mut DummyFeeEstimate::new(),
+                    InputOutputSetup {
+                        output_balance: Value::from(30),
+                        fixed_outputs: vec![UTxOBuilder::new(
+                            Address::new(""),
+                            Value::from(30),
+                            vec![],
+                        )],
+                        ..InputOutputSetup::default()
+                    },
+                )
+                .unwrap()
+                .chosen_inputs
+        };
+
+        assert_eq!(select(42), select(42));
+    }
+
+    /// the input selected for the token already covers the main asset,
+    /// no other input is added
+    #[test]
+    fn multi_asset_within_boundary() {
+        let inputs = vec![
+            create_utxo(
+                1,
+                0,
+                "0".to_string(),
+                Value::<Regulated>::from(200_000_000),
+                vec![create_asset(
+                    "My Token".to_string(),
+                    Value::from(9_000_000_000_000),
+                )],
+            ),
+            create_utxo(
+                2,
+                0,
+                "0".to_string(),
+                Value::<Regulated>::from(9_000_000_000_000),
+                vec![],
+            ),
+        ];
+
+        let mut random_improve = RandomImprove::try_from(inputs).unwrap().with_seed(0);
+        let result = random_improve
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                multi_asset_setup(3_000_000, "My Token", 1_000_000),
+            )
+            .unwrap();
+
+        assert_eq!(result.chosen_inputs.len(), 1);
+        assert_eq!(
+            result.chosen_inputs[0].assets[0].fingerprint,
+            TokenId::new("My Token")
+        );
+        assert_eq!(random_improve.available_inputs().len(), 1);
+    }
+
+    /// whatever the random draws, every asset of the output is covered
+    #[test]
+    fn multi_asset_targets_are_enforced() {
+        let mut inputs = vec![];
+        for tx in 0..10 {
+            let drip = |quantity| vec![create_asset("tDRIP".to_string(), Value::from(quantity))];
+            inputs.push(create_utxo(
+                tx,
+                0,
+                "0".to_string(),
+                Value::from(2_500_000),
+                drip(1000),
+            ));
+            inputs.push(create_utxo(
+                tx,
+                1,
+                "0".to_string(),
+                Value::from(500_000_000),
+                vec![],
+            ));
+            inputs.push(create_utxo(
+                tx,
+                2,
+                "0".to_string(),
+                Value::from(500_000_000),
+                drip(1_000_000),
+            ));
+            inputs.push(create_utxo(
+                tx,
+                3,
+                "0".to_string(),
+                Value::from(5_000_000),
+                vec![],
+            ));
+        }
+
+        for seed in 0..20 {
+            let mut random_improve = RandomImprove::try_from(inputs.clone())
+                .unwrap()
+                .with_seed(seed);
+            let result = random_improve
+                .select_inputs(
+                    &mut DummyFeeEstimate::new(),
+                    multi_asset_setup(600_000_000, "tDRIP", 1500),
+                )
+                .unwrap();
+
+            assert!(result.input_balance >= result.output_balance);
+            assert!(result
+                .input_asset_balance
+                .is_superset_of(&result.output_asset_balance));
+            assert_eq!(
+                random_improve.available_inputs().len(),
+                inputs.len() - result.chosen_inputs.len()
+            );
+        }
+    }
+
+    #[test]
+    fn multi_asset_balance_insufficient() {
+        let inputs = vec![
+            create_utxo(
+                0,
+                0,
+                "0".to_string(),
+                Value::<Regulated>::from(2_500_000),
+                vec![create_asset("tDRIP".to_string(), Value::from(1000))],
+            ),
+            create_utxo(
+                0,
+                1,
+                "0".to_string(),
+                Value::<Regulated>::from(500_000_000),
+                vec![],
+            ),
+        ];
+
+        let mut random_improve = RandomImprove::try_from(inputs).unwrap().with_seed(0);
+        let error = random_improve
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                multi_asset_setup(3_000_000, "tDRIP", 1001),
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("UTxO Balance Insufficient"));
+    }
 }
@@ -0,0 +1,290 @@
+use crate::algorithm::InputSelectionAlgorithm;
+use crate::common::{InputOutputSetup, InputSelectionResult};
+use crate::estimate::TransactionFeeEstimator;
+use crate::{calculate_main_token_balance, UTxOStoreSupport};
+use anyhow::anyhow;
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{Regulated, UTxOStore, Value};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// Single Random Draw selection
+///
+/// The available inputs are drawn in a random order until the outputs,
+/// the fee and every asset of the outputs are covered.
+///
+/// Like [`RandomImprove`](super::RandomImprove), the random number generator
+/// is seeded from the entropy of the system unless [`SingleRandomDraw::with_seed`]
+/// or [`SingleRandomDraw::with_rng`] is used.
+pub struct SingleRandomDraw<R = StdRng> {
+    available_inputs: Vec<UTxODetails>,
+    rng: R,
+}
+
+impl SingleRandomDraw {
+    /// seed the random number generator so the same inputs always give
+    /// the same selection
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: Rng> SingleRandomDraw<R> {
+    /// use the given random number generator for the selection
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_rng<R2: Rng>(self, rng: R2) -> SingleRandomDraw<R2> {
+        SingleRandomDraw {
+            available_inputs: self.available_inputs,
+            rng,
+        }
+    }
+}
+
+fn is_covered(
+    input_balance: &Value<Regulated>,
+    input_asset_balance: &MultiAssetValue,
+    output_balance: &Value<Regulated>,
+    output_asset_balance: &MultiAssetValue,
+    fee: &Value<Regulated>,
+) -> bool {
+    !calculate_main_token_balance(input_balance, output_balance, fee).in_debt()
+        && input_asset_balance.is_superset_of(output_asset_balance)
+}
+
+impl TryFrom<Vec<UTxODetails>> for SingleRandomDraw {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Vec<UTxODetails>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            available_inputs: value,
+            rng: StdRng::from_entropy(),
+        })
+    }
+}
+
+impl TryFrom<UTxOStore> for SingleRandomDraw {
+    type Error = anyhow::Error;
+
+    fn try_from(value: UTxOStore) -> Result<Self, Self::Error> {
+        // the store does not iterate in a stable order: sort the inputs so
+        // a seeded selection is reproducible
+        let mut inputs = value
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>();
+        inputs.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        Self::try_from(inputs)
+    }
+}
+
+impl<R: Rng> UTxOStoreSupport for SingleRandomDraw<R> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        let mut inputs = utxos
+            .iter()
+            .map(|(_, v)| v.as_ref().clone())
+            .collect::<Vec<_>>();
+        inputs.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+        self.available_inputs = inputs;
+        Ok(())
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        let mut store = UTxOStore::new().thaw();
+        for input in self.available_inputs.iter() {
+            store.insert(input.clone())?;
+        }
+        Ok(store.freeze())
+    }
+}
+
+impl<R: Rng> InputSelectionAlgorithm for SingleRandomDraw<R> {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.available_inputs = available_inputs;
+        Ok(())
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let mut candidates = self
+            .available_inputs
+            .iter()
+            .filter(|input| input_output_setup.is_selectable(&input.pointer))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut input_balance = input_output_setup.input_balance;
+        let output_balance = input_output_setup.output_balance;
+        let mut fee = estimator.min_required_fee()?;
+
+        let mut asset_input_balance = input_output_setup.input_asset_balance;
+        let asset_output_balance = input_output_setup.output_asset_balance;
+
+        candidates.shuffle(&mut self.rng);
+        let mut candidates = candidates.into_iter();
+
+        let mut chosen_inputs = vec![];
+        while !is_covered(
+            &input_balance,
+            &asset_input_balance,
+            &output_balance,
+            &asset_output_balance,
+            &fee,
+        ) {
+            let input = candidates
+                .next()
+                .ok_or_else(|| anyhow!("UTxO Balance Insufficient"))?;
+
+            fee += estimator.fee_for_input(&input)?;
+            estimator.add_input(input.clone())?;
+            input_balance += &input.value;
+            asset_input_balance.extend(input.assets.iter());
+            chosen_inputs.push(input);
+        }
+
+        self.available_inputs.retain(|input| {
+            !chosen_inputs
+                .iter()
+                .chain(input_output_setup.fixed_inputs.iter())
+                .any(|chosen| chosen.pointer == input.pointer)
+        });
+
+        Ok(InputSelectionResult {
+            fixed_inputs: input_output_setup.fixed_inputs,
+            fixed_outputs: input_output_setup.fixed_outputs,
+            chosen_inputs,
+            changes: vec![],
+            input_balance,
+            output_balance,
+            fee,
+
+            input_asset_balance: asset_input_balance,
+            output_asset_balance: asset_output_balance,
+            trace: None,
+        }
+        .into_stable_order())
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.available_inputs.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use dcspark_core::TokenId;
+    use std::collections::HashSet;
+
+    fn inputs() -> Vec<UTxODetails> {
+        let mut inputs: Vec<_> = (0..20)
+            .map(|index| create_utxo(0, index, "0".to_string(), Value::from(index + 1), vec![]))
+            .collect();
+        inputs.push(create_utxo(
+            1,
+            0,
+            "0".to_string(),
+            Value::from(1),
+            vec![create_asset("kek".to_string(), Value::from(10))],
+        ));
+        inputs
+    }
+
+    fn setup(output_balance: u64) -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup {
+            output_balance: Value::from(output_balance),
+            output_asset_balance: [create_asset("kek".to_string(), Value::from(5))]
+                .into_iter()
+                .collect(),
+            ..InputOutputSetup::default()
+        }
+    }
+
+    #[test]
+    fn covers_outputs_and_assets() {
+        let mut algorithm = SingleRandomDraw::try_from(inputs()).unwrap();
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(50))
+            .unwrap();
+
+        assert!(result.input_balance >= result.output_balance);
+        assert!(result.input_asset_balance.quantity_of(&TokenId::new("kek")) >= Value::from(5));
+        assert_eq!(
+            algorithm.available_inputs().len(),
+            inputs().len() - result.chosen_inputs.len()
+        );
+    }
+
+    #[test]
+    fn seeded_selection_is_reproducible() {
+        let select = |seed| {
+            SingleRandomDraw::try_from(inputs())
+                .unwrap()
+                .with_seed(seed)
+                .select_inputs(&mut DummyFeeEstimate::new(), setup(50))
+                .unwrap()
+                .chosen_inputs
+        };
+
+        assert_eq!(select(42), select(42));
+    }
+
+    #[test]
+    fn balance_insufficient() {
+        let mut algorithm = SingleRandomDraw::try_from(inputs()).unwrap().with_seed(0);
+
+        assert!(algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), setup(1_000))
+            .is_err());
+    }
+
+    #[test]
+    fn excluded_and_fixed_inputs_are_not_selected() {
+        let inputs = inputs();
+        let fixed = inputs.last().cloned().unwrap();
+        let excluded = inputs[10..20]
+            .iter()
+            .map(|input| input.pointer.clone())
+            .collect::<HashSet<_>>();
+
+        let mut algorithm = SingleRandomDraw::try_from(inputs).unwrap().with_seed(0);
+        let result = algorithm
+            .select_inputs(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup {
+                    input_balance: fixed.value.clone(),
+                    input_asset_balance: fixed.assets.iter().collect(),
+                    excluded_inputs: excluded.clone(),
+                    fixed_inputs: vec![fixed.clone()],
+                    ..setup(56)
+                },
+            )
+            .unwrap();
+
+        // the 10 smallest inputs are all required to cover the output
+        assert!(result
+            .chosen_inputs
+            .iter()
+            .all(|input| !excluded.contains(&input.pointer)));
+        assert_eq!(result.chosen_inputs.len(), 10);
+
+        let available = algorithm.available_inputs();
+        assert!(available.iter().all(|input| input.pointer != fixed.pointer));
+        assert_eq!(available.len(), excluded.len());
+    }
+}
@@ -12,14 +12,18 @@ pub fn create_utxo(
 ) -> UTxODetails {
     UTxODetails {
         pointer: UtxoPointer {
-            transaction_id: TransactionId::new(tx.to_string()),
+            transaction_id: TransactionId::new_unchecked(tx.to_string()),
             output_index: OutputIndex::new(index),
         },
         address: Address::new(address),
         value,
         assets,
         metadata: Arc::new(Default::default()),
+        datum: None,
+        script_ref: None,
         extra: None,
+        creation_height: None,
+        script_spend: None,
     }
 }
 
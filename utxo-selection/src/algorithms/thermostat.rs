@@ -1,23 +1,75 @@
+use crate::change_address::{checkpoint, next_change_address, next_provided_address, rewind};
+use crate::change_strategy::build_excess_changes;
 use crate::{
-    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
-    UTxOStoreSupport,
+    restore_utxos, BoxedChangeAddressProvider, ChangeAddressProvider, ChangeStrategy,
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, SingleChange,
+    TransactionFeeEstimator, UTxOStoreSupport,
 };
-use anyhow::{anyhow, Context};
-use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
-use dcspark_core::{Address, Balance, Regulated, TokenId, UTxOStore, Value};
+use anyhow::{anyhow, bail, Context};
+use dcspark_core::tx::{MultiAssetValue, TransactionAsset, UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, AssetId, Balance, Regulated, TokenId, UTxOStore, Value};
 use deps::bigdecimal::ToPrimitive;
-use serde::Deserialize;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ThermostatAlgoConfigFields")]
 pub struct ThermostatAlgoConfig {
     num_accumulators: usize,
     num_accumulators_assets: usize,
     native_utxo_thermostat_min: Value<Regulated>,
     native_utxo_thermostat_max: Value<Regulated>,
-    main_token: TokenId,
+    main_token: AssetId,
+    /// the settings of specific tokens, the ones above apply to the
+    /// tokens without overrides
+    token_overrides: HashMap<TokenId, TokenThermostatConfig>,
+    /// spend the UTxOs holding several tokens when there is room left in
+    /// the transaction, their tokens are moved to the change of each token
+    cleanup_mixed_utxos: bool,
+}
+
+/// the fields of [`ThermostatAlgoConfig`] before validation
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThermostatAlgoConfigFields {
+    num_accumulators: usize,
+    num_accumulators_assets: usize,
+    native_utxo_thermostat_min: Value<Regulated>,
+    native_utxo_thermostat_max: Value<Regulated>,
+    main_token: AssetId,
+    #[serde(default)]
+    token_overrides: HashMap<TokenId, TokenThermostatConfig>,
+    #[serde(default)]
+    cleanup_mixed_utxos: bool,
+}
+
+impl TryFrom<ThermostatAlgoConfigFields> for ThermostatAlgoConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(fields: ThermostatAlgoConfigFields) -> Result<Self, Self::Error> {
+        let config = ThermostatAlgoConfig {
+            num_accumulators: fields.num_accumulators,
+            num_accumulators_assets: fields.num_accumulators_assets,
+            native_utxo_thermostat_min: fields.native_utxo_thermostat_min,
+            native_utxo_thermostat_max: fields.native_utxo_thermostat_max,
+            main_token: fields.main_token,
+            token_overrides: fields.token_overrides,
+            cleanup_mixed_utxos: fields.cleanup_mixed_utxos,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// the accumulators and the band of main asset of the changes of one token,
+/// the settings left out are the ones of the [`ThermostatAlgoConfig`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenThermostatConfig {
+    pub num_accumulators: Option<usize>,
+    pub native_utxo_thermostat_min: Option<Value<Regulated>>,
+    pub native_utxo_thermostat_max: Option<Value<Regulated>>,
 }
 
 impl Default for ThermostatAlgoConfig {
@@ -27,14 +79,163 @@ impl Default for ThermostatAlgoConfig {
             num_accumulators_assets: 20,
             native_utxo_thermostat_min: Value::<Regulated>::from(50_000_000),
             native_utxo_thermostat_max: Value::<Regulated>::from(200_000_000),
-            main_token: TokenId::MAIN,
+            main_token: AssetId::Native,
+            token_overrides: HashMap::new(),
+            cleanup_mixed_utxos: false,
+        }
+    }
+}
+
+impl ThermostatAlgoConfig {
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_num_accumulators(self, num_accumulators: usize) -> Self {
+        Self {
+            num_accumulators,
+            ..self
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_num_accumulators_assets(self, num_accumulators_assets: usize) -> Self {
+        Self {
+            num_accumulators_assets,
+            ..self
+        }
+    }
+
+    /// the band of main asset of the changes of the tokens
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_native_utxo_thermostat(self, min: Value<Regulated>, max: Value<Regulated>) -> Self {
+        Self {
+            native_utxo_thermostat_min: min,
+            native_utxo_thermostat_max: max,
+            ..self
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_main_token(self, main_token: AssetId) -> Self {
+        Self { main_token, ..self }
+    }
+
+    /// check the settings are consistent, this is done when the config is
+    /// deserialized
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.num_accumulators == 0 || self.num_accumulators_assets == 0 {
+            bail!("The thermostat requires at least one accumulator");
         }
+        if self.native_utxo_thermostat_max < self.native_utxo_thermostat_min {
+            bail!(
+                "The thermostat max ({}) is below the min ({})",
+                self.native_utxo_thermostat_max,
+                self.native_utxo_thermostat_min
+            );
+        }
+        for (token, config) in self.token_overrides.iter() {
+            if config.num_accumulators == Some(0) {
+                bail!("The thermostat requires at least one accumulator for {token}");
+            }
+            let (min, max) = self.thermostat_band_of(token);
+            if max < min {
+                bail!("The thermostat max ({max}) is below the min ({min}) for {token}");
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_cleanup_mixed_utxos(self, cleanup_mixed_utxos: bool) -> Self {
+        Self {
+            cleanup_mixed_utxos,
+            ..self
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_token_override(mut self, token: TokenId, config: TokenThermostatConfig) -> Self {
+        self.token_overrides.insert(token, config);
+        self
+    }
+
+    fn num_accumulators_of(&self, token: &TokenId) -> usize {
+        self.token_overrides
+            .get(token)
+            .and_then(|config| config.num_accumulators)
+            .unwrap_or(self.num_accumulators_assets)
+    }
+
+    /// the min and max of main asset of the changes of the token
+    fn thermostat_band_of(&self, token: &TokenId) -> (Value<Regulated>, Value<Regulated>) {
+        let config = self.token_overrides.get(token);
+        let min = config
+            .and_then(|config| config.native_utxo_thermostat_min.clone())
+            .unwrap_or_else(|| self.native_utxo_thermostat_min.clone());
+        let max = config
+            .and_then(|config| config.native_utxo_thermostat_max.clone())
+            .unwrap_or_else(|| self.native_utxo_thermostat_max.clone());
+        (min, max)
     }
 }
 
-pub struct Thermostat {
+/// the maintenance transaction recommended for the accumulators of an
+/// asset, see [`Thermostat::accumulator_report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum AccumulatorRecommendation {
+    /// the number of accumulators is within half and twice the target
+    Healthy,
+    /// fewer than half the target: the accumulators should be split
+    Split,
+    /// more than twice the target, or the asset is only held in mixed
+    /// UTxOs: the UTxOs should be consolidated
+    Consolidate,
+}
+
+/// the accumulators of one asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccumulatorHealth {
+    pub asset: AssetId,
+    /// the UTxOs holding only this asset (and the main asset), the ones the
+    /// thermostat selects
+    pub accumulators: usize,
+    /// the number of accumulators of the config
+    pub target: usize,
+    /// the UTxOs holding this asset with other tokens, they are only spent
+    /// with [`ThermostatAlgoConfig::with_cleanup_mixed_utxos`]
+    pub mixed: usize,
+    pub recommendation: AccumulatorRecommendation,
+}
+
+/// the state of the accumulators of a [`UTxOStore`], the assets are in
+/// the order of their [`AssetId`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AccumulatorReport {
+    pub assets: Vec<AccumulatorHealth>,
+}
+
+impl AccumulatorReport {
+    /// the most pressing recommendation of the assets, a consolidation
+    /// first as it reduces the UTxOs every payment goes through
+    pub fn recommendation(&self) -> AccumulatorRecommendation {
+        self.assets
+            .iter()
+            .map(|health| health.recommendation)
+            .max()
+            .unwrap_or(AccumulatorRecommendation::Healthy)
+    }
+
+    /// the assets requiring a maintenance transaction
+    pub fn unhealthy(&self) -> impl Iterator<Item = &AccumulatorHealth> {
+        self.assets
+            .iter()
+            .filter(|health| health.recommendation != AccumulatorRecommendation::Healthy)
+    }
+}
+
+pub struct Thermostat<S = SingleChange> {
     optional_change_address: Option<Address>,
-    changes: HashMap<TokenId, UTxOBuilder>,
+    change_addresses: Option<BoxedChangeAddressProvider>,
+    change_strategy: Option<S>,
+    changes: BTreeMap<AssetId, UTxOBuilder>,
     extra_changes: Vec<UTxOBuilder>,
 
     outputs: Vec<UTxOBuilder>,
@@ -52,7 +253,9 @@ impl Thermostat {
     pub fn new(config: ThermostatAlgoConfig) -> Self {
         Self {
             optional_change_address: None,
-            changes: HashMap::new(),
+            change_addresses: None,
+            change_strategy: None,
+            changes: BTreeMap::new(),
             extra_changes: vec![],
 
             outputs: vec![],
@@ -65,6 +268,116 @@ impl Thermostat {
             available_utxos: Default::default(),
         }
     }
+}
+
+impl<S: ChangeStrategy> Thermostat<S> {
+    /// return the excess of main asset with the given strategy instead of
+    /// the main accumulator, the tokens keep their accumulators
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_strategy<S2: ChangeStrategy>(self, change_strategy: S2) -> Thermostat<S2> {
+        Thermostat {
+            optional_change_address: self.optional_change_address,
+            change_addresses: self.change_addresses,
+            change_strategy: Some(change_strategy),
+            changes: self.changes,
+            extra_changes: self.extra_changes,
+
+            outputs: self.outputs,
+            selected_inputs: self.selected_inputs,
+            selected_inputs_value: self.selected_inputs_value,
+
+            balance: self.balance,
+            asset_balance: self.asset_balance,
+            config: self.config,
+            available_utxos: self.available_utxos,
+        }
+    }
+
+    /// send each new change, including the halves of the split
+    /// accumulators, to the next address of the `provider` instead of the
+    /// change address of the setup
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_change_addresses<P: ChangeAddressProvider + Send + 'static>(
+        self,
+        provider: P,
+    ) -> Self {
+        Self {
+            change_addresses: Some(Box::new(provider)),
+            ..self
+        }
+    }
+
+    /// the provider set with [`Thermostat::with_change_addresses`], e.g. to
+    /// persist its [`ChangeAddressProvider::next_index`] after a selection
+    pub fn change_addresses(&self) -> Option<&(dyn ChangeAddressProvider + Send)> {
+        self.change_addresses.as_deref()
+    }
+
+    /// compare the accumulators of every asset of the `utxos` to the
+    /// targets of the config
+    ///
+    /// The thermostat only consolidates the accumulators above the target
+    /// while paying, and only splits the changes of the payments: the
+    /// report tells when a dedicated maintenance transaction is worth it.
+    pub fn accumulator_report(&self, utxos: &UTxOStore) -> AccumulatorReport {
+        // (accumulators, mixed) of every asset
+        let mut counts = BTreeMap::<AssetId, (usize, usize)>::new();
+        counts.insert(self.config.main_token.clone(), (0, 0));
+        for (_, utxo) in utxos.iter() {
+            let mut tokens = utxo.assets.iter().map(|asset| &asset.fingerprint);
+            let mixed = match tokens.next() {
+                Some(first) => tokens.any(|token| token != first),
+                None => false,
+            };
+
+            // an asset may be in several entries of the UTxO, it is counted
+            // once
+            let mut assets: BTreeSet<AssetId> = utxo
+                .assets
+                .iter()
+                .map(|asset| AssetId::Token(asset.fingerprint.clone()))
+                .collect();
+            if utxo.assets.is_empty() {
+                assets.insert(AssetId::Native);
+            }
+            for asset in assets {
+                let (accumulators, mixed_utxos) = counts.entry(asset).or_default();
+                if mixed {
+                    *mixed_utxos += 1;
+                } else {
+                    *accumulators += 1;
+                }
+            }
+        }
+
+        let assets = counts
+            .into_iter()
+            .map(|(asset, (accumulators, mixed))| {
+                let target = match &asset {
+                    asset if asset == &self.config.main_token => self.config.num_accumulators,
+                    AssetId::Native => self.config.num_accumulators,
+                    AssetId::Token(token) => self.config.num_accumulators_of(token),
+                };
+                let recommendation =
+                    if accumulators > target * 2 || (accumulators == 0 && mixed > 0) {
+                        AccumulatorRecommendation::Consolidate
+                    } else if accumulators * 2 < target {
+                        AccumulatorRecommendation::Split
+                    } else {
+                        AccumulatorRecommendation::Healthy
+                    };
+                AccumulatorHealth {
+                    asset,
+                    accumulators,
+                    target,
+                    mixed,
+                    recommendation,
+                }
+            })
+            .collect();
+
+        AccumulatorReport { assets }
+    }
 
     fn remaining_number_inputs_allowed<
         Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
@@ -129,13 +442,9 @@ impl Thermostat {
         Ok(&self.balance - &estimate.min_required_fee()?)
     }
 
+    /// the balance of the given token, the balance of the native asset is
+    /// given by [`Self::current_balance`]
     fn current_balance_of(&self, asset: &TokenId) -> Balance<Regulated> {
-        debug_assert_ne!(
-            asset,
-            &TokenId::MAIN,
-            "The {asset} can only be used with the current_balance function"
-        );
-
         self.asset_balance
             .get(asset)
             .cloned()
@@ -147,7 +456,7 @@ impl Thermostat {
     >(
         &mut self,
         utxos: UTxOStore,
-        asset: &TokenId,
+        asset: &AssetId,
         estimate: &mut Estimate,
     ) -> anyhow::Result<UTxOStore> {
         let utxo = utxos
@@ -158,13 +467,17 @@ impl Thermostat {
                 // the reasoning for the following 2 checks is that we want to only select the utxo that consists only the asset
                 // that we want to unwrap and nothing else (The utxo can have mixed assets and this is something unhandled later
                 // in the algorithm, so we want to avoid a situation)
-                // TODO: Those checks will require introducing the cleanup strategy algorithm which will be responsible for cleaning up mixed utxos, which
-                // are purposely not used (so eventually they will be used) - the cleanup is required to be implemented (or any other way of handling the issue presented here)
-                utxo.assets.len() <= 1
-                    && utxo
-                        .assets
-                        .iter()
-                        .all(|tx_asset| &tx_asset.fingerprint == asset)
+                // the mixed utxos are spent by `cleanup_mixed_utxos` instead, if enabled in the config
+                match asset {
+                    AssetId::Native => utxo.assets.is_empty(),
+                    AssetId::Token(token_id) => {
+                        utxo.assets.len() <= 1
+                            && utxo
+                                .assets
+                                .iter()
+                                .all(|tx_asset| &tx_asset.fingerprint == token_id)
+                    }
+                }
             })
             .cloned()
             .ok_or_else(|| anyhow!("No more input to select for {asset}"))?;
@@ -199,9 +512,10 @@ impl Thermostat {
         asset: &TokenId,
         estimate: &mut Estimate,
     ) -> anyhow::Result<UTxOStore> {
+        let asset_id = AssetId::Token(asset.clone());
         while let Balance::Debt(debt) = self.current_balance_of(asset) {
             utxos = self
-                .select_input_for(utxos, asset, estimate)
+                .select_input_for(utxos, &asset_id, estimate)
                 .with_context(|| anyhow!("Could not get inputs to fund {debt} for {asset}"))?;
         }
 
@@ -260,7 +574,7 @@ impl Thermostat {
 
                 // setting the entry in a scope so it does not prevent us from
                 // manipulating `self` later
-                let entry = self.changes.entry(asset.clone());
+                let entry = self.changes.entry(AssetId::Token(asset.clone()));
                 let entry: &mut UTxOBuilder = match entry {
                     Entry::Occupied(entry) => {
                         let mut_entry = entry.into_mut();
@@ -269,8 +583,11 @@ impl Thermostat {
                         mut_entry
                     }
                     Entry::Vacant(entry) => {
-                        let mut change =
-                            UTxOBuilder::new(address.clone(), wmain_excess.clone(), vec![]);
+                        let mut change = UTxOBuilder::new(
+                            next_change_address(&mut self.change_addresses, address)?,
+                            wmain_excess.clone(),
+                            vec![],
+                        );
 
                         let min_ada_required = estimate.min_value_for_output(change.clone())?;
 
@@ -281,11 +598,18 @@ impl Thermostat {
                         }
 
                         let fee_for_change = estimate.fee_for_output(&change)?;
-                        if change.value < &min_ada_required + &fee_for_change {
-                            self.balance -= &wmain_excess;
-                        } else {
-                            change.value -= &fee_for_change;
-                            self.balance -= &wmain_excess - &fee_for_change;
+                        match change
+                            .value
+                            .checked_sub(&fee_for_change)
+                            .filter(|value| value >= &min_ada_required)
+                        {
+                            Some(value) => {
+                                change.value = value;
+                                self.balance -= &wmain_excess - &fee_for_change;
+                            }
+                            None => {
+                                self.balance -= &wmain_excess;
+                            }
                         }
 
                         estimate.add_output(change.clone()).map_err(|err| {
@@ -319,9 +643,10 @@ impl Thermostat {
                 }
             }
 
+            let (thermostat_min, thermostat_max) = self.config.thermostat_band_of(&asset);
             let entry = self
                 .changes
-                .get_mut(&asset)
+                .get_mut(&AssetId::Token(asset))
                 .expect("We cannot have a None here since we just added it before");
 
             // TODO: the entry.value should be set to the self.current_balance() excess
@@ -332,13 +657,13 @@ impl Thermostat {
             // because right now we are setting all the excess without
             // balancing it properly
 
-            if entry.value < self.config.native_utxo_thermostat_min {
-                let difference = &self.config.native_utxo_thermostat_max - &entry.value;
-                entry.value = self.config.native_utxo_thermostat_max.clone();
+            if entry.value < thermostat_min {
+                let difference = &thermostat_max - &entry.value;
+                entry.value = thermostat_max;
                 self.balance -= difference;
-            } else if entry.value > self.config.native_utxo_thermostat_max {
-                let difference = &entry.value - &self.config.native_utxo_thermostat_max;
-                entry.value = (self.config.native_utxo_thermostat_max).clone();
+            } else if entry.value > thermostat_max {
+                let difference = &entry.value - &thermostat_max;
+                entry.value = thermostat_max;
                 self.balance += difference;
             }
         }
@@ -362,21 +687,48 @@ impl Thermostat {
                 .ok_or_else(|| anyhow!("Change address required"))?;
 
             match self.changes.entry(self.config.main_token.clone()) {
+                Entry::Vacant(_) if self.change_strategy.is_some() => {
+                    let strategy = self.change_strategy.as_ref().expect("checked above");
+                    // the changes built by the strategy are not accumulators,
+                    // they are not split afterward
+                    let changes = build_excess_changes(
+                        strategy,
+                        estimate,
+                        &mut self.change_addresses,
+                        UTxOBuilder::new(address.clone(), excess, vec![]),
+                    )?;
+                    for change in changes {
+                        self.balance -= &change.value;
+                        self.extra_changes.push(change);
+                    }
+                }
                 Entry::Vacant(entry) => {
-                    let mut change = UTxOBuilder::new(address.clone(), excess.clone(), vec![]);
+                    let start = checkpoint(&self.change_addresses);
+                    let mut change = UTxOBuilder::new(
+                        next_change_address(&mut self.change_addresses, address)?,
+                        excess.clone(),
+                        vec![],
+                    );
                     let min_ada_required = estimate.min_value_for_output(change.clone())?;
 
                     if min_ada_required > change.value {
+                        rewind(&mut self.change_addresses, start);
                         return Ok(());
                     }
 
                     let fee_for_change = estimate.fee_for_output(&change)?;
 
-                    if change.value < &min_ada_required + &fee_for_change {
-                        return Ok(());
-                    }
-
-                    change.value -= &fee_for_change;
+                    change.value = match change
+                        .value
+                        .checked_sub(&fee_for_change)
+                        .filter(|value| value >= &min_ada_required)
+                    {
+                        Some(value) => value,
+                        None => {
+                            rewind(&mut self.change_addresses, start);
+                            return Ok(());
+                        }
+                    };
                     self.balance -= &excess - &fee_for_change;
 
                     estimate.add_output(change.clone()).map_err(|err| {
@@ -415,26 +767,41 @@ impl Thermostat {
                     // be missing out in a potential large chunk of value when computing
                     // the pivot
                     let total_current_balance = total_current_balance + &asset.quantity;
-                    let pivot = total_current_balance / self.config.num_accumulators_assets;
+                    let pivot =
+                        total_current_balance / self.config.num_accumulators_of(&asset.fingerprint);
 
                     if asset.quantity > pivot {
-                        let quantity = &mut new.assets.get_mut(0).unwrap().quantity;
-                        *quantity = (quantity.clone() / 2).truncate();
-                        asset.quantity -= quantity.clone();
+                        // the halves always sum up to the original quantity, so
+                        // no dust is lost when splitting odd quantities
+                        let mut halves = asset.quantity.split_weighted(&[1, 1]);
+                        let moved = halves.pop().expect("two parts requested");
+                        let kept = halves.pop().expect("two parts requested");
+                        new.assets.get_mut(0).unwrap().quantity = moved;
+                        let start = checkpoint(&self.change_addresses);
+                        new.address =
+                            next_change_address(&mut self.change_addresses, &new.address)?;
 
                         let fee_for_output = estimate.fee_for_output(&new)?;
-                        let fee_new = (&fee_for_output / 2).truncate();
-                        let value = (&new.value / 2).truncate();
+                        let fee_new = fee_for_output.percent(1, 2);
+                        let value = new.value.percent(1, 2);
 
                         if value <= fee_new || value <= fee_for_output {
+                            // the accumulator is left untouched, the address
+                            // is given to the next change
+                            rewind(&mut self.change_addresses, start);
                             continue;
                         }
 
+                        asset.quantity = kept;
+
                         // new.value + change.value + fee_for_output = original value
                         // (original valuye / 2 - fee_new) + (original value - original_value / 2 + fee_new - (fee_new + (fee_for_output - fee_new)) + fee_for_output =? original value
 
-                        new.value = &value - &fee_new;
-                        change.value = &change.value - &new.value - &fee_for_output;
+                        new.value = value.saturating_sub(&fee_new);
+                        change.value = change
+                            .value
+                            .checked_sub(&(&new.value + &fee_for_output))
+                            .ok_or_else(|| anyhow!("Splitting the accumulator underflowed"))?;
                         self.balance += &fee_for_output;
 
                         estimate.add_output(new.clone())?;
@@ -446,19 +813,28 @@ impl Thermostat {
                     // the pivot
                     let total_current_balance = total_current_balance + &change.value;
                     let pivot = total_current_balance / self.config.num_accumulators;
-                    let fee_for_output = estimate.fee_for_output(&new)?;
-                    let current = &change.value - &fee_for_output;
+                    let current = change
+                        .value
+                        .saturating_sub(&estimate.fee_for_output(change)?);
 
-                    let fee_new = (&fee_for_output / 2).truncate();
-                    let value = (&new.value / 2).truncate();
+                    if current > pivot {
+                        let start = checkpoint(&self.change_addresses);
+                        new.address =
+                            next_change_address(&mut self.change_addresses, &new.address)?;
+                        let fee_for_output = estimate.fee_for_output(&new)?;
+                        let fee_new = fee_for_output.percent(1, 2);
+                        let value = new.value.percent(1, 2);
 
-                    if value <= fee_new || value <= fee_for_output {
-                        continue;
-                    }
+                        if value <= fee_new || value <= fee_for_output {
+                            rewind(&mut self.change_addresses, start);
+                            continue;
+                        }
 
-                    if current > pivot {
-                        new.value = &value - &fee_new;
-                        change.value = &change.value - &new.value - &fee_for_output;
+                        new.value = value.saturating_sub(&fee_new);
+                        change.value = change
+                            .value
+                            .checked_sub(&(&new.value + &fee_for_output))
+                            .ok_or_else(|| anyhow!("Splitting the accumulator underflowed"))?;
                         self.balance += &fee_for_output;
 
                         self.extra_changes.push(new.clone());
@@ -481,8 +857,8 @@ impl Thermostat {
     ) -> anyhow::Result<()> {
         let mut utxos = self.available_utxos.clone();
 
-        let mut assets: Vec<_> = self.asset_balance.keys().cloned().collect();
-        for asset in &assets {
+        let tokens: Vec<_> = self.asset_balance.keys().cloned().collect();
+        for asset in &tokens {
             // 1. we select the inputs for the asset until we have greater or equal to the
             //    balance for that asset
             utxos = self.select_input_for_asset_until_balanced(utxos, asset, estimator)?;
@@ -507,16 +883,14 @@ impl Thermostat {
 
         // 2. check if there are enough space for more inputs.
 
-        // here we are adding the TokenId::MAIN at the end of the array of
-        // remaining inputs allowed.
+        // here we are adding the main asset to the array of remaining inputs
+        // allowed if there are no tokens.
         //
         // This way it is not the priority yet, we will try to do the operation
-        // on the native asset we have first. But then we will also try to
+        // on the tokens we have first. But then we will also try to
         // add an extra input in it too.
-        //
-        // this value is later popped out so we don't do something silly on the
-        // handling of re-balancing the excess
-        if self.asset_balance.is_empty() || assets.is_empty() {
+        let mut assets: Vec<_> = tokens.iter().cloned().map(AssetId::Token).collect();
+        if assets.is_empty() {
             assets.push(self.config.main_token.clone());
         }
         let mut empty = vec![false; assets.len()];
@@ -543,17 +917,21 @@ impl Thermostat {
             // since we know there is at least one item in the assets array
             index = index.saturating_add(1) % assets.len();
         }
-        // We pop out the TokenId::MAIN so we don't do something silly on the
-        // handling of re-balancing the excess
-        if self.asset_balance.is_empty()
-            || (assets.len() == 1 && assets.first().cloned().unwrap() == self.config.main_token)
-        {
-            let _ = assets.pop();
+        if self.config.cleanup_mixed_utxos {
+            utxos = self.cleanup_mixed_utxos(utxos, estimator)?;
         }
 
-        for asset in assets {
+        // only the tokens are re-balanced here, the excess of the main asset
+        // is handled by `balance_excess`. The mixed UTxOs may have brought
+        // new tokens.
+        let tokens: Vec<_> = self.asset_balance.keys().cloned().collect();
+        for asset in tokens {
             self.balance_excess_of_asset(&utxos, asset, estimator)?;
         }
+        if self.config.cleanup_mixed_utxos {
+            // the changes of the new tokens may require more main asset
+            utxos = self.select_input_for_main_until_balanced(utxos, estimator)?;
+        }
 
         self.balance_excess(estimator)?;
         self.split_accumulators(&utxos, estimator)?;
@@ -561,9 +939,53 @@ impl Thermostat {
         Ok(())
     }
 
+    /// spend the UTxOs holding more than one token while there is room for
+    /// more inputs, so they are not left aside forever
+    ///
+    /// Their tokens end up in the change of each token with
+    /// [`Self::balance_excess_of_asset`].
+    fn cleanup_mixed_utxos<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &mut self,
+        utxos: UTxOStore,
+        estimate: &mut Estimate,
+    ) -> anyhow::Result<UTxOStore> {
+        let mut mixed: Vec<UTxODetails> = utxos
+            .iter()
+            .map(|(_, utxo)| utxo.as_ref().clone())
+            .filter(|utxo| {
+                utxo.assets
+                    .iter()
+                    .any(|asset| asset.fingerprint != utxo.assets[0].fingerprint)
+            })
+            .collect();
+        mixed.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+
+        let mut utxos = utxos.thaw();
+        for utxo in mixed {
+            // keep room for the changes of the new tokens too (up to two
+            // outputs each), roughly one input worth per output
+            let new_tokens = utxo
+                .assets
+                .iter()
+                .filter(|asset| !self.asset_balance.contains_key(&asset.fingerprint))
+                .count();
+            if self.remaining_number_inputs_allowed(estimate)? <= new_tokens * 2 {
+                break;
+            }
+
+            estimate.add_input(utxo.clone())?;
+            utxos.remove(&utxo.pointer)?;
+            self.add_input(utxo);
+        }
+
+        Ok(utxos.freeze())
+    }
+
     pub fn reset(&mut self) {
         self.optional_change_address = None;
-        self.changes = HashMap::new();
+        self.changes = BTreeMap::new();
         self.extra_changes = vec![];
         self.selected_inputs = vec![];
         self.selected_inputs_value = Value::zero();
@@ -572,7 +994,7 @@ impl Thermostat {
     }
 }
 
-impl InputSelectionAlgorithm for Thermostat {
+impl<S: ChangeStrategy> InputSelectionAlgorithm for Thermostat<S> {
     type InputUtxo = UTxODetails;
     type OutputUtxo = UTxOBuilder;
 
@@ -597,6 +1019,8 @@ impl InputSelectionAlgorithm for Thermostat {
         input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
     ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
         self.reset();
+        let (selectable, excluded) = input_output_setup.split_selectable(&self.available_utxos)?;
+        let original_utxos = std::mem::replace(&mut self.available_utxos, selectable);
         self.outputs = input_output_setup.fixed_outputs.clone();
         for (token, asset) in input_output_setup.input_asset_balance.iter() {
             *self
@@ -615,52 +1039,39 @@ impl InputSelectionAlgorithm for Thermostat {
         self.balance -= &input_output_setup.output_balance;
         self.optional_change_address = input_output_setup.change_address;
 
-        self.select(estimator)?;
+        let start = checkpoint(&self.change_addresses);
+        if let Err(error) = self.select(estimator) {
+            self.available_utxos = original_utxos;
+            rewind(&mut self.change_addresses, start);
+            return Err(error);
+        }
+        self.available_utxos = restore_utxos(std::mem::take(&mut self.available_utxos), excluded)?;
 
         let mut input_balance = Value::zero();
-        let mut input_asset_balance = HashMap::new();
+        let mut input_asset_balance = MultiAssetValue::new();
         for input in self
             .selected_inputs
             .iter()
             .chain(input_output_setup.fixed_inputs.iter())
         {
-            for asset in input.assets.iter() {
-                input_asset_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset {
-                        policy_id: asset.policy_id.clone(),
-                        asset_name: asset.asset_name.clone(),
-                        fingerprint: asset.fingerprint.clone(),
-                        quantity: Value::zero(),
-                    })
-                    .quantity += &asset.quantity;
-            }
+            input_asset_balance.extend(input.assets.iter());
             input_balance += &input.value;
         }
         let mut output_balance = Value::zero();
-        let mut output_asset_balance = HashMap::new();
+        let mut output_asset_balance = MultiAssetValue::new();
         for output in self
             .changes
             .values()
             .chain(self.extra_changes.iter())
             .chain(input_output_setup.fixed_outputs.iter())
         {
-            for asset in output.assets.iter() {
-                output_asset_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset {
-                        policy_id: asset.policy_id.clone(),
-                        asset_name: asset.asset_name.clone(),
-                        fingerprint: asset.fingerprint.clone(),
-                        quantity: Value::zero(),
-                    })
-                    .quantity += &asset.quantity;
-            }
+            output_asset_balance.extend(output.assets.iter());
             output_balance += &output.value;
         }
 
         let fee = match &self.balance {
             Balance::Debt(_debt) => {
+                rewind(&mut self.change_addresses, start);
                 return Err(anyhow!("Unbalanced ada"));
             }
             Balance::Balanced => Value::zero(),
@@ -682,7 +1093,9 @@ impl InputSelectionAlgorithm for Thermostat {
                 .cloned()
                 .collect(),
             fee,
-        })
+            trace: None,
+        }
+        .into_stable_order())
     }
 
     fn available_inputs(&self) -> Vec<Self::InputUtxo> {
@@ -691,9 +1104,21 @@ impl InputSelectionAlgorithm for Thermostat {
             .map(|(_, utxo)| utxo.as_ref().clone())
             .collect()
     }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        checkpoint(&self.change_addresses)
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        rewind(&mut self.change_addresses, checkpoint);
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        next_provided_address(&mut self.change_addresses)
+    }
 }
 
-impl UTxOStoreSupport for Thermostat {
+impl<S: ChangeStrategy> UTxOStoreSupport for Thermostat<S> {
     fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
         self.available_utxos = utxos;
         self.reset();
@@ -709,6 +1134,8 @@ impl UTxOStoreSupport for Thermostat {
 mod tests {
     use super::*;
     use crate::estimators::ThermostatFeeEstimator;
+    use crate::verify::{check_balanced, BalanceReport};
+    use crate::{DerivedChangeAddresses, SplitChange};
     use cardano_multiplatform_lib::ledger::common::value::BigNum;
     use cardano_utils::multisig_plan::MultisigPlan;
     use cardano_utils::network_id::NetworkInfo;
@@ -719,33 +1146,7 @@ mod tests {
     use std::sync::Arc;
 
     fn verify_balanced_result(result: &InputSelectionResult<UTxODetails, UTxOBuilder>) {
-        assert_eq!(
-            result.input_balance.clone() - &result.output_balance - &result.fee,
-            Value::zero()
-        );
-        let mut balance_by_token = HashMap::<TokenId, Value<Regulated>>::new();
-        for input in result.fixed_inputs.iter().chain(&result.chosen_inputs) {
-            *balance_by_token.entry(TokenId::MAIN).or_default() += &input.value;
-            for asset in input.assets.iter() {
-                *balance_by_token
-                    .entry(asset.fingerprint.clone())
-                    .or_default() += &asset.quantity;
-            }
-        }
-
-        for output in result.fixed_outputs.iter().chain(&result.changes) {
-            *balance_by_token.entry(TokenId::MAIN).or_default() -= &output.value;
-            for asset in output.assets.iter() {
-                *balance_by_token
-                    .entry(asset.fingerprint.clone())
-                    .or_default() -= &asset.quantity;
-            }
-        }
-        *balance_by_token.entry(TokenId::MAIN).or_default() -= &result.fee;
-
-        for (_token, value) in balance_by_token.into_iter() {
-            assert_eq!(value, Value::zero());
-        }
+        assert_eq!(check_balanced(result), BalanceReport::default());
     }
     fn thermostat_config() -> ThermostatAlgoConfig {
         ThermostatAlgoConfig {
@@ -753,7 +1154,9 @@ mod tests {
             num_accumulators_assets: 20,
             native_utxo_thermostat_min: Value::<Regulated>::from(50_000_000),
             native_utxo_thermostat_max: Value::<Regulated>::from(200_000_000),
-            main_token: TokenId::MAIN,
+            main_token: AssetId::Native,
+            token_overrides: HashMap::new(),
+            cleanup_mixed_utxos: false,
         }
     }
     /// helper function to prepare a basic `Selection` structure
@@ -845,7 +1248,7 @@ mod tests {
             $utxo_store
                 .insert(UTxODetails {
                     pointer: UtxoPointer {
-                        transaction_id: TransactionId::new($TxId),
+                        transaction_id: TransactionId::new_unchecked($TxId),
                         output_index: OutputIndex::new($OutputIndex),
                     },
                     address: Address::new_static(
@@ -854,7 +1257,11 @@ mod tests {
                     value: $value.parse().unwrap(),
                     assets: utxo_asset_sample!($($assets)*),
                     metadata: Arc::new(serde_json::Value::Null),
+                    datum: None,
+                    script_ref: None,
                     extra: None,
+                    creation_height: None,
+                    script_spend: None,
                 })
                 .unwrap();
         };
@@ -923,12 +1330,15 @@ mod tests {
             output_asset_balance: HashMap::from([(
                 my_token,
                 output.assets.first().cloned().unwrap(),
-            )]),
+            )])
+            .into(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -944,7 +1354,7 @@ mod tests {
 
         assert_eq!(
             input.pointer.transaction_id,
-            TransactionId::new("transaction 1")
+            TransactionId::new_unchecked("transaction 1")
         );
         assert_eq!(input.assets[0].fingerprint, TokenId::new("My Token"));
 
@@ -1017,12 +1427,15 @@ mod tests {
             output_asset_balance: HashMap::from([(
                 TokenId::new("m10s18"),
                 output.assets.first().cloned().unwrap(),
-            )]),
+            )])
+            .into(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wz6lvjg3anml96vl22mls5vae3x2cgaqwy2ewp5gj3fcxdcw652wz",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1037,7 +1450,9 @@ mod tests {
 
         assert_eq!(
             input.pointer.transaction_id,
-            TransactionId::new("7eb432314c5b25609ec7c708a4615a9ee7546aacb0118915ef965c092091ce54")
+            TransactionId::new_unchecked(
+                "7eb432314c5b25609ec7c708a4615a9ee7546aacb0118915ef965c092091ce54"
+            )
         );
         assert_eq!(input.assets[0].fingerprint, TokenId::new("m10s18"));
 
@@ -1069,6 +1484,331 @@ mod tests {
         );
     }
 
+    /// the excess of main asset is returned with the change strategy
+    /// instead of the main accumulator
+    #[test]
+    fn test_thermostat_change_strategy() {
+        let (thermostat, mut estimator) = selection();
+        let mut thermostat = thermostat.with_change_strategy(SplitChange::new(3));
+        thermostat.set_available_utxos(utxos(1)).unwrap();
+
+        let (address, value, assets) = sample_output();
+        let output = UTxOBuilder::new(address.clone(), value.clone(), assets);
+        estimator.add_output(output.clone()).unwrap();
+        let setup = InputOutputSetup::<UTxODetails, UTxOBuilder> {
+            output_balance: value,
+            fixed_outputs: vec![output],
+            change_address: Some(address),
+            ..InputOutputSetup::default()
+        };
+
+        let result = thermostat.select_inputs(&mut estimator, setup).unwrap();
+
+        verify_balanced_result(&result);
+        assert_eq!(
+            result
+                .changes
+                .iter()
+                .filter(|change| change.assets.is_empty())
+                .count(),
+            3
+        );
+    }
+
+    /// the change addresses are only taken for the changes of a successful
+    /// selection
+    #[test]
+    fn test_thermostat_change_addresses_not_burnt() {
+        const CHANGE_ADDRESS: &str =
+            "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj";
+
+        let (thermostat, mut estimator) = selection();
+        let mut thermostat = thermostat.with_change_addresses(DerivedChangeAddresses::new(|_| {
+            Ok(Address::new(CHANGE_ADDRESS))
+        }));
+        thermostat.set_available_utxos(utxos(1)).unwrap();
+        let next_index = |thermostat: &Thermostat| {
+            thermostat
+                .change_addresses()
+                .and_then(|provider| provider.next_index())
+        };
+
+        let (address, _, _) = sample_output();
+        let setup = |value: &str, assets: Vec<TransactionAsset>| {
+            let output = UTxOBuilder::new(address.clone(), value.parse().unwrap(), assets);
+            InputOutputSetup::<UTxODetails, UTxOBuilder> {
+                output_balance: output.value.clone(),
+                output_asset_balance: output.assets.iter().collect(),
+                fixed_outputs: vec![output],
+                change_address: Some(Address::new(CHANGE_ADDRESS)),
+                ..InputOutputSetup::default()
+            }
+        };
+
+        // the change of the token is built before the selection fails on
+        // the main asset
+        let too_much = setup("2_000_000000", utxo_asset_sample!("tDRIP", "1"));
+        estimator
+            .add_output(too_much.fixed_outputs[0].clone())
+            .unwrap();
+        assert!(thermostat.select_inputs(&mut estimator, too_much).is_err());
+        assert_eq!(next_index(&thermostat), Some(0));
+
+        let (_, mut estimator) = selection();
+        let payment = setup("3_000000", vec![]);
+        estimator
+            .add_output(payment.fixed_outputs[0].clone())
+            .unwrap();
+        let result = thermostat.select_inputs(&mut estimator, payment).unwrap();
+
+        verify_balanced_result(&result);
+        assert!(!result.changes.is_empty());
+        assert_eq!(next_index(&thermostat), Some(result.changes.len() as u32));
+    }
+
+    #[test]
+    fn test_thermostat_config_validation() {
+        let json = serde_json::to_value(thermostat_config()).unwrap();
+        let config: ThermostatAlgoConfig = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(config.num_accumulators, 20);
+
+        let mut invalid = json.clone();
+        invalid["native_utxo_thermostat_max"] = serde_json::json!("1000000");
+        let error = serde_json::from_value::<ThermostatAlgoConfig>(invalid).unwrap_err();
+        assert!(error.to_string().contains("is below the min"));
+
+        let mut invalid = json;
+        invalid["num_accumulators"] = serde_json::json!(0);
+        assert!(serde_json::from_value::<ThermostatAlgoConfig>(invalid).is_err());
+
+        assert!(thermostat_config()
+            .with_token_override(
+                TokenId::new("My Token"),
+                TokenThermostatConfig {
+                    native_utxo_thermostat_min: Some(Value::from(300_000_000)),
+                    ..TokenThermostatConfig::default()
+                },
+            )
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_accumulator_report() {
+        use crate::algorithms::test_utils::{create_asset, create_utxo};
+
+        let mut utxos = UTxOStore::new().thaw();
+        for index in 0..50 {
+            utxos
+                .insert(create_utxo(
+                    0,
+                    index,
+                    "0".to_string(),
+                    Value::from(10),
+                    vec![],
+                ))
+                .unwrap();
+        }
+        for index in 0..15 {
+            let assets = vec![create_asset("token".to_string(), Value::from(10))];
+            utxos
+                .insert(create_utxo(
+                    1,
+                    index,
+                    "0".to_string(),
+                    Value::from(10),
+                    assets,
+                ))
+                .unwrap();
+        }
+        let mixed = vec![
+            create_asset("token".to_string(), Value::from(10)),
+            create_asset("other".to_string(), Value::from(10)),
+        ];
+        utxos
+            .insert(create_utxo(2, 0, "0".to_string(), Value::from(10), mixed))
+            .unwrap();
+        // the token is counted once even when not in consecutive entries
+        let mixed = vec![
+            create_asset("token".to_string(), Value::from(5)),
+            create_asset("other".to_string(), Value::from(10)),
+            create_asset("token".to_string(), Value::from(5)),
+        ];
+        utxos
+            .insert(create_utxo(2, 1, "0".to_string(), Value::from(10), mixed))
+            .unwrap();
+        let utxos = utxos.freeze();
+
+        let thermostat = Thermostat::new(thermostat_config().with_token_override(
+            TokenId::new("token"),
+            TokenThermostatConfig {
+                num_accumulators: Some(40),
+                ..TokenThermostatConfig::default()
+            },
+        ));
+        let report = thermostat.accumulator_report(&utxos);
+
+        let health = |asset: AssetId| {
+            report
+                .assets
+                .iter()
+                .find(|health| health.asset == asset)
+                .unwrap()
+                .clone()
+        };
+        let native = health(AssetId::Native);
+        assert_eq!((native.accumulators, native.target), (50, 20));
+        assert_eq!(
+            native.recommendation,
+            AccumulatorRecommendation::Consolidate
+        );
+        let token = health(AssetId::Token(TokenId::new("token")));
+        assert_eq!((token.accumulators, token.target, token.mixed), (15, 40, 2));
+        assert_eq!(token.recommendation, AccumulatorRecommendation::Split);
+        let other = health(AssetId::Token(TokenId::new("other")));
+        assert_eq!(other.recommendation, AccumulatorRecommendation::Consolidate);
+
+        assert_eq!(
+            report.recommendation(),
+            AccumulatorRecommendation::Consolidate
+        );
+        assert_eq!(report.unhealthy().count(), 3);
+    }
+
+    /// test the mixed UTxOs are spent and their tokens split in the changes
+    #[test]
+    fn test_thermostat_cleanup_mixed_utxos() {
+        let mut utxos = UTxOStore::new().thaw();
+        utxo_sample!(utxos, "transaction 1", 0, "500_000000",);
+        let mixed = UTxODetails {
+            pointer: UtxoPointer {
+                transaction_id: TransactionId::new_unchecked("transaction 2"),
+                output_index: OutputIndex::new(0),
+            },
+            address: Address::new_static(
+                "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
+            ),
+            value: "5_000000".parse().unwrap(),
+            assets: ["00000001", "00000002"]
+                .into_iter()
+                .map(|name| TransactionAsset {
+                    policy_id: PolicyId::new(
+                        "00000000000000000000000000000000000000000000000000000000",
+                    ),
+                    asset_name: AssetName::new(name),
+                    fingerprint: TokenId::new(name),
+                    quantity: Value::from(10),
+                })
+                .collect(),
+            metadata: Arc::new(serde_json::Value::Null),
+            datum: None,
+            script_ref: None,
+            extra: None,
+            creation_height: None,
+            script_spend: None,
+        };
+        utxos.insert(mixed.clone()).unwrap();
+        let utxos = utxos.freeze();
+
+        let (address, value, assets) = sample_output();
+        let output = UTxOBuilder::new(address, value, assets);
+
+        let (_, mut estimator) = selection();
+        estimator.add_protocol_magic("unittest.cardano-evm.c1");
+        let mut thermostat = Thermostat::new(thermostat_config().with_cleanup_mixed_utxos(true));
+        thermostat.set_available_utxos(utxos).unwrap();
+        estimator.add_output(output.clone()).unwrap();
+
+        let result = thermostat
+            .select_inputs(
+                &mut estimator,
+                InputOutputSetup::from_fixed_inputs_and_outputs(
+                    vec![],
+                    vec![output],
+                    Some(Address::new(
+                        "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
+                    )),
+                ),
+            )
+            .unwrap();
+        verify_balanced_result(&result);
+
+        assert!(result
+            .chosen_inputs
+            .iter()
+            .any(|input| input.pointer == mixed.pointer));
+        for token in ["00000001", "00000002"] {
+            let change = result
+                .changes
+                .iter()
+                .find(|change| {
+                    change
+                        .assets
+                        .iter()
+                        .any(|asset| asset.fingerprint == TokenId::new(token))
+                })
+                .unwrap();
+            assert_eq!(change.assets.len(), 1);
+            assert_eq!(change.assets[0].quantity, Value::from(10));
+        }
+    }
+
+    /// test the band of main asset of a token can be overridden
+    #[test]
+    fn test_thermostat_token_override() {
+        let mut utxos = UTxOStore::new().thaw();
+        utxo_sample!(
+            utxos,
+            "transaction 1",
+            0,
+            "51_000000",
+            "My Token",
+            "9_000_000_000_000"
+        );
+        utxo_sample!(utxos, "transaction 2", 0, "9_000_000_000000",);
+        let utxos = utxos.freeze();
+
+        let output = UTxOBuilder::new(
+            Address::new("addr_test1qqpftzcepsz6c4ecapkr8vzxmyev8yqlny53xp3kxd4p3kuzn0g6ackzyh9r2kj9kgdqx6npjulm3fy6fe9v6unwxxkqxjer8j"),
+            "3_000000".parse().unwrap(),
+            utxo_asset_sample!("My Token", "1_000_000"),
+        );
+
+        let (_, mut estimator) = selection();
+        estimator.add_protocol_magic("unittest.cardano-evm.c1");
+        let mut thermostat = Thermostat::new(thermostat_config().with_token_override(
+            TokenId::new("My Token"),
+            TokenThermostatConfig {
+                native_utxo_thermostat_min: Some(Value::from(60_000_000)),
+                native_utxo_thermostat_max: Some(Value::from(80_000_000)),
+                ..TokenThermostatConfig::default()
+            },
+        ));
+        thermostat.set_available_utxos(utxos).unwrap();
+        estimator.add_output(output.clone()).unwrap();
+
+        let result = thermostat
+            .select_inputs(
+                &mut estimator,
+                InputOutputSetup::from_fixed_inputs_and_outputs(
+                    vec![],
+                    vec![output],
+                    Some(Address::new(
+                        "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
+                    )),
+                ),
+            )
+            .unwrap();
+        verify_balanced_result(&result);
+
+        let change = result
+            .changes
+            .iter()
+            .find(|change| !change.assets.is_empty())
+            .unwrap();
+        assert_eq!(change.value, Value::<Regulated>::from(80_000_000));
+    }
+
     /// test the thermostat that if we go under the min threshold we request
     /// more ada to go in the UTxO
     #[test]
@@ -1103,12 +1843,15 @@ mod tests {
             output_asset_balance: HashMap::from([(
                 TokenId::new("My Token"),
                 output.assets.first().cloned().unwrap(),
-            )]),
+            )])
+            .into(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1122,13 +1865,13 @@ mod tests {
         let input = inputs[0].clone();
         assert_eq!(
             input.pointer.transaction_id,
-            TransactionId::new("transaction 1")
+            TransactionId::new_unchecked("transaction 1")
         );
         assert_eq!(input.assets[0].fingerprint, TokenId::new("My Token"));
         let input = inputs[1].clone();
         assert_eq!(
             input.pointer.transaction_id,
-            TransactionId::new("transaction 2")
+            TransactionId::new_unchecked("transaction 2")
         );
         assert!(input.assets.is_empty());
 
@@ -1204,7 +1947,7 @@ mod tests {
 
         // total ada
         let total_ada = Value::<cardano::Lovelace>::from_regulated(
-            &utxos.get_balance_of(&TokenId::MAIN).unwrap(),
+            &utxos.get_balance_of(&AssetId::Native).unwrap(),
         )
         .to_ada();
         assert_eq!(total_ada, Value::from(10_000_000));
@@ -1225,12 +1968,14 @@ mod tests {
             input_balance: Default::default(),
             input_asset_balance: Default::default(),
             output_balance: output.value.clone(),
-            output_asset_balance: HashMap::new(),
+            output_asset_balance: Default::default(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1244,7 +1989,7 @@ mod tests {
         let input = inputs[0].clone();
         assert_eq!(
             input.pointer.transaction_id,
-            TransactionId::new("transaction 1")
+            TransactionId::new_unchecked("transaction 1")
         );
 
         let outputs = result.fixed_outputs;
@@ -1293,7 +2038,7 @@ mod tests {
 
         // total ada
         let total_ada = Value::<cardano::Lovelace>::from_regulated(
-            &utxos.get_balance_of(&TokenId::MAIN).unwrap(),
+            &utxos.get_balance_of(&AssetId::Native).unwrap(),
         )
         .to_ada();
         assert_eq!(total_ada, Value::from(10_000_000));
@@ -1314,12 +2059,14 @@ mod tests {
             input_balance: Default::default(),
             input_asset_balance: Default::default(),
             output_balance: output.value.clone(),
-            output_asset_balance: HashMap::new(),
+            output_asset_balance: Default::default(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1380,7 +2127,7 @@ mod tests {
         let utxos = utxos.freeze();
 
         // total ada
-        let total_ada = utxos.get_balance_of(&TokenId::MAIN).unwrap();
+        let total_ada = utxos.get_balance_of(&AssetId::Native).unwrap();
         assert_eq!(total_ada, Value::from(20_000_000_000_000));
         assert_eq!(
             total_ada / thermostat_config().num_accumulators,
@@ -1399,12 +2146,14 @@ mod tests {
             input_balance: Default::default(),
             input_asset_balance: Default::default(),
             output_balance: output.value.clone(),
-            output_asset_balance: HashMap::new(),
+            output_asset_balance: Default::default(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1467,16 +2216,14 @@ mod tests {
             input_balance: Default::default(),
             input_asset_balance: Default::default(),
             output_balance: output.value.clone(),
-            output_asset_balance: HashMap::from_iter(
-                output_assets
-                    .iter()
-                    .map(|asset| (asset.fingerprint.clone(), asset.clone())),
-            ),
+            output_asset_balance: output_assets.iter().collect(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -1507,16 +2254,14 @@ mod tests {
             input_balance: Default::default(),
             input_asset_balance: Default::default(),
             output_balance: output.value.clone(),
-            output_asset_balance: HashMap::from_iter(
-                output_assets
-                    .iter()
-                    .map(|asset| (asset.fingerprint.clone(), asset.clone())),
-            ),
+            output_asset_balance: output_assets.iter().collect(),
             fixed_inputs: vec![],
             fixed_outputs: vec![output.clone()],
             change_address: Some(Address::new(
                 "addr_test1wpjf80wvstelml6vw7d46y6j6575klf3s4mxp7ytrcrz5ecl33pgj",
             )),
+            excluded_inputs: Default::default(),
+            collateral: None,
         };
 
         thermostat.set_available_utxos(utxos).unwrap();
@@ -0,0 +1,313 @@
+use crate::{
+    assets_in_selection_order, ConstraintViolation, InputOutputSetup, TransactionFeeEstimator,
+};
+use anyhow::{anyhow, bail};
+use dcspark_core::tx::{MultiAssetValue, UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, AssetId, Regulated, UTxOStore, Value};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// how the payments to the same address are combined in a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum OutputDeduplication {
+    /// one output per payment
+    #[default]
+    Keep,
+    /// the payments to the same address are merged in one output, the
+    /// payments with a datum, a reference script or extra data are never
+    /// merged
+    MergeByAddress,
+}
+
+/// the settings of [`batch_payments`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchConfig {
+    /// the maximum number of payment outputs of a transaction, leave room
+    /// for the changes if the transaction has a limit on its outputs
+    pub max_outputs: usize,
+    #[serde(default)]
+    pub deduplication: OutputDeduplication,
+}
+
+/// combine the payments according to the `deduplication` policy, the
+/// outputs are in the order of the first payment to their address
+pub fn deduplicate_payments(
+    payments: Vec<UTxOBuilder>,
+    deduplication: OutputDeduplication,
+) -> Vec<UTxOBuilder> {
+    if deduplication == OutputDeduplication::Keep {
+        return payments;
+    }
+
+    let mut outputs: Vec<UTxOBuilder> = Vec::with_capacity(payments.len());
+    let mut merged = HashMap::<Address, usize>::new();
+    for payment in payments {
+        let mergeable =
+            payment.datum.is_none() && payment.script_ref.is_none() && payment.extra.is_none();
+        if !mergeable {
+            outputs.push(payment);
+            continue;
+        }
+
+        match merged.get(&payment.address) {
+            Some(&index) => {
+                let output = &mut outputs[index];
+                output.value += &payment.value;
+                let mut assets = MultiAssetValue::new();
+                assets.extend(output.assets.iter());
+                assets.extend(payment.assets.iter());
+                output.assets = assets_in_selection_order(&assets)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+            }
+            None => {
+                merged.insert(payment.address.clone(), outputs.len());
+                outputs.push(payment);
+            }
+        }
+    }
+    outputs
+}
+
+/// the setups of the transactions paying all the `payments` from the
+/// `available_utxos`
+///
+/// The payments are deduplicated then split, in order, in transactions of
+/// at most [`BatchConfig::max_outputs`] outputs whose outputs fit in the
+/// [`TransactionFeeEstimator::max_size`] of the `estimator`, or in its
+/// [`ConstraintViolation`]s. The inputs and the changes are added by the
+/// selection of each transaction, leave room for them in the limits. The
+/// setups have no fixed inputs: the selection covers every output.
+///
+/// Fails if the payments exceed the value of the `available_utxos`, or if
+/// a payment does not fit in a transaction on its own.
+pub fn batch_payments<Estimate>(
+    payments: Vec<UTxOBuilder>,
+    config: &BatchConfig,
+    change_address: Option<Address>,
+    estimator: &Estimate,
+    available_utxos: &UTxOStore,
+) -> anyhow::Result<Vec<InputOutputSetup<UTxODetails, UTxOBuilder>>>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    if config.max_outputs == 0 {
+        bail!("A batch requires at least one output per transaction");
+    }
+
+    let outputs = deduplicate_payments(payments, config.deduplication);
+    check_available_value(&outputs, available_utxos)?;
+
+    let fork = || {
+        estimator
+            .fork()
+            .ok_or_else(|| anyhow!("The estimator can't be copied to size the batches"))
+    };
+    let mut batches = vec![];
+    let mut batch = vec![];
+    let mut batch_estimator = fork()?;
+    for output in outputs {
+        if batch.len() < config.max_outputs && fits(&mut batch_estimator, &output)? {
+            batch.push(output);
+            continue;
+        }
+
+        if !batch.is_empty() {
+            batches.push(std::mem::take(&mut batch));
+            batch_estimator = fork()?;
+        }
+        if !fits(&mut batch_estimator, &output)? {
+            bail!(
+                "The payment of {} to {} does not fit in a transaction",
+                output.value,
+                output.address
+            );
+        }
+        batch.push(output);
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    Ok(batches
+        .into_iter()
+        .map(|outputs| {
+            InputOutputSetup::from_fixed_inputs_and_outputs(vec![], outputs, change_address.clone())
+        })
+        .collect())
+}
+
+/// add the `output` to the `estimator` of the batch, `false` if the
+/// transaction is then too large
+fn fits<Estimate>(estimator: &mut Estimate, output: &UTxOBuilder) -> anyhow::Result<bool>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    match estimator.add_output(output.clone()) {
+        Ok(()) => Ok(estimator.current_size()? <= estimator.max_size()?),
+        Err(error) if error.downcast_ref::<ConstraintViolation>().is_some() => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// check the `available_utxos` hold the main asset and the tokens of the
+/// `outputs`
+fn check_available_value(
+    outputs: &[UTxOBuilder],
+    available_utxos: &UTxOStore,
+) -> anyhow::Result<()> {
+    let available = |asset: &AssetId| {
+        available_utxos
+            .get_balance_of(asset)
+            .unwrap_or_else(Value::zero)
+    };
+
+    let value = outputs
+        .iter()
+        .fold(Value::<Regulated>::zero(), |value, output| {
+            value + &output.value
+        });
+    let available_value = available(&AssetId::Native);
+    if value > available_value {
+        bail!("The payments ({value}) exceed the available value ({available_value})");
+    }
+
+    let assets: MultiAssetValue = outputs
+        .iter()
+        .flat_map(|output| output.assets.iter())
+        .collect();
+    for asset in assets.assets() {
+        let available_value = available(&AssetId::Token(asset.fingerprint.clone()));
+        if asset.quantity > available_value {
+            bail!(
+                "The payments of {} ({}) exceed the available value ({available_value})",
+                asset.fingerprint,
+                asset.quantity
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::properties::LinearFeeEstimate;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{ConstrainedEstimator, SelectionConstraints};
+    use dcspark_core::TokenId;
+
+    fn payment(address: &'static str, value: u64) -> UTxOBuilder {
+        UTxOBuilder::new(Address::new(address), Value::from(value), vec![])
+    }
+
+    fn wallet(value: u64, assets: Vec<dcspark_core::tx::TransactionAsset>) -> UTxOStore {
+        let mut store = UTxOStore::new().thaw();
+        store
+            .insert(create_utxo(
+                0,
+                0,
+                "0".to_string(),
+                Value::from(value),
+                assets,
+            ))
+            .unwrap();
+        store.freeze()
+    }
+
+    #[test]
+    fn merge_by_address() {
+        let mut with_tokens = payment("a", 2);
+        with_tokens.assets = vec![create_asset("kek".to_string(), Value::from(3))];
+        let payments = vec![payment("a", 1), payment("b", 5), with_tokens];
+
+        let outputs = deduplicate_payments(payments, OutputDeduplication::MergeByAddress);
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].address, Address::new("a"));
+        assert_eq!(outputs[0].value, Value::from(3));
+        assert_eq!(outputs[0].assets[0].fingerprint, TokenId::new("kek"));
+        assert_eq!(outputs[1].value, Value::from(5));
+    }
+
+    #[test]
+    fn split_in_transactions() {
+        let payments = (0..5).map(|value| payment("a", value + 1)).collect();
+        let config = BatchConfig {
+            max_outputs: 2,
+            deduplication: OutputDeduplication::Keep,
+        };
+
+        let setups = batch_payments(
+            payments,
+            &config,
+            Some(Address::new("change")),
+            &DummyFeeEstimate::new(),
+            &wallet(100, vec![]),
+        )
+        .unwrap();
+
+        assert_eq!(setups.len(), 3);
+        assert_eq!(setups[0].fixed_outputs.len(), 2);
+        assert_eq!(setups[0].output_balance, Value::from(3));
+        assert_eq!(setups[2].fixed_outputs.len(), 1);
+        assert_eq!(setups[2].change_address, Some(Address::new("change")));
+    }
+
+    #[test]
+    fn split_by_size() {
+        let payments = (0..5).map(|value| payment("a", value + 1)).collect();
+        let config = BatchConfig {
+            max_outputs: 10,
+            deduplication: OutputDeduplication::Keep,
+        };
+        // the size of the estimator is the number of inputs and outputs
+        let estimator = ConstrainedEstimator::new(
+            LinearFeeEstimate::default(),
+            SelectionConstraints {
+                max_tx_bytes: Some(2),
+                ..SelectionConstraints::default()
+            },
+        );
+
+        let setups =
+            batch_payments(payments, &config, None, &estimator, &wallet(100, vec![])).unwrap();
+
+        let outputs = setups
+            .iter()
+            .map(|setup| setup.fixed_outputs.len())
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn payments_above_available_value() {
+        let config = BatchConfig {
+            max_outputs: 2,
+            deduplication: OutputDeduplication::Keep,
+        };
+        let payments = vec![payment("a", 6), payment("b", 5)];
+        assert!(batch_payments(
+            payments,
+            &config,
+            None,
+            &DummyFeeEstimate::new(),
+            &wallet(10, vec![])
+        )
+        .is_err());
+
+        let mut with_tokens = payment("a", 1);
+        with_tokens.assets = vec![create_asset("kek".to_string(), Value::from(3))];
+        let wallet = wallet(10, vec![create_asset("kek".to_string(), Value::from(2))]);
+        assert!(batch_payments(
+            vec![with_tokens],
+            &config,
+            None,
+            &DummyFeeEstimate::new(),
+            &wallet
+        )
+        .is_err());
+    }
+}
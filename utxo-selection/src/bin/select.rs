@@ -0,0 +1,257 @@
+//! Simulate a coin selection on a snapshot of UTxOs
+//!
+//! The UTxOs are the JSON array written by `utxo-dump --format json`, the
+//! payments a JSON array of [`UTxOBuilder`]. The selection runs in a
+//! [`SelectionPipeline`] balanced by a [`SingleOutputChangeBalancer`], the
+//! chosen inputs, the changes and the fee are printed as JSON.
+//!
+//! ```sh
+//! cargo run -p utxo-selection --bin select -- \
+//!     --utxos utxos.json --payments payments.json \
+//!     --change-address addr_test1... --plan plan.json --compare
+//! ```
+//!
+//! With `--compare` every algorithm runs on the same snapshot and a table
+//! of the number of inputs, the number of changes and the fee is printed.
+
+use anyhow::{anyhow, bail, Context};
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_utils::multisig_plan::MultisigPlan;
+use cardano_utils::network_id::NetworkInfo;
+use cardano_utils::payment_credentials::CardanoPaymentCredentials;
+use clap::{ArgEnum, Parser};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, UTxOStore};
+use deps::serde_json;
+use std::path::{Path, PathBuf};
+use utxo_selection::algorithms::{
+    Knapsack, LargestFirst, LargestFirstMultiAsset, RandomImprove, SingleOutputChangeBalancer,
+    SingleRandomDraw, Thermostat, ThermostatAlgoConfig,
+};
+use utxo_selection::estimators::{CmlFeeEstimator, ProtocolParameters, ThermostatFeeEstimator};
+use utxo_selection::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, SelectionPipeline,
+    TransactionFeeEstimator, UTxOStoreSupport,
+};
+
+/// `utxoCostPerByte` of the babbage era, used without `--protocol-parameters`
+const COINS_PER_UTXO_BYTE: u64 = 4310;
+
+#[derive(Parser, Debug)]
+#[clap(version)]
+struct Cli {
+    /// the UTxOs to select from, a JSON array as written by `utxo-dump`
+    #[clap(long)]
+    utxos: PathBuf,
+    /// the outputs to pay, a JSON array of `{address, value, assets}`
+    #[clap(long)]
+    payments: PathBuf,
+    #[clap(long)]
+    change_address: String,
+    #[clap(long, arg_enum, default_value = "thermostat")]
+    algorithm: Algorithm,
+    /// run every algorithm and print a comparison table
+    #[clap(long)]
+    compare: bool,
+    /// seed of the randomized algorithms
+    #[clap(long, default_value = "0")]
+    seed: u64,
+    #[clap(long, arg_enum, default_value = "thermostat")]
+    estimator: Estimator,
+    /// the multisig plan of the inputs, `{"quorum": .., "keys": [..]}`,
+    /// required by the thermostat estimator
+    #[clap(long)]
+    plan: Option<PathBuf>,
+    #[clap(long, arg_enum, default_value = "testnet")]
+    network: Network,
+    /// protocol parameters as written by `cardano-cli query protocol-parameters`,
+    /// required by the cml estimator
+    #[clap(long)]
+    protocol_parameters: Option<PathBuf>,
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Thermostat,
+    LargestFirst,
+    LargestFirstMultiAsset,
+    RandomImprove,
+    SingleRandomDraw,
+    Knapsack,
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Estimator {
+    Thermostat,
+    Cml,
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl From<Network> for NetworkInfo {
+    fn from(network: Network) -> Self {
+        match network {
+            Network::Mainnet => NetworkInfo::Mainnet,
+            Network::Testnet => NetworkInfo::Testnet,
+        }
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("couldn't parse {}", path.display()))
+}
+
+fn store(utxos: Vec<UTxODetails>) -> anyhow::Result<UTxOStore> {
+    let mut store = UTxOStore::new().thaw();
+    for utxo in utxos {
+        store.insert(utxo)?;
+    }
+    Ok(store.freeze())
+}
+
+fn run<Primary, Estimate>(
+    primary: Primary,
+    estimator: &Estimate,
+    setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>>
+where
+    Primary: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    let mut estimator = estimator
+        .fork()
+        .ok_or_else(|| anyhow!("The estimator can't be copied to run the selection"))?;
+    // the algorithms only account for the inputs and the changes they add
+    for output in setup.fixed_outputs.iter() {
+        estimator.add_output(output.clone())?;
+    }
+    SelectionPipeline::new(primary, SingleOutputChangeBalancer::default())
+        .select(&mut estimator, setup)
+}
+
+fn select<Estimate>(
+    algorithm: Algorithm,
+    utxos: &UTxOStore,
+    estimator: &Estimate,
+    setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    seed: u64,
+) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    let utxos = utxos.clone();
+    match algorithm {
+        Algorithm::Thermostat => {
+            let mut thermostat = Thermostat::new(ThermostatAlgoConfig::default());
+            thermostat.set_available_utxos(utxos)?;
+            run(thermostat, estimator, setup)
+        }
+        Algorithm::LargestFirst => run(LargestFirst::try_from(utxos)?, estimator, setup),
+        Algorithm::LargestFirstMultiAsset => {
+            run(LargestFirstMultiAsset::try_from(utxos)?, estimator, setup)
+        }
+        Algorithm::RandomImprove => run(
+            RandomImprove::try_from(utxos)?.with_seed(seed),
+            estimator,
+            setup,
+        ),
+        Algorithm::SingleRandomDraw => run(
+            SingleRandomDraw::try_from(utxos)?.with_seed(seed),
+            estimator,
+            setup,
+        ),
+        Algorithm::Knapsack => run(Knapsack::try_from(utxos)?, estimator, setup),
+    }
+}
+
+fn simulate<Estimate>(
+    cli: &Cli,
+    utxos: &UTxOStore,
+    estimator: &Estimate,
+    setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+) -> anyhow::Result<()>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    if !cli.compare {
+        let result = select(cli.algorithm, utxos, estimator, setup, cli.seed)?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<28} {:>8} {:>8} {:>12}",
+        "algorithm", "inputs", "changes", "fee"
+    );
+    for algorithm in Algorithm::value_variants() {
+        let name = algorithm
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        match select(*algorithm, utxos, estimator, setup.clone(), cli.seed) {
+            Ok(result) => println!(
+                "{name:<28} {:>8} {:>8} {:>12}",
+                result.chosen_inputs.len(),
+                result.changes.len(),
+                result.fee.to_string()
+            ),
+            Err(error) => println!("{name:<28} failed: {error:#}"),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let utxos = store(read_json(&cli.utxos)?)?;
+    let payments: Vec<UTxOBuilder> = read_json(&cli.payments)?;
+    let setup = InputOutputSetup::from_fixed_inputs_and_outputs(
+        vec![],
+        payments,
+        Some(Address::new(cli.change_address.clone())),
+    );
+    let parameters = cli
+        .protocol_parameters
+        .as_ref()
+        .map(ProtocolParameters::load)
+        .transpose()?;
+
+    match cli.estimator {
+        Estimator::Thermostat => {
+            let plan: MultisigPlan = match &cli.plan {
+                Some(plan) => read_json(plan)?,
+                None => bail!("the thermostat estimator requires --plan"),
+            };
+            let network = NetworkInfo::from(cli.network);
+            let estimator = match &parameters {
+                Some(parameters) => {
+                    ThermostatFeeEstimator::from_protocol_parameters(&network, &plan, parameters)
+                }
+                None => {
+                    ThermostatFeeEstimator::new(network, &plan, BigNum::from(COINS_PER_UTXO_BYTE))
+                }
+            };
+            simulate(&cli, &utxos, &estimator, setup)
+        }
+        Estimator::Cml => {
+            let parameters = match &parameters {
+                Some(parameters) => parameters,
+                None => bail!("the cml estimator requires --protocol-parameters"),
+            };
+            let estimator = CmlFeeEstimator::from_protocol_parameters(
+                parameters,
+                CardanoPaymentCredentials::PaymentKey,
+            )?;
+            simulate(&cli, &utxos, &estimator, setup)
+        }
+    }
+}
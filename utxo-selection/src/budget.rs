@@ -0,0 +1,216 @@
+use crate::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
+};
+use anyhow::Context as _;
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// the time budget of a selection, shared with the task running it so the
+/// selection can be cancelled
+///
+/// The expensive searches (e.g. the [`Knapsack`](crate::algorithms::Knapsack))
+/// check the budget regularly and fail with [`SelectionCancelled`] once it
+/// is exhausted.
+#[derive(Debug, Clone, Default)]
+pub struct SelectionBudget {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+/// the error returned when the [`SelectionBudget`] is exhausted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionCancelled;
+
+impl fmt::Display for SelectionCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The selection was cancelled or ran out of time")
+    }
+}
+
+impl std::error::Error for SelectionCancelled {}
+
+impl SelectionBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// cancel the selections sharing this budget
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_exhausted() {
+            return Err(SelectionCancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// the algorithm and the estimator given back by [`select_inputs_blocking`]
+/// with the result of the selection
+pub struct BlockingSelection<Algorithm, Estimate> {
+    pub algorithm: Algorithm,
+    pub estimator: Estimate,
+    pub result: anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>>,
+}
+
+/// cancel the budget if the future running the selection is dropped
+struct CancelOnDrop(Option<SelectionBudget>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(budget) = self.0.take() {
+            budget.cancel();
+        }
+    }
+}
+
+/// run the selection on the blocking threads of tokio so it does not
+/// block the runtime
+///
+/// The `budget` is the one given to the algorithm: dropping the returned
+/// future (e.g. on a `tokio::time::timeout`) cancels it so the selection
+/// stops early. A panic of the selection is resumed on the caller.
+pub async fn select_inputs_blocking<Algorithm, Estimate>(
+    mut algorithm: Algorithm,
+    mut estimator: Estimate,
+    input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    budget: SelectionBudget,
+) -> anyhow::Result<BlockingSelection<Algorithm, Estimate>>
+where
+    Algorithm:
+        InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder> + Send + 'static,
+    Estimate:
+        TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder> + Send + 'static,
+{
+    let mut guard = CancelOnDrop(Some(budget));
+
+    let task = tokio::task::spawn_blocking(move || {
+        let result = algorithm.select_inputs(&mut estimator, input_output_setup);
+        BlockingSelection {
+            algorithm,
+            estimator,
+            result,
+        }
+    });
+    let selection = match task.await {
+        Ok(selection) => selection,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => return Err(error).context("The selection task was cancelled"),
+    };
+
+    guard.0 = None;
+    Ok(selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::LargestFirst;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use dcspark_core::{Regulated, Value};
+
+    struct PanickingAlgorithm;
+
+    impl InputSelectionAlgorithm for PanickingAlgorithm {
+        type InputUtxo = UTxODetails;
+        type OutputUtxo = UTxOBuilder;
+
+        fn set_available_inputs(&mut self, _: Vec<UTxODetails>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn select_inputs<
+            Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+        >(
+            &mut self,
+            _: &mut Estimate,
+            _: InputOutputSetup<UTxODetails, UTxOBuilder>,
+        ) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>> {
+            panic!("selection bug")
+        }
+
+        fn available_inputs(&self) -> Vec<UTxODetails> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn budget_cancelled() {
+        let budget = SelectionBudget::new().with_timeout(Duration::from_secs(3600));
+        assert!(budget.check().is_ok());
+
+        budget.clone().cancel();
+        let error = budget.check().unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&SelectionCancelled));
+    }
+
+    #[test]
+    fn budget_past_deadline() {
+        let budget = SelectionBudget::new().with_deadline(Instant::now());
+        assert!(budget.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn select_on_blocking_thread() {
+        let algorithm = LargestFirst::try_from(vec![
+            create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]),
+        ])
+        .unwrap();
+        let budget = SelectionBudget::new();
+
+        let selection = select_inputs_blocking(
+            algorithm,
+            DummyFeeEstimate::new(),
+            InputOutputSetup {
+                output_balance: Value::from(15),
+                ..InputOutputSetup::default()
+            },
+            budget.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(selection.result.unwrap().chosen_inputs.len(), 1);
+        assert_eq!(selection.algorithm.available_inputs().len(), 1);
+        assert!(!budget.is_exhausted());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "selection bug")]
+    async fn selection_panic_is_resumed() {
+        let _ = select_inputs_blocking(
+            PanickingAlgorithm,
+            DummyFeeEstimate::new(),
+            InputOutputSetup::default(),
+            SelectionBudget::new(),
+        )
+        .await;
+    }
+}
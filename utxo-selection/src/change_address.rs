@@ -0,0 +1,275 @@
+use crate::TransactionFeeEstimator;
+use anyhow::{anyhow, bail};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::Address;
+
+/// the addresses of the change outputs
+///
+/// The algorithms creating changes (the
+/// [`SingleOutputChangeBalancer`](crate::algorithms::SingleOutputChangeBalancer),
+/// the [`Thermostat`](crate::algorithms::Thermostat) and the
+/// [`Consolidate`](crate::algorithms::Consolidate)) take the address of
+/// every new change from the provider set with their
+/// `with_change_addresses`, the `change_address` of the
+/// [`InputOutputSetup`](crate::InputOutputSetup) is used otherwise.
+///
+/// The algorithms take a [`checkpoint`](ChangeAddressProvider::checkpoint)
+/// before creating the changes and [`rewind`](ChangeAddressProvider::rewind)
+/// to it when the changes are dropped (a split abandoned, a selection
+/// failing or a fee estimate), so no address is skipped.
+pub trait ChangeAddressProvider {
+    /// the address of the next change output
+    fn next_change_address(&mut self) -> anyhow::Result<Address>;
+
+    /// the current position of the provider
+    fn checkpoint(&self) -> u64 {
+        0
+    }
+
+    /// give again the addresses returned since the `checkpoint`
+    fn rewind(&mut self, _checkpoint: u64) {}
+
+    /// the index of the next address to derive, `None` if the addresses
+    /// are not derived from an index
+    fn next_index(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// the same address for every change
+impl ChangeAddressProvider for Address {
+    fn next_change_address(&mut self) -> anyhow::Result<Address> {
+        Ok(self.clone())
+    }
+}
+
+/// the given addresses in turn
+#[derive(Debug, Clone)]
+pub struct RoundRobinChangeAddresses {
+    addresses: Vec<Address>,
+    next: usize,
+}
+
+impl RoundRobinChangeAddresses {
+    pub fn new(addresses: Vec<Address>) -> anyhow::Result<Self> {
+        if addresses.is_empty() {
+            bail!("At least one change address is required");
+        }
+        Ok(Self { addresses, next: 0 })
+    }
+}
+
+impl ChangeAddressProvider for RoundRobinChangeAddresses {
+    fn next_change_address(&mut self) -> anyhow::Result<Address> {
+        let address = self.addresses[self.next].clone();
+        self.next = (self.next + 1) % self.addresses.len();
+        Ok(address)
+    }
+
+    fn checkpoint(&self) -> u64 {
+        self.next as u64
+    }
+
+    fn rewind(&mut self, checkpoint: u64) {
+        self.next = checkpoint as usize;
+    }
+}
+
+/// a fresh address for every change, derived from its index (e.g. the
+/// change chain of an HD wallet)
+///
+/// The derivation is chain specific and given as a function of the index.
+/// The wallet persists [`DerivedChangeAddresses::next_index`] to resume the
+/// derivation after the last used address, the algorithms give it with
+/// their `change_addresses` once the provider is set.
+pub struct DerivedChangeAddresses<F> {
+    derive: F,
+    next_index: u32,
+}
+
+impl<F: FnMut(u32) -> anyhow::Result<Address>> DerivedChangeAddresses<F> {
+    pub fn new(derive: F) -> Self {
+        Self {
+            derive,
+            next_index: 0,
+        }
+    }
+
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn starting_at(self, next_index: u32) -> Self {
+        Self { next_index, ..self }
+    }
+
+    /// the index of the next address to derive
+    pub fn next_index(&self) -> u32 {
+        self.next_index
+    }
+}
+
+impl<F: FnMut(u32) -> anyhow::Result<Address>> ChangeAddressProvider for DerivedChangeAddresses<F> {
+    fn next_change_address(&mut self) -> anyhow::Result<Address> {
+        let address = (self.derive)(self.next_index)?;
+        self.next_index = self
+            .next_index
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("No more change address to derive"))?;
+        Ok(address)
+    }
+
+    fn checkpoint(&self) -> u64 {
+        self.next_index.into()
+    }
+
+    fn rewind(&mut self, checkpoint: u64) {
+        self.next_index = checkpoint as u32;
+    }
+
+    fn next_index(&self) -> Option<u32> {
+        Some(self.next_index)
+    }
+}
+
+/// the provider set on an algorithm
+pub type BoxedChangeAddressProvider = Box<dyn ChangeAddressProvider + Send>;
+
+/// the next address of the `provider` if set, the `change_address` of the
+/// setup otherwise
+pub(crate) fn next_change_address(
+    provider: &mut Option<BoxedChangeAddressProvider>,
+    change_address: &Address,
+) -> anyhow::Result<Address> {
+    match provider {
+        Some(provider) => provider.next_change_address(),
+        None => Ok(change_address.clone()),
+    }
+}
+
+/// the checkpoint of the `provider` if set
+pub(crate) fn checkpoint(provider: &Option<BoxedChangeAddressProvider>) -> u64 {
+    provider
+        .as_ref()
+        .map_or(0, |provider| provider.checkpoint())
+}
+
+/// the next address of the `provider` if set
+pub(crate) fn next_provided_address(
+    provider: &mut Option<BoxedChangeAddressProvider>,
+) -> anyhow::Result<Option<Address>> {
+    provider
+        .as_mut()
+        .map(|provider| provider.next_change_address())
+        .transpose()
+}
+
+/// rewind the `provider` if set to the `checkpoint`
+pub(crate) fn rewind(provider: &mut Option<BoxedChangeAddressProvider>, checkpoint: u64) {
+    if let Some(provider) = provider {
+        provider.rewind(checkpoint);
+    }
+}
+
+/// give the changes built with the change address of the setup their
+/// address from the `provider`
+///
+/// The difference of fee due to the size of the new address is paid by
+/// the change. The provider is rewound if a change can't pay for it.
+pub(crate) fn assign_change_addresses<
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+>(
+    estimator: &mut Estimate,
+    provider: &mut Option<BoxedChangeAddressProvider>,
+    changes: Vec<UTxOBuilder>,
+) -> anyhow::Result<Vec<UTxOBuilder>> {
+    if provider.is_none() {
+        return Ok(changes);
+    }
+
+    let start = checkpoint(provider);
+    let changes = changes
+        .into_iter()
+        .map(|mut change| {
+            let fee = estimator.fee_for_output(&change)?;
+            change.address = next_change_address(provider, &change.address)?;
+            let new_fee = estimator.fee_for_output(&change)?;
+            change.value = (&change.value + &fee)
+                .checked_sub(&new_fee)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "The change ({}) can't pay for the fee of its address ({})",
+                        change.value,
+                        new_fee
+                    )
+                })?;
+            let min_value = estimator.min_value_for_output(change.clone())?;
+            if change.value < min_value {
+                bail!(
+                    "The change ({}) is below the minimum UTxO value ({})",
+                    change.value,
+                    min_value
+                );
+            }
+            Ok(change)
+        })
+        .collect();
+    if changes.is_err() {
+        rewind(provider, start);
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let mut addresses =
+            RoundRobinChangeAddresses::new(vec![Address::new("a"), Address::new("b")]).unwrap();
+
+        let next = (0..3)
+            .map(|_| addresses.next_change_address().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            next,
+            vec![Address::new("a"), Address::new("b"), Address::new("a")]
+        );
+        assert!(RoundRobinChangeAddresses::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn derived_from_index() {
+        let mut addresses =
+            DerivedChangeAddresses::new(|index| Ok(Address::new(format!("change_{index}"))))
+                .starting_at(5);
+
+        assert_eq!(
+            addresses.next_change_address().unwrap(),
+            Address::new("change_5")
+        );
+        assert_eq!(addresses.next_index(), 6);
+    }
+
+    #[test]
+    fn rewind_to_checkpoint() {
+        let mut round_robin =
+            RoundRobinChangeAddresses::new(vec![Address::new("a"), Address::new("b")]).unwrap();
+        let checkpoint = round_robin.checkpoint();
+        round_robin.next_change_address().unwrap();
+        round_robin.rewind(checkpoint);
+        assert_eq!(
+            round_robin.next_change_address().unwrap(),
+            Address::new("a")
+        );
+
+        let mut provider: Option<BoxedChangeAddressProvider> = Some(Box::new(
+            DerivedChangeAddresses::new(|index| Ok(Address::new(format!("change_{index}"))))
+                .starting_at(5),
+        ));
+        let start = super::checkpoint(&provider);
+        next_change_address(&mut provider, &Address::new("change")).unwrap();
+        assert_eq!(provider.as_ref().unwrap().next_index(), Some(6));
+        rewind(&mut provider, start);
+        assert_eq!(provider.as_ref().unwrap().next_index(), Some(5));
+        assert_eq!(Address::new("change").next_index(), None);
+    }
+}
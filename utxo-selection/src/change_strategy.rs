@@ -0,0 +1,432 @@
+use crate::change_address::assign_change_addresses;
+use crate::{
+    calculate_main_token_balance, BoxedChangeAddressProvider, InputSelectionResult,
+    TransactionFeeEstimator,
+};
+use anyhow::{anyhow, bail, Context as _};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, Balance, Regulated, Value};
+use deps::bigdecimal::ToPrimitive;
+
+/// the way the excess of a transaction is returned in the change outputs
+///
+/// The selection computes the excess (main asset and tokens) and the
+/// strategy decides how many change outputs hold it. The strategy is set
+/// with the `with_change_strategy` of the algorithms building changes:
+///
+/// * the [`SingleOutputChangeBalancer`](crate::algorithms::SingleOutputChangeBalancer),
+///   the [`Knapsack`](crate::algorithms::Knapsack) and the
+///   [`LargestFirstMultiAsset`](crate::algorithms::LargestFirstMultiAsset)
+///   return the whole excess with the strategy;
+/// * the [`Thermostat`](crate::algorithms::Thermostat) and the
+///   [`Consolidate`](crate::algorithms::Consolidate) keep one change per
+///   token, only the excess of main asset is returned with the strategy.
+pub trait ChangeStrategy {
+    /// split the `excess` into the change outputs
+    ///
+    /// The values of the returned outputs and their fees sum up to the
+    /// value of `excess` and the outputs hold all the assets of `excess`.
+    /// The outputs are not added to the `estimator`.
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &mut Estimate,
+        excess: UTxOBuilder,
+    ) -> anyhow::Result<Vec<UTxOBuilder>>;
+}
+
+/// the whole excess in one change output
+#[derive(Debug, Clone, Default)]
+pub struct SingleChange;
+
+impl ChangeStrategy for SingleChange {
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &mut Estimate,
+        excess: UTxOBuilder,
+    ) -> anyhow::Result<Vec<UTxOBuilder>> {
+        pay_output_fees(estimator, vec![excess])
+    }
+}
+
+/// the excess split in `parts` changes of equal value, the tokens are
+/// split as well
+///
+/// Falls back to a single change if the parts can't hold the minimum UTxO
+/// value.
+#[derive(Debug, Clone)]
+pub struct SplitChange {
+    parts: usize,
+}
+
+impl SplitChange {
+    pub fn new(parts: usize) -> Self {
+        Self {
+            parts: parts.max(1),
+        }
+    }
+}
+
+impl ChangeStrategy for SplitChange {
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &mut Estimate,
+        excess: UTxOBuilder,
+    ) -> anyhow::Result<Vec<UTxOBuilder>> {
+        let weights = vec![1; self.parts];
+        let mut parts: Vec<UTxOBuilder> = excess
+            .value
+            .split_weighted(&weights)
+            .into_iter()
+            .map(|value| UTxOBuilder {
+                value,
+                assets: vec![],
+                ..excess.clone()
+            })
+            .collect();
+        for asset in excess.assets.iter() {
+            let quantities = asset.quantity.split_weighted(&weights);
+            for (part, quantity) in parts.iter_mut().zip(quantities) {
+                if quantity > Value::zero() {
+                    let mut asset = asset.clone();
+                    asset.quantity = quantity;
+                    part.assets.push(asset);
+                }
+            }
+        }
+
+        pay_output_fees(estimator, parts).or_else(|_| SingleChange.build_changes(estimator, excess))
+    }
+}
+
+/// changes of exactly `denomination` (up to `max_outputs` of them), the
+/// remainder and the tokens in a last change paying the fees
+#[derive(Debug, Clone)]
+pub struct DenominationChange {
+    denomination: Value<Regulated>,
+    max_outputs: usize,
+}
+
+impl DenominationChange {
+    pub fn new(denomination: Value<Regulated>, max_outputs: usize) -> Self {
+        Self {
+            denomination,
+            max_outputs,
+        }
+    }
+}
+
+impl ChangeStrategy for DenominationChange {
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &mut Estimate,
+        excess: UTxOBuilder,
+    ) -> anyhow::Result<Vec<UTxOBuilder>> {
+        let denomination = to_u64(&self.denomination)?;
+        if denomination == 0 {
+            bail!("The denomination of the change can't be zero");
+        }
+        let denominated = UTxOBuilder {
+            value: self.denomination.clone(),
+            assets: vec![],
+            ..excess.clone()
+        };
+        let fee_for_denominated = estimator.fee_for_output(&denominated)?;
+
+        // the largest number of denominated changes leaving enough in the
+        // remainder for the fees
+        let mut count = usize::try_from(to_u64(&excess.value)? / denomination)
+            .unwrap_or(usize::MAX)
+            .min(self.max_outputs);
+        while count > 0 {
+            let fees = &fee_for_denominated * count;
+            let remainder = (&excess.value - &(&self.denomination * count)).checked_sub(&fees);
+            if let Some(remainder) = remainder {
+                let remainder = UTxOBuilder {
+                    value: remainder,
+                    ..excess.clone()
+                };
+                if let Ok(mut changes) = pay_output_fees(estimator, vec![remainder]) {
+                    changes.extend((0..count).map(|_| denominated.clone()));
+                    return Ok(changes);
+                }
+            }
+            count -= 1;
+        }
+
+        SingleChange.build_changes(estimator, excess)
+    }
+}
+
+/// the change split in halves while it is larger than its share of the
+/// wallet, in the manner of the accumulators of the
+/// [`Thermostat`](crate::algorithms::Thermostat)
+///
+/// The share (the pivot) is the balance of the wallet, including the
+/// excess, divided by `num_accumulators`.
+#[derive(Debug, Clone)]
+pub struct AccumulatorChange {
+    num_accumulators: usize,
+    wallet_balance: Value<Regulated>,
+}
+
+impl AccumulatorChange {
+    pub fn new(num_accumulators: usize, wallet_balance: Value<Regulated>) -> Self {
+        Self {
+            num_accumulators: num_accumulators.max(1),
+            wallet_balance,
+        }
+    }
+}
+
+impl ChangeStrategy for AccumulatorChange {
+    fn build_changes<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &mut Estimate,
+        excess: UTxOBuilder,
+    ) -> anyhow::Result<Vec<UTxOBuilder>> {
+        let pivot = (&self.wallet_balance + &excess.value).percent(1, self.num_accumulators as u64);
+
+        let mut values = vec![excess.value.clone()];
+        while values.len() < self.num_accumulators {
+            let index = values
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.cmp(b))
+                .map(|(index, _)| index)
+                .expect("there is always one value");
+            if values[index] <= pivot {
+                break;
+            }
+            let largest = values.remove(index);
+            values.extend(largest.split_weighted(&[1, 1]));
+        }
+
+        // the tokens remain in the first change
+        let parts = values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| UTxOBuilder {
+                value,
+                assets: if index == 0 {
+                    excess.assets.clone()
+                } else {
+                    vec![]
+                },
+                ..excess.clone()
+            })
+            .collect();
+
+        pay_output_fees(estimator, parts).or_else(|_| SingleChange.build_changes(estimator, excess))
+    }
+}
+
+/// build the changes of the `excess` with the `strategy` and add them to
+/// the `estimator`
+///
+/// The changes are sent to the address of the `excess`, or to the next
+/// addresses of the `provider` if set. If the excess can't pay for a change
+/// it is left to the fee, this fails if the excess holds tokens.
+pub(crate) fn build_excess_changes<
+    S: ChangeStrategy,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+>(
+    strategy: &S,
+    estimator: &mut Estimate,
+    provider: &mut Option<BoxedChangeAddressProvider>,
+    excess: UTxOBuilder,
+) -> anyhow::Result<Vec<UTxOBuilder>> {
+    let changes = strategy
+        .build_changes(estimator, excess.clone())
+        .map(|changes| {
+            changes
+                .into_iter()
+                .filter(|change| change.value > Value::zero() || !change.assets.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .and_then(|changes| assign_change_addresses(estimator, provider, changes));
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(_) if excess.assets.is_empty() => return Ok(vec![]),
+        Err(error) => return Err(error.context("The tokens can't be returned in the change")),
+    };
+
+    for change in changes.iter() {
+        estimator.add_output(change.clone())?;
+    }
+    Ok(changes)
+}
+
+/// return the excess of main asset and tokens of the `result` in the
+/// changes built by the `strategy`, see [`build_excess_changes`]
+pub(crate) fn balance_with_changes<
+    S: ChangeStrategy,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+>(
+    strategy: &S,
+    estimator: &mut Estimate,
+    change_address: Option<Address>,
+    mut result: InputSelectionResult<UTxODetails, UTxOBuilder>,
+) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>> {
+    let change_address = change_address.context("change address is not provided")?;
+    let value = match calculate_main_token_balance(
+        &result.input_balance,
+        &result.output_balance,
+        &result.fee,
+    ) {
+        Balance::Debt(debt) => {
+            bail!("there's not enough main token to return the change: {debt} missing")
+        }
+        Balance::Balanced => Value::zero(),
+        Balance::Excess(excess) => excess,
+    };
+    let assets = result
+        .input_asset_balance
+        .checked_sub(&result.output_asset_balance)
+        .ok_or_else(|| anyhow!("there's lack of assets selected, can't return the change"))?
+        .into_iter()
+        .map(|(_, asset)| asset)
+        .collect();
+
+    let changes = build_excess_changes(
+        strategy,
+        estimator,
+        &mut None,
+        UTxOBuilder::new(change_address, value.clone(), assets),
+    )?;
+    let changes_value: Value<Regulated> = changes.iter().map(|change| &change.value).sum();
+    result.fee += &value - &changes_value;
+    result.output_balance += &changes_value;
+    for change in changes.iter() {
+        result.output_asset_balance.extend(change.assets.iter());
+    }
+    result.changes.extend(changes);
+    Ok(result)
+}
+
+/// deduct the fee of each output from its value, fails if an output can't
+/// pay its fee and hold the minimum UTxO value
+fn pay_output_fees<
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+>(
+    estimator: &mut Estimate,
+    outputs: Vec<UTxOBuilder>,
+) -> anyhow::Result<Vec<UTxOBuilder>> {
+    outputs
+        .into_iter()
+        .map(|mut output| {
+            let fee = estimator.fee_for_output(&output)?;
+            output.value = output.value.checked_sub(&fee).ok_or_else(|| {
+                anyhow!(
+                    "The change ({}) can't pay for its fee ({})",
+                    output.value,
+                    fee
+                )
+            })?;
+            let min_value = estimator.min_value_for_output(output.clone())?;
+            if output.value < min_value {
+                bail!(
+                    "The change ({}) is below the minimum UTxO value ({})",
+                    output.value,
+                    min_value
+                );
+            }
+            Ok(output)
+        })
+        .collect()
+}
+
+fn to_u64(value: &Value<Regulated>) -> anyhow::Result<u64> {
+    value
+        .to_u64()
+        .ok_or_else(|| anyhow!("Can't convert {value} to u64"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_asset;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use dcspark_core::{Address, TokenId};
+
+    fn excess(value: u64) -> UTxOBuilder {
+        UTxOBuilder::new(
+            Address::new("change"),
+            Value::from(value),
+            vec![create_asset("kek".to_string(), Value::from(3))],
+        )
+    }
+
+    fn total(changes: &[UTxOBuilder]) -> Value<Regulated> {
+        changes.iter().map(|change| &change.value).sum()
+    }
+
+    #[test]
+    fn split_in_parts() {
+        let changes = SplitChange::new(2)
+            .build_changes(&mut DummyFeeEstimate::new(), excess(11))
+            .unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(total(&changes), Value::from(11));
+        let tokens: Value<Regulated> = changes
+            .iter()
+            .flat_map(|change| change.assets.iter())
+            .filter(|asset| asset.fingerprint == TokenId::new("kek"))
+            .map(|asset| &asset.quantity)
+            .sum();
+        assert_eq!(tokens, Value::from(3));
+    }
+
+    #[test]
+    fn split_falls_back_to_single_change() {
+        let changes = SplitChange::new(4)
+            .build_changes(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(5)),
+                excess(11),
+            )
+            .unwrap();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].value, Value::from(11));
+    }
+
+    #[test]
+    fn round_to_denomination() {
+        let changes = DenominationChange::new(Value::from(5), 10)
+            .build_changes(
+                &mut DummyFeeEstimate::with_min_value_for_output(Value::from(2)),
+                excess(16),
+            )
+            .unwrap();
+
+        // 5 + 5 + 5 would leave 1 for the remainder, below the minimum
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].value, Value::from(6));
+        assert_eq!(changes[0].assets.len(), 1);
+        assert!(changes[1..]
+            .iter()
+            .all(|change| change.value == Value::from(5) && change.assets.is_empty()));
+    }
+
+    #[test]
+    fn accumulators_split_above_pivot() {
+        let changes = AccumulatorChange::new(4, Value::from(60))
+            .build_changes(&mut DummyFeeEstimate::new(), excess(40))
+            .unwrap();
+
+        // the pivot is 25: 40 is split in two changes of 20
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|change| change.value == Value::from(20)));
+        assert_eq!(total(&changes), Value::from(40));
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, bail};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, Regulated, Value};
+use deps::bigdecimal::ToPrimitive;
+
+/// the collateral requirements of a transaction running plutus scripts
+#[derive(Debug, Clone)]
+pub struct CollateralSetup {
+    /// the collateral to provide in percent of the fee (the
+    /// `collateralPercentage` protocol parameter)
+    pub percentage: u64,
+    /// the `maxCollateralInputs` protocol parameter
+    pub max_collateral_inputs: usize,
+    /// where to return the excess of the collateral inputs, without it
+    /// the whole value of the collateral inputs is forfeited if a script
+    /// fails
+    pub return_address: Option<Address>,
+}
+
+/// the collateral selected for a transaction
+#[derive(Debug, Clone)]
+pub struct Collateral {
+    pub inputs: Vec<UTxODetails>,
+    /// the value collected if a script fails
+    pub total_collateral: Value<Regulated>,
+    pub collateral_return: Option<UTxOBuilder>,
+}
+
+/// select the collateral inputs among the pure main asset UTxOs
+///
+/// The smallest UTxO covering the collateral on its own is preferred,
+/// otherwise the largest UTxOs are selected up to `max_collateral_inputs`.
+/// The UTxOs must be locked by a key, this is not verified here.
+///
+/// The collateral return output is not accounted in the `fee`, the caller
+/// is responsible for the fee of the return output.
+pub fn select_collateral(
+    available_inputs: &[UTxODetails],
+    fee: &Value<Regulated>,
+    setup: &CollateralSetup,
+) -> anyhow::Result<Collateral> {
+    let fee = fee
+        .to_u64()
+        .ok_or_else(|| anyhow!("Can't convert fee {fee} to u64"))?;
+    // the ledger requires `collateral * 100 >= fee * percentage`
+    let required = (u128::from(fee) * u128::from(setup.percentage)).div_ceil(100);
+    let required = Value::<Regulated>::from(
+        u64::try_from(required).map_err(|_| anyhow!("collateral {required} overflows"))?,
+    );
+
+    let mut candidates = available_inputs
+        .iter()
+        .filter(|utxo| utxo.assets.is_empty() && utxo.datum.is_none() && utxo.script_ref.is_none())
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| {
+        b.value
+            .cmp(&a.value)
+            .then_with(|| a.pointer.cmp(&b.pointer))
+    });
+
+    let inputs = match candidates.iter().rev().find(|utxo| utxo.value >= required) {
+        Some(utxo) => vec![(*utxo).clone()],
+        None => {
+            let mut inputs = vec![];
+            let mut total = Value::<Regulated>::zero();
+            for utxo in candidates.into_iter().take(setup.max_collateral_inputs) {
+                if total >= required {
+                    break;
+                }
+                total += &utxo.value;
+                inputs.push(utxo.clone());
+            }
+            if total < required {
+                bail!(
+                    "Not enough pure UTxOs to cover the collateral of {required} with {max} inputs",
+                    max = setup.max_collateral_inputs
+                );
+            }
+            inputs
+        }
+    };
+
+    let total: Value<Regulated> = inputs.iter().map(|utxo| &utxo.value).sum();
+    let (total_collateral, collateral_return) = match &setup.return_address {
+        Some(address) if total > required => (
+            required.clone(),
+            Some(UTxOBuilder::new(
+                address.clone(),
+                &total - &required,
+                vec![],
+            )),
+        ),
+        _ => (total, None),
+    };
+
+    Ok(Collateral {
+        inputs,
+        total_collateral,
+        collateral_return,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+
+    fn inputs() -> Vec<UTxODetails> {
+        vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(3_000_000), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(1_000_000), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::from(2_000_000), vec![]),
+            create_utxo(
+                0,
+                3,
+                "0".to_string(),
+                Value::from(10_000_000),
+                vec![create_asset("kek".to_string(), Value::from(1))],
+            ),
+        ]
+    }
+
+    fn setup(return_address: Option<Address>) -> CollateralSetup {
+        CollateralSetup {
+            percentage: 150,
+            max_collateral_inputs: 3,
+            return_address,
+        }
+    }
+
+    #[test]
+    fn smallest_covering_utxo() {
+        let collateral = select_collateral(
+            &inputs(),
+            &Value::from(1_000_001),
+            &setup(Some(Address::new("return"))),
+        )
+        .unwrap();
+
+        // 1_500_002 (rounded up) is covered by the 2 ADA UTxO alone
+        assert_eq!(collateral.inputs.len(), 1);
+        assert_eq!(collateral.inputs[0].value, Value::from(2_000_000));
+        assert_eq!(collateral.total_collateral, Value::from(1_500_002));
+        assert_eq!(
+            collateral.collateral_return.unwrap().value,
+            Value::from(499_998)
+        );
+    }
+
+    #[test]
+    fn largest_first_without_return() {
+        let collateral =
+            select_collateral(&inputs(), &Value::from(2_500_000), &setup(None)).unwrap();
+
+        // 3_750_000 requires two UTxOs, the token UTxO is never used
+        assert_eq!(collateral.inputs.len(), 2);
+        assert_eq!(collateral.total_collateral, Value::from(5_000_000));
+        assert!(collateral.collateral_return.is_none());
+    }
+
+    #[test]
+    fn not_enough_collateral() {
+        let error =
+            select_collateral(&inputs(), &Value::from(5_000_000), &setup(None)).unwrap_err();
+        assert!(error.to_string().contains("Not enough pure UTxOs"));
+    }
+}
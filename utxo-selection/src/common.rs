@@ -1,19 +1,33 @@
-use dcspark_core::tx::{TransactionAsset, UTxOBuilder, UTxODetails};
-use dcspark_core::{Address, Balance, Regulated, TokenId, Value};
-use std::collections::HashMap;
+use crate::{CollateralSetup, SelectionTrace};
+use dcspark_core::tx::{
+    MultiAssetValue, TransactionAsset, TransactionDraft, UTxOBuilder, UTxODetails, UtxoPointer,
+};
+use dcspark_core::{Address, Balance, Regulated, TokenId, UTxOStore, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub struct InputOutputSetup<InputUtxo: Clone, OutputUtxo: Clone> {
     pub input_balance: Value<Regulated>,
-    pub input_asset_balance: HashMap<TokenId, TransactionAsset>,
+    pub input_asset_balance: MultiAssetValue,
 
     pub output_balance: Value<Regulated>,
-    pub output_asset_balance: HashMap<TokenId, TransactionAsset>,
+    pub output_asset_balance: MultiAssetValue,
 
+    /// the inputs the transaction must spend, the algorithms never select
+    /// them a second time
     pub fixed_inputs: Vec<InputUtxo>,
     pub fixed_outputs: Vec<OutputUtxo>,
 
     pub change_address: Option<Address>,
+
+    /// the UTxOs the algorithms must not select (e.g. pending or frozen),
+    /// they remain available for the next selections
+    pub excluded_inputs: HashSet<UtxoPointer>,
+
+    /// the collateral requirements if the transaction runs plutus scripts,
+    /// see [`select_collateral`](crate::select_collateral)
+    pub collateral: Option<CollateralSetup>,
 }
 
 impl<InputUtxo: Clone, OutputUtxo: Clone> Default for InputOutputSetup<InputUtxo, OutputUtxo> {
@@ -26,6 +40,8 @@ impl<InputUtxo: Clone, OutputUtxo: Clone> Default for InputOutputSetup<InputUtxo
             fixed_inputs: vec![],
             fixed_outputs: vec![],
             change_address: None,
+            excluded_inputs: HashSet::new(),
+            collateral: None,
         }
     }
 }
@@ -37,36 +53,18 @@ impl InputOutputSetup<UTxODetails, UTxOBuilder> {
         change_address: Option<Address>,
     ) -> Self {
         let mut input_balance = Value::<Regulated>::zero();
-        let mut input_asset_balance = HashMap::<TokenId, TransactionAsset>::new();
+        let mut input_asset_balance = MultiAssetValue::new();
 
         for input in inputs.iter() {
             input_balance += &input.value;
-            for asset in input.assets.iter() {
-                input_asset_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset::new(
-                        asset.policy_id.clone(),
-                        asset.asset_name.clone(),
-                        asset.fingerprint.clone(),
-                    ))
-                    .quantity += &asset.quantity;
-            }
+            input_asset_balance.extend(input.assets.iter());
         }
 
         let mut output_balance = Value::<Regulated>::zero();
-        let mut output_asset_balance = HashMap::<TokenId, TransactionAsset>::new();
+        let mut output_asset_balance = MultiAssetValue::new();
         for output in outputs.iter() {
             output_balance += &output.value;
-            for asset in output.assets.iter() {
-                output_asset_balance
-                    .entry(asset.fingerprint.clone())
-                    .or_insert(TransactionAsset::new(
-                        asset.policy_id.clone(),
-                        asset.asset_name.clone(),
-                        asset.fingerprint.clone(),
-                    ))
-                    .quantity += &asset.quantity;
-            }
+            output_asset_balance.extend(output.assets.iter());
         }
         Self {
             input_balance,
@@ -76,17 +74,90 @@ impl InputOutputSetup<UTxODetails, UTxOBuilder> {
             fixed_inputs: inputs,
             fixed_outputs: outputs,
             change_address,
+            excluded_inputs: HashSet::new(),
+            collateral: None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl<OutputUtxo: Clone> InputOutputSetup<UTxODetails, OutputUtxo> {
+    /// check the UTxO may be selected: it is neither excluded nor already
+    /// one of the fixed inputs
+    pub fn is_selectable(&self, pointer: &UtxoPointer) -> bool {
+        !self.excluded_inputs.contains(pointer)
+            && !self
+                .fixed_inputs
+                .iter()
+                .any(|input| &input.pointer == pointer)
+    }
+
+    /// split the `utxos` between the ones the selection may pick and the
+    /// excluded ones, the fixed inputs are removed from both
+    ///
+    /// The excluded UTxOs are to be put back in the available UTxOs after
+    /// the selection with [`restore_utxos`].
+    pub fn split_selectable(
+        &self,
+        utxos: &UTxOStore,
+    ) -> anyhow::Result<(UTxOStore, Vec<UTxODetails>)> {
+        let mut selectable = utxos.thaw();
+        let mut excluded = vec![];
+        for (pointer, utxo) in utxos.iter() {
+            if !self.is_selectable(pointer) {
+                selectable.remove(pointer)?;
+                if self.excluded_inputs.contains(pointer) {
+                    excluded.push(utxo.as_ref().clone());
+                }
+            }
+        }
+        Ok((selectable.freeze(), excluded))
+    }
+}
+
+/// put back the UTxOs set aside by [`InputOutputSetup::split_selectable`]
+pub fn restore_utxos(utxos: UTxOStore, set_aside: Vec<UTxODetails>) -> anyhow::Result<UTxOStore> {
+    let mut utxos = utxos.thaw();
+    for utxo in set_aside {
+        utxos.insert(utxo)?;
+    }
+    Ok(utxos.freeze())
+}
+
+/// the result of a selection
+///
+/// The results of the algorithms of this crate are in a stable order (see
+/// [`InputSelectionResult::into_stable_order`]): the same selection always
+/// builds the same transaction, whatever the iteration order of the maps.
+///
+/// The result can be sent to another process or stored for audit, the
+/// fields are serialized with their names:
+///
+/// * the balances (`input_balance`, `output_balance` and `fee`) are
+///   decimal strings;
+/// * the asset balances are objects mapping the fingerprints to the assets
+///   with their `policyId`, `assetName`, `fingerprint` and `quantity`;
+/// * the inputs are the [`UTxODetails`] with their `pointer` as
+///   `<transaction id>#<output index>`, the outputs are the
+///   [`UTxOBuilder`];
+/// * the `trace` is left out when it was not recorded.
+///
+/// ```json
+/// {
+///   "input_balance": "30", "input_asset_balance": {},
+///   "output_balance": "29", "output_asset_balance": {},
+///   "fixed_inputs": [], "fixed_outputs": [],
+///   "chosen_inputs": [{ "pointer": "<transaction id>#0", "address": "addr1...", "value": "30", "metadata": null }],
+///   "changes": [{ "address": "addr1...", "value": "29", "assets": [] }],
+///   "fee": "1"
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputSelectionResult<InputUtxo: Clone, OutputUtxo: Clone> {
     pub input_balance: Value<Regulated>,
-    pub input_asset_balance: HashMap<TokenId, TransactionAsset>,
+    pub input_asset_balance: MultiAssetValue,
 
     pub output_balance: Value<Regulated>,
-    pub output_asset_balance: HashMap<TokenId, TransactionAsset>,
+    pub output_asset_balance: MultiAssetValue,
 
     pub fixed_inputs: Vec<InputUtxo>,
     pub fixed_outputs: Vec<OutputUtxo>,
@@ -95,6 +166,11 @@ pub struct InputSelectionResult<InputUtxo: Clone, OutputUtxo: Clone> {
     pub changes: Vec<OutputUtxo>,
 
     pub fee: Value<Regulated>,
+
+    /// the decisions of the selection, only recorded when running it with
+    /// [`select_inputs_traced`](crate::select_inputs_traced)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<SelectionTrace>,
 }
 
 pub fn calculate_main_token_balance(
@@ -110,8 +186,8 @@ pub fn calculate_main_token_balance(
 }
 
 pub fn calculate_asset_balance(
-    input_asset_balance: &HashMap<TokenId, TransactionAsset>,
-    output_asset_balance: &HashMap<TokenId, TransactionAsset>,
+    input_asset_balance: &MultiAssetValue,
+    output_asset_balance: &MultiAssetValue,
 ) -> HashMap<TokenId, Balance<Regulated>> {
     let mut token_balances = HashMap::<TokenId, Balance<Regulated>>::new();
     for (token, asset) in input_asset_balance.iter() {
@@ -123,17 +199,22 @@ pub fn calculate_asset_balance(
     token_balances
 }
 
+/// the assets in the order they are covered by the selections: by policy
+/// id then asset name, so the selection does not depend on the iteration
+/// order of the map
+pub fn assets_in_selection_order(assets: &MultiAssetValue) -> Vec<&TransactionAsset> {
+    let mut assets = assets.assets().collect::<Vec<_>>();
+    assets.sort_by(|left, right| {
+        (&left.policy_id, &left.asset_name).cmp(&(&right.policy_id, &right.asset_name))
+    });
+    assets
+}
+
 pub fn are_assets_balanced(
-    input_asset_balance: &HashMap<TokenId, TransactionAsset>,
-    output_asset_balance: &HashMap<TokenId, TransactionAsset>,
+    input_asset_balance: &MultiAssetValue,
+    output_asset_balance: &MultiAssetValue,
 ) -> bool {
-    let token_balances = calculate_asset_balance(input_asset_balance, output_asset_balance);
-    for balance in token_balances.values() {
-        if !balance.balanced() {
-            return false;
-        }
-    }
-    true
+    input_asset_balance == output_asset_balance
 }
 
 impl<InputUtxo: Clone, OutputUtxo: Clone> InputSelectionResult<InputUtxo, OutputUtxo> {
@@ -149,30 +230,123 @@ impl<InputUtxo: Clone, OutputUtxo: Clone> InputSelectionResult<InputUtxo, Output
 }
 
 impl InputSelectionResult<UTxODetails, UTxOBuilder> {
-    pub fn are_utxos_balanced(&self) -> bool {
-        if !self.is_balanced() {
-            return false;
+    /// sort the chosen inputs by [`UtxoPointer`] and the assets of the
+    /// changes by [`TokenId`]
+    ///
+    /// The changes remain in the order they were built by the algorithm
+    /// (or its [`ChangeStrategy`](crate::ChangeStrategy)). The fixed inputs
+    /// and outputs are left in the order of the [`InputOutputSetup`].
+    #[must_use = "The function does not modify the state, the new value is returned"]
+    pub fn into_stable_order(mut self) -> Self {
+        self.chosen_inputs
+            .sort_by(|left, right| left.pointer.cmp(&right.pointer));
+        for change in self.changes.iter_mut() {
+            change
+                .assets
+                .sort_by(|left, right| left.fingerprint.cmp(&right.fingerprint));
         }
+        self
+    }
 
-        let mut tokens_map = HashMap::<TokenId, Balance<Regulated>>::new();
-        for input in self.fixed_inputs.iter().chain(self.chosen_inputs.iter()) {
-            *tokens_map.entry(TokenId::MAIN).or_default() += &input.value;
-            for asset in input.assets.iter() {
-                *tokens_map.entry(asset.fingerprint.clone()).or_default() += &asset.quantity;
-            }
+    /// turn the result of the selection into a [`TransactionDraft`] that
+    /// can be serialized by the chain specific crates
+    pub fn into_draft(self) -> TransactionDraft {
+        TransactionDraft {
+            fixed_inputs: self.fixed_inputs,
+            fixed_outputs: self.fixed_outputs,
+            chosen_inputs: self.chosen_inputs,
+            changes: self.changes,
+            fee: self.fee,
+            ..TransactionDraft::default()
         }
-        for output in self.fixed_outputs.iter().chain(self.changes.iter()) {
-            *tokens_map.entry(TokenId::MAIN).or_default() -= &output.value;
-            for asset in output.assets.iter() {
-                *tokens_map.entry(asset.fingerprint.clone()).or_default() -= &asset.quantity;
-            }
-        }
-        *tokens_map.entry(TokenId::MAIN).or_default() -= &self.fee;
-        for balance in tokens_map.values() {
-            if !balance.balanced() {
-                return false;
-            }
+    }
+
+    /// check the UTxOs of the result balance as well as its declared
+    /// balances, see [`check_balanced`](crate::verify::check_balanced) for
+    /// the details of the imbalances
+    pub fn are_utxos_balanced(&self) -> bool {
+        self.is_balanced() && crate::verify::check_balanced(self).utxos.is_balanced()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use deps::serde_json;
+    use std::sync::Arc;
+
+    #[test]
+    fn serde_roundtrip() {
+        let mut input = create_utxo(
+            0,
+            0,
+            "0".to_string(),
+            Value::from(30),
+            vec![create_asset("kek".to_string(), Value::from(3))],
+        );
+        input.metadata = Arc::new(serde_json::json!({ "label": 674 }));
+        let result = InputSelectionResult {
+            input_balance: Value::from(30),
+            input_asset_balance: input.assets.iter().collect(),
+            output_balance: Value::from(29),
+            output_asset_balance: input.assets.iter().collect(),
+            fixed_inputs: vec![],
+            fixed_outputs: vec![],
+            chosen_inputs: vec![input.clone()],
+            changes: vec![UTxOBuilder::new(
+                Address::new("change"),
+                Value::from(29),
+                input.assets.clone(),
+            )],
+            fee: Value::from(1),
+            trace: None,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["fee"], serde_json::json!("1"));
+        assert!(json.get("trace").is_none());
+        let decoded: InputSelectionResult<UTxODetails, UTxOBuilder> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, result);
+    }
+
+    #[test]
+    fn stable_order() {
+        let result = InputSelectionResult {
+            input_balance: Value::from(30),
+            input_asset_balance: MultiAssetValue::new(),
+            output_balance: Value::from(30),
+            output_asset_balance: MultiAssetValue::new(),
+            fixed_inputs: vec![],
+            fixed_outputs: vec![],
+            chosen_inputs: vec![
+                create_utxo(1, 0, "0".to_string(), Value::from(10), vec![]),
+                create_utxo(0, 1, "0".to_string(), Value::from(10), vec![]),
+                create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]),
+            ],
+            changes: vec![UTxOBuilder::new(
+                Address::new("change"),
+                Value::from(30),
+                vec![
+                    create_asset("lol".to_string(), Value::from(1)),
+                    create_asset("kek".to_string(), Value::from(1)),
+                ],
+            )],
+            fee: Value::zero(),
+            trace: None,
         }
-        true
+        .into_stable_order();
+
+        let pointers = result
+            .chosen_inputs
+            .iter()
+            .map(|input| input.pointer.clone())
+            .collect::<Vec<_>>();
+        let mut sorted = pointers.clone();
+        sorted.sort();
+        assert_eq!(pointers, sorted);
+        assert_eq!(result.changes[0].assets[0].fingerprint, TokenId::new("kek"));
+        assert_eq!(result.changes[0].assets[1].fingerprint, TokenId::new("lol"));
     }
 }
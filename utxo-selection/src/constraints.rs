@@ -0,0 +1,217 @@
+use crate::{FeeBreakdown, TransactionFeeEstimator};
+use dcspark_core::{Regulated, Value};
+use serde::Deserialize;
+use std::fmt;
+
+/// the limits of the transaction built by the selection
+///
+/// The constraints are enforced by wrapping the estimator in a
+/// [`ConstrainedEstimator`], so every algorithm honors them.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SelectionConstraints {
+    pub max_inputs: Option<usize>,
+    pub max_outputs: Option<usize>,
+    pub max_tx_bytes: Option<usize>,
+}
+
+/// the error returned when the selection can't fit in the
+/// [`SelectionConstraints`]
+///
+/// It is returned inside the [`anyhow::Error`] of the selection, use
+/// `error.downcast_ref::<ConstraintViolation>()` to detect it and split
+/// the payment in smaller transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    TooManyInputs { max: usize },
+    TooManyOutputs { max: usize },
+    TransactionTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyInputs { max } => {
+                write!(f, "the transaction requires more than {max} inputs")
+            }
+            Self::TooManyOutputs { max } => {
+                write!(f, "the transaction requires more than {max} outputs")
+            }
+            Self::TransactionTooLarge { size, max } => write!(
+                f,
+                "the transaction size ({size} bytes) exceeds the maximum of {max} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+/// estimator enforcing the [`SelectionConstraints`] on the inputs and
+/// outputs added by the selection
+///
+/// The fixed inputs and outputs must be added through this estimator as
+/// well to be accounted in the limits.
+#[derive(Clone)]
+pub struct ConstrainedEstimator<Estimate> {
+    inner: Estimate,
+    constraints: SelectionConstraints,
+    inputs: usize,
+    outputs: usize,
+}
+
+impl<Estimate: TransactionFeeEstimator> ConstrainedEstimator<Estimate> {
+    pub fn new(inner: Estimate, constraints: SelectionConstraints) -> Self {
+        Self {
+            inner,
+            constraints,
+            inputs: 0,
+            outputs: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> Estimate {
+        self.inner
+    }
+
+    fn check_size(&self) -> anyhow::Result<()> {
+        if let Some(max) = self.constraints.max_tx_bytes {
+            let size = self.inner.current_size()?;
+            if size > max {
+                return Err(ConstraintViolation::TransactionTooLarge { size, max }.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Estimate: TransactionFeeEstimator> TransactionFeeEstimator for ConstrainedEstimator<Estimate> {
+    type InputUtxo = Estimate::InputUtxo;
+    type OutputUtxo = Estimate::OutputUtxo;
+
+    fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
+        self.inner.min_required_fee()
+    }
+
+    fn fee_for_input(&self, input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
+        self.inner.fee_for_input(input)
+    }
+
+    fn add_input(&mut self, input: Self::InputUtxo) -> anyhow::Result<()> {
+        if let Some(max) = self.constraints.max_inputs {
+            if self.inputs >= max {
+                return Err(ConstraintViolation::TooManyInputs { max }.into());
+            }
+        }
+        self.inner.add_input(input)?;
+        self.inputs += 1;
+        self.check_size()
+    }
+
+    fn fee_for_output(&self, output: &Self::OutputUtxo) -> anyhow::Result<Value<Regulated>> {
+        self.inner.fee_for_output(output)
+    }
+
+    fn add_output(&mut self, output: Self::OutputUtxo) -> anyhow::Result<()> {
+        if let Some(max) = self.constraints.max_outputs {
+            if self.outputs >= max {
+                return Err(ConstraintViolation::TooManyOutputs { max }.into());
+            }
+        }
+        self.inner.add_output(output)?;
+        self.outputs += 1;
+        self.check_size()
+    }
+
+    fn min_value_for_output(
+        &mut self,
+        output: Self::OutputUtxo,
+    ) -> anyhow::Result<Value<Regulated>> {
+        self.inner.min_value_for_output(output)
+    }
+
+    fn current_size(&self) -> anyhow::Result<usize> {
+        self.inner.current_size()
+    }
+
+    fn max_size(&self) -> anyhow::Result<usize> {
+        let max_size = self.inner.max_size()?;
+        Ok(self
+            .constraints
+            .max_tx_bytes
+            .map_or(max_size, |max| max.min(max_size)))
+    }
+
+    fn fee_breakdown(&self) -> anyhow::Result<FeeBreakdown> {
+        self.inner.fee_breakdown()
+    }
+
+    fn fork(&self) -> Option<Self> {
+        Some(Self {
+            inner: self.inner.fork()?,
+            constraints: self.constraints.clone(),
+            inputs: self.inputs,
+            outputs: self.outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::LargestFirst;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{InputOutputSetup, InputSelectionAlgorithm};
+
+    fn select(constraints: SelectionConstraints, output_balance: u64) -> anyhow::Result<usize> {
+        let mut largest_first = LargestFirst::try_from(vec![
+            create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::<Regulated>::from(11), vec![]),
+        ])?;
+        let mut estimator = ConstrainedEstimator::new(DummyFeeEstimate::new(), constraints);
+
+        let result = largest_first.select_inputs(
+            &mut estimator,
+            InputOutputSetup {
+                output_balance: Value::from(output_balance),
+                ..InputOutputSetup::default()
+            },
+        )?;
+        Ok(result.chosen_inputs.len())
+    }
+
+    #[test]
+    fn within_constraints() {
+        let constraints = SelectionConstraints {
+            max_inputs: Some(2),
+            ..SelectionConstraints::default()
+        };
+        assert_eq!(select(constraints, 25).unwrap(), 2);
+    }
+
+    #[test]
+    fn too_many_inputs() {
+        let constraints = SelectionConstraints {
+            max_inputs: Some(1),
+            ..SelectionConstraints::default()
+        };
+        let error = select(constraints, 25).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ConstraintViolation>(),
+            Some(&ConstraintViolation::TooManyInputs { max: 1 })
+        );
+    }
+
+    #[test]
+    fn max_size_is_lowered() {
+        let estimator = ConstrainedEstimator::new(
+            DummyFeeEstimate::<(), ()>::new(),
+            SelectionConstraints {
+                max_tx_bytes: Some(16384),
+                ..SelectionConstraints::default()
+            },
+        );
+        assert_eq!(estimator.max_size().unwrap(), 16384);
+    }
+}
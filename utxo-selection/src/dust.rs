@@ -0,0 +1,279 @@
+use crate::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
+    UTxOStoreSupport,
+};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Address, Regulated, UTxOStore, Value};
+
+/// remove the changes of main asset only below the `dust_threshold`
+///
+/// The value of a dust change is added to the largest remaining change, or
+/// to the fee if there is no other change. The changes holding tokens are
+/// kept whatever their value: their tokens can't be given away in the fee.
+///
+/// The estimator of the selection is not updated, the fee of the removed
+/// changes is left in the fee of the transaction.
+pub fn fold_dust_changes(
+    mut result: InputSelectionResult<UTxODetails, UTxOBuilder>,
+    dust_threshold: &Value<Regulated>,
+) -> InputSelectionResult<UTxODetails, UTxOBuilder> {
+    let (dust, mut changes): (Vec<_>, Vec<_>) = result
+        .changes
+        .into_iter()
+        .partition(|change| change.assets.is_empty() && &change.value < dust_threshold);
+
+    for change in dust {
+        match changes.iter_mut().max_by(|a, b| a.value.cmp(&b.value)) {
+            Some(largest) => largest.value += &change.value,
+            None => {
+                result.output_balance -= &change.value;
+                result.fee += &change.value;
+            }
+        }
+    }
+
+    result.changes = changes;
+    result
+}
+
+/// an algorithm never creating changes of main asset only below the
+/// dust threshold, see [`fold_dust_changes`]
+///
+/// Wrap the algorithm building the changes: the balancer of a
+/// [`SelectionPipeline`](crate::SelectionPipeline), or the primary
+/// algorithm if it builds its own changes like the
+/// [`Thermostat`](crate::algorithms::Thermostat).
+///
+/// The change addresses of the removed changes are given back to the
+/// provider of the algorithm: the remaining changes take the first
+/// addresses of the selection.
+pub struct DustSuppression<Algorithm> {
+    algorithm: Algorithm,
+    dust_threshold: Value<Regulated>,
+}
+
+impl<Algorithm> DustSuppression<Algorithm> {
+    pub fn new(algorithm: Algorithm, dust_threshold: Value<Regulated>) -> Self {
+        Self {
+            algorithm,
+            dust_threshold,
+        }
+    }
+
+    pub fn into_inner(self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+impl<Algorithm: InputSelectionAlgorithm> DustSuppression<Algorithm> {
+    /// rewind the provider of the algorithm to the `start` of the
+    /// selection and give its addresses again to the `changes`
+    ///
+    /// The provider is left at the `end` of the selection if the algorithm
+    /// can't give its addresses.
+    fn reassign_change_addresses(
+        &mut self,
+        start: u64,
+        end: u64,
+        changes: &mut [UTxOBuilder],
+    ) -> anyhow::Result<()> {
+        self.algorithm.rewind_change_addresses(start);
+        for change in changes.iter_mut() {
+            match self.algorithm.next_change_address()? {
+                Some(address) => change.address = address,
+                None => {
+                    self.algorithm.rewind_change_addresses(end);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Algorithm: UTxOStoreSupport> UTxOStoreSupport for DustSuppression<Algorithm> {
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.algorithm.set_available_utxos(utxos)
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        self.algorithm.get_available_utxos()
+    }
+}
+
+impl<Algorithm> InputSelectionAlgorithm for DustSuppression<Algorithm>
+where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<Self::InputUtxo>,
+    ) -> anyhow::Result<()> {
+        self.algorithm.set_available_inputs(available_inputs)
+    }
+
+    fn select_inputs<
+        Estimate: TransactionFeeEstimator<InputUtxo = Self::InputUtxo, OutputUtxo = Self::OutputUtxo>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<Self::InputUtxo, Self::OutputUtxo>,
+    ) -> anyhow::Result<InputSelectionResult<Self::InputUtxo, Self::OutputUtxo>> {
+        let start = self.algorithm.change_addresses_checkpoint();
+        let result = self
+            .algorithm
+            .select_inputs(estimator, input_output_setup)?;
+        let end = self.algorithm.change_addresses_checkpoint();
+
+        let changes = result.changes.len();
+        let mut result = fold_dust_changes(result, &self.dust_threshold);
+        if result.changes.len() < changes {
+            self.reassign_change_addresses(start, end, &mut result.changes)?;
+        }
+        Ok(result)
+    }
+
+    fn available_inputs(&self) -> Vec<Self::InputUtxo> {
+        self.algorithm.available_inputs()
+    }
+
+    fn change_addresses_checkpoint(&self) -> u64 {
+        self.algorithm.change_addresses_checkpoint()
+    }
+
+    fn rewind_change_addresses(&mut self, checkpoint: u64) {
+        self.algorithm.rewind_change_addresses(checkpoint)
+    }
+
+    fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+        self.algorithm.next_change_address()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::change_address::{checkpoint, next_change_address, next_provided_address, rewind};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::verify::check_balanced;
+    use crate::{BoxedChangeAddressProvider, DerivedChangeAddresses};
+
+    fn change(value: u64) -> UTxOBuilder {
+        UTxOBuilder::new(Address::new("change"), Value::from(value), vec![])
+    }
+
+    fn result(changes: Vec<UTxOBuilder>) -> InputSelectionResult<UTxODetails, UTxOBuilder> {
+        let changes_value: Value<Regulated> = changes.iter().map(|change| &change.value).sum();
+        InputSelectionResult {
+            input_balance: Value::from(100),
+            input_asset_balance: Default::default(),
+            output_balance: &Value::from(90) + &changes_value,
+            output_asset_balance: Default::default(),
+            fixed_inputs: vec![],
+            fixed_outputs: vec![UTxOBuilder::new(
+                Address::new("output"),
+                Value::from(90),
+                vec![],
+            )],
+            chosen_inputs: vec![create_utxo(0, 0, "0".to_string(), Value::from(100), vec![])],
+            changes,
+            fee: &Value::from(10) - &changes_value,
+            trace: None,
+        }
+    }
+
+    /// a change of 2 and a change of 5, their addresses taken from the
+    /// provider
+    struct TwoChanges {
+        change_addresses: Option<BoxedChangeAddressProvider>,
+    }
+
+    impl InputSelectionAlgorithm for TwoChanges {
+        type InputUtxo = UTxODetails;
+        type OutputUtxo = UTxOBuilder;
+
+        fn set_available_inputs(&mut self, _: Vec<UTxODetails>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn select_inputs<
+            Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+        >(
+            &mut self,
+            _: &mut Estimate,
+            _: InputOutputSetup<UTxODetails, UTxOBuilder>,
+        ) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>> {
+            let mut changes = vec![change(2), change(5)];
+            for change in changes.iter_mut() {
+                change.address = next_change_address(&mut self.change_addresses, &change.address)?;
+            }
+            Ok(result(changes))
+        }
+
+        fn available_inputs(&self) -> Vec<UTxODetails> {
+            vec![]
+        }
+
+        fn change_addresses_checkpoint(&self) -> u64 {
+            checkpoint(&self.change_addresses)
+        }
+
+        fn rewind_change_addresses(&mut self, checkpoint: u64) {
+            rewind(&mut self.change_addresses, checkpoint);
+        }
+
+        fn next_change_address(&mut self) -> anyhow::Result<Option<Address>> {
+            next_provided_address(&mut self.change_addresses)
+        }
+    }
+
+    #[test]
+    fn dust_goes_to_the_largest_change() {
+        let mut with_tokens = change(1);
+        with_tokens.assets = vec![create_asset("kek".to_string(), Value::from(3))];
+
+        let folded = fold_dust_changes(
+            result(vec![change(2), change(5), with_tokens]),
+            &Value::from(3),
+        );
+
+        assert_eq!(folded.changes.len(), 2);
+        assert_eq!(folded.changes[0].value, Value::from(7));
+        assert_eq!(folded.changes[1].value, Value::from(1));
+        assert!(check_balanced(&folded).is_balanced());
+    }
+
+    #[test]
+    fn dust_goes_to_the_fee() {
+        let folded = fold_dust_changes(result(vec![change(2)]), &Value::from(3));
+
+        assert!(folded.changes.is_empty());
+        assert_eq!(folded.fee, Value::from(10));
+        assert!(check_balanced(&folded).is_balanced());
+    }
+
+    #[test]
+    fn dust_change_address_given_back() {
+        let mut algorithm = DustSuppression::new(
+            TwoChanges {
+                change_addresses: Some(Box::new(DerivedChangeAddresses::new(|index| {
+                    Ok(Address::new(format!("change_{index}")))
+                }))),
+            },
+            Value::from(3),
+        );
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), InputOutputSetup::default())
+            .unwrap();
+
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].address, Address::new("change_0"));
+        assert_eq!(result.changes[0].value, Value::from(7));
+        assert_eq!(algorithm.change_addresses_checkpoint(), 1);
+    }
+}
@@ -1,4 +1,29 @@
 use dcspark_core::{Regulated, Value};
+use serde::Serialize;
+
+/// the fee of the transaction split in its components, see
+/// [`TransactionFeeEstimator::fee_breakdown`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FeeBreakdown {
+    /// the constant fee of every transaction
+    pub base: Value<Regulated>,
+    /// the size of the transaction not attributed to the inputs and the
+    /// outputs (the body, the witnesses and the metadata)
+    pub size: Value<Regulated>,
+    pub inputs: Value<Regulated>,
+    pub outputs: Value<Regulated>,
+    /// the scripts: their size, their execution units and the reference
+    /// scripts
+    pub scripts: Value<Regulated>,
+    /// the part of the fee the estimator does not attribute
+    pub other: Value<Regulated>,
+}
+
+impl FeeBreakdown {
+    pub fn total(&self) -> Value<Regulated> {
+        &self.base + &self.size + &self.inputs + &self.outputs + &self.scripts + &self.other
+    }
+}
 
 ///
 /// This trait is designed to hide the fee calculation under abstraction.
@@ -22,4 +47,29 @@ pub trait TransactionFeeEstimator {
 
     fn current_size(&self) -> anyhow::Result<usize>;
     fn max_size(&self) -> anyhow::Result<usize>;
+
+    /// the [`min_required_fee`](Self::min_required_fee) split in its
+    /// components, the total of the breakdown is always the minimum fee
+    ///
+    /// The estimators not implementing it report the whole fee in
+    /// [`FeeBreakdown::other`].
+    fn fee_breakdown(&self) -> anyhow::Result<FeeBreakdown> {
+        Ok(FeeBreakdown {
+            other: self.min_required_fee()?,
+            ..FeeBreakdown::default()
+        })
+    }
+
+    /// a copy of the estimator, in its current state, to try a selection
+    /// without modifying this one
+    ///
+    /// Used by the [`BestOfSelector`](crate::algorithms::BestOfSelector)
+    /// to compare the algorithms. The estimators that can't be copied
+    /// return `None`.
+    fn fork(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
@@ -2,20 +2,47 @@ use anyhow::anyhow;
 use cardano_multiplatform_lib::builders::input_builder::InputBuilderResult;
 use cardano_multiplatform_lib::builders::output_builder::SingleOutputBuilderResult;
 use cardano_multiplatform_lib::builders::tx_builder::TransactionBuilder;
+use cardano_multiplatform_lib::ledger::alonzo::fees::LinearFee;
 use cardano_multiplatform_lib::ledger::common::value::BigNum;
 use cardano_multiplatform_lib::TransactionOutput;
+use cardano_utils::network_id::fee_for_extra_size;
 use cardano_utils::payment_credentials::CardanoPaymentCredentials;
+use cardano_utils::transaction::estimate_redeemer_size;
 use cardano_utils::utxo::{utxo_builder_to_cml_output, utxo_details_to_cml_input};
 use dcspark_core::tx::{UTxOBuilder, UTxODetails};
 use dcspark_core::{Regulated, Value};
 
-use crate::TransactionFeeEstimator;
+use crate::estimators::{
+    reference_scripts_fee, script_spend_fee, ExecutionUnitPrices, ProtocolParameters,
+};
+use crate::{FeeBreakdown, MinUtxoPolicy, TransactionFeeEstimator};
 
+#[derive(Clone)]
 pub struct CmlFeeEstimator {
     builder: TransactionBuilder,
     script_calculation: bool,
     creds: CardanoPaymentCredentials,
-    coins_per_utxo_byte: BigNum,
+    min_utxo_policy: MinUtxoPolicy,
+    max_tx_size: usize,
+    /// the constant fee of the transactions, only known when built from
+    /// the protocol parameters
+    fee_constant: Value<Regulated>,
+
+    /// the price of the reference scripts (Conway), not priced by the
+    /// transaction builder
+    ref_script_cost_per_byte: Option<u64>,
+    /// the size of the reference scripts of the inputs added so far
+    ref_scripts_size: usize,
+
+    /// the prices of the execution units and the fee of the size of the
+    /// redeemers of the script inputs, only known when built from the
+    /// protocol parameters
+    execution_unit_prices: Option<(ExecutionUnitPrices, LinearFee)>,
+    /// the fee of the script executions of the inputs added so far
+    script_spends_fee: Value<Regulated>,
+    /// the size of the redeemers of the inputs added so far, not known
+    /// to the transaction builder
+    redeemers_size: usize,
 }
 
 const DEFAULT_TX_SIZE: usize = 16384;
@@ -35,24 +62,94 @@ impl CmlFeeEstimator {
             builder: tx_builder,
             script_calculation,
             creds: credentials,
-            coins_per_utxo_byte,
+            min_utxo_policy: MinUtxoPolicy::new(coins_per_utxo_byte),
+            max_tx_size: DEFAULT_TX_SIZE,
+            fee_constant: Value::zero(),
+            ref_script_cost_per_byte: None,
+            ref_scripts_size: 0,
+            execution_unit_prices: None,
+            script_spends_fee: Value::zero(),
+            redeemers_size: 0,
         })
     }
-}
 
-impl TransactionFeeEstimator for CmlFeeEstimator {
-    type InputUtxo = UTxODetails;
-    type OutputUtxo = UTxOBuilder;
+    /// configure the estimator from the live protocol parameters
+    ///
+    /// The fee includes the execution units and the script data hash of
+    /// the script transactions, and the reference scripts of the inputs
+    /// from Conway (`minFeeRefScriptCostPerByte`).
+    pub fn from_protocol_parameters(
+        parameters: &ProtocolParameters,
+        credentials: CardanoPaymentCredentials,
+    ) -> anyhow::Result<Self> {
+        let config = parameters.builder_config()?;
+        let mut estimator = Self::new(
+            TransactionBuilder::new(&config),
+            credentials,
+            true,
+            BigNum::from(parameters.utxo_cost_per_byte),
+        )?;
+        estimator.max_tx_size = parameters.max_tx_size as usize;
+        estimator.fee_constant = Value::from(parameters.tx_fee_fixed);
+        estimator.ref_script_cost_per_byte = parameters.min_fee_ref_script_cost_per_byte;
+        estimator.execution_unit_prices = Some((
+            parameters.execution_unit_prices.clone(),
+            parameters.linear_fee(),
+        ));
+        Ok(estimator)
+    }
 
-    fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
+    fn builder_fee(&self, script_calculation: bool) -> anyhow::Result<Value<Regulated>> {
         let fee = self
             .builder
-            .min_fee(self.script_calculation)
+            .min_fee(script_calculation)
             .map_err(|err| anyhow!("can't calculate fees: {}", err))?;
 
         Ok(Value::<Regulated>::from(u64::from(fee)))
     }
 
+    fn ref_scripts_fee(&self, size: usize) -> Value<Regulated> {
+        let fee = self.ref_script_cost_per_byte.map_or(0, |cost_per_byte| {
+            reference_scripts_fee(cost_per_byte, size)
+        });
+        Value::from(fee)
+    }
+
+    /// the fee of the execution units and the redeemer of the input, see
+    /// [`UTxODetails::script_spend`]
+    fn script_spend_fee(&self, input: &UTxODetails) -> anyhow::Result<Value<Regulated>> {
+        let spend = match &input.script_spend {
+            Some(spend) => spend,
+            None => return Ok(Value::zero()),
+        };
+        let (prices, linear_fee) = self.execution_unit_prices.as_ref().ok_or_else(|| {
+            anyhow!(
+                "the execution unit prices are required to spend the script input {}, build the estimator from the protocol parameters",
+                input.pointer
+            )
+        })?;
+        let redeemer = fee_for_extra_size(estimate_redeemer_size(input), linear_fee);
+        Ok(script_spend_fee(spend, prices, 0)? + Value::from(u64::from(redeemer)))
+    }
+}
+
+fn ref_script_size(input: &UTxODetails) -> usize {
+    input
+        .script_ref
+        .as_ref()
+        .map_or(0, |script_ref| script_ref.script().len())
+}
+
+impl TransactionFeeEstimator for CmlFeeEstimator {
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
+        Ok(self.builder_fee(self.script_calculation)?
+            + self.ref_scripts_fee(self.ref_scripts_size)
+            + &self.script_spends_fee)
+    }
+
     fn fee_for_input(&self, input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
         let converted_input: InputBuilderResult = utxo_details_to_cml_input(input, &self.creds)?;
 
@@ -61,15 +158,25 @@ impl TransactionFeeEstimator for CmlFeeEstimator {
             .fee_for_input(&converted_input)
             .map_err(|err| anyhow!("Can't check input fee: {}", err))?;
 
-        Ok(Value::<Regulated>::from(u64::from(fee)))
+        let ref_scripts_fee = self
+            .ref_scripts_fee(self.ref_scripts_size + ref_script_size(input))
+            .saturating_sub(&self.ref_scripts_fee(self.ref_scripts_size));
+        Ok(Value::<Regulated>::from(u64::from(fee))
+            + ref_scripts_fee
+            + self.script_spend_fee(input)?)
     }
 
     fn add_input(&mut self, input: Self::InputUtxo) -> anyhow::Result<()> {
         let converted_input: InputBuilderResult = utxo_details_to_cml_input(&input, &self.creds)?;
+        let script_spend_fee = self.script_spend_fee(&input)?;
 
         self.builder
             .add_input(&converted_input)
-            .map_err(|err| anyhow!("Can't add input {}", err))
+            .map_err(|err| anyhow!("Can't add input {}", err))?;
+        self.ref_scripts_size += ref_script_size(&input);
+        self.redeemers_size += estimate_redeemer_size(&input);
+        self.script_spends_fee += script_spend_fee;
+        Ok(())
     }
 
     fn fee_for_output(&self, output: &Self::OutputUtxo) -> anyhow::Result<Value<Regulated>> {
@@ -95,27 +202,38 @@ impl TransactionFeeEstimator for CmlFeeEstimator {
         &mut self,
         output: Self::OutputUtxo,
     ) -> anyhow::Result<Value<Regulated>> {
-        let output: TransactionOutput = utxo_builder_to_cml_output(&output)?;
-
-        let lovelace = cardano_multiplatform_lib::ledger::babbage::min_ada::min_pure_ada(
-            &self.coins_per_utxo_byte,
-            &output.address(),
-            &output.datum(),
-            &output.script_ref(),
-        )
-        .map_err(|err| anyhow!("Can't add estimate min ada {}", err))?;
-
-        Ok(Value::from(u64::from(lovelace)))
+        self.min_utxo_policy.min_value_for_output(&output)
     }
 
     fn current_size(&self) -> anyhow::Result<usize> {
-        self.builder
+        let size = self
+            .builder
             .full_size()
-            .map_err(|err| anyhow!("can't calculate size: {}", err))
+            .map_err(|err| anyhow!("can't calculate size: {}", err))?;
+        Ok(size + self.redeemers_size)
     }
 
     fn max_size(&self) -> anyhow::Result<usize> {
-        Ok(DEFAULT_TX_SIZE)
+        Ok(self.max_tx_size)
+    }
+
+    /// the transaction builder does not tell the size of each input and
+    /// output, they are reported in [`FeeBreakdown::size`]
+    fn fee_breakdown(&self) -> anyhow::Result<FeeBreakdown> {
+        let fee_without_scripts = self.builder_fee(false)?;
+        let fee = self.builder_fee(self.script_calculation)?;
+        Ok(FeeBreakdown {
+            base: self.fee_constant.clone(),
+            size: fee_without_scripts.saturating_sub(&self.fee_constant),
+            scripts: fee.saturating_sub(&fee_without_scripts)
+                + self.ref_scripts_fee(self.ref_scripts_size)
+                + &self.script_spends_fee,
+            ..FeeBreakdown::default()
+        })
+    }
+
+    fn fork(&self) -> Option<Self> {
+        Some(self.clone())
     }
 }
 
@@ -185,12 +303,16 @@ mod tests {
         let mut thermostat = Thermostat::new(ThermostatAlgoConfig::default());
 
         let store = vec![UTxODetails {
-            pointer: UtxoPointer { transaction_id: TransactionId::new("ac8f9af3d7760348030515e007c84584537ad056ada73c8a0b86ada14b22d4e0"), output_index: Default::default() },
+            pointer: UtxoPointer { transaction_id: TransactionId::new_unchecked("ac8f9af3d7760348030515e007c84584537ad056ada73c8a0b86ada14b22d4e0"), output_index: Default::default() },
             address: Address::new("addr1q9meks43s2gg5w8s67n4wjfy476t6scg6h34x497le6j886pgt7rsny5d0ncq0ncm8mdm4xag8ej46fsf4fuxsnuhyxq4r0mlu"),
             value: Value::from(10000000),
             assets: vec![],
             metadata: Arc::new(Default::default()),
-            extra: None
+            datum: None,
+            script_ref: None,
+            extra: None,
+            creation_height: None,
+            script_spend: None
         }];
         thermostat.set_available_inputs(store).unwrap();
 
@@ -202,6 +324,8 @@ mod tests {
             fixed_inputs: vec![],
             fixed_outputs: vec![UTxOBuilder::new(Address::new("addr1q99d9num2ngfkamdpgttty6wk42p4tvvvmm29hqex7y9avexqm79yn72ukr3enfwwdtpeju0rha978puyx7g90jspvxqskjafk"), Value::from(1000000), vec![])],
             change_address: Some(Address::new("addr1q9meks43s2gg5w8s67n4wjfy476t6scg6h34x497le6j886pgt7rsny5d0ncq0ncm8mdm4xag8ej46fsf4fuxsnuhyxq4r0mlu")),
+            excluded_inputs: Default::default(),
+            collateral: None,
         }).unwrap();
 
         assert!(result.is_balanced());
@@ -2,7 +2,9 @@ use crate::TransactionFeeEstimator;
 use dcspark_core::{Regulated, Value};
 use std::marker::PhantomData;
 
+#[derive(Clone)]
 pub(crate) struct DummyFeeEstimate<Input, Output> {
+    min_value_for_output: Value<Regulated>,
     phantom_data: PhantomData<(Input, Output)>,
 }
 
@@ -10,6 +12,16 @@ impl<Input, Output> DummyFeeEstimate<Input, Output> {
     #[allow(unused)]
     pub fn new() -> Self {
         DummyFeeEstimate {
+            min_value_for_output: Value::zero(),
+            phantom_data: Default::default(),
+        }
+    }
+
+    /// every output requires at least `min_value_for_output`
+    #[allow(unused)]
+    pub fn with_min_value_for_output(min_value_for_output: Value<Regulated>) -> Self {
+        DummyFeeEstimate {
+            min_value_for_output,
             phantom_data: Default::default(),
         }
     }
@@ -43,7 +55,7 @@ impl<Input: Clone, Output: Clone> TransactionFeeEstimator for DummyFeeEstimate<I
         &mut self,
         _output: Self::OutputUtxo,
     ) -> anyhow::Result<Value<Regulated>> {
-        Ok(Value::zero())
+        Ok(self.min_value_for_output.clone())
     }
 
     fn current_size(&self) -> anyhow::Result<usize> {
@@ -53,4 +65,8 @@ impl<Input: Clone, Output: Clone> TransactionFeeEstimator for DummyFeeEstimate<I
     fn max_size(&self) -> anyhow::Result<usize> {
         Ok(usize::MAX)
     }
+
+    fn fork(&self) -> Option<Self> {
+        Some(self.clone())
+    }
 }
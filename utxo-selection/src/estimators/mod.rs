@@ -1,6 +1,8 @@
 mod cml_fee_estimator;
 pub(crate) mod dummy_estimator;
+mod protocol_parameters;
 mod thermostat_estimator;
 
 pub use cml_fee_estimator::*;
+pub use protocol_parameters::*;
 pub use thermostat_estimator::*;
@@ -0,0 +1,333 @@
+use anyhow::{anyhow, Context};
+use cardano_multiplatform_lib::builders::tx_builder::{
+    TransactionBuilderConfig, TransactionBuilderConfigBuilder,
+};
+use cardano_multiplatform_lib::ledger::alonzo::fees::LinearFee;
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_multiplatform_lib::plutus::ExUnitPrices;
+use cardano_multiplatform_lib::UnitInterval;
+use cardano_utils::network_id::NetworkInfo;
+use dcspark_core::tx::ScriptSpend;
+use dcspark_core::{Regulated, Value};
+use deps::serde_json;
+use serde::Deserialize;
+use std::path::Path;
+
+/// the precision the execution unit prices are converted with
+const PRICE_DENOMINATOR: u64 = 10_000_000;
+
+/// the protocol parameters of the chain the fee estimation depends on
+///
+/// The fields follow the names of the output of
+/// `cardano-cli query protocol-parameters` so the live parameters can be
+/// deserialized directly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolParameters {
+    pub tx_fee_per_byte: u64,
+    pub tx_fee_fixed: u64,
+    pub utxo_cost_per_byte: u64,
+    pub max_tx_size: u32,
+    pub max_value_size: u32,
+    pub stake_pool_deposit: u64,
+    pub stake_address_deposit: u64,
+    pub execution_unit_prices: ExecutionUnitPrices,
+    pub collateral_percentage: u32,
+    pub max_collateral_inputs: u32,
+    /// the price of the reference scripts, from Conway
+    #[serde(default)]
+    pub min_fee_ref_script_cost_per_byte: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionUnitPrices {
+    pub price_memory: f64,
+    pub price_steps: f64,
+}
+
+impl ProtocolParameters {
+    /// load the JSON file written by `cardano-cli query protocol-parameters`
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path).with_context(|| {
+            anyhow!("failed to read protocol parameters from {}", path.display())
+        })?;
+        serde_json::from_reader(file)
+            .with_context(|| anyhow!("failed to read protocol parameters from {}", path.display()))
+    }
+
+    /// the given network with the fee constants replaced by the protocol
+    /// parameters
+    pub fn network_info(&self, network: &NetworkInfo) -> NetworkInfo {
+        let network = network.network_info();
+        NetworkInfo::Custom {
+            protocol_magic: network.protocol_magic(),
+            network_id: network.network_id(),
+            linear_fee_coefficient: self.tx_fee_per_byte.to_string(),
+            linear_fee_constant: self.tx_fee_fixed.to_string(),
+            // a word is 8 bytes
+            coins_per_utxo_word: (self.utxo_cost_per_byte * 8).to_string(),
+            pool_deposit: self.stake_pool_deposit.to_string(),
+            key_deposit: self.stake_address_deposit.to_string(),
+            max_value_size: self.max_value_size,
+            max_tx_size: self.max_tx_size,
+        }
+    }
+
+    /// the fee of the transactions from their size
+    pub fn linear_fee(&self) -> LinearFee {
+        LinearFee::new(
+            &BigNum::from(self.tx_fee_per_byte),
+            &BigNum::from(self.tx_fee_fixed),
+        )
+    }
+
+    /// the configuration of the CML transaction builder
+    pub fn builder_config(&self) -> anyhow::Result<TransactionBuilderConfig> {
+        #[allow(deprecated)]
+        TransactionBuilderConfigBuilder::new()
+            .fee_algo(&self.linear_fee())
+            .coins_per_utxo_byte(&BigNum::from(self.utxo_cost_per_byte))
+            .pool_deposit(&BigNum::from(self.stake_pool_deposit))
+            .key_deposit(&BigNum::from(self.stake_address_deposit))
+            .max_value_size(self.max_value_size)
+            .max_tx_size(self.max_tx_size)
+            .ex_unit_prices(&ExUnitPrices::new(
+                &unit_interval(self.execution_unit_prices.price_memory)?,
+                &unit_interval(self.execution_unit_prices.price_steps)?,
+            ))
+            .collateral_percentage(self.collateral_percentage)
+            .max_collateral_inputs(self.max_collateral_inputs)
+            .build()
+            .map_err(|err| anyhow!("can't configure the transaction builder: {}", err))
+    }
+}
+
+/// the prices are given as decimals, they are converted to a ratio with
+/// a precision of 7 decimals
+fn unit_interval(price: f64) -> anyhow::Result<UnitInterval> {
+    Ok(UnitInterval::new(
+        &BigNum::from(price_numerator(price)?),
+        &BigNum::from(PRICE_DENOMINATOR),
+    ))
+}
+
+/// the numerator of the price over [`PRICE_DENOMINATOR`]
+fn price_numerator(price: f64) -> anyhow::Result<u64> {
+    if !price.is_finite() || price < 0.0 {
+        return Err(anyhow!("invalid execution unit price: {price}"));
+    }
+    Ok((price * PRICE_DENOMINATOR as f64).round() as u64)
+}
+
+/// the fee of spending a script-locked input: its execution units at the
+/// `prices`, rounded up, plus its redeemer at `fee_per_byte`
+pub fn script_spend_fee(
+    spend: &ScriptSpend,
+    prices: &ExecutionUnitPrices,
+    fee_per_byte: u64,
+) -> anyhow::Result<Value<Regulated>> {
+    let denominator = u128::from(PRICE_DENOMINATOR);
+    let execution = u128::from(spend.mem) * u128::from(price_numerator(prices.price_memory)?)
+        + u128::from(spend.steps) * u128::from(price_numerator(prices.price_steps)?);
+    let execution = (execution + denominator - 1) / denominator;
+    let redeemer = spend.redeemer_size as u128 * u128::from(fee_per_byte);
+    let fee = u64::try_from(execution + redeemer)
+        .map_err(|_| anyhow!("the fee of the script execution overflows"))?;
+    Ok(Value::from(fee))
+}
+
+/// the fee of the reference scripts of a transaction (Conway)
+///
+/// The price of each tier of 25 600 bytes is 1.2 times the price of the
+/// previous tier, starting at `cost_per_byte`.
+pub fn reference_scripts_fee(cost_per_byte: u64, size: usize) -> u64 {
+    const TIER_SIZE: u128 = 25_600;
+
+    // the fee and the price are kept as fractions over `denominator`
+    let mut fee = 0u128;
+    let mut price = u128::from(cost_per_byte);
+    let mut denominator = 1u128;
+    let mut remaining = size as u128;
+    while remaining >= TIER_SIZE {
+        fee = (fee + TIER_SIZE * price) * 5;
+        price *= 6;
+        denominator *= 5;
+        remaining -= TIER_SIZE;
+    }
+    ((fee + remaining * price) / denominator) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::estimators::ThermostatFeeEstimator;
+    use crate::TransactionFeeEstimator;
+    use cardano_utils::multisig_plan::MultisigPlan;
+    use dcspark_core::Value;
+
+    #[test]
+    fn reference_scripts_fee_tiers() {
+        assert_eq!(reference_scripts_fee(15, 0), 0);
+        assert_eq!(reference_scripts_fee(15, 1_000), 15_000);
+        assert_eq!(reference_scripts_fee(15, 25_600), 384_000);
+        // the bytes above the first tier cost 18 each
+        assert_eq!(reference_scripts_fee(15, 30_000), 384_000 + 4_400 * 18);
+    }
+
+    fn parameters() -> ProtocolParameters {
+        serde_json::from_str(
+            r#"{
+                "txFeePerByte": 44,
+                "txFeeFixed": 155381,
+                "utxoCostPerByte": 4310,
+                "maxTxSize": 16384,
+                "maxValueSize": 5000,
+                "stakePoolDeposit": 500000000,
+                "stakeAddressDeposit": 2000000,
+                "executionUnitPrices": {
+                    "priceMemory": 0.0577,
+                    "priceSteps": 0.0000721
+                },
+                "collateralPercentage": 150,
+                "maxCollateralInputs": 3,
+                "minFeeRefScriptCostPerByte": 15
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn script_spend_fee_is_rounded_up() {
+        let spend = ScriptSpend {
+            mem: 1_000_000,
+            steps: 500_000_000,
+            redeemer_size: 10,
+        };
+
+        // 57_700 for the memory, 36_050 for the steps and 440 for the
+        // redeemer
+        let fee = script_spend_fee(&spend, &parameters().execution_unit_prices, 44).unwrap();
+        assert_eq!(fee, Value::from(94_190));
+
+        let spend = ScriptSpend {
+            mem: 1,
+            steps: 0,
+            redeemer_size: 0,
+        };
+        let fee = script_spend_fee(&spend, &parameters().execution_unit_prices, 44).unwrap();
+        assert_eq!(fee, Value::from(1));
+    }
+
+    #[test]
+    fn deserialize_cli_parameters() {
+        let parameters = parameters();
+
+        assert_eq!(parameters.min_fee_ref_script_cost_per_byte, Some(15));
+        assert!(parameters.builder_config().is_ok());
+    }
+
+    #[test]
+    fn thermostat_estimator_from_parameters() {
+        let plan: MultisigPlan = serde_json::from_value(serde_json::json! {
+            {
+                "quorum": 2u8,
+                "keys": [
+                    "00000000000000000000000000000000000000000000000000000000",
+                    "00000000000000000000000000000000000000000000000000000001",
+                ]
+            }
+        })
+        .unwrap();
+
+        // the parameters above are the ones assumed for the testnet
+        let estimator = ThermostatFeeEstimator::from_protocol_parameters(
+            &NetworkInfo::Testnet,
+            &plan,
+            &parameters(),
+        );
+        let assumed = ThermostatFeeEstimator::new(NetworkInfo::Testnet, &plan, BigNum::from(4310));
+
+        assert_eq!(
+            estimator.min_required_fee().unwrap(),
+            assumed.min_required_fee().unwrap()
+        );
+        assert_eq!(estimator.max_size().unwrap(), 16384);
+    }
+
+    #[test]
+    fn thermostat_script_inputs() {
+        let plan: MultisigPlan = serde_json::from_value(serde_json::json! {
+            {
+                "quorum": 1u8,
+                "keys": ["00000000000000000000000000000000000000000000000000000000"]
+            }
+        })
+        .unwrap();
+        let spend = ScriptSpend {
+            mem: 1_000_000,
+            steps: 500_000_000,
+            redeemer_size: 10,
+        };
+        let key_input = create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]);
+        let mut script_input = create_utxo(0, 1, "0".to_string(), Value::from(10), vec![]);
+        script_input.script_spend = Some(spend);
+
+        let mut estimator = ThermostatFeeEstimator::from_protocol_parameters(
+            &NetworkInfo::Testnet,
+            &plan,
+            &parameters(),
+        );
+        let script_fee = script_spend_fee(&spend, &parameters().execution_unit_prices, 44).unwrap();
+        assert_eq!(
+            estimator.fee_for_input(&script_input).unwrap(),
+            estimator.fee_for_input(&key_input).unwrap() + &script_fee
+        );
+
+        let before = estimator.min_required_fee().unwrap();
+        estimator.add_input(script_input.clone()).unwrap();
+        assert_eq!(
+            estimator.min_required_fee().unwrap(),
+            &before + &estimator.fee_for_input(&script_input).unwrap()
+        );
+        assert_eq!(
+            estimator.fee_breakdown().unwrap().total(),
+            estimator.min_required_fee().unwrap()
+        );
+
+        // the prices are not known without the protocol parameters
+        let assumed = ThermostatFeeEstimator::new(NetworkInfo::Testnet, &plan, BigNum::from(4310));
+        assert!(assumed.fee_for_input(&script_input).is_err());
+    }
+
+    #[test]
+    fn thermostat_fee_breakdown() {
+        let plan: MultisigPlan = serde_json::from_value(serde_json::json! {
+            {
+                "quorum": 1u8,
+                "keys": ["00000000000000000000000000000000000000000000000000000000"]
+            }
+        })
+        .unwrap();
+        let mut estimator = ThermostatFeeEstimator::from_protocol_parameters(
+            &NetworkInfo::Testnet,
+            &plan,
+            &parameters(),
+        );
+        estimator
+            .add_input(create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]))
+            .unwrap();
+
+        let breakdown = estimator.fee_breakdown().unwrap();
+        assert_eq!(breakdown.base, Value::from(155381));
+        assert_eq!(
+            breakdown.inputs,
+            estimator
+                .fee_for_input(&create_utxo(0, 1, "0".to_string(), Value::from(10), vec![]))
+                .unwrap()
+        );
+        assert_eq!(breakdown.total(), estimator.min_required_fee().unwrap());
+    }
+}
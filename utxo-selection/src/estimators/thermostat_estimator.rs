@@ -1,35 +1,50 @@
-use crate::TransactionFeeEstimator;
+use crate::estimators::{script_spend_fee, ExecutionUnitPrices, ProtocolParameters};
+use crate::{FeeBreakdown, MinUtxoPolicy, TransactionFeeEstimator};
 use anyhow::anyhow;
-use cardano_multiplatform_lib::ledger::common::value::BigNum;
-use cardano_multiplatform_lib::TransactionOutput;
+use cardano_multiplatform_lib::ledger::alonzo::fees::LinearFee;
+use cardano_multiplatform_lib::ledger::common::value::{BigNum, Coin};
 use cardano_utils::multisig_plan::MultisigPlan;
-use cardano_utils::network_id::NetworkInfo;
-use cardano_utils::utxo::utxo_builder_to_cml_output;
-use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use cardano_utils::network_id::{fee_for_extra_size, fee_for_size, NetworkInfo};
+use cardano_utils::transaction::{estimate_input_size, estimate_tx_size};
+use dcspark_core::tx::{TransactionDraft, UTxOBuilder, UTxODetails};
 use dcspark_core::{Balance, Regulated, TokenId, Value};
 use std::collections::HashMap;
 
+/// the fee is the [`fee_for_size`] of the estimated size of the
+/// transaction (see [`estimate_tx_size`]), plus the execution of the
+/// scripts of the inputs
+#[derive(Clone)]
 pub struct ThermostatFeeEstimator {
-    network_info: NetworkInfo,
+    linear_fee: LinearFee,
 
-    cost_empty: Value<Regulated>,
-    cost_input: Value<Regulated>,
-    cost_output: Value<Regulated>,
-    cost_metadata: Value<Regulated>,
+    /// the size of the script of the plan and of the extra fields of the
+    /// body it requires, reported apart in the fee breakdown
+    script_size: usize,
+    /// the assumed execution cost of spending an input from the plan
+    cost_execution: Value<Regulated>,
+    /// the execution cost of the inputs added so far: `cost_execution`
+    /// for each input, or the cost of its [`UTxODetails::script_spend`]
+    inputs_execution: Value<Regulated>,
+    /// the prices of the execution units, only known when built from the
+    /// protocol parameters
+    execution_unit_prices: Option<ExecutionUnitPrices>,
 
     current_size: usize,
     max_size: usize,
-    size_of_one_input: usize,
     size_of_one_output: usize,
 
     outputs: Vec<UTxOBuilder>,
     inputs: Vec<UTxODetails>,
 
-    coins_per_utxo_byte: BigNum,
+    min_utxo_policy: MinUtxoPolicy,
 
     asset_balance: HashMap<TokenId, Balance<Regulated>>,
 }
 
+fn coin_to_value(coin: Coin) -> Value<Regulated> {
+    Value::from(u64::from(coin))
+}
+
 impl ThermostatFeeEstimator {
     #[allow(unused)]
     pub fn new(
@@ -37,68 +52,79 @@ impl ThermostatFeeEstimator {
         plan: &MultisigPlan,
         coins_per_utxo_byte: BigNum,
     ) -> Self {
-        // compute the cost of an empty transaction this is with the
-        // the native script included so we know what it will cost
-        // already from there.
-        // it also contains the `quorum` number of witnesses.
-        let mut cost_empty = {
-            let v = network_info.assumed_empty_transaction();
-            v.to_str().parse().unwrap()
-        };
-        let cost_script: Value<Regulated> = {
-            let v = network_info.assumed_cost_native_script(plan);
-            v.to_str().parse().unwrap()
-        };
-        let cost_witness: Value<Regulated> = {
-            let v = network_info.assumed_cost_one_witness();
-            v.to_str().parse().unwrap()
-        };
-        cost_empty += cost_script + (&cost_witness * plan.quorum);
+        // the size of an empty transaction is with the native script
+        // and the `quorum` number of witnesses included so we know what
+        // it will cost already from there.
+        let current_size = estimate_tx_size(&TransactionDraft::default(), plan);
 
-        let cost_input = {
-            let v = network_info.assumed_cost_one_input();
-            v.to_str().parse().unwrap()
-        };
-        let cost_output = {
-            let v = network_info.assumed_cost_one_output();
-            v.to_str().parse().unwrap()
-        };
+        // every input locked by a plutus script needs to pay for
+        // the execution of the script
+        let cost_execution = Value::from(plan.assumed_execution_fee());
 
-        let current_size =
-            network_info.estimated_size_empty() + network_info.estimate_size_overhead(plan);
-        let max_size = network_info.max_tx_size();
-        let size_of_one_input = network_info.estimated_size_input();
-        let size_of_one_output = network_info.estimated_size_output();
         Self {
-            network_info,
+            linear_fee: network_info.linear_fee(),
 
-            cost_empty,
-            cost_input,
-            cost_output,
-            cost_metadata: Value::zero(),
+            script_size: plan.script_witness_size() + plan.body_overhead_size(),
+            cost_execution,
+            inputs_execution: Value::zero(),
+            execution_unit_prices: None,
 
             current_size,
-            max_size,
-            size_of_one_input,
-            size_of_one_output,
+            max_size: network_info.max_tx_size(),
+            size_of_one_output: network_info.estimated_size_output(),
 
             outputs: Vec::new(),
             inputs: Vec::new(),
-            coins_per_utxo_byte,
+            min_utxo_policy: MinUtxoPolicy::new(coins_per_utxo_byte),
             asset_balance: HashMap::new(),
         }
     }
 
-    #[allow(unused)]
-    pub fn add_protocol_magic(&mut self, protocol_magic: impl AsRef<str>) {
-        self.current_size = protocol_magic.as_ref().len() + 5;
-        self.cost_metadata = {
-            let v = self
-                .network_info
-                .assumed_cost_metadata_protocol_magic(protocol_magic);
+    /// the costs from the live protocol parameters instead of the
+    /// constants assumed for the `network`
+    ///
+    /// Only the protocol magic and the network id of `network` are kept.
+    pub fn from_protocol_parameters(
+        network: &NetworkInfo,
+        plan: &MultisigPlan,
+        parameters: &ProtocolParameters,
+    ) -> Self {
+        let mut estimator = Self::new(
+            parameters.network_info(network),
+            plan,
+            BigNum::from(parameters.utxo_cost_per_byte),
+        );
+        estimator.execution_unit_prices = Some(parameters.execution_unit_prices.clone());
+        estimator
+    }
 
-            v.to_str().parse().unwrap()
+    /// the execution cost of the input: the assumed execution fee of the
+    /// plan, or the cost of the execution units of the
+    /// [`UTxODetails::script_spend`] if known. Its redeemer is accounted
+    /// in the size of the input.
+    fn execution_fee_of(&self, input: &UTxODetails) -> anyhow::Result<Value<Regulated>> {
+        let spend = match &input.script_spend {
+            Some(spend) => spend,
+            None => return Ok(self.cost_execution.clone()),
         };
+        let prices = self.execution_unit_prices.as_ref().ok_or_else(|| {
+            anyhow!(
+                "the execution unit prices are required to spend the script input {}, build the estimator from the protocol parameters",
+                input.pointer
+            )
+        })?;
+        script_spend_fee(spend, prices, 0)
+    }
+
+    fn fee_for_extra_size(&self, size: usize) -> Value<Regulated> {
+        coin_to_value(fee_for_extra_size(size, &self.linear_fee))
+    }
+
+    #[allow(unused)]
+    pub fn add_protocol_magic(&mut self, protocol_magic: impl AsRef<str>) {
+        // see NetworkInfo::assumed_cost_metadata_protocol_magic for the
+        // CBOR overhead
+        self.current_size += protocol_magic.as_ref().len() + 5;
     }
 }
 
@@ -107,16 +133,14 @@ impl TransactionFeeEstimator for ThermostatFeeEstimator {
     type OutputUtxo = UTxOBuilder;
 
     fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
-        let num_outputs = self.outputs.len();
-        let num_inputs = self.inputs.len();
-        Ok(&self.cost_empty
-            + &self.cost_metadata
-            + (&self.cost_output * num_outputs)
-            + (&self.cost_input * num_inputs))
+        Ok(
+            coin_to_value(fee_for_size(self.current_size, &self.linear_fee))
+                + &self.inputs_execution,
+        )
     }
 
-    fn fee_for_input(&self, _input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
-        Ok(self.cost_input.clone())
+    fn fee_for_input(&self, input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
+        Ok(self.fee_for_extra_size(estimate_input_size(input)) + self.execution_fee_of(input)?)
     }
 
     fn add_input(&mut self, input: Self::InputUtxo) -> anyhow::Result<()> {
@@ -129,13 +153,14 @@ impl TransactionFeeEstimator for ThermostatFeeEstimator {
             *balance += &asset.quantity;
         }
 
-        self.current_size += self.size_of_one_input;
+        self.inputs_execution += self.execution_fee_of(&input)?;
+        self.current_size += estimate_input_size(&input);
         self.inputs.push(input);
         Ok(())
     }
 
     fn fee_for_output(&self, _output: &Self::OutputUtxo) -> anyhow::Result<Value<Regulated>> {
-        Ok(self.cost_output.clone())
+        Ok(self.fee_for_extra_size(self.size_of_one_output))
     }
 
     fn add_output(&mut self, output: Self::OutputUtxo) -> anyhow::Result<()> {
@@ -155,17 +180,7 @@ impl TransactionFeeEstimator for ThermostatFeeEstimator {
         &mut self,
         output: Self::OutputUtxo,
     ) -> anyhow::Result<Value<Regulated>> {
-        let output: TransactionOutput = utxo_builder_to_cml_output(&output)?;
-
-        let lovelace = cardano_multiplatform_lib::ledger::babbage::min_ada::min_pure_ada(
-            &self.coins_per_utxo_byte,
-            &output.address(),
-            &output.datum(),
-            &output.script_ref(),
-        )
-        .map_err(|err| anyhow!("Can't add estimate min ada {}", err))?;
-
-        Ok(Value::from(u64::from(lovelace)))
+        self.min_utxo_policy.min_value_for_output(&output)
     }
 
     fn current_size(&self) -> anyhow::Result<usize> {
@@ -175,4 +190,87 @@ impl TransactionFeeEstimator for ThermostatFeeEstimator {
     fn max_size(&self) -> anyhow::Result<usize> {
         Ok(self.max_size)
     }
+
+    fn fee_breakdown(&self) -> anyhow::Result<FeeBreakdown> {
+        let inputs_size = self.inputs.iter().map(estimate_input_size).sum::<usize>();
+        let outputs_size = self.outputs.len() * self.size_of_one_output;
+        // the empty transaction, the witnesses and the metadata
+        let other_size = self
+            .current_size
+            .saturating_sub(self.script_size + inputs_size + outputs_size);
+        Ok(FeeBreakdown {
+            base: coin_to_value(self.linear_fee.constant()),
+            size: self.fee_for_extra_size(other_size),
+            inputs: self.fee_for_extra_size(inputs_size),
+            outputs: self.fee_for_extra_size(outputs_size),
+            scripts: self.fee_for_extra_size(self.script_size) + &self.inputs_execution,
+            other: Value::zero(),
+        })
+    }
+
+    fn fork(&self) -> Option<Self> {
+        Some(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use deps::serde_json;
+
+    fn plan() -> MultisigPlan {
+        serde_json::from_value(serde_json::json! {
+            {
+                "quorum": 1u8,
+                "keys": ["00000000000000000000000000000000000000000000000000000000"]
+            }
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn protocol_magic_adds_to_the_size() {
+        let mut estimator =
+            ThermostatFeeEstimator::new(NetworkInfo::Testnet, &plan(), BigNum::from(4310));
+        estimator
+            .add_input(create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]))
+            .unwrap();
+        let size = estimator.current_size().unwrap();
+        let fee = estimator.min_required_fee().unwrap();
+
+        estimator.add_protocol_magic("unittest.cardano-evm.c1");
+
+        assert_eq!(
+            estimator.current_size().unwrap(),
+            size + "unittest.cardano-evm.c1".len() + 5
+        );
+        assert!(estimator.min_required_fee().unwrap() > fee);
+    }
+
+    #[test]
+    fn fee_of_the_estimated_size() {
+        let mut estimator =
+            ThermostatFeeEstimator::new(NetworkInfo::Testnet, &plan(), BigNum::from(4310));
+        estimator
+            .add_input(create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]))
+            .unwrap();
+        estimator
+            .add_output(UTxOBuilder::new(
+                dcspark_core::Address::new("address"),
+                Value::from(10),
+                vec![],
+            ))
+            .unwrap();
+
+        let size = estimator.current_size().unwrap();
+        assert_eq!(
+            estimator.min_required_fee().unwrap(),
+            coin_to_value(fee_for_size(size, &NetworkInfo::Testnet.linear_fee()))
+        );
+        assert_eq!(
+            estimator.fee_breakdown().unwrap().total(),
+            estimator.min_required_fee().unwrap()
+        );
+    }
 }
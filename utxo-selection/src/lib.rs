@@ -1,10 +1,39 @@
 pub mod algorithms;
 pub mod estimators;
+pub mod verify;
 
 mod algorithm;
+mod batch;
+mod budget;
+mod change_address;
+mod change_strategy;
+mod collateral;
 mod common;
+mod constraints;
+mod dust;
 mod estimate;
+mod min_utxo;
+mod partial;
+mod pipeline;
+mod quote;
+mod scorer;
+mod trace;
+mod waste;
 
 pub use algorithm::*;
+pub use batch::*;
+pub use budget::*;
+pub use change_address::*;
+pub use change_strategy::*;
+pub use collateral::*;
 pub use common::*;
+pub use constraints::*;
+pub use dust::*;
 pub use estimate::*;
+pub use min_utxo::*;
+pub use partial::*;
+pub use pipeline::*;
+pub use quote::*;
+pub use scorer::*;
+pub use trace::*;
+pub use waste::*;
@@ -0,0 +1,35 @@
+use cardano_multiplatform_lib::ledger::common::value::BigNum;
+use cardano_utils::utxo::min_ada_required;
+use dcspark_core::tx::UTxOBuilder;
+use dcspark_core::{Regulated, Value};
+
+/// the minimum value of main asset the ledger requires in an output
+///
+/// The requirement depends on the size of the output
+/// (`coinsPerUTxOByte` protocol parameter): an output holding tokens,
+/// a datum or a script reference requires more than a pure output.
+#[derive(Debug, Clone)]
+pub struct MinUtxoPolicy {
+    coins_per_utxo_byte: BigNum,
+}
+
+impl MinUtxoPolicy {
+    pub fn new(coins_per_utxo_byte: BigNum) -> Self {
+        Self {
+            coins_per_utxo_byte,
+        }
+    }
+
+    /// the minimum value for the given output, including its assets
+    pub fn min_value_for_output(&self, output: &UTxOBuilder) -> anyhow::Result<Value<Regulated>> {
+        min_ada_required(output, &self.coins_per_utxo_byte)
+    }
+
+    /// the value missing in the output to reach the minimum, zero if the
+    /// output already holds enough
+    pub fn missing_value(&self, output: &UTxOBuilder) -> anyhow::Result<Value<Regulated>> {
+        Ok(self
+            .min_value_for_output(output)?
+            .saturating_sub(&output.value))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
+};
+use dcspark_core::tx::{MultiAssetValue, TransactionAsset, UTxOBuilder, UTxODetails};
+use dcspark_core::{TokenId, Value};
+use std::collections::HashSet;
+
+/// the result of [`select_tokens_partially`]
+#[derive(Debug, Clone)]
+pub struct PartialSelection {
+    /// the selection, its `output_asset_balance` holds all the tokens of
+    /// the outputs so it is not balanced while `remaining` is not empty
+    pub result: InputSelectionResult<UTxODetails, UTxOBuilder>,
+    /// the quantities of the outputs not covered by the inputs, to move in
+    /// a following transaction
+    pub remaining: MultiAssetValue,
+}
+
+/// select the inputs for the main asset and the given `tokens` only
+///
+/// The other tokens of the outputs are left out of the selection and
+/// reported in [`PartialSelection::remaining`] instead of failing the
+/// selection. This is for the payments too large for one transaction,
+/// with the assets moved in stages.
+pub fn select_tokens_partially<Algorithm, Estimate>(
+    algorithm: &mut Algorithm,
+    estimator: &mut Estimate,
+    mut input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    tokens: &HashSet<TokenId>,
+) -> anyhow::Result<PartialSelection>
+where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    let output_asset_balance = std::mem::take(&mut input_output_setup.output_asset_balance);
+    input_output_setup.output_asset_balance = output_asset_balance
+        .assets()
+        .filter(|asset| tokens.contains(&asset.fingerprint))
+        .collect();
+
+    let mut result = algorithm.select_inputs(estimator, input_output_setup)?;
+
+    let mut remaining = MultiAssetValue::new();
+    for asset in output_asset_balance.assets() {
+        let covered = result.input_asset_balance.quantity_of(&asset.fingerprint);
+        if let Some(missing) = asset.quantity.checked_sub(&covered) {
+            if missing > Value::zero() {
+                remaining.add_asset(&TransactionAsset {
+                    quantity: missing,
+                    ..asset.clone()
+                });
+            }
+        }
+    }
+    result.output_asset_balance = output_asset_balance;
+
+    Ok(PartialSelection { result, remaining })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::{create_asset, create_utxo};
+    use crate::algorithms::LargestFirst;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+
+    #[test]
+    fn select_one_token_of_two() {
+        let mut largest_first = LargestFirst::try_from(vec![
+            create_utxo(
+                0,
+                0,
+                "0".to_string(),
+                Value::from(2),
+                vec![create_asset("kek".to_string(), Value::from(10))],
+            ),
+            create_utxo(0, 1, "0".to_string(), Value::from(10), vec![]),
+        ])
+        .unwrap();
+
+        let partial = select_tokens_partially(
+            &mut largest_first,
+            &mut DummyFeeEstimate::new(),
+            InputOutputSetup {
+                output_balance: Value::from(5),
+                output_asset_balance: vec![
+                    create_asset("kek".to_string(), Value::from(10)),
+                    create_asset("lol".to_string(), Value::from(3)),
+                ]
+                .into_iter()
+                .collect(),
+                ..InputOutputSetup::default()
+            },
+            &[TokenId::new("kek")].into_iter().collect(),
+        )
+        .unwrap();
+
+        assert_eq!(partial.result.chosen_inputs.len(), 2);
+        assert_eq!(
+            partial.remaining,
+            vec![create_asset("lol".to_string(), Value::from(3))]
+                .into_iter()
+                .collect::<MultiAssetValue>()
+        );
+        assert!(!partial.result.is_balanced());
+    }
+}
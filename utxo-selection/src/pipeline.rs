@@ -0,0 +1,183 @@
+use crate::{
+    InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult, TransactionFeeEstimator,
+    UTxOStoreSupport,
+};
+use anyhow::{anyhow, Context};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::UTxOStore;
+
+/// run a primary selection then balance the change of the transaction
+///
+/// The change balancer is only run if the primary selection did not
+/// balance the transaction (e.g. the [`Thermostat`](crate::algorithms::Thermostat)
+/// builds its own changes). The change strategy is the one of the
+/// balancer, see
+/// [`SingleOutputChangeBalancer::with_change_strategy`](crate::algorithms::SingleOutputChangeBalancer::with_change_strategy).
+///
+/// The available inputs are handed between the two algorithms as a
+/// [`UTxOStore`], wrap the algorithms without [`UTxOStoreSupport`] in a
+/// [`UTxOStoreAdapter`](crate::UTxOStoreAdapter).
+pub struct SelectionPipeline<Primary, Balancer> {
+    primary: Primary,
+    balancer: Balancer,
+}
+
+impl<Primary, Balancer> SelectionPipeline<Primary, Balancer>
+where
+    Primary: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+    Balancer: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+{
+    pub fn new(primary: Primary, balancer: Balancer) -> Self {
+        Self { primary, balancer }
+    }
+
+    pub fn set_available_inputs(
+        &mut self,
+        available_inputs: Vec<UTxODetails>,
+    ) -> anyhow::Result<()> {
+        self.primary.set_available_inputs(available_inputs)
+    }
+
+    pub fn available_inputs(&self) -> Vec<UTxODetails> {
+        self.primary.available_inputs()
+    }
+
+    /// select the inputs and the changes of the transaction, the result is
+    /// always balanced
+    pub fn select<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &mut self,
+        estimator: &mut Estimate,
+        input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    ) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>> {
+        let fixed_inputs = input_output_setup.fixed_inputs.clone();
+        let fixed_outputs = input_output_setup.fixed_outputs.clone();
+        let change_address = input_output_setup.change_address.clone();
+        let excluded_inputs = input_output_setup.excluded_inputs.clone();
+        let collateral = input_output_setup.collateral.clone();
+
+        let primary = self
+            .primary
+            .select_inputs(estimator, input_output_setup)
+            .context("Primary selection failed")?;
+        if primary.is_balanced() {
+            return Ok(primary);
+        }
+
+        // the inputs and the changes of the primary selection are fixed
+        // for the balancer
+        self.balancer
+            .set_available_utxos(self.primary.get_available_utxos()?)?;
+        let balanced = self
+            .balancer
+            .select_inputs(
+                estimator,
+                InputOutputSetup {
+                    input_balance: primary.input_balance,
+                    input_asset_balance: primary.input_asset_balance,
+                    output_balance: primary.output_balance,
+                    output_asset_balance: primary.output_asset_balance,
+                    fixed_inputs: primary
+                        .fixed_inputs
+                        .into_iter()
+                        .chain(primary.chosen_inputs.iter().cloned())
+                        .collect(),
+                    fixed_outputs: primary
+                        .fixed_outputs
+                        .into_iter()
+                        .chain(primary.changes.iter().cloned())
+                        .collect(),
+                    change_address,
+                    excluded_inputs,
+                    collateral,
+                },
+            )
+            .context("Balancing the change failed")?;
+        self.primary
+            .set_available_utxos(self.balancer.get_available_utxos()?)?;
+
+        let result = InputSelectionResult {
+            input_balance: balanced.input_balance,
+            input_asset_balance: balanced.input_asset_balance,
+            output_balance: balanced.output_balance,
+            output_asset_balance: balanced.output_asset_balance,
+            fixed_inputs,
+            fixed_outputs,
+            chosen_inputs: primary
+                .chosen_inputs
+                .into_iter()
+                .chain(balanced.chosen_inputs)
+                .collect(),
+            changes: primary
+                .changes
+                .into_iter()
+                .chain(balanced.changes)
+                .collect(),
+            fee: balanced.fee,
+            trace: None,
+        }
+        .into_stable_order();
+        if !result.is_balanced() {
+            return Err(anyhow!(
+                "The selection is not balanced after balancing the change"
+            ));
+        }
+        Ok(result)
+    }
+}
+
+impl<Primary: UTxOStoreSupport, Balancer> UTxOStoreSupport
+    for SelectionPipeline<Primary, Balancer>
+{
+    fn set_available_utxos(&mut self, utxos: UTxOStore) -> anyhow::Result<()> {
+        self.primary.set_available_utxos(utxos)
+    }
+
+    fn get_available_utxos(&mut self) -> anyhow::Result<UTxOStore> {
+        self.primary.get_available_utxos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::{LargestFirst, SingleOutputChangeBalancer};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use dcspark_core::{Address, Regulated, Value};
+
+    #[test]
+    fn select_and_balance_change() {
+        let inputs = vec![
+            create_utxo(0, 0, "0".to_string(), Value::<Regulated>::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::<Regulated>::from(20), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::<Regulated>::from(11), vec![]),
+        ];
+        let mut pipeline = SelectionPipeline::new(
+            LargestFirst::try_from(inputs).unwrap(),
+            SingleOutputChangeBalancer::default(),
+        );
+
+        let output = UTxOBuilder::new(Address::new("output"), Value::from(25), vec![]);
+        let result = pipeline
+            .select(
+                &mut DummyFeeEstimate::new(),
+                InputOutputSetup::from_fixed_inputs_and_outputs(
+                    vec![],
+                    vec![output],
+                    Some(Address::new("change")),
+                ),
+            )
+            .unwrap();
+
+        assert!(result.are_utxos_balanced());
+        assert_eq!(result.chosen_inputs.len(), 2);
+        assert_eq!(result.fixed_outputs.len(), 1);
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].value, Value::<Regulated>::from(6));
+        assert_eq!(pipeline.available_inputs().len(), 1);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{InputOutputSetup, InputSelectionAlgorithm, TransactionFeeEstimator, UTxOStoreSupport};
+use anyhow::anyhow;
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Regulated, Value};
+use serde::Serialize;
+
+/// the preview of a payment returned by [`estimate_payment`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeeQuote {
+    pub fee: Value<Regulated>,
+    /// the inputs of the transaction, the fixed inputs included
+    pub num_inputs: usize,
+    pub change_outputs: usize,
+}
+
+/// run the selection for the payment and quote its fee, without committing
+/// to the transaction
+///
+/// The selection runs on a copy of the `estimator` (see
+/// [`TransactionFeeEstimator::fork`]), the available inputs
+/// and the change addresses of the `algorithm` are restored afterwards, so
+/// the same algorithm can select the inputs of the actual transaction once
+/// the quote is accepted.
+/// The randomized algorithms may select differently on the second run.
+pub fn estimate_payment<Algorithm, Estimate>(
+    input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+    algorithm: &mut Algorithm,
+    estimator: &Estimate,
+) -> anyhow::Result<FeeQuote>
+where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+        + UTxOStoreSupport,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    let mut estimator = estimator
+        .fork()
+        .ok_or_else(|| anyhow!("The estimator can't be copied to quote the payment"))?;
+
+    let available_utxos = algorithm.get_available_utxos()?;
+    let change_addresses = algorithm.change_addresses_checkpoint();
+    let result = algorithm.select_inputs(&mut estimator, input_output_setup);
+    algorithm.set_available_utxos(available_utxos)?;
+    algorithm.rewind_change_addresses(change_addresses);
+
+    let result = result?;
+    Ok(FeeQuote {
+        fee: result.fee,
+        num_inputs: result.fixed_inputs.len() + result.chosen_inputs.len(),
+        change_outputs: result.changes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::{BestOfSelector, LargestFirst, SingleOutputChangeBalancer};
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{DerivedChangeAddresses, DustSuppression, TracingEstimator};
+    use dcspark_core::Address;
+
+    fn balancer() -> SingleOutputChangeBalancer {
+        SingleOutputChangeBalancer::default().with_change_addresses(DerivedChangeAddresses::new(
+            |index| Ok(Address::new(format!("change_{index}"))),
+        ))
+    }
+
+    /// a fixed input of 30 for an output of 10, the balancer adds a change
+    /// of 20
+    fn paid_by_fixed_input() -> InputOutputSetup<UTxODetails, UTxOBuilder> {
+        InputOutputSetup {
+            input_balance: Value::from(30),
+            output_balance: Value::from(10),
+            fixed_inputs: vec![create_utxo(0, 0, "0".to_string(), Value::from(30), vec![])],
+            change_address: Some(Address::new("change")),
+            ..InputOutputSetup::default()
+        }
+    }
+
+    fn quote_then_select<Algorithm>(mut algorithm: Algorithm)
+    where
+        Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>
+            + UTxOStoreSupport,
+    {
+        let quote = estimate_payment(
+            paid_by_fixed_input(),
+            &mut algorithm,
+            &DummyFeeEstimate::new(),
+        )
+        .unwrap();
+        assert_eq!(quote.change_outputs, 1);
+
+        let result = algorithm
+            .select_inputs(&mut DummyFeeEstimate::new(), paid_by_fixed_input())
+            .unwrap();
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].address, Address::new("change_0"));
+    }
+
+    #[test]
+    fn quote_does_not_consume_inputs() {
+        let mut largest_first = LargestFirst::try_from(vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(20), vec![]),
+        ])
+        .unwrap();
+        let setup = InputOutputSetup {
+            output_balance: Value::from(25),
+            ..InputOutputSetup::default()
+        };
+
+        let quote =
+            estimate_payment(setup.clone(), &mut largest_first, &DummyFeeEstimate::new()).unwrap();
+
+        assert_eq!(
+            quote,
+            FeeQuote {
+                fee: Value::zero(),
+                num_inputs: 2,
+                change_outputs: 0,
+            }
+        );
+        assert_eq!(largest_first.available_inputs().len(), 2);
+
+        let result = largest_first
+            .select_inputs(&mut DummyFeeEstimate::new(), setup)
+            .unwrap();
+        assert_eq!(result.chosen_inputs.len(), 2);
+    }
+
+    #[test]
+    fn quote_requires_fork() {
+        let mut largest_first = LargestFirst::try_from(vec![create_utxo(
+            0,
+            0,
+            "0".to_string(),
+            Value::from(10),
+            vec![],
+        )])
+        .unwrap();
+        // the tracing estimator only borrows its inner estimator
+        let mut inner = DummyFeeEstimate::new();
+        let estimator = TracingEstimator::new(&mut inner);
+
+        assert!(estimate_payment(
+            InputOutputSetup {
+                output_balance: Value::from(5),
+                ..InputOutputSetup::default()
+            },
+            &mut largest_first,
+            &estimator,
+        )
+        .is_err());
+        assert_eq!(largest_first.available_inputs().len(), 1);
+    }
+
+    #[test]
+    fn quote_does_not_consume_change_addresses() {
+        quote_then_select(balancer());
+        quote_then_select(DustSuppression::new(balancer(), Value::from(5)));
+        quote_then_select(BestOfSelector::new(balancer(), balancer()));
+    }
+}
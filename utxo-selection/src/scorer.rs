@@ -0,0 +1,131 @@
+use dcspark_core::tx::UTxODetails;
+use dcspark_core::BlockNumber;
+use rand::Rng;
+
+/// the preference of the selection between the candidate inputs
+///
+/// The [`LargestFirst`](crate::algorithms::LargestFirst) picks the
+/// candidate of highest score (the largest on a tie) and the
+/// [`RandomImprove`](crate::algorithms::RandomImprove) draws the candidates
+/// with a probability proportional to their score plus one.
+pub trait CandidateScorer {
+    /// the score of spending `candidate` in a transaction already spending
+    /// the `selected` inputs, the higher the more preferred
+    fn score(&self, candidate: &UTxODetails, selected: &[UTxODetails]) -> u64;
+}
+
+/// prefer the older UTxOs so the transaction accumulates coin-age
+///
+/// The score is the number of blocks since the creation of the UTxO, the
+/// UTxOs without [`UTxODetails::creation_height`] score zero.
+#[derive(Debug, Clone)]
+pub struct CoinAgeScorer {
+    current_height: BlockNumber,
+}
+
+impl CoinAgeScorer {
+    pub fn new(current_height: BlockNumber) -> Self {
+        Self { current_height }
+    }
+}
+
+impl CandidateScorer for CoinAgeScorer {
+    fn score(&self, candidate: &UTxODetails, _selected: &[UTxODetails]) -> u64 {
+        candidate.creation_height.map_or(0, |creation_height| {
+            self.current_height
+                .into_inner()
+                .saturating_sub(creation_height.into_inner())
+        })
+    }
+}
+
+/// prefer the UTxOs at the addresses the transaction already spends from
+/// so it links as few addresses as possible
+///
+/// The candidates at these addresses score `weight`, the others zero.
+#[derive(Debug, Clone)]
+pub struct AddressDiversityScorer {
+    weight: u64,
+}
+
+impl AddressDiversityScorer {
+    pub fn new(weight: u64) -> Self {
+        Self { weight }
+    }
+}
+
+impl Default for AddressDiversityScorer {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl CandidateScorer for AddressDiversityScorer {
+    fn score(&self, candidate: &UTxODetails, selected: &[UTxODetails]) -> u64 {
+        if selected
+            .iter()
+            .any(|input| input.address == candidate.address)
+        {
+            self.weight
+        } else {
+            0
+        }
+    }
+}
+
+/// draw the position of one of the `candidates`, uniformly without scorer
+pub(crate) fn draw_candidate<'a, R: Rng + ?Sized>(
+    rng: &mut R,
+    candidates: impl ExactSizeIterator<Item = &'a UTxODetails>,
+    scorer: Option<&(dyn CandidateScorer + Send + Sync)>,
+    selected: &[UTxODetails],
+) -> usize {
+    let scorer = match scorer {
+        Some(scorer) => scorer,
+        None => return rng.gen_range(0..candidates.len()),
+    };
+
+    let weights = candidates
+        .map(|candidate| u128::from(scorer.score(candidate, selected)) + 1)
+        .collect::<Vec<_>>();
+    let mut target = rng.gen_range(0..weights.iter().sum::<u128>());
+    for (position, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return position;
+        }
+        target -= weight;
+    }
+    weights.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use dcspark_core::Value;
+
+    #[test]
+    fn older_utxos_score_higher() {
+        let scorer = CoinAgeScorer::new(BlockNumber::new(100));
+        let mut old = create_utxo(0, 0, "0".to_string(), Value::from(1), vec![]);
+        old.creation_height = Some(BlockNumber::new(10));
+        let mut recent = create_utxo(0, 1, "0".to_string(), Value::from(1), vec![]);
+        recent.creation_height = Some(BlockNumber::new(90));
+        let unknown = create_utxo(0, 2, "0".to_string(), Value::from(1), vec![]);
+
+        assert_eq!(scorer.score(&old, &[]), 90);
+        assert_eq!(scorer.score(&recent, &[]), 10);
+        assert_eq!(scorer.score(&unknown, &[]), 0);
+    }
+
+    #[test]
+    fn same_address_scores_higher() {
+        let scorer = AddressDiversityScorer::default();
+        let selected = vec![create_utxo(0, 0, "a".to_string(), Value::from(1), vec![])];
+
+        let same = create_utxo(0, 1, "a".to_string(), Value::from(1), vec![]);
+        let other = create_utxo(0, 2, "b".to_string(), Value::from(1), vec![]);
+        assert_eq!(scorer.score(&same, &selected), 100);
+        assert_eq!(scorer.score(&other, &selected), 0);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    FeeBreakdown, InputOutputSetup, InputSelectionAlgorithm, InputSelectionResult,
+    TransactionFeeEstimator,
+};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails, UtxoPointer};
+use dcspark_core::{Regulated, Value};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+/// the decisions of a selection, in order, to explain why the inputs were
+/// selected
+///
+/// It is recorded by [`select_inputs_traced`] and can be serialized to be
+/// attached to a bug report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectionTrace {
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// the algorithm estimated the fee of spending the input
+    CandidateConsidered {
+        input: UtxoPointer,
+        value: Value<Regulated>,
+        fee: Value<Regulated>,
+    },
+    InputAccepted {
+        input: UtxoPointer,
+        value: Value<Regulated>,
+        fee_delta: Value<Regulated>,
+        /// the main asset of the inputs added so far
+        input_balance: Value<Regulated>,
+    },
+    /// the estimator failed on the input, e.g. on a
+    /// [`ConstraintViolation`](crate::ConstraintViolation), with the error
+    /// as the reason. Or the input was considered but not added by the end
+    /// of the selection.
+    CandidateRejected { input: UtxoPointer, reason: String },
+    OutputAdded {
+        value: Value<Regulated>,
+        assets: usize,
+        fee_delta: Value<Regulated>,
+        /// the main asset of the outputs added so far
+        output_balance: Value<Regulated>,
+    },
+    /// the estimator failed to add the output
+    OutputRejected {
+        value: Value<Regulated>,
+        assets: usize,
+        reason: String,
+    },
+}
+
+/// estimator recording the [`TraceEvent`]s of the selection using it
+pub struct TracingEstimator<'a, Estimate> {
+    inner: &'a mut Estimate,
+    // `fee_for_input` only borrows the estimator
+    events: RefCell<Vec<TraceEvent>>,
+    considered: RefCell<HashSet<UtxoPointer>>,
+    accepted: HashSet<UtxoPointer>,
+    rejected: RefCell<HashSet<UtxoPointer>>,
+    input_balance: Value<Regulated>,
+    output_balance: Value<Regulated>,
+}
+
+impl<'a, Estimate> TracingEstimator<'a, Estimate>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    pub fn new(inner: &'a mut Estimate) -> Self {
+        Self {
+            inner,
+            events: RefCell::new(vec![]),
+            considered: RefCell::new(HashSet::new()),
+            accepted: HashSet::new(),
+            rejected: RefCell::new(HashSet::new()),
+            input_balance: Value::zero(),
+            output_balance: Value::zero(),
+        }
+    }
+
+    /// record the error of the estimator on the input, once per input
+    fn reject(&self, input: &UtxoPointer, error: &anyhow::Error) {
+        if self.rejected.borrow_mut().insert(input.clone()) {
+            self.events
+                .borrow_mut()
+                .push(TraceEvent::CandidateRejected {
+                    input: input.clone(),
+                    reason: format!("{error:#}"),
+                });
+        }
+    }
+
+    /// the recorded trace, the candidates never added nor rejected by the
+    /// estimator are reported as rejected at the end
+    pub fn into_trace(self) -> SelectionTrace {
+        let mut events = self.events.into_inner();
+        let rejected_by_estimator = self.rejected.into_inner();
+        let mut rejected: Vec<_> = self
+            .considered
+            .into_inner()
+            .into_iter()
+            .filter(|input| {
+                !self.accepted.contains(input) && !rejected_by_estimator.contains(input)
+            })
+            .collect();
+        rejected.sort();
+        events.extend(
+            rejected
+                .into_iter()
+                .map(|input| TraceEvent::CandidateRejected {
+                    input,
+                    reason: "considered but not selected".to_string(),
+                }),
+        );
+        SelectionTrace { events }
+    }
+}
+
+impl<'a, Estimate> TransactionFeeEstimator for TracingEstimator<'a, Estimate>
+where
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    type InputUtxo = UTxODetails;
+    type OutputUtxo = UTxOBuilder;
+
+    fn min_required_fee(&self) -> anyhow::Result<Value<Regulated>> {
+        self.inner.min_required_fee()
+    }
+
+    fn fee_for_input(&self, input: &Self::InputUtxo) -> anyhow::Result<Value<Regulated>> {
+        let fee = self.inner.fee_for_input(input).map_err(|error| {
+            self.reject(&input.pointer, &error);
+            error
+        })?;
+        if self.considered.borrow_mut().insert(input.pointer.clone()) {
+            self.events
+                .borrow_mut()
+                .push(TraceEvent::CandidateConsidered {
+                    input: input.pointer.clone(),
+                    value: input.value.clone(),
+                    fee: fee.clone(),
+                });
+        }
+        Ok(fee)
+    }
+
+    fn add_input(&mut self, input: Self::InputUtxo) -> anyhow::Result<()> {
+        let fee_before = self.inner.min_required_fee()?;
+        let pointer = input.pointer.clone();
+        let value = input.value.clone();
+        if let Err(error) = self.inner.add_input(input) {
+            self.reject(&pointer, &error);
+            return Err(error);
+        }
+        let fee_delta = self.inner.min_required_fee()?.saturating_sub(&fee_before);
+
+        self.input_balance += &value;
+        self.accepted.insert(pointer.clone());
+        self.events.get_mut().push(TraceEvent::InputAccepted {
+            input: pointer,
+            value,
+            fee_delta,
+            input_balance: self.input_balance.clone(),
+        });
+        Ok(())
+    }
+
+    fn fee_for_output(&self, output: &Self::OutputUtxo) -> anyhow::Result<Value<Regulated>> {
+        self.inner.fee_for_output(output)
+    }
+
+    fn add_output(&mut self, output: Self::OutputUtxo) -> anyhow::Result<()> {
+        let fee_before = self.inner.min_required_fee()?;
+        let value = output.value.clone();
+        let assets = output.assets.len();
+        if let Err(error) = self.inner.add_output(output) {
+            self.events.get_mut().push(TraceEvent::OutputRejected {
+                value,
+                assets,
+                reason: format!("{error:#}"),
+            });
+            return Err(error);
+        }
+        let fee_delta = self.inner.min_required_fee()?.saturating_sub(&fee_before);
+
+        self.output_balance += &value;
+        self.events.get_mut().push(TraceEvent::OutputAdded {
+            value,
+            assets,
+            fee_delta,
+            output_balance: self.output_balance.clone(),
+        });
+        Ok(())
+    }
+
+    fn min_value_for_output(
+        &mut self,
+        output: Self::OutputUtxo,
+    ) -> anyhow::Result<Value<Regulated>> {
+        self.inner.min_value_for_output(output)
+    }
+
+    fn current_size(&self) -> anyhow::Result<usize> {
+        self.inner.current_size()
+    }
+
+    fn max_size(&self) -> anyhow::Result<usize> {
+        self.inner.max_size()
+    }
+
+    fn fee_breakdown(&self) -> anyhow::Result<FeeBreakdown> {
+        self.inner.fee_breakdown()
+    }
+}
+
+/// run the selection and attach the [`SelectionTrace`] to the result
+pub fn select_inputs_traced<Algorithm, Estimate>(
+    algorithm: &mut Algorithm,
+    estimator: &mut Estimate,
+    input_output_setup: InputOutputSetup<UTxODetails, UTxOBuilder>,
+) -> anyhow::Result<InputSelectionResult<UTxODetails, UTxOBuilder>>
+where
+    Algorithm: InputSelectionAlgorithm<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+{
+    let mut tracing = TracingEstimator::new(estimator);
+    let mut result = algorithm.select_inputs(&mut tracing, input_output_setup)?;
+    result.trace = Some(tracing.into_trace());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::algorithms::Knapsack;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+    use crate::{ConstrainedEstimator, ConstraintViolation, SelectionConstraints};
+    use deps::serde_json;
+
+    #[test]
+    fn trace_knapsack() {
+        let mut algorithm = Knapsack::try_from(vec![
+            create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]),
+            create_utxo(0, 1, "0".to_string(), Value::from(5), vec![]),
+            create_utxo(0, 2, "0".to_string(), Value::from(3), vec![]),
+        ])
+        .unwrap();
+
+        let result = select_inputs_traced(
+            &mut algorithm,
+            &mut DummyFeeEstimate::new(),
+            InputOutputSetup {
+                output_balance: Value::from(8),
+                ..InputOutputSetup::default()
+            },
+        )
+        .unwrap();
+
+        let trace = result.trace.unwrap();
+        let count = |f: fn(&TraceEvent) -> bool| trace.events.iter().filter(|e| f(e)).count();
+        assert_eq!(
+            count(|event| matches!(event, TraceEvent::CandidateConsidered { .. })),
+            3
+        );
+        assert_eq!(
+            count(|event| matches!(event, TraceEvent::InputAccepted { .. })),
+            2
+        );
+        assert_eq!(
+            trace.events.last(),
+            Some(&TraceEvent::CandidateRejected {
+                input: create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]).pointer,
+                reason: "considered but not selected".to_string(),
+            })
+        );
+
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(
+            serde_json::from_value::<SelectionTrace>(json).unwrap(),
+            trace
+        );
+    }
+
+    #[test]
+    fn trace_constraint_violation() {
+        let mut estimator = ConstrainedEstimator::new(
+            DummyFeeEstimate::new(),
+            SelectionConstraints {
+                max_inputs: Some(1),
+                ..SelectionConstraints::default()
+            },
+        );
+        let mut tracing = TracingEstimator::new(&mut estimator);
+        let first = create_utxo(0, 0, "0".to_string(), Value::from(10), vec![]);
+        let second = create_utxo(0, 1, "0".to_string(), Value::from(5), vec![]);
+
+        tracing.fee_for_input(&second).unwrap();
+        tracing.add_input(first).unwrap();
+        assert!(tracing.add_input(second.clone()).is_err());
+
+        let trace = tracing.into_trace();
+        let rejected = trace
+            .events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::CandidateRejected { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rejected,
+            vec![&TraceEvent::CandidateRejected {
+                input: second.pointer,
+                reason: ConstraintViolation::TooManyInputs { max: 1 }.to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Checks of the invariants of the selection results
+//!
+//! The checks are cheap enough to run on every transaction before it is
+//! signed, not only in the tests.
+
+use crate::InputSelectionResult;
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{AssetId, BalanceSheet};
+use serde::Serialize;
+use std::fmt;
+
+/// the imbalances of a selection result, see [`check_balanced`]
+///
+/// Only the unbalanced assets are listed: the report of a balanced result
+/// is empty.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct BalanceReport {
+    /// the balances declared in the result: the input balances minus the
+    /// output balances and the fee
+    pub declared: BalanceSheet,
+    /// the balances of the UTxOs of the result: the fixed and chosen
+    /// inputs minus the fixed outputs, the changes and the fee
+    pub utxos: BalanceSheet,
+}
+
+impl BalanceReport {
+    pub fn is_balanced(&self) -> bool {
+        self.declared.is_balanced() && self.utxos.is_balanced()
+    }
+
+    /// fail with the report if the result is not balanced
+    pub fn into_result(self) -> anyhow::Result<()> {
+        if self.is_balanced() {
+            Ok(())
+        } else {
+            Err(self.into())
+        }
+    }
+}
+
+fn fmt_sheet(f: &mut fmt::Formatter<'_>, name: &str, sheet: &BalanceSheet) -> fmt::Result {
+    let mut balances = sheet.iter().collect::<Vec<_>>();
+    balances.sort_by(|(left, _), (right, _)| left.cmp(right));
+    for (asset, balance) in balances {
+        write!(f, " {name} {asset}: {balance};")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for BalanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_balanced() {
+            return write!(f, "The selection is balanced");
+        }
+        write!(f, "The selection is not balanced:")?;
+        fmt_sheet(f, "declared", &self.declared)?;
+        fmt_sheet(f, "utxos", &self.utxos)
+    }
+}
+
+impl std::error::Error for BalanceReport {}
+
+/// check the selection `result` does not create or destroy any asset: the
+/// balances it declares and the UTxOs it holds must both balance
+pub fn check_balanced(result: &InputSelectionResult<UTxODetails, UTxOBuilder>) -> BalanceReport {
+    let mut declared = BalanceSheet::new();
+    declared.add_value(&AssetId::Native, &result.input_balance);
+    declared.sub_value(&AssetId::Native, &result.output_balance);
+    declared.sub_value(&AssetId::Native, &result.fee);
+    for asset in result.input_asset_balance.assets() {
+        declared += asset;
+    }
+    for asset in result.output_asset_balance.assets() {
+        declared -= asset;
+    }
+
+    let mut utxos = BalanceSheet::new();
+    for input in result
+        .fixed_inputs
+        .iter()
+        .chain(result.chosen_inputs.iter())
+    {
+        utxos.add_value(&AssetId::Native, &input.value);
+        for asset in input.assets.iter() {
+            utxos += asset;
+        }
+    }
+    for output in result.fixed_outputs.iter().chain(result.changes.iter()) {
+        utxos.sub_value(&AssetId::Native, &output.value);
+        for asset in output.assets.iter() {
+            utxos -= asset;
+        }
+    }
+    utxos.sub_value(&AssetId::Native, &result.fee);
+
+    BalanceReport { declared, utxos }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use dcspark_core::{Address, Balance, Regulated, Value};
+
+    fn result(change: u64) -> InputSelectionResult<UTxODetails, UTxOBuilder> {
+        InputSelectionResult {
+            input_balance: Value::from(10),
+            input_asset_balance: Default::default(),
+            output_balance: Value::from(9),
+            output_asset_balance: Default::default(),
+            fixed_inputs: vec![],
+            fixed_outputs: vec![],
+            chosen_inputs: vec![create_utxo(0, 0, "0".to_string(), Value::from(10), vec![])],
+            changes: vec![UTxOBuilder::new(
+                Address::new("change"),
+                Value::from(change),
+                vec![],
+            )],
+            fee: Value::from(1),
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn balanced() {
+        let report = check_balanced(&result(9));
+        assert!(report.is_balanced());
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn changes_do_not_match_the_balance() {
+        let report = check_balanced(&result(7));
+
+        assert!(report.declared.is_balanced());
+        assert_eq!(
+            report.utxos.get(&AssetId::Native),
+            Balance::<Regulated>::Excess(Value::from(2))
+        );
+        let error = report.into_result().unwrap_err();
+        assert!(error.downcast_ref::<BalanceReport>().is_some());
+    }
+}
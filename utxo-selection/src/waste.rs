@@ -0,0 +1,121 @@
+use crate::{calculate_main_token_balance, InputSelectionResult, TransactionFeeEstimator};
+use dcspark_core::tx::{UTxOBuilder, UTxODetails};
+use dcspark_core::{Balance, Regulated, Value};
+use deps::bigdecimal::BigDecimal;
+
+/// the waste of a selection, the lower the better
+///
+/// The waste is what the transaction costs on top of the fee of its inputs
+/// at the long-term fee rate:
+///
+/// * the fee of the chosen inputs minus their fee at the long-term rate,
+///   negative when the current fee rate is below the long-term one;
+/// * the fee of the changes and the fee of spending them later at the
+///   long-term rate;
+/// * the excess of main asset not returned in a change.
+///
+/// The fee rates are not known to the estimators, the long-term rate is
+/// given as a percentage of the current one.
+#[derive(Debug, Clone)]
+pub struct WasteMetric {
+    long_term_fee_percent: u64,
+}
+
+impl Default for WasteMetric {
+    fn default() -> Self {
+        Self {
+            long_term_fee_percent: 100,
+        }
+    }
+}
+
+impl WasteMetric {
+    pub fn new(long_term_fee_percent: u64) -> Self {
+        Self {
+            long_term_fee_percent,
+        }
+    }
+
+    fn long_term_fee(&self, fee: &Value<Regulated>) -> Value<Regulated> {
+        fee.percent(self.long_term_fee_percent, 100)
+    }
+
+    /// the waste of the `result`, the `estimator` is the one the selection
+    /// ran with
+    ///
+    /// The cost of spending a change later is estimated from the inputs of
+    /// the transaction.
+    pub fn waste<
+        Estimate: TransactionFeeEstimator<InputUtxo = UTxODetails, OutputUtxo = UTxOBuilder>,
+    >(
+        &self,
+        estimator: &Estimate,
+        result: &InputSelectionResult<UTxODetails, UTxOBuilder>,
+    ) -> anyhow::Result<BigDecimal> {
+        let mut waste = BigDecimal::from(0);
+
+        for input in result.chosen_inputs.iter() {
+            let fee = estimator.fee_for_input(input)?;
+            waste += fee.raw() - self.long_term_fee(&fee).raw();
+        }
+
+        if !result.changes.is_empty() {
+            let inputs = result.fixed_inputs.len() + result.chosen_inputs.len();
+            let mut input_fees = Value::<Regulated>::zero();
+            for input in result
+                .fixed_inputs
+                .iter()
+                .chain(result.chosen_inputs.iter())
+            {
+                input_fees += estimator.fee_for_input(input)?;
+            }
+            let spend_fee = if inputs == 0 {
+                Value::zero()
+            } else {
+                self.long_term_fee(&input_fees).percent(1, inputs as u64)
+            };
+
+            for change in result.changes.iter() {
+                waste += (estimator.fee_for_output(change)? + &spend_fee).raw();
+            }
+        }
+
+        if let Balance::Excess(excess) = calculate_main_token_balance(
+            &result.input_balance,
+            &result.output_balance,
+            &estimator.min_required_fee()?,
+        ) {
+            waste += excess.raw();
+        }
+
+        Ok(waste)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::test_utils::create_utxo;
+    use crate::estimators::dummy_estimator::DummyFeeEstimate;
+
+    #[test]
+    fn excess_is_waste() {
+        let result = InputSelectionResult {
+            input_balance: Value::from(30),
+            input_asset_balance: Default::default(),
+            output_balance: Value::from(25),
+            output_asset_balance: Default::default(),
+            fixed_inputs: vec![],
+            fixed_outputs: vec![],
+            chosen_inputs: vec![create_utxo(0, 0, "0".to_string(), Value::from(30), vec![])],
+            changes: vec![],
+            fee: Value::zero(),
+            trace: None,
+        };
+
+        let waste = WasteMetric::default()
+            .waste(&DummyFeeEstimate::new(), &result)
+            .unwrap();
+        assert_eq!(waste, BigDecimal::from(5));
+    }
+}